@@ -7,12 +7,25 @@ fn main() -> anyhow::Result<()> {
     match &cli {
         commands::Cli::Init(cmd) => cmd.execute()?,
         commands::Cli::Login(cmd) => cmd.execute()?,
+        commands::Cli::Logout(cmd) => cmd.execute()?,
         commands::Cli::Build(cmd) => cmd.execute()?,
         commands::Cli::Create(cmd) => cmd.execute()?,
         commands::Cli::Deploy(cmd) => cmd.execute()?,
         commands::Cli::Destroy(cmd) => cmd.execute()?,
         commands::Cli::Show(cmd) => cmd.execute()?,
+        commands::Cli::Versions(cmd) => cmd.execute()?,
+        commands::Cli::Rollback(cmd) => cmd.execute()?,
+        commands::Cli::Promote(cmd) => cmd.execute()?,
+        commands::Cli::Restore(cmd) => cmd.execute()?,
+        commands::Cli::Export(cmd) => cmd.execute()?,
+        commands::Cli::Import(cmd) => cmd.execute()?,
+        commands::Cli::Canary(cmd) => cmd.execute()?,
         commands::Cli::Template(cmd) => cmd.execute()?,
+        commands::Cli::Key(cmd) => cmd.execute()?,
+        commands::Cli::Project(cmd) => cmd.execute()?,
+        commands::Cli::Token(cmd) => cmd.execute()?,
+        commands::Cli::Org(cmd) => cmd.execute()?,
+        commands::Cli::Audit(cmd) => cmd.execute()?,
     }
     Ok(())
 }
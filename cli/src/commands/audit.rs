@@ -0,0 +1,113 @@
+use super::{deploy::get_session, Command};
+use crate::{config::Config, terminal::Terminal};
+use chrono::NaiveDateTime;
+use clap::Parser;
+use client::audit::AuditClient;
+use common::dtos::{AuditAction, AuditFilterDTO, GetAuditEventDTO};
+
+/// The length of the hashes shown
+const HASH_LENGTH: usize = 8;
+
+#[derive(Parser, Debug)]
+pub struct AuditCommand {
+    /// Shows the events of everyone in the project instead of your own
+    #[arg(long)]
+    pub project: Option<String>,
+
+    /// Only events of the stage
+    #[arg(long)]
+    pub stage: Option<String>,
+
+    /// Only events of the handler, key, token, organization or member
+    #[arg(long)]
+    pub target: Option<String>,
+
+    /// Only events of the action, e.g. handler_deploy or login
+    #[arg(long)]
+    pub action: Option<AuditAction>,
+
+    /// Only events of the user with the login
+    #[arg(long)]
+    pub user: Option<String>,
+
+    /// Only events since the time in UTC, e.g. 2023-10-02T08:00:00
+    #[arg(long)]
+    pub since: Option<NaiveDateTime>,
+
+    /// Only events before the time in UTC
+    #[arg(long)]
+    pub until: Option<NaiveDateTime>,
+
+    /// The number of events shown, the newest first
+    #[arg(long, default_value_t = 100)]
+    pub limit: i64,
+}
+
+impl Command for AuditCommand {
+    fn execute(&self) -> anyhow::Result<()> {
+        let terminal = Terminal::new();
+        let config = Config::default();
+        let session = get_session(&config)?.ok_or(anyhow::anyhow!(
+            "You are not logged in - Use \"noops login\""
+        ))?;
+        let audit_client = AuditClient::new(&config.base_url, session);
+
+        let filter = AuditFilterDTO {
+            project: self.project.clone(),
+            stage: self.stage.clone(),
+            target: self.target.clone(),
+            action: self.action,
+            user: self.user.clone(),
+            since: self.since,
+            until: self.until,
+            limit: Some(self.limit),
+        };
+        let events = audit_client.list(&filter)?;
+        if events.is_empty() {
+            terminal.write_text("No events found\n")?;
+        }
+        for event in events {
+            terminal.write_text(format!("{}\n", format_event(&event)))?;
+        }
+        Ok(())
+    }
+}
+
+fn format_event(event: &GetAuditEventDTO) -> String {
+    let actor = match &event.token {
+        Some(token) => format!("{} (token {})", event.login, token),
+        None => event.login.clone(),
+    };
+    let subject = [&event.project, &event.stage, &event.target]
+        .into_iter()
+        .flatten()
+        .map(String::as_str)
+        .collect::<Vec<_>>()
+        .join("/");
+    let mut line = format!(
+        "{}\t{}\t{}\t{}\t{}",
+        event.created_at.format("%Y-%m-%d %H:%M:%S UTC"),
+        actor,
+        event.ip.as_deref().unwrap_or("-"),
+        event.action,
+        if subject.is_empty() { "-" } else { &subject },
+    );
+    if event.old_hash.is_some() || event.new_hash.is_some() {
+        line.push_str(&format!(
+            "\t{} -> {}",
+            short_hash(&event.old_hash),
+            short_hash(&event.new_hash)
+        ));
+    }
+    if let Some(details) = &event.details {
+        line.push_str(&format!("\t{}", details));
+    }
+    line
+}
+
+fn short_hash(hash: &Option<String>) -> &str {
+    match hash {
+        Some(hash) => &hash[..hash.len().min(HASH_LENGTH)],
+        None => "none",
+    }
+}
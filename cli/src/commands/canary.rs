@@ -0,0 +1,79 @@
+use super::{deploy::get_session, Command};
+use crate::{config::Config, info, manifest::Manifest, terminal::Terminal};
+use anyhow::Context;
+use clap::Subcommand;
+use client::handler::HandlerClient;
+use common::dtos::DEFAULT_STAGE;
+
+#[derive(Debug, Subcommand)]
+pub enum CanaryCommand {
+    /// Shows the traffic weight and the status codes of the stable and the canary version
+    Status {
+        /// The handler with the canary release
+        name: String,
+
+        /// The stage of the handler
+        #[arg(long, default_value = DEFAULT_STAGE)]
+        stage: String,
+    },
+    /// Makes the canary version the active version
+    Promote {
+        /// The handler with the canary release
+        name: String,
+
+        /// The stage of the handler
+        #[arg(long, default_value = DEFAULT_STAGE)]
+        stage: String,
+    },
+    /// Routes all traffic back to the active version
+    Abort {
+        /// The handler with the canary release
+        name: String,
+
+        /// The stage of the handler
+        #[arg(long, default_value = DEFAULT_STAGE)]
+        stage: String,
+    },
+}
+
+impl Command for CanaryCommand {
+    fn execute(&self) -> anyhow::Result<()> {
+        let terminal = Terminal::new();
+        let config = Config::default();
+        let manifest = Manifest::from_yaml(&config.manifest)?;
+
+        let session = get_session(&config)?.ok_or(anyhow::anyhow!(
+            "You are not logged in - Use \"noops login\""
+        ))?;
+        let (name, stage) = match &self {
+            CanaryCommand::Status { name, stage }
+            | CanaryCommand::Promote { name, stage }
+            | CanaryCommand::Abort { name, stage } => (name, stage),
+        };
+        let handler_client = HandlerClient::new(&config.base_url, session, stage);
+
+        match &self {
+            CanaryCommand::Status { .. } => {
+                info::show_canary(name, &manifest, &handler_client, &terminal)
+            }
+            CanaryCommand::Promote { .. } => {
+                let text = format!("Promoting canary of {}", name);
+                let spinner = terminal.spinner(&text);
+                handler_client
+                    .promote_canary(&manifest.project_name, name)
+                    .context(format!("Promoting canary of \"{}\" failed", name))?;
+                spinner.finish_with_message(text);
+                Ok(())
+            }
+            CanaryCommand::Abort { .. } => {
+                let text = format!("Aborting canary of {}", name);
+                let spinner = terminal.spinner(&text);
+                handler_client
+                    .abort_canary(&manifest.project_name, name)
+                    .context(format!("Aborting canary of \"{}\" failed", name))?;
+                spinner.finish_with_message(text);
+                Ok(())
+            }
+        }
+    }
+}
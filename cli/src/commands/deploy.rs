@@ -1,9 +1,27 @@
-use std::{fs::File, io::Read, path::Path};
+use std::{
+    env,
+    fs::{self, File},
+    io::{Read, Write},
+    path::{Path, PathBuf},
+};
 
 use super::{build::BuildCommand, Command};
-use crate::{config::Config, deploy, manifest::Manifest, terminal::Terminal};
+use crate::{
+    config::Config,
+    deploy::{self, DeployOptions},
+    manifest::Manifest,
+    terminal::Terminal,
+};
+use anyhow::Context;
 use clap::Parser;
-use client::{handler::HandlerClient, project::ProjectClient};
+use client::{handler::HandlerClient, project::ProjectClient, session::Session};
+use common::{
+    dtos::{GetJWTDTO, DEFAULT_STAGE},
+    signature::{self, SigningKey},
+};
+
+/// The environment variable of an API token created with "noops token create"
+const TOKEN_VARIABLE: &str = "NOOPS_TOKEN";
 
 #[derive(Parser, Debug)]
 pub struct DeployCommand {
@@ -13,6 +31,18 @@ pub struct DeployCommand {
     /// Builds the handler(s) before deploying
     #[arg(short, long)]
     pub build: bool,
+
+    /// The stage to deploy to
+    #[arg(long, default_value = DEFAULT_STAGE)]
+    pub stage: String,
+
+    /// Deploys updated handlers as canary releases receiving this share of the traffic, e.g. 10%
+    #[arg(long, value_parser = parse_canary_weight)]
+    pub canary: Option<u8>,
+
+    /// Signs the handlers with the key created by "noops key generate"
+    #[arg(long, env = "NOOPS_SIGNING_KEY")]
+    pub signing_key: Option<PathBuf>,
 }
 
 impl Command for DeployCommand {
@@ -28,34 +58,99 @@ impl Command for DeployCommand {
         let config = Config::default();
         let manifest = Manifest::from_yaml(&config.manifest)?;
 
-        let jwt = get_jwt(&config.jwt_file)?.ok_or(anyhow::anyhow!(
+        let session = get_session(&config)?.ok_or(anyhow::anyhow!(
             "You are not logged in - Use \"noops login\""
         ))?;
 
-        let handler_client = HandlerClient::new(&config.base_url, jwt.clone());
-        let project_client = ProjectClient::new(&config.base_url, jwt);
+        let handler_client = HandlerClient::new(&config.base_url, session.clone(), &self.stage);
+        let project_client = ProjectClient::new(&config.base_url, session);
+        let options = DeployOptions {
+            canary: self.canary,
+            signing_key: self
+                .signing_key
+                .as_deref()
+                .map(read_signing_key)
+                .transpose()?,
+        };
 
         match self.name.clone() {
             Some(name) => deploy::deploy_handler(
                 &name,
                 &terminal,
                 manifest,
+                &self.stage,
+                &project_client,
+                &handler_client,
+                &options,
+            )?,
+            None => deploy::deploy_project(
+                &terminal,
+                manifest,
+                &self.stage,
                 &project_client,
                 &handler_client,
+                &options,
             )?,
-            None => deploy::deploy_project(&terminal, manifest, &project_client, &handler_client)?,
         }
 
         Ok(())
     }
 }
 
-pub fn get_jwt(path: &Path) -> anyhow::Result<Option<String>> {
+/// The session of the logged in user, the refreshed tokens are written back to the file. An API
+/// token in `NOOPS_TOKEN` takes precedence, e.g. in CI.
+pub fn get_session(config: &Config) -> anyhow::Result<Option<Session>> {
+    if let Ok(token) = env::var(TOKEN_VARIABLE) {
+        let tokens = GetJWTDTO {
+            jwt: token,
+            refresh_token: String::new(),
+        };
+        return Ok(Some(Session::new(&config.base_url, tokens)));
+    }
+    let Some(tokens) = read_tokens(&config.jwt_file)? else {
+        return Ok(None);
+    };
+    let path = config.jwt_file.clone();
+    let session = Session::new(&config.base_url, tokens)
+        .on_refresh(move |tokens| write_tokens(&path, tokens));
+    Ok(Some(session))
+}
+
+/// The tokens stored by "noops login", logins before refresh tokens only stored the access token
+pub fn read_tokens(path: &Path) -> anyhow::Result<Option<GetJWTDTO>> {
     if !path.exists() {
         return Ok(None);
     }
-    let mut jwt = String::default();
+    let mut content = String::default();
     let mut file = File::open(path)?;
-    file.read_to_string(&mut jwt)?;
-    Ok(Some(jwt))
+    file.read_to_string(&mut content)?;
+    let tokens = serde_json::from_str(&content).unwrap_or(GetJWTDTO {
+        jwt: content.trim().to_string(),
+        refresh_token: String::new(),
+    });
+    Ok(Some(tokens))
+}
+
+pub fn write_tokens(path: &Path, tokens: &GetJWTDTO) -> anyhow::Result<()> {
+    let mut file = File::create(path)?;
+    file.write_all(serde_json::to_string(tokens)?.as_bytes())?;
+    Ok(())
+}
+
+pub fn read_signing_key(path: &Path) -> anyhow::Result<SigningKey> {
+    let encoded = fs::read_to_string(path)
+        .context(format!("Reading signing key {} failed", path.display()))?;
+    signature::decode_signing_key(&encoded)
+        .ok_or(anyhow::anyhow!("{} is not a signing key", path.display()))
+}
+
+fn parse_canary_weight(weight: &str) -> Result<u8, String> {
+    let weight: u8 = weight
+        .trim_end_matches('%')
+        .parse()
+        .map_err(|_| format!("\"{}\" is not a percentage", weight))?;
+    if weight > 100 {
+        return Err("The canary weight must be between 0% and 100%".to_string());
+    }
+    Ok(weight)
 }
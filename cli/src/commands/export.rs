@@ -0,0 +1,46 @@
+use super::{deploy::get_session, Command};
+use crate::{config::Config, terminal::Terminal};
+use anyhow::Context;
+use clap::Parser;
+use client::project::ProjectClient;
+use std::path::PathBuf;
+
+const ARCHIVE_EXTENSION: &str = "tar.zst";
+
+#[derive(Parser, Debug)]
+pub struct ExportCommand {
+    /// The project to export
+    pub project: String,
+
+    /// The file to write the archive to, defaults to <project>.tar.zst
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+}
+
+impl Command for ExportCommand {
+    fn execute(&self) -> anyhow::Result<()> {
+        let terminal = Terminal::new();
+        let config = Config::default();
+
+        let session = get_session(&config)?.ok_or(anyhow::anyhow!(
+            "You are not logged in - Use \"noops login\""
+        ))?;
+        let project_client = ProjectClient::new(&config.base_url, session);
+        let output = self
+            .output
+            .clone()
+            .unwrap_or_else(|| PathBuf::from(format!("{}.{}", self.project, ARCHIVE_EXTENSION)));
+
+        let spinner = terminal.spinner(format!("Exporting project {}", self.project));
+        let archive = project_client
+            .export(&self.project)
+            .context(format!("Exporting project \"{}\" failed", self.project))?;
+        std::fs::write(&output, archive).context(format!("Writing {} failed", output.display()))?;
+        spinner.finish_with_message(format!(
+            "Exported project {} to {}",
+            self.project,
+            output.display()
+        ));
+        Ok(())
+    }
+}
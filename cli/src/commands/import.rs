@@ -0,0 +1,61 @@
+use super::{
+    deploy::{get_session, read_signing_key},
+    Command,
+};
+use crate::{config::Config, terminal::Terminal};
+use anyhow::Context;
+use clap::Parser;
+use client::project::ProjectClient;
+use common::signature;
+use std::path::PathBuf;
+
+#[derive(Parser, Debug)]
+pub struct ImportCommand {
+    /// The archive written by "noops export"
+    pub archive: PathBuf,
+
+    /// The name of the imported project, defaults to the name of the exported project
+    #[arg(long)]
+    pub project: Option<String>,
+
+    /// Signs the archive with the key created by "noops key generate", required if the
+    /// archive has trusted keys
+    #[arg(long, env = "NOOPS_SIGNING_KEY")]
+    pub signing_key: Option<PathBuf>,
+}
+
+impl Command for ImportCommand {
+    fn execute(&self) -> anyhow::Result<()> {
+        let terminal = Terminal::new();
+        let config = Config::default();
+
+        let session = get_session(&config)?.ok_or(anyhow::anyhow!(
+            "You are not logged in - Use \"noops login\""
+        ))?;
+        let project_client = ProjectClient::new(&config.base_url, session);
+        let archive = std::fs::read(&self.archive)
+            .context(format!("Reading {} failed", self.archive.display()))?;
+        let signature = match &self.signing_key {
+            Some(path) => Some(signature::sign(&read_signing_key(path)?, &archive)),
+            None => None,
+        };
+
+        let spinner = terminal.spinner(format!("Importing {}", self.archive.display()));
+        let project = project_client
+            .import(archive, self.project.as_deref(), signature)
+            .context(format!("Importing {} failed", self.archive.display()))?;
+        spinner.finish_with_message(format!(
+            "Imported project {} with {} handlers",
+            project.name,
+            project.handlers.len()
+        ));
+
+        for handler in project.handlers {
+            terminal.write_text(format!(
+                "\t{} ({}) at version {}\n",
+                handler.name, handler.stage, handler.version
+            ))?;
+        }
+        Ok(())
+    }
+}
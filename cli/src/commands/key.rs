@@ -0,0 +1,105 @@
+use super::{deploy::get_session, Command};
+use crate::{config::Config, manifest::Manifest, terminal::Terminal};
+use anyhow::Context;
+use clap::Subcommand;
+use client::project::ProjectClient;
+use common::{dtos::CreateTrustedKeyDTO, signature};
+use std::{fs::OpenOptions, io::Write, path::PathBuf};
+
+#[derive(Debug, Subcommand)]
+pub enum KeyCommand {
+    /// Creates a signing key and prints its public key
+    Generate {
+        /// The file to write the signing key to
+        path: PathBuf,
+    },
+    /// Trusts the public key, the project then only accepts handlers signed by a trusted key
+    Add {
+        /// The name of the key, e.g. "ci"
+        name: String,
+
+        /// The public key printed by "noops key generate"
+        public_key: String,
+    },
+    /// Lists the trusted keys of the project
+    List,
+    /// Stops trusting the key
+    Remove {
+        /// The name of the key
+        name: String,
+    },
+}
+
+impl Command for KeyCommand {
+    fn execute(&self) -> anyhow::Result<()> {
+        let terminal = Terminal::new();
+
+        match &self {
+            KeyCommand::Generate { path } => {
+                let key = signature::generate_key();
+                let mut options = OpenOptions::new();
+                options.write(true).create_new(true);
+                #[cfg(unix)]
+                std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+                let mut file = options
+                    .open(path)
+                    .context(format!("Creating {} failed", path.display()))?;
+                file.write_all(signature::encode_signing_key(&key).as_bytes())?;
+
+                terminal.write_text(format!("Signing key written to {}\n", path.display()))?;
+                terminal.write_text(format!("Public key: {}\n", signature::public_key(&key)))?;
+            }
+            KeyCommand::Add { name, public_key } => {
+                let (project, project_client) = project_client()?;
+                let text = format!("Trusting key {}", name);
+                let spinner = terminal.spinner(&text);
+                let key = CreateTrustedKeyDTO {
+                    name: name.clone(),
+                    public_key: public_key.clone(),
+                };
+                project_client
+                    .add_trusted_key(&project, &key)
+                    .context(format!("Trusting key \"{}\" failed", name))?;
+                spinner.finish_with_message(text);
+            }
+            KeyCommand::List => {
+                let (project, project_client) = project_client()?;
+                let keys = project_client.trusted_keys(&project)?;
+                if keys.is_empty() {
+                    terminal.write_text("The project accepts unsigned handlers\n")?;
+                }
+                for key in keys {
+                    terminal.write_text(format!(
+                        "{}\t{}\tadded {}\n",
+                        key.name,
+                        key.public_key,
+                        key.created_at.format("%Y-%m-%d %H:%M:%S UTC")
+                    ))?;
+                }
+            }
+            KeyCommand::Remove { name } => {
+                let (project, project_client) = project_client()?;
+                let text = format!("Removing key {}", name);
+                let spinner = terminal.spinner(&text);
+                project_client
+                    .remove_trusted_key(&project, name)
+                    .context(format!("Removing key \"{}\" failed", name))?;
+                spinner.finish_with_message(text);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The project of the manifest and a client for it
+pub(super) fn project_client() -> anyhow::Result<(String, ProjectClient)> {
+    let config = Config::default();
+    let manifest = Manifest::from_yaml(&config.manifest)?;
+    let session = get_session(&config)?.ok_or(anyhow::anyhow!(
+        "You are not logged in - Use \"noops login\""
+    ))?;
+    Ok((
+        manifest.project_name,
+        ProjectClient::new(&config.base_url, session),
+    ))
+}
@@ -1,25 +1,25 @@
-use std::{fs::File, io::Write, path::Path};
-
-use super::Command;
+use super::{deploy::write_tokens, Command};
 use crate::config::Config;
 use clap::Parser;
 use client::auth::AuthClient;
 
 #[derive(Parser, Debug)]
-pub struct LoginCommand;
+pub struct LoginCommand {
+    /// Logs in as the local user of a server running with --dev-auth
+    #[arg(long)]
+    pub dev: bool,
+}
 
 impl Command for LoginCommand {
     fn execute(&self) -> anyhow::Result<()> {
         let config = Config::default();
         let auth_client = AuthClient::new(&config.base_url);
-        let jwt = auth_client.login()?;
-        set_jwt(&config.jwt_file, &jwt)?;
+        let tokens = if self.dev {
+            auth_client.login_dev()?
+        } else {
+            auth_client.login()?
+        };
+        write_tokens(&config.jwt_file, &tokens)?;
         Ok(())
     }
 }
-
-fn set_jwt(path: &Path, jwt: &str) -> anyhow::Result<()> {
-    let mut file = File::create(path)?;
-    file.write_all(jwt.as_bytes())?;
-    Ok(())
-}
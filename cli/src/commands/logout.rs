@@ -0,0 +1,30 @@
+use std::fs;
+
+use super::{deploy::read_tokens, Command};
+use crate::{config::Config, terminal::Terminal};
+use clap::Parser;
+use client::auth::AuthClient;
+
+#[derive(Parser, Debug)]
+pub struct LogoutCommand;
+
+impl Command for LogoutCommand {
+    fn execute(&self) -> anyhow::Result<()> {
+        let terminal = Terminal::new();
+        let config = Config::default();
+        let Some(tokens) = read_tokens(&config.jwt_file)? else {
+            terminal.write_text("You are not logged in")?;
+            return Ok(());
+        };
+
+        if !tokens.refresh_token.is_empty() {
+            let auth_client = AuthClient::new(&config.base_url);
+            if let Err(err) = auth_client.logout(&tokens.refresh_token) {
+                log::warn!("Revoking the session failed: {}", err);
+            }
+        }
+        fs::remove_file(&config.jwt_file)?;
+        terminal.write_text("Logged out")?;
+        Ok(())
+    }
+}
@@ -1,15 +1,32 @@
+pub mod audit;
 pub mod build;
+pub mod canary;
 pub mod create;
 pub mod deploy;
 pub mod destroy;
+pub mod export;
+pub mod import;
 pub mod init;
+pub mod key;
 pub mod login;
+pub mod logout;
+pub mod org;
+pub mod project;
+pub mod promote;
+pub mod restore;
+pub mod rollback;
 pub mod show;
 pub mod template;
+pub mod token;
+pub mod versions;
 
 use self::{
-    build::BuildCommand, create::CreateCommand, deploy::DeployCommand, destroy::DestroyCommand,
-    init::InitCommand, login::LoginCommand, show::ShowCommand, template::TemplateCommand,
+    audit::AuditCommand, build::BuildCommand, canary::CanaryCommand, create::CreateCommand,
+    deploy::DeployCommand, destroy::DestroyCommand, export::ExportCommand, import::ImportCommand,
+    init::InitCommand, key::KeyCommand, login::LoginCommand, logout::LogoutCommand,
+    org::OrgCommand, project::ProjectCommand, promote::PromoteCommand, restore::RestoreCommand,
+    rollback::RollbackCommand, show::ShowCommand, template::TemplateCommand, token::TokenCommand,
+    versions::VersionsCommand,
 };
 use clap::Parser;
 
@@ -27,6 +44,9 @@ pub enum Cli {
     /// Login in the noops cloud
     Login(LoginCommand),
 
+    /// Logout from the noops cloud and revoke the session
+    Logout(LogoutCommand),
+
     /// Create a handler
     Create(CreateCommand),
 
@@ -42,7 +62,49 @@ pub enum Cli {
     /// Show information about the project or a handler
     Show(ShowCommand),
 
+    /// List the deployed versions of a handler
+    Versions(VersionsCommand),
+
+    /// Activate a previously deployed version of a handler
+    Rollback(RollbackCommand),
+
+    /// Copy the deployed handlers of a stage to another stage without rebuilding them
+    Promote(PromoteCommand),
+
+    /// Restore a deleted handler or project from the trash
+    #[command(subcommand)]
+    Restore(RestoreCommand),
+
+    /// Write a project with its handlers, versions, variables and trusted keys to an archive
+    Export(ExportCommand),
+
+    /// Create a project from an archive written by "noops export"
+    Import(ImportCommand),
+
+    /// Canary release subcommand
+    #[command(subcommand)]
+    Canary(CanaryCommand),
+
     /// Template subcommand
     #[command(subcommand)]
     Template(TemplateCommand),
+
+    /// Signing keys subcommand
+    #[command(subcommand)]
+    Key(KeyCommand),
+
+    /// Project subcommand
+    #[command(subcommand)]
+    Project(ProjectCommand),
+
+    /// API tokens subcommand
+    #[command(subcommand)]
+    Token(TokenCommand),
+
+    /// Organizations subcommand
+    #[command(subcommand)]
+    Org(OrgCommand),
+
+    /// Show who changed what and when, your own actions or those in a project
+    Audit(AuditCommand),
 }
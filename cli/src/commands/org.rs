@@ -0,0 +1,100 @@
+use super::{deploy::get_session, Command};
+use crate::{config::Config, terminal::Terminal};
+use anyhow::Context;
+use clap::Subcommand;
+use client::org::OrgClient;
+use common::dtos::Role;
+
+#[derive(Debug, Subcommand)]
+pub enum OrgCommand {
+    /// Creates an organization with you as its owner
+    Create {
+        /// The name of the organization, its projects are named like "<org>/<project>"
+        name: String,
+    },
+    /// Lists your organizations with your role in them
+    List,
+    /// Lists the members of the organization
+    Members {
+        /// The name of the organization
+        org: String,
+    },
+    /// Adds a user to the organization or changes their role
+    Add {
+        /// The name of the organization
+        org: String,
+
+        /// The login of the user, they must have logged in once. Qualify it with the
+        /// identity provider like github:alice if users of several providers share it.
+        login: String,
+
+        /// The role of the user: viewer, maintainer or owner
+        #[arg(long, default_value_t = Role::Viewer)]
+        role: Role,
+    },
+    /// Removes a user from the organization, or leaves it with your own login
+    Remove {
+        /// The name of the organization
+        org: String,
+
+        /// The login of the user, qualified with the identity provider like github:alice if
+        /// users of several providers share it
+        login: String,
+    },
+}
+
+impl Command for OrgCommand {
+    fn execute(&self) -> anyhow::Result<()> {
+        let terminal = Terminal::new();
+        let config = Config::default();
+        let session = get_session(&config)?.ok_or(anyhow::anyhow!(
+            "You are not logged in - Use \"noops login\""
+        ))?;
+        let org_client = OrgClient::new(&config.base_url, session);
+
+        match &self {
+            OrgCommand::Create { name } => {
+                let text = format!("Creating organization {}", name);
+                let spinner = terminal.spinner(&text);
+                org_client
+                    .create(name)
+                    .context(format!("Creating organization \"{}\" failed", name))?;
+                spinner.finish_with_message(text);
+            }
+            OrgCommand::List => {
+                let orgs = org_client.list()?;
+                if orgs.is_empty() {
+                    terminal.write_text("You are not a member of any organization\n")?;
+                }
+                for org in orgs {
+                    terminal.write_text(format!("{}\t{}\n", org.name, org.role))?;
+                }
+            }
+            OrgCommand::Members { org } => {
+                for member in org_client.members(org)? {
+                    terminal.write_text(format!(
+                        "{}:{}\t{}\n",
+                        member.provider, member.login, member.role
+                    ))?;
+                }
+            }
+            OrgCommand::Add { org, login, role } => {
+                let text = format!("Adding {} to {} as {}", login, org, role);
+                let spinner = terminal.spinner(&text);
+                org_client
+                    .set_member(org, login, *role)
+                    .context(format!("Adding \"{}\" to \"{}\" failed", login, org))?;
+                spinner.finish_with_message(text);
+            }
+            OrgCommand::Remove { org, login } => {
+                let text = format!("Removing {} from {}", login, org);
+                let spinner = terminal.spinner(&text);
+                org_client
+                    .remove_member(org, login)
+                    .context(format!("Removing \"{}\" from \"{}\" failed", login, org))?;
+                spinner.finish_with_message(text);
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,99 @@
+use super::{key::project_client, Command};
+use crate::{config::Config, manifest::Manifest, terminal::Terminal};
+use anyhow::Context;
+use clap::Subcommand;
+use client::project::ProjectClient;
+use common::dtos::DEFAULT_STAGE;
+use std::collections::BTreeSet;
+
+#[derive(Debug, Subcommand)]
+pub enum ProjectCommand {
+    /// Moves the project with all its handlers, versions, variables and trusted keys to the
+    /// trash, from which "noops restore project" restores it until the retention period ends
+    Destroy {
+        /// Destroys the project without asking for confirmation
+        #[arg(long)]
+        yes: bool,
+    },
+}
+
+impl Command for ProjectCommand {
+    fn execute(&self) -> anyhow::Result<()> {
+        let terminal = Terminal::new();
+
+        match &self {
+            ProjectCommand::Destroy { yes } => {
+                let (project, project_client) = project_client()?;
+                if !yes {
+                    terminal.write_text(removals(&project, &project_client)?)?;
+                    if !terminal.confirm_prompt(format!("Destroy project {}?", project))? {
+                        terminal.write_text("Aborting\n")?;
+                        return Ok(());
+                    }
+                }
+
+                let text = format!("Destroying project {}", project);
+                let spinner = terminal.spinner(&text);
+                project_client
+                    .delete(&project)
+                    .context(format!("Destroying project \"{}\" failed", project))?;
+                spinner.finish_with_message(text);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Describes everything destroying the project moves to the trash
+fn removals(project: &str, project_client: &ProjectClient) -> anyhow::Result<String> {
+    let deployed = project_client.get(project)?;
+    let keys = project_client.trusted_keys(project)?;
+
+    // Variables can be set for stages without handlers, so the stages of the manifest are
+    // looked at as well
+    let config = Config::default();
+    let manifest = Manifest::from_yaml(&config.manifest)?;
+    let stages: BTreeSet<&str> = deployed
+        .handlers
+        .iter()
+        .map(|handler| handler.stage.as_str())
+        .chain(manifest.stages.keys().map(String::as_str))
+        .chain([DEFAULT_STAGE])
+        .collect();
+
+    let mut text = format!("Destroying project {} moves to the trash:\n", project);
+    text.push_str("  Handlers, with all their versions and components:\n");
+    if deployed.handlers.is_empty() {
+        text.push_str("    none\n");
+    }
+    for handler in &deployed.handlers {
+        text.push_str(&format!(
+            "    {} ({}, version {})\n",
+            handler.name, handler.stage, handler.version
+        ));
+    }
+
+    text.push_str("  Variables:\n");
+    let mut any_variables = false;
+    for stage in stages {
+        let variables = project_client.variables(project, stage)?;
+        if variables.is_empty() {
+            continue;
+        }
+        any_variables = true;
+        let names: Vec<&str> = variables.keys().map(String::as_str).collect();
+        text.push_str(&format!("    {}: {}\n", stage, names.join(", ")));
+    }
+    if !any_variables {
+        text.push_str("    none\n");
+    }
+
+    text.push_str("  Trusted keys:\n");
+    if keys.is_empty() {
+        text.push_str("    none\n");
+    }
+    for key in &keys {
+        text.push_str(&format!("    {}\n", key.name));
+    }
+    Ok(text)
+}
@@ -0,0 +1,46 @@
+use super::{deploy::get_session, Command};
+use crate::{config::Config, manifest::Manifest, terminal::Terminal};
+use anyhow::Context;
+use clap::Parser;
+use client::project::ProjectClient;
+
+#[derive(Parser, Debug)]
+pub struct PromoteCommand {
+    /// The stage to copy the handlers from
+    pub source: String,
+
+    /// The stage to copy the handlers to
+    pub target: String,
+}
+
+impl Command for PromoteCommand {
+    fn execute(&self) -> anyhow::Result<()> {
+        let terminal = Terminal::new();
+        let config = Config::default();
+        let manifest = Manifest::from_yaml(&config.manifest)?;
+
+        let session = get_session(&config)?.ok_or(anyhow::anyhow!(
+            "You are not logged in - Use \"noops login\""
+        ))?;
+        let project_client = ProjectClient::new(&config.base_url, session);
+
+        terminal.write_heading("Promoting stage")?;
+
+        let spinner = terminal.spinner(format!("Promoting {} to {}", self.source, self.target));
+        let handlers = project_client
+            .promote(&manifest.project_name, &self.source, &self.target)
+            .context(format!(
+                "Promoting stage \"{}\" to \"{}\" failed",
+                self.source, self.target
+            ))?;
+        spinner.finish_with_message(format!("Promoted {} to {}", self.source, self.target));
+
+        for handler in handlers {
+            terminal.write_text(format!(
+                "\t{} is now at version {}",
+                handler.name, handler.version
+            ))?;
+        }
+        Ok(())
+    }
+}
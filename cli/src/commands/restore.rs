@@ -0,0 +1,60 @@
+use super::{deploy::get_session, key::project_client, Command};
+use crate::{config::Config, manifest::Manifest, terminal::Terminal};
+use anyhow::Context;
+use clap::Subcommand;
+use client::handler::HandlerClient;
+use common::dtos::DEFAULT_STAGE;
+
+#[derive(Debug, Subcommand)]
+pub enum RestoreCommand {
+    /// Restores a deleted handler from the trash
+    Handler {
+        /// The handler to restore
+        name: String,
+
+        /// The stage of the handler
+        #[arg(long, default_value = DEFAULT_STAGE)]
+        stage: String,
+    },
+    /// Restores the deleted project from the trash together with the handlers deleted with it
+    Project,
+}
+
+impl Command for RestoreCommand {
+    fn execute(&self) -> anyhow::Result<()> {
+        let terminal = Terminal::new();
+
+        match &self {
+            RestoreCommand::Handler { name, stage } => {
+                let config = Config::default();
+                let manifest = Manifest::from_yaml(&config.manifest)?;
+                let session = get_session(&config)?.ok_or(anyhow::anyhow!(
+                    "You are not logged in - Use \"noops login\""
+                ))?;
+                let handler_client = HandlerClient::new(&config.base_url, session, stage);
+
+                let spinner = terminal.spinner(format!("Restoring {}", name));
+                let handler = handler_client
+                    .restore(&manifest.project_name, name)
+                    .context(format!("Restoring handler \"{}\" failed", name))?;
+                spinner.finish_with_message(format!(
+                    "Restored {} at version {}",
+                    name, handler.version
+                ));
+            }
+            RestoreCommand::Project => {
+                let (project, project_client) = project_client()?;
+                let spinner = terminal.spinner(format!("Restoring project {}", project));
+                let restored = project_client
+                    .restore(&project)
+                    .context(format!("Restoring project \"{}\" failed", project))?;
+                spinner.finish_with_message(format!(
+                    "Restored project {} with {} handlers",
+                    project,
+                    restored.handlers.len()
+                ));
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,45 @@
+use super::{deploy::get_session, Command};
+use crate::{config::Config, manifest::Manifest, terminal::Terminal};
+use anyhow::Context;
+use clap::Parser;
+use client::handler::HandlerClient;
+use common::dtos::DEFAULT_STAGE;
+
+#[derive(Parser, Debug)]
+pub struct RollbackCommand {
+    /// The handler to roll back
+    pub name: String,
+
+    /// The version to activate, defaults to the version before the active one
+    #[arg(long)]
+    pub to: Option<i32>,
+
+    /// The stage of the handler
+    #[arg(long, default_value = DEFAULT_STAGE)]
+    pub stage: String,
+}
+
+impl Command for RollbackCommand {
+    fn execute(&self) -> anyhow::Result<()> {
+        let terminal = Terminal::new();
+        let config = Config::default();
+        let manifest = Manifest::from_yaml(&config.manifest)?;
+
+        let session = get_session(&config)?.ok_or(anyhow::anyhow!(
+            "You are not logged in - Use \"noops login\""
+        ))?;
+        let handler_client = HandlerClient::new(&config.base_url, session, &self.stage);
+
+        terminal.write_heading("Rolling back handler")?;
+
+        let spinner = terminal.spinner(format!("Rolling back {}", &self.name));
+        let handler = handler_client
+            .rollback(&manifest.project_name, &self.name, self.to)
+            .context(format!("Rolling back handler \"{}\" failed", self.name))?;
+        spinner.finish_with_message(format!(
+            "{} is now at version {}",
+            &self.name, handler.version
+        ));
+        Ok(())
+    }
+}
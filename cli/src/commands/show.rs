@@ -1,12 +1,17 @@
-use super::{deploy::get_jwt, Command};
+use super::{deploy::get_session, Command};
 use crate::{config::Config, info, manifest::Manifest, terminal::Terminal};
 use clap::Parser;
 use client::{handler::HandlerClient, project::ProjectClient};
+use common::dtos::DEFAULT_STAGE;
 
 #[derive(Parser, Debug)]
 pub struct ShowCommand {
     /// The handler to show
     pub name: Option<String>,
+
+    /// The stage to show
+    #[arg(long, default_value = DEFAULT_STAGE)]
+    pub stage: String,
 }
 
 impl Command for ShowCommand {
@@ -15,13 +20,13 @@ impl Command for ShowCommand {
         let config = Config::default();
         let manifest = Manifest::from_yaml(&config.manifest)?;
 
-        let jwt = get_jwt(&config.jwt_file)?.ok_or(anyhow::anyhow!("You are not logged in"))?;
-        let handler_client = HandlerClient::new(&config.base_url, jwt.clone());
-        let project_client = ProjectClient::new(&config.base_url, jwt);
+        let session = get_session(&config)?.ok_or(anyhow::anyhow!("You are not logged in"))?;
+        let handler_client = HandlerClient::new(&config.base_url, session.clone(), &self.stage);
+        let project_client = ProjectClient::new(&config.base_url, session);
 
         match self.name.clone() {
             Some(name) => info::show_handler(&name, &manifest, &handler_client, &terminal)?,
-            None => info::show_project(&manifest, &project_client, &terminal)?,
+            None => info::show_project(&manifest, &self.stage, &project_client, &terminal)?,
         }
 
         Ok(())
@@ -0,0 +1,90 @@
+use super::{deploy::get_session, Command};
+use crate::{config::Config, terminal::Terminal};
+use anyhow::Context;
+use clap::Subcommand;
+use client::token::TokenClient;
+use common::dtos::{CreateTokenDTO, TokenScope};
+
+#[derive(Debug, Subcommand)]
+pub enum TokenCommand {
+    /// Creates an API token, e.g. for CI, and prints it once
+    Create {
+        /// The name of the token, e.g. "github-actions"
+        name: String,
+
+        /// What the token permits: read, deploy or full
+        #[arg(long, default_value_t = TokenScope::Deploy)]
+        scope: TokenScope,
+
+        /// Limits the token to the project
+        #[arg(long)]
+        project: Option<String>,
+    },
+    /// Lists your API tokens
+    List,
+    /// Revokes the API token
+    Revoke {
+        /// The name of the token
+        name: String,
+    },
+}
+
+impl Command for TokenCommand {
+    fn execute(&self) -> anyhow::Result<()> {
+        let terminal = Terminal::new();
+        let config = Config::default();
+        let session = get_session(&config)?.ok_or(anyhow::anyhow!(
+            "You are not logged in - Use \"noops login\""
+        ))?;
+        let token_client = TokenClient::new(&config.base_url, session);
+
+        match &self {
+            TokenCommand::Create {
+                name,
+                scope,
+                project,
+            } => {
+                let token = CreateTokenDTO {
+                    name: name.clone(),
+                    scope: *scope,
+                    project: project.clone(),
+                };
+                let created = token_client
+                    .create(&token)
+                    .context(format!("Creating token \"{}\" failed", name))?;
+                terminal.write_text(format!("{}\n", created.token))?;
+                terminal.write_text(
+                    "Store the token as NOOPS_TOKEN in your CI, it can't be shown again\n",
+                )?;
+            }
+            TokenCommand::List => {
+                let tokens = token_client.list()?;
+                if tokens.is_empty() {
+                    terminal.write_text("You have no API tokens\n")?;
+                }
+                for token in tokens {
+                    let last_used = token
+                        .last_used_at
+                        .map(|used| used.format("%Y-%m-%d %H:%M:%S UTC").to_string())
+                        .unwrap_or("never".to_string());
+                    terminal.write_text(format!(
+                        "{}\t{}\t{}\tlast used {}\n",
+                        token.name,
+                        token.scope,
+                        token.project.as_deref().unwrap_or("all projects"),
+                        last_used
+                    ))?;
+                }
+            }
+            TokenCommand::Revoke { name } => {
+                let text = format!("Revoking token {}", name);
+                let spinner = terminal.spinner(&text);
+                token_client
+                    .revoke(name)
+                    .context(format!("Revoking token \"{}\" failed", name))?;
+                spinner.finish_with_message(text);
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,29 @@
+use super::{deploy::get_session, Command};
+use crate::{config::Config, info, manifest::Manifest, terminal::Terminal};
+use clap::Parser;
+use client::handler::HandlerClient;
+use common::dtos::DEFAULT_STAGE;
+
+#[derive(Parser, Debug)]
+pub struct VersionsCommand {
+    /// The handler to list the versions of
+    pub name: String,
+
+    /// The stage of the handler
+    #[arg(long, default_value = DEFAULT_STAGE)]
+    pub stage: String,
+}
+
+impl Command for VersionsCommand {
+    fn execute(&self) -> anyhow::Result<()> {
+        let terminal = Terminal::new();
+        let config = Config::default();
+        let manifest = Manifest::from_yaml(&config.manifest)?;
+
+        let session = get_session(&config)?.ok_or(anyhow::anyhow!("You are not logged in"))?;
+        let handler_client = HandlerClient::new(&config.base_url, session, &self.stage);
+
+        info::show_versions(&self.name, &manifest, &handler_client, &terminal)?;
+        Ok(())
+    }
+}
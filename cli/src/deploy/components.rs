@@ -1,5 +1,8 @@
 use crate::manifest::Handler;
-use common::dtos::{CreateFunctionDTO, GetHandlerDTO, Language};
+use common::{
+    dtos::{CreateFunctionDTO, GetHandlerDTO, Language},
+    signature::{self, SigningKey},
+};
 use std::{fs, hash::Hash};
 
 #[derive(Debug, Clone, Default, Eq, PartialOrd, Ord)]
@@ -8,6 +11,17 @@ pub struct BuildedComponent {
     pub language: Language,
     pub hash: String,
     pub wasm: Option<Vec<u8>>,
+    pub signature: Option<String>,
+}
+
+impl BuildedComponent {
+    /// Signs the module with the key, if one is given
+    pub fn signed(self, key: Option<&SigningKey>) -> Self {
+        let signature = key
+            .zip(self.wasm.as_ref())
+            .map(|(key, wasm)| signature::sign(key, wasm));
+        Self { signature, ..self }
+    }
 }
 
 impl Hash for BuildedComponent {
@@ -34,6 +48,7 @@ impl TryFrom<Handler> for BuildedComponent {
             language: value.language,
             hash,
             wasm: Some(wasm),
+            signature: None,
         };
         Ok(component_with_payload)
     }
@@ -45,6 +60,8 @@ impl From<BuildedComponent> for CreateFunctionDTO {
             name: value.name,
             language: value.language,
             wasm: value.wasm.unwrap(),
+            canary: None,
+            signature: value.signature,
         }
     }
 }
@@ -56,6 +73,7 @@ impl From<GetHandlerDTO> for BuildedComponent {
             language: value.language,
             hash: value.hash,
             wasm: Default::default(),
+            signature: None,
         }
     }
 }
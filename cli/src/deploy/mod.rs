@@ -7,6 +7,16 @@ mod update;
 use self::{components::BuildedComponent, plan::DeployPlan};
 use crate::{manifest::Manifest, terminal::Terminal};
 use client::{handler::HandlerClient, project::ProjectClient};
+use common::signature::SigningKey;
+
+/// How the handlers are deployed
+#[derive(Default)]
+pub struct DeployOptions {
+    /// Deploys updated handlers as canary releases receiving this percentage of the traffic
+    pub canary: Option<u8>,
+    /// Signs the handlers, projects with trusted keys only accept signed handlers
+    pub signing_key: Option<SigningKey>,
+}
 
 trait DeployStep {
     fn deploy(&self, project: &str, client: &HandlerClient) -> anyhow::Result<()>;
@@ -15,12 +25,14 @@ trait DeployStep {
 pub fn deploy_project(
     terminal: &Terminal,
     manifest: Manifest,
+    stage: &str,
     project_client: &ProjectClient,
     handler_client: &HandlerClient,
+    options: &DeployOptions,
 ) -> anyhow::Result<()> {
     terminal.write_heading("Deploying project")?;
 
-    let project = manifest.project_name;
+    let project = manifest.project_name.clone();
     if !project_client.exists(&project)? {
         project_client.create(&project)?;
     };
@@ -31,17 +43,21 @@ pub fn deploy_project(
         .filter(|component| component.is_build())
         .cloned()
         .map(|component| BuildedComponent::try_from(component).unwrap())
+        .map(|component| component.signed(options.signing_key.as_ref()))
         .collect();
 
     let remote_handler: Vec<BuildedComponent> = project_client
         .get(&project)?
         .handlers
         .into_iter()
+        .filter(|handler| handler.stage == stage)
         .map(BuildedComponent::from)
         .collect();
 
-    let plan = DeployPlan::new(local_handlers, remote_handler);
-    prompt_deploy(&plan, terminal, handler_client, &project)?;
+    let plan = DeployPlan::new(local_handlers, remote_handler, options.canary);
+    if prompt_deploy(&plan, terminal, handler_client, &project)? {
+        deploy_variables(terminal, &manifest, stage, project_client)?;
+    }
 
     Ok(())
 }
@@ -50,8 +66,10 @@ pub fn deploy_handler(
     name: &str,
     terminal: &Terminal,
     manifest: Manifest,
+    stage: &str,
     project_client: &ProjectClient,
     handler_client: &HandlerClient,
+    options: &DeployOptions,
 ) -> anyhow::Result<()> {
     terminal.write_heading("Deploying handler")?;
 
@@ -60,24 +78,33 @@ pub fn deploy_handler(
         project_client.create(&project)?;
     };
 
-    let local_handler: BuildedComponent = manifest
-        .get(name)
-        .ok_or(anyhow::anyhow!("Handler not found"))?
-        .try_into()?;
+    let local_handler = BuildedComponent::try_from(
+        manifest
+            .get(name)
+            .ok_or(anyhow::anyhow!("Handler not found"))?,
+    )?
+    .signed(options.signing_key.as_ref());
 
-    let remote_handler: BuildedComponent = handler_client.read(&project, name)?.into();
+    let remote_handler: Vec<BuildedComponent> = handler_client
+        .read_opt(&project, name)?
+        .map(BuildedComponent::from)
+        .into_iter()
+        .collect();
 
-    let plan = DeployPlan::new(vec![local_handler], vec![remote_handler]);
-    prompt_deploy(&plan, terminal, handler_client, &project)?;
+    let plan = DeployPlan::new(vec![local_handler], remote_handler, options.canary);
+    if prompt_deploy(&plan, terminal, handler_client, &project)? {
+        deploy_variables(terminal, &manifest, stage, project_client)?;
+    }
     Ok(())
 }
 
+/// Returns false if the deployment has been aborted
 fn prompt_deploy(
     plan: &DeployPlan,
     terminal: &Terminal,
     handler_client: &HandlerClient,
     project: &str,
-) -> anyhow::Result<()> {
+) -> anyhow::Result<bool> {
     if plan.has_steps() {
         terminal.write_text(plan.to_string())?;
         let response = terminal.confirm_prompt("Deploy?")?;
@@ -85,9 +112,30 @@ fn prompt_deploy(
             plan.deploy(terminal, project, handler_client)?;
         } else {
             terminal.write_text("Aborting")?;
+            return Ok(false);
         }
     } else {
         terminal.write_text("Nothing to deploy")?;
     }
+    Ok(true)
+}
+
+fn deploy_variables(
+    terminal: &Terminal,
+    manifest: &Manifest,
+    stage: &str,
+    project_client: &ProjectClient,
+) -> anyhow::Result<()> {
+    let Some(local_stage) = manifest.stages.get(stage) else {
+        return Ok(());
+    };
+    if project_client.variables(&manifest.project_name, stage)? == local_stage.env {
+        return Ok(());
+    }
+
+    let message = format!("Updating variables of stage {}", stage);
+    let spinner = terminal.spinner(&message);
+    project_client.update_variables(&manifest.project_name, stage, &local_stage.env)?;
+    spinner.finish_with_message(message);
     Ok(())
 }
@@ -22,12 +22,13 @@ impl DeployPlan {
     pub fn new(
         local_handlers: Vec<BuildedComponent>,
         remote_handlers: Vec<BuildedComponent>,
+        canary: Option<u8>,
     ) -> Self {
         let local_handlers: HashSet<BuildedComponent> = HashSet::from_iter(local_handlers);
         let remote_handlers: HashSet<BuildedComponent> = HashSet::from_iter(remote_handlers);
 
         let create_steps = create::create_steps(&local_handlers, &remote_handlers);
-        let update_steps = update::update_steps(&local_handlers, &remote_handlers);
+        let update_steps = update::update_steps(&local_handlers, &remote_handlers, canary);
         let delete_steps = delete::delete_steps(&local_handlers, &remote_handlers);
 
         Self {
@@ -1,21 +1,30 @@
 use super::{BuildedComponent, DeployStep};
 use client::handler::HandlerClient;
+use common::dtos::CreateFunctionDTO;
 use console::style;
 use std::{collections::HashSet, fmt::Display};
 
+/// Updates a handler, optionally as a canary release with the given traffic weight
 #[derive(Debug, Clone, Default)]
-pub struct UpdateStep(pub BuildedComponent);
+pub struct UpdateStep(pub BuildedComponent, pub Option<u8>);
 
 impl DeployStep for UpdateStep {
     fn deploy(&self, project: &str, client: &HandlerClient) -> anyhow::Result<()> {
-        client.update(project, &self.0.clone().into())?;
+        let function = CreateFunctionDTO {
+            canary: self.1,
+            ..self.0.clone().into()
+        };
+        client.update(project, &function)?;
         Ok(())
     }
 }
 
 impl Display for UpdateStep {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let text = format!("\t~ {}", &self.0.name);
+        let text = match self.1 {
+            Some(weight) => format!("\t~ {} (canary {}%)", &self.0.name, weight),
+            None => format!("\t~ {}", &self.0.name),
+        };
         let text = style(text.as_str()).yellow();
         f.write_str(&text.to_string())
     }
@@ -24,6 +33,7 @@ impl Display for UpdateStep {
 pub fn update_steps(
     local_handlers: &HashSet<BuildedComponent>,
     remote_handlers: &HashSet<BuildedComponent>,
+    canary: Option<u8>,
 ) -> Vec<UpdateStep> {
     let mut local_updates: Vec<BuildedComponent> = local_handlers
         .intersection(remote_handlers)
@@ -37,10 +47,12 @@ pub fn update_steps(
         .collect();
     remote_updates.sort();
 
+    // Handlers deployed before the modules were hashed with SHA-256 never match and are
+    // deployed once more, which replaces their legacy hash
     local_updates
         .iter()
         .zip(remote_updates.iter())
         .filter(|(local_handler, remote_handler)| local_handler.hash != remote_handler.hash)
-        .map(|(local, _)| UpdateStep(local.clone()))
+        .map(|(local, _)| UpdateStep(local.clone(), canary))
         .collect()
 }
@@ -0,0 +1,44 @@
+use common::dtos::{GetCanaryDTO, VersionStatusCodesDTO};
+use std::fmt::Display;
+
+pub struct CanaryInformation(pub GetCanaryDTO);
+
+impl Display for CanaryInformation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!(
+            "Weight:\t\t{}%\n\nStable:\n{}\nCanary:\n{}",
+            self.0.weight,
+            VersionStatusCodes(&self.0.stable),
+            VersionStatusCodes(&self.0.canary)
+        ))
+    }
+}
+
+struct VersionStatusCodes<'a>(&'a VersionStatusCodesDTO);
+
+impl Display for VersionStatusCodes<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let total: i64 = self.0.status_codes.values().sum();
+        let errors: i64 = self
+            .0
+            .status_codes
+            .iter()
+            .filter(|(status_code, _)| **status_code >= 500)
+            .map(|(_, count)| count)
+            .sum();
+        let error_rate = if total > 0 {
+            errors as f64 / total as f64 * 100.0
+        } else {
+            0.0
+        };
+
+        f.write_fmt(format_args!(
+            "  Version:\t{}\n  Requests:\t{}\n  Error rate:\t{:.1}%\n",
+            self.0.number, total, error_rate
+        ))?;
+        for (status_code, count) in &self.0.status_codes {
+            f.write_fmt(format_args!("  {}:\t\t{}\n", status_code, count))?;
+        }
+        Ok(())
+    }
+}
@@ -8,15 +8,21 @@ pub struct ComponentInformation {
     pub deployed: bool,
     pub build: bool,
     pub link: String,
+    pub version: String,
+    pub digest: String,
 }
 
 impl ComponentInformation {
     pub fn new(local_component: &Handler, remote_component: Option<GetHandlerDTO>) -> Self {
         let deployed = remote_component.is_some();
-        let link = if let Some(remote_component) = remote_component {
-            remote_component.link
+        let (link, version, digest) = if let Some(remote_component) = remote_component {
+            (
+                remote_component.link,
+                remote_component.version.to_string(),
+                remote_component.digest.unwrap_or("N/A".to_string()),
+            )
         } else {
-            "N/A".to_string()
+            ("N/A".to_string(), "N/A".to_string(), "N/A".to_string())
         };
 
         ComponentInformation {
@@ -25,6 +31,8 @@ impl ComponentInformation {
             deployed,
             build: local_component.is_build(),
             link,
+            version,
+            digest,
         }
     }
 }
@@ -32,8 +40,14 @@ impl ComponentInformation {
 impl Display for ComponentInformation {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.write_fmt(format_args!(
-            "Name:\t\t{}\nLanguage:\t{}\nBuild:\t\t{}\nDeployed:\t{}\nLink:\t\t{}\n",
-            self.name, self.language, self.build, self.deployed, self.link
+            "Name:\t\t{}\nLanguage:\t{}\nBuild:\t\t{}\nDeployed:\t{}\nVersion:\t{}\nDigest:\t\t{}\nLink:\t\t{}\n",
+            self.name,
+            self.language,
+            self.build,
+            self.deployed,
+            self.version,
+            self.digest,
+            self.link
         ))
     }
 }
@@ -1,8 +1,14 @@
+mod canary;
 mod component;
 mod project;
+mod quota;
+mod version;
 
 use crate::{
-    info::{component::ComponentInformation, project::ProjectInformation},
+    info::{
+        canary::CanaryInformation, component::ComponentInformation, project::ProjectInformation,
+        quota::QuotaInformation, version::VersionInformation,
+    },
     manifest::Manifest,
     terminal::Terminal,
 };
@@ -11,6 +17,7 @@ use common::dtos::GetHandlerDTO;
 
 pub fn show_project(
     manifest: &Manifest,
+    stage: &str,
     project_client: &ProjectClient,
     terminal: &Terminal,
 ) -> anyhow::Result<()> {
@@ -18,7 +25,12 @@ pub fn show_project(
     let mut remote_components: Vec<GetHandlerDTO> = Default::default();
 
     if deployed {
-        remote_components = project_client.get(&manifest.project_name)?.handlers;
+        remote_components = project_client
+            .get(&manifest.project_name)?
+            .handlers
+            .into_iter()
+            .filter(|component| component.stage == stage)
+            .collect();
     }
     let local_components = manifest.handlers.clone();
 
@@ -36,6 +48,7 @@ pub fn show_project(
 
     let project_info = ProjectInformation::new(
         manifest.project_name.clone(),
+        stage.to_string(),
         deployed,
         component_information,
     );
@@ -43,6 +56,12 @@ pub fn show_project(
     terminal.write_heading("Showing Project")?;
     terminal.write_text(project_info.to_string())?;
 
+    if deployed {
+        let quotas = project_client.quotas(&manifest.project_name)?;
+        terminal.write_heading("Quotas")?;
+        terminal.write_text(QuotaInformation(quotas).to_string())?;
+    }
+
     Ok(())
 }
 
@@ -64,3 +83,39 @@ pub fn show_handler(
 
     Ok(())
 }
+
+pub fn show_versions(
+    name: &str,
+    manifest: &Manifest,
+    handler_client: &HandlerClient,
+    terminal: &Terminal,
+) -> anyhow::Result<()> {
+    manifest
+        .get(name)
+        .ok_or(anyhow::anyhow!("Handler not found"))?;
+    let versions = handler_client.versions(&manifest.project_name, name)?;
+
+    terminal.write_heading(format!("Versions of {}", name))?;
+    for version in versions {
+        terminal.write_text(format!("{}\n", VersionInformation(version)))?;
+    }
+
+    Ok(())
+}
+
+pub fn show_canary(
+    name: &str,
+    manifest: &Manifest,
+    handler_client: &HandlerClient,
+    terminal: &Terminal,
+) -> anyhow::Result<()> {
+    manifest
+        .get(name)
+        .ok_or(anyhow::anyhow!("Handler not found"))?;
+    let canary = handler_client.canary(&manifest.project_name, name)?;
+
+    terminal.write_heading(format!("Canary release of {}", name))?;
+    terminal.write_text(CanaryInformation(canary).to_string())?;
+
+    Ok(())
+}
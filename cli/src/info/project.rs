@@ -3,14 +3,21 @@ use std::fmt::Display;
 
 pub struct ProjectInformation {
     name: String,
+    stage: String,
     deployed: bool,
     components: Vec<ComponentInformation>,
 }
 
 impl ProjectInformation {
-    pub fn new(name: String, deployed: bool, components: Vec<ComponentInformation>) -> Self {
+    pub fn new(
+        name: String,
+        stage: String,
+        deployed: bool,
+        components: Vec<ComponentInformation>,
+    ) -> Self {
         Self {
             name,
+            stage,
             deployed,
             components,
         }
@@ -20,8 +27,9 @@ impl ProjectInformation {
 impl Display for ProjectInformation {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.write_fmt(format_args!(
-            "Name:\t\t{}\nDeployed:\t{}\nComponents:\t{}\n",
+            "Name:\t\t{}\nStage:\t\t{}\nDeployed:\t{}\nComponents:\t{}\n",
             self.name,
+            self.stage,
             self.deployed,
             self.components.len()
         ))?;
@@ -0,0 +1,35 @@
+use common::dtos::{GetQuotasDTO, QuotaDTO};
+use std::fmt::Display;
+
+pub struct QuotaInformation(pub GetQuotasDTO);
+
+impl Display for QuotaInformation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let quotas = &self.0;
+        f.write_fmt(format_args!(
+            "Projects:\t\t{}\nHandlers:\t\t{}\nStorage:\t\t{} MB\nInvocations today:\t{}\nMax. artifact size:\t{:.1} MB\n",
+            Usage(quotas.projects, 1),
+            Usage(quotas.handlers, 1),
+            Usage(quotas.storage, 1_000_000),
+            Usage(quotas.invocations, 1),
+            quotas.artifact_size as f64 / 1_000_000.0
+        ))
+    }
+}
+
+struct Usage(QuotaDTO, i64);
+
+impl Display for Usage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let Usage(quota, unit) = self;
+        if *unit == 1 {
+            f.write_fmt(format_args!("{} / {}", quota.used, quota.limit))
+        } else {
+            f.write_fmt(format_args!(
+                "{:.1} / {:.1}",
+                quota.used as f64 / *unit as f64,
+                quota.limit as f64 / *unit as f64
+            ))
+        }
+    }
+}
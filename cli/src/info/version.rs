@@ -0,0 +1,18 @@
+use common::dtos::GetHandlerVersionDTO;
+use std::fmt::Display;
+
+pub struct VersionInformation(pub GetHandlerVersionDTO);
+
+impl Display for VersionInformation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let version = &self.0;
+        f.write_fmt(format_args!(
+            "Version:\t{}{}\nDeployed at:\t{}\nDeployed by:\t{}\nHash:\t\t{}\n",
+            version.number,
+            if version.active { " (active)" } else { "" },
+            version.deployed_at.format("%Y-%m-%d %H:%M:%S UTC"),
+            version.deployed_by.as_deref().unwrap_or("deleted user"),
+            version.hash
+        ))
+    }
+}
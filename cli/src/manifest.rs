@@ -1,13 +1,25 @@
 use crate::{config::Config, template::Template};
 use common::dtos::Language;
 use serde::{Deserialize, Serialize};
-use std::path::{Path, PathBuf};
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
 
 #[derive(Serialize, Deserialize, Debug, Default)]
 pub struct Manifest {
     #[serde(rename = "project")]
     pub project_name: String,
     pub handlers: Vec<Handler>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub stages: BTreeMap<String, Stage>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct Stage {
+    /// Environment variables passed to the handlers of the stage
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, Default)]
@@ -0,0 +1,37 @@
+use crate::session::Session;
+use common::dtos;
+use reqwest::{blocking::Client as ReqwestClient, Url};
+
+pub struct AuditClient {
+    base_url: Url,
+    client: ReqwestClient,
+    session: Session,
+}
+
+impl AuditClient {
+    pub fn new(base_url: &str, session: Session) -> Self {
+        Self {
+            base_url: Url::parse(base_url).unwrap(),
+            client: ReqwestClient::new(),
+            session,
+        }
+    }
+
+    pub fn list(
+        &self,
+        filter: &dtos::AuditFilterDTO,
+    ) -> anyhow::Result<Vec<dtos::GetAuditEventDTO>> {
+        let url = self.base_url.join("audit")?;
+
+        let response = self.session.send(self.client.get(url).query(filter))?;
+
+        if !response.status().is_success() {
+            anyhow::bail!(
+                "Request failed with status code {}: {}",
+                response.status(),
+                response.text()?,
+            );
+        }
+        Ok(response.json()?)
+    }
+}
@@ -1,5 +1,5 @@
 use anyhow::Result;
-use common::dtos::GetJWTDTO;
+use common::dtos::{AuthProviderDTO, GetJWTDTO, RefreshTokenDTO};
 use oauth2::basic::BasicClient;
 use oauth2::reqwest::http_client;
 use oauth2::{
@@ -9,10 +9,8 @@ use oauth2::{
 use reqwest::{self, StatusCode};
 use reqwest::{blocking::Client as ReqwestClient, Url};
 
-const CLIENT_ID: &str = "213ab154663f83fa7e80";
-const DEVICE_AUTHORIZATION_URL: &str = "https://github.com/login/device/code";
-const AUTHORIZATION_URL: &str = "https://github.com/login/oauth/authorize";
-const TOKEN_URL: &str = "https://github.com/login/oauth/access_token";
+/// The identity provider of a server running with --dev-auth
+const DEV_PROVIDER: &str = "dev";
 
 pub struct AuthClient {
     url: Url,
@@ -22,18 +20,80 @@ pub struct AuthClient {
 impl AuthClient {
     pub fn new(base_url: &str) -> Self {
         Self {
-            url: Url::parse(base_url).unwrap().join("auth/login").unwrap(),
+            url: Url::parse(base_url).unwrap().join("auth/").unwrap(),
             client: ReqwestClient::new(),
         }
     }
 
-    pub fn login(&self) -> anyhow::Result<String> {
-        let gh_token = get_github_token()?;
+    /// Logs in with the identity provider of the server
+    pub fn login(&self) -> anyhow::Result<GetJWTDTO> {
+        let provider = self.provider()?;
+        if provider.name == DEV_PROVIDER {
+            anyhow::bail!("The server runs with --dev-auth, log in with --dev");
+        }
+        let access_token = get_access_token(&provider)?;
+        self.exchange(access_token.secret())
+    }
+
+    /// Logs in as the local user of a server running with --dev-auth, without an identity
+    /// provider
+    pub fn login_dev(&self) -> anyhow::Result<GetJWTDTO> {
+        if self.provider()?.name != DEV_PROVIDER {
+            anyhow::bail!("The server doesn't run with --dev-auth");
+        }
+        self.exchange(DEV_PROVIDER)
+    }
+
+    /// Exchanges the access token of the identity provider for the tokens of the server
+    fn exchange(&self, access_token: &str) -> anyhow::Result<GetJWTDTO> {
+        let mut url = self.url.join("login")?;
+        url.query_pairs_mut().append_pair("token", access_token);
+        let response = self.client.get(url).send()?;
+
+        if !response.status().is_success() {
+            anyhow::bail!(
+                "Request failed with status code {}: {}",
+                response.status(),
+                response.text()?,
+            );
+        }
+        Ok(response.json()?)
+    }
+
+    /// The identity provider of the server
+    pub fn provider(&self) -> anyhow::Result<AuthProviderDTO> {
+        let url = self.url.join("provider")?;
+        let response = self.client.get(url).send()?;
+
+        if !response.status().is_success() {
+            anyhow::bail!(
+                "Request failed with status code {}: {}",
+                response.status(),
+                response.text()?,
+            );
+        }
+        Ok(response.json()?)
+    }
+
+    /// Revokes the session of the refresh token on the server
+    pub fn logout(&self, refresh_token: &str) -> anyhow::Result<()> {
+        let url = self.url.join("logout")?;
+        let response = self
+            .client
+            .post(url)
+            .json(&RefreshTokenDTO {
+                refresh_token: refresh_token.to_string(),
+            })
+            .send()?;
 
-        let mut url = self.url.clone();
-        url.set_query(Some(&format!("token={}", gh_token.secret())));
-        let response: GetJWTDTO = self.client.get(url).send()?.json()?;
-        Ok(response.jwt)
+        if !response.status().is_success() {
+            anyhow::bail!(
+                "Request failed with status code {}: {}",
+                response.status(),
+                response.text()?,
+            );
+        }
+        Ok(())
     }
 }
 
@@ -50,29 +110,27 @@ fn custom_http_client(
     Ok(response)
 }
 
-fn get_github_token() -> anyhow::Result<AccessToken> {
-    let device_auth_url =
-        DeviceAuthorizationUrl::new(DEVICE_AUTHORIZATION_URL.to_string()).unwrap();
+/// Obtains an access token from the identity provider with the device flow
+fn get_access_token(provider: &AuthProviderDTO) -> anyhow::Result<AccessToken> {
+    let device_auth_url = DeviceAuthorizationUrl::new(provider.device_authorization_url.clone())?;
     let client = BasicClient::new(
-        ClientId::new(CLIENT_ID.to_string()),
+        ClientId::new(provider.client_id.clone()),
         None,
-        AuthUrl::new(AUTHORIZATION_URL.to_string()).unwrap(),
-        Some(TokenUrl::new(TOKEN_URL.to_string()).unwrap()),
+        AuthUrl::new(provider.authorization_url.clone())?,
+        Some(TokenUrl::new(provider.token_url.clone())?),
     )
     .set_device_authorization_url(device_auth_url);
 
     let details: StandardDeviceAuthorizationResponse = client
-        .exchange_device_code()
-        .unwrap()
-        .add_scope(Scope::new("read:user".to_string()))
-        .add_scope(Scope::new("user:email".to_string()))
+        .exchange_device_code()?
+        .add_scopes(provider.scopes.iter().cloned().map(Scope::new))
         .request(http_client)
-        .map_err(|err| err.to_string())
-        .unwrap();
+        .map_err(|err| anyhow::anyhow!("Requesting a device code failed: {}", err))?;
 
     let uri = details.verification_uri().to_string();
     println!(
-        "Open this URL in your browser:\n{}\nand enter the code: {}",
+        "Open this URL in your browser to log in with {}:\n{}\nand enter the code: {}",
+        provider.name,
         uri,
         details.user_code().secret()
     );
@@ -80,8 +138,7 @@ fn get_github_token() -> anyhow::Result<AccessToken> {
     let token_result = client
         .exchange_device_access_token(&details)
         .request(custom_http_client, std::thread::sleep, None)
-        .map_err(|err| err.to_string())
-        .unwrap();
+        .map_err(|err| anyhow::anyhow!("Logging in failed: {}", err))?;
 
     Ok(token_result.access_token().to_owned())
 }
@@ -1,30 +1,31 @@
-use common::dtos::{CreateFunctionDTO, GetHandlerDTO};
-use reqwest::{blocking::Client as ReqwestClient, header::AUTHORIZATION, StatusCode, Url};
+use crate::{project_segment, session::Session};
+use common::dtos::{
+    CreateFunctionDTO, GetCanaryDTO, GetHandlerDTO, GetHandlerVersionDTO, RollbackDTO,
+};
+use reqwest::{blocking::Client as ReqwestClient, StatusCode, Url};
 
+/// Manages the handlers of a single stage
 pub struct HandlerClient {
     base_url: Url,
     client: ReqwestClient,
-    jwt: String,
+    session: Session,
+    stage: String,
 }
 
 impl HandlerClient {
-    pub fn new(base_url: &str, jwt: String) -> Self {
+    pub fn new(base_url: &str, session: Session, stage: &str) -> Self {
         Self {
             base_url: Url::parse(base_url).unwrap(),
             client: ReqwestClient::new(),
-            jwt,
+            session,
+            stage: stage.to_string(),
         }
     }
 
     pub fn create(&self, project: &str, function: &CreateFunctionDTO) -> anyhow::Result<()> {
         let url = self.function_url(project, &function.name)?;
 
-        let response = self
-            .client
-            .put(url)
-            .header(AUTHORIZATION, format!("Bearer {}", self.jwt))
-            .json(function)
-            .send()?;
+        let response = self.session.send(self.client.put(url).json(function))?;
 
         if !response.status().is_success() {
             anyhow::bail!(
@@ -39,12 +40,7 @@ impl HandlerClient {
     pub fn read(&self, project: &str, function: &str) -> anyhow::Result<GetHandlerDTO> {
         let url = self.function_url(project, function)?;
 
-        let response = self
-            .client
-            .get(url)
-            .header(AUTHORIZATION, format!("Bearer {}", self.jwt))
-            .json(function)
-            .send()?;
+        let response = self.session.send(self.client.get(url).json(function))?;
 
         if !response.status().is_success() {
             anyhow::bail!(
@@ -59,11 +55,7 @@ impl HandlerClient {
     pub fn read_opt(&self, project: &str, function: &str) -> anyhow::Result<Option<GetHandlerDTO>> {
         let url = self.function_url(project, function)?;
 
-        let response = self
-            .client
-            .get(url)
-            .header(AUTHORIZATION, format!("Bearer {}", self.jwt))
-            .send()?;
+        let response = self.session.send(self.client.get(url))?;
 
         if !response.status().is_success() && response.status() != StatusCode::NOT_FOUND {
             anyhow::bail!(
@@ -83,11 +75,7 @@ impl HandlerClient {
     pub fn exists(&self, project: &str, function: &str) -> anyhow::Result<bool> {
         let url = self.function_url(project, function)?;
 
-        let response = self
-            .client
-            .get(url)
-            .header(AUTHORIZATION, format!("Bearer {}", self.jwt))
-            .send()?;
+        let response = self.session.send(self.client.get(url))?;
 
         if !response.status().is_success() && response.status() != StatusCode::NOT_FOUND {
             anyhow::bail!(
@@ -103,12 +91,7 @@ impl HandlerClient {
     pub fn update(&self, project: &str, function: &CreateFunctionDTO) -> anyhow::Result<()> {
         let url = self.function_url(project, &function.name)?;
 
-        let response = self
-            .client
-            .put(url)
-            .header(AUTHORIZATION, format!("Bearer {}", self.jwt))
-            .json(function)
-            .send()?;
+        let response = self.session.send(self.client.put(url).json(function))?;
 
         if !response.status().is_success() {
             anyhow::bail!(
@@ -123,11 +106,7 @@ impl HandlerClient {
     pub fn delete(&self, project: &str, function: &str) -> anyhow::Result<()> {
         let url = self.function_url(project, function)?;
 
-        let response = self
-            .client
-            .delete(url)
-            .header(AUTHORIZATION, format!("Bearer {}", self.jwt))
-            .send()?;
+        let response = self.session.send(self.client.delete(url))?;
 
         if !response.status().is_success() {
             anyhow::bail!(
@@ -139,11 +118,121 @@ impl HandlerClient {
         Ok(())
     }
 
+    pub fn versions(
+        &self,
+        project: &str,
+        function: &str,
+    ) -> anyhow::Result<Vec<GetHandlerVersionDTO>> {
+        let url = self.function_sub_url(project, function, "versions")?;
+
+        let response = self.session.send(self.client.get(url))?;
+
+        if !response.status().is_success() {
+            anyhow::bail!(
+                "Request failed with status code {}: {}",
+                response.status(),
+                response.text()?,
+            );
+        }
+        Ok(response.json()?)
+    }
+
+    pub fn rollback(
+        &self,
+        project: &str,
+        function: &str,
+        version: Option<i32>,
+    ) -> anyhow::Result<GetHandlerDTO> {
+        let url = self.function_sub_url(project, function, "rollback")?;
+
+        let response = self
+            .session
+            .send(self.client.post(url).json(&RollbackDTO { version }))?;
+
+        if !response.status().is_success() {
+            anyhow::bail!(
+                "Request failed with status code {}: {}",
+                response.status(),
+                response.text()?,
+            );
+        }
+        Ok(response.json()?)
+    }
+
+    pub fn restore(&self, project: &str, function: &str) -> anyhow::Result<GetHandlerDTO> {
+        let url = self.function_sub_url(project, function, "restore")?;
+
+        let response = self.session.send(self.client.post(url))?;
+
+        if !response.status().is_success() {
+            anyhow::bail!(
+                "Request failed with status code {}: {}",
+                response.status(),
+                response.text()?,
+            );
+        }
+        Ok(response.json()?)
+    }
+
+    pub fn canary(&self, project: &str, function: &str) -> anyhow::Result<GetCanaryDTO> {
+        let url = self.function_sub_url(project, function, "canary")?;
+
+        let response = self.session.send(self.client.get(url))?;
+
+        if !response.status().is_success() {
+            anyhow::bail!(
+                "Request failed with status code {}: {}",
+                response.status(),
+                response.text()?,
+            );
+        }
+        Ok(response.json()?)
+    }
+
+    pub fn promote_canary(&self, project: &str, function: &str) -> anyhow::Result<GetHandlerDTO> {
+        self.canary_action(project, function, "canary/promote")
+    }
+
+    pub fn abort_canary(&self, project: &str, function: &str) -> anyhow::Result<GetHandlerDTO> {
+        self.canary_action(project, function, "canary/abort")
+    }
+
+    fn canary_action(
+        &self,
+        project: &str,
+        function: &str,
+        action: &str,
+    ) -> anyhow::Result<GetHandlerDTO> {
+        let url = self.function_sub_url(project, function, action)?;
+
+        let response = self.session.send(self.client.post(url))?;
+
+        if !response.status().is_success() {
+            anyhow::bail!(
+                "Request failed with status code {}: {}",
+                response.status(),
+                response.text()?,
+            );
+        }
+        Ok(response.json()?)
+    }
+
     fn function_url(&self, project: &str, function: &str) -> anyhow::Result<Url> {
-        let url = self
+        let mut url = self
             .base_url
-            .join(&(project.to_string() + "/"))?
+            .join(&(project_segment(project) + "/"))?
             .join(function)?;
+        url.query_pairs_mut().append_pair("stage", &self.stage);
+        Ok(url)
+    }
+
+    fn function_sub_url(&self, project: &str, function: &str, path: &str) -> anyhow::Result<Url> {
+        let mut url = self
+            .base_url
+            .join(&(project_segment(project) + "/"))?
+            .join(&(function.to_string() + "/"))?
+            .join(path)?;
+        url.query_pairs_mut().append_pair("stage", &self.stage);
         Ok(url)
     }
 }
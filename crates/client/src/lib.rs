@@ -1,3 +1,13 @@
+pub mod audit;
 pub mod auth;
 pub mod handler;
+pub mod org;
 pub mod project;
+pub mod session;
+pub mod token;
+
+/// The project name as a segment of a path. Projects of organizations are named like
+/// `acme/shop`, so the slash is encoded.
+pub(crate) fn project_segment(name: &str) -> String {
+    name.replace('/', "%2F")
+}
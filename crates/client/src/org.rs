@@ -0,0 +1,115 @@
+use crate::session::Session;
+use common::dtos;
+use reqwest::{blocking::Client as ReqwestClient, Url};
+
+pub struct OrgClient {
+    base_url: Url,
+    client: ReqwestClient,
+    session: Session,
+}
+
+impl OrgClient {
+    pub fn new(base_url: &str, session: Session) -> Self {
+        Self {
+            base_url: Url::parse(base_url).unwrap(),
+            client: ReqwestClient::new(),
+            session,
+        }
+    }
+
+    pub fn create(&self, name: &str) -> anyhow::Result<()> {
+        let url = self.base_url.join("orgs")?;
+        let org = dtos::CreateOrgDTO {
+            name: name.to_string(),
+        };
+
+        let response = self.session.send(self.client.post(url).json(&org))?;
+
+        if !response.status().is_success() {
+            anyhow::bail!(
+                "Request failed with status code {}: {}",
+                response.status(),
+                response.text()?,
+            );
+        }
+        Ok(())
+    }
+
+    pub fn list(&self) -> anyhow::Result<Vec<dtos::GetOrgDTO>> {
+        let url = self.base_url.join("orgs")?;
+
+        let response = self.session.send(self.client.get(url))?;
+
+        if !response.status().is_success() {
+            anyhow::bail!(
+                "Request failed with status code {}: {}",
+                response.status(),
+                response.text()?,
+            );
+        }
+        Ok(response.json()?)
+    }
+
+    pub fn members(&self, org: &str) -> anyhow::Result<Vec<dtos::GetMemberDTO>> {
+        let url = self.members_url(org)?;
+
+        let response = self.session.send(self.client.get(url))?;
+
+        if !response.status().is_success() {
+            anyhow::bail!(
+                "Request failed with status code {}: {}",
+                response.status(),
+                response.text()?,
+            );
+        }
+        Ok(response.json()?)
+    }
+
+    /// Adds the user to the organization or changes their role
+    pub fn set_member(&self, org: &str, login: &str, role: dtos::Role) -> anyhow::Result<()> {
+        let url = self.member_url(org, login)?;
+
+        let response = self
+            .session
+            .send(self.client.put(url).json(&dtos::SetMemberDTO { role }))?;
+
+        if !response.status().is_success() {
+            anyhow::bail!(
+                "Request failed with status code {}: {}",
+                response.status(),
+                response.text()?,
+            );
+        }
+        Ok(())
+    }
+
+    pub fn remove_member(&self, org: &str, login: &str) -> anyhow::Result<()> {
+        let url = self.member_url(org, login)?;
+
+        let response = self.session.send(self.client.delete(url))?;
+
+        if !response.status().is_success() {
+            anyhow::bail!(
+                "Request failed with status code {}: {}",
+                response.status(),
+                response.text()?,
+            );
+        }
+        Ok(())
+    }
+
+    fn members_url(&self, org: &str) -> anyhow::Result<Url> {
+        Ok(self.base_url.join(&format!("orgs/{}/members/", org))?)
+    }
+
+    /// The login may be qualified with the identity provider, like `github:alice`, whose
+    /// issuer URL contains slashes, so it is encoded as a single segment
+    fn member_url(&self, org: &str, login: &str) -> anyhow::Result<Url> {
+        let mut url = self.members_url(org)?;
+        url.path_segments_mut()
+            .map_err(|_| anyhow::anyhow!("Invalid base URL"))?
+            .pop_if_empty()
+            .push(login);
+        Ok(url)
+    }
+}
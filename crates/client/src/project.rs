@@ -1,29 +1,26 @@
+use crate::{project_segment, session::Session};
 use common::dtos;
-use reqwest::{blocking::Client as ReqwestClient, header::AUTHORIZATION, StatusCode, Url};
+use reqwest::{blocking::Client as ReqwestClient, StatusCode, Url};
 
 pub struct ProjectClient {
     base_url: Url,
     client: ReqwestClient,
-    jwt: String,
+    session: Session,
 }
 
 impl ProjectClient {
-    pub fn new(base_url: &str, jwt: String) -> Self {
+    pub fn new(base_url: &str, session: Session) -> Self {
         Self {
             base_url: Url::parse(base_url).unwrap(),
             client: ReqwestClient::new(),
-            jwt,
+            session,
         }
     }
 
     pub fn create(&self, name: &str) -> anyhow::Result<()> {
         let url = self.project_url(name)?;
 
-        let response = self
-            .client
-            .post(url)
-            .header(AUTHORIZATION, format!("Bearer {}", self.jwt))
-            .send()?;
+        let response = self.session.send(self.client.post(url))?;
 
         if !response.status().is_success() {
             anyhow::bail!(
@@ -38,11 +35,7 @@ impl ProjectClient {
     pub fn get(&self, name: &str) -> anyhow::Result<dtos::GetProjectDTO> {
         let url = self.project_url(name)?;
 
-        let response = self
-            .client
-            .get(url)
-            .header(AUTHORIZATION, format!("Bearer {}", self.jwt))
-            .send()?;
+        let response = self.session.send(self.client.get(url))?;
 
         if !response.status().is_success() {
             anyhow::bail!(
@@ -54,14 +47,10 @@ impl ProjectClient {
         Ok(response.json()?)
     }
 
-    pub fn _delete(&self, name: &str) -> anyhow::Result<()> {
+    pub fn delete(&self, name: &str) -> anyhow::Result<()> {
         let url = self.project_url(name)?;
 
-        let response = self
-            .client
-            .delete(url)
-            .header(AUTHORIZATION, format!("Bearer {}", self.jwt))
-            .send()?;
+        let response = self.session.send(self.client.delete(url))?;
 
         if !response.status().is_success() {
             anyhow::bail!(
@@ -73,14 +62,92 @@ impl ProjectClient {
         Ok(())
     }
 
+    pub fn restore(&self, name: &str) -> anyhow::Result<dtos::GetProjectDTO> {
+        let url = self
+            .base_url
+            .join(&format!("{}/restore", project_segment(name)))?;
+
+        let response = self.session.send(self.client.post(url))?;
+
+        if !response.status().is_success() {
+            anyhow::bail!(
+                "Request failed with status code {}: {}",
+                response.status(),
+                response.text()?,
+            );
+        }
+        Ok(response.json()?)
+    }
+
+    /// The usage of the quotas of the user, with the handlers of the project
+    pub fn quotas(&self, name: &str) -> anyhow::Result<dtos::GetQuotasDTO> {
+        let url = self
+            .base_url
+            .join(&format!("{}/quotas", project_segment(name)))?;
+
+        let response = self.session.send(self.client.get(url))?;
+
+        if !response.status().is_success() {
+            anyhow::bail!(
+                "Request failed with status code {}: {}",
+                response.status(),
+                response.text()?,
+            );
+        }
+        Ok(response.json()?)
+    }
+
+    /// Downloads the archive of the project
+    pub fn export(&self, name: &str) -> anyhow::Result<Vec<u8>> {
+        let url = self
+            .base_url
+            .join(&format!("{}/export", project_segment(name)))?;
+
+        let response = self.session.send(self.client.get(url))?;
+
+        if !response.status().is_success() {
+            anyhow::bail!(
+                "Request failed with status code {}: {}",
+                response.status(),
+                response.text()?,
+            );
+        }
+        Ok(response.bytes()?.to_vec())
+    }
+
+    /// Creates a project from an archive, named like the exported project unless a name is given
+    pub fn import(
+        &self,
+        archive: Vec<u8>,
+        name: Option<&str>,
+        signature: Option<String>,
+    ) -> anyhow::Result<dtos::GetProjectDTO> {
+        let url = self.base_url.join("import")?;
+
+        let response = self.session.send(
+            self.client
+                .post(url)
+                .query(&dtos::ImportDTO {
+                    project: name.map(str::to_string),
+                    signature,
+                })
+                .body(archive),
+        )?;
+
+        if !response.status().is_success() {
+            anyhow::bail!(
+                "Request failed with status code {}: {}",
+                response.status(),
+                response.text()?,
+            );
+        }
+        Ok(response.json()?)
+    }
+
     pub fn exists(&self, name: &str) -> anyhow::Result<bool> {
         let url = self.project_url(name)?;
 
-        let response = self
-            .client
-            .get(url)
-            .header(AUTHORIZATION, format!("Bearer {}", self.jwt))
-            .send()?;
+        let response = self.session.send(self.client.get(url))?;
 
         if !response.status().is_success() && response.status() != StatusCode::NOT_FOUND {
             anyhow::bail!(
@@ -93,7 +160,128 @@ impl ProjectClient {
         Ok(response.status().is_success() && response.status() != StatusCode::NOT_FOUND)
     }
 
+    pub fn variables(&self, name: &str, stage: &str) -> anyhow::Result<dtos::StageVariablesDTO> {
+        let url = self.stage_url(name, stage, "variables")?;
+
+        let response = self.session.send(self.client.get(url))?;
+
+        if !response.status().is_success() {
+            anyhow::bail!(
+                "Request failed with status code {}: {}",
+                response.status(),
+                response.text()?,
+            );
+        }
+        Ok(response.json()?)
+    }
+
+    pub fn update_variables(
+        &self,
+        name: &str,
+        stage: &str,
+        variables: &dtos::StageVariablesDTO,
+    ) -> anyhow::Result<()> {
+        let url = self.stage_url(name, stage, "variables")?;
+
+        let response = self.session.send(self.client.put(url).json(variables))?;
+
+        if !response.status().is_success() {
+            anyhow::bail!(
+                "Request failed with status code {}: {}",
+                response.status(),
+                response.text()?,
+            );
+        }
+        Ok(())
+    }
+
+    pub fn promote(
+        &self,
+        name: &str,
+        source: &str,
+        target: &str,
+    ) -> anyhow::Result<Vec<dtos::GetHandlerDTO>> {
+        let url = self.stage_url(name, source, "promote")?;
+
+        let response = self
+            .session
+            .send(self.client.post(url).json(&dtos::PromoteDTO {
+                target: target.to_string(),
+            }))?;
+
+        if !response.status().is_success() {
+            anyhow::bail!(
+                "Request failed with status code {}: {}",
+                response.status(),
+                response.text()?,
+            );
+        }
+        Ok(response.json()?)
+    }
+
+    pub fn trusted_keys(&self, name: &str) -> anyhow::Result<Vec<dtos::GetTrustedKeyDTO>> {
+        let url = self.keys_url(name)?;
+
+        let response = self.session.send(self.client.get(url))?;
+
+        if !response.status().is_success() {
+            anyhow::bail!(
+                "Request failed with status code {}: {}",
+                response.status(),
+                response.text()?,
+            );
+        }
+        Ok(response.json()?)
+    }
+
+    pub fn add_trusted_key(
+        &self,
+        name: &str,
+        key: &dtos::CreateTrustedKeyDTO,
+    ) -> anyhow::Result<()> {
+        let url = self.keys_url(name)?;
+
+        let response = self.session.send(self.client.post(url).json(key))?;
+
+        if !response.status().is_success() {
+            anyhow::bail!(
+                "Request failed with status code {}: {}",
+                response.status(),
+                response.text()?,
+            );
+        }
+        Ok(())
+    }
+
+    pub fn remove_trusted_key(&self, name: &str, key_name: &str) -> anyhow::Result<()> {
+        let url = self.keys_url(name)?.join(key_name)?;
+
+        let response = self.session.send(self.client.delete(url))?;
+
+        if !response.status().is_success() {
+            anyhow::bail!(
+                "Request failed with status code {}: {}",
+                response.status(),
+                response.text()?,
+            );
+        }
+        Ok(())
+    }
+
     fn project_url(&self, name: &str) -> anyhow::Result<Url> {
-        Ok(self.base_url.join(name)?)
+        Ok(self.base_url.join(&project_segment(name))?)
+    }
+
+    fn keys_url(&self, name: &str) -> anyhow::Result<Url> {
+        Ok(self
+            .base_url
+            .join(&format!("{}/keys/", project_segment(name)))?)
+    }
+
+    fn stage_url(&self, name: &str, stage: &str, path: &str) -> anyhow::Result<Url> {
+        Ok(self
+            .base_url
+            .join(&format!("{}/stages/{}/", project_segment(name), stage))?
+            .join(path)?)
     }
 }
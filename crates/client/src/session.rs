@@ -0,0 +1,83 @@
+use common::dtos::{GetJWTDTO, RefreshTokenDTO};
+use reqwest::{
+    blocking::{Client as ReqwestClient, RequestBuilder, Response},
+    StatusCode, Url,
+};
+use std::sync::{Arc, Mutex};
+
+const TOKEN_EXPIRED: &str = "Token expired";
+
+type RefreshCallback = dyn Fn(&GetJWTDTO) -> anyhow::Result<()> + Send + Sync;
+
+/// The tokens of the logged in user, shared by the clients. A request rejected because the
+/// access token expired is sent again once with tokens exchanged for the refresh token.
+#[derive(Clone)]
+pub struct Session {
+    refresh_url: Url,
+    client: ReqwestClient,
+    tokens: Arc<Mutex<GetJWTDTO>>,
+    on_refresh: Arc<RefreshCallback>,
+}
+
+impl Session {
+    pub fn new(base_url: &str, tokens: GetJWTDTO) -> Self {
+        Self {
+            refresh_url: Url::parse(base_url).unwrap().join("auth/refresh").unwrap(),
+            client: ReqwestClient::new(),
+            tokens: Arc::new(Mutex::new(tokens)),
+            on_refresh: Arc::new(|_| Ok(())),
+        }
+    }
+
+    /// Called with the new tokens after every refresh, e.g. to store them
+    pub fn on_refresh(
+        self,
+        on_refresh: impl Fn(&GetJWTDTO) -> anyhow::Result<()> + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            on_refresh: Arc::new(on_refresh),
+            ..self
+        }
+    }
+
+    pub fn jwt(&self) -> String {
+        self.tokens.lock().unwrap().jwt.clone()
+    }
+
+    /// Sends the request with the access token, refreshing the tokens if it expired
+    pub fn send(&self, request: RequestBuilder) -> anyhow::Result<Response> {
+        let retry = request.try_clone();
+        let response = request.bearer_auth(self.jwt()).send()?;
+        let Some(retry) = retry else {
+            return Ok(response);
+        };
+        if response.status() != StatusCode::UNAUTHORIZED {
+            return Ok(response);
+        }
+
+        let status = response.status();
+        let body = response.text()?;
+        if !body.contains(TOKEN_EXPIRED) {
+            anyhow::bail!("Request failed with status code {}: {}", status, body);
+        }
+        self.refresh()?;
+        Ok(retry.bearer_auth(self.jwt()).send()?)
+    }
+
+    fn refresh(&self) -> anyhow::Result<()> {
+        let mut tokens = self.tokens.lock().unwrap();
+        let response = self
+            .client
+            .post(self.refresh_url.clone())
+            .json(&RefreshTokenDTO {
+                refresh_token: tokens.refresh_token.clone(),
+            })
+            .send()?;
+
+        if !response.status().is_success() {
+            anyhow::bail!("Your login expired - Use \"noops login\"");
+        }
+        *tokens = response.json()?;
+        (self.on_refresh)(&tokens)
+    }
+}
@@ -0,0 +1,64 @@
+use crate::session::Session;
+use common::dtos;
+use reqwest::{blocking::Client as ReqwestClient, Url};
+
+pub struct TokenClient {
+    base_url: Url,
+    client: ReqwestClient,
+    session: Session,
+}
+
+impl TokenClient {
+    pub fn new(base_url: &str, session: Session) -> Self {
+        Self {
+            base_url: Url::parse(base_url).unwrap(),
+            client: ReqwestClient::new(),
+            session,
+        }
+    }
+
+    pub fn create(&self, token: &dtos::CreateTokenDTO) -> anyhow::Result<dtos::CreatedTokenDTO> {
+        let url = self.base_url.join("tokens")?;
+
+        let response = self.session.send(self.client.post(url).json(token))?;
+
+        if !response.status().is_success() {
+            anyhow::bail!(
+                "Request failed with status code {}: {}",
+                response.status(),
+                response.text()?,
+            );
+        }
+        Ok(response.json()?)
+    }
+
+    pub fn list(&self) -> anyhow::Result<Vec<dtos::GetTokenDTO>> {
+        let url = self.base_url.join("tokens")?;
+
+        let response = self.session.send(self.client.get(url))?;
+
+        if !response.status().is_success() {
+            anyhow::bail!(
+                "Request failed with status code {}: {}",
+                response.status(),
+                response.text()?,
+            );
+        }
+        Ok(response.json()?)
+    }
+
+    pub fn revoke(&self, name: &str) -> anyhow::Result<()> {
+        let url = self.base_url.join("tokens/")?.join(name)?;
+
+        let response = self.session.send(self.client.delete(url))?;
+
+        if !response.status().is_success() {
+            anyhow::bail!(
+                "Request failed with status code {}: {}",
+                response.status(),
+                response.text()?,
+            );
+        }
+        Ok(())
+    }
+}
@@ -1,11 +1,18 @@
+use chrono::NaiveDateTime;
 use diesel::backend::Backend;
 use diesel::{deserialize::FromSql, sql_types::Text};
 use diesel::{
-    serialize::{IsNull, Output, ToSql},
+    serialize::{Output, ToSql},
     *,
 };
 use serde::{Deserialize, Serialize};
-use std::fmt::Display;
+use std::{collections::BTreeMap, fmt::Display, str::FromStr};
+
+/// The stage used when no stage is given
+pub const DEFAULT_STAGE: &str = "prod";
+
+/// Environment variables of a stage by name
+pub type StageVariablesDTO = BTreeMap<String, String>;
 
 #[derive(
     AsExpression,
@@ -31,16 +38,32 @@ pub enum Language {
     Golang,
 }
 
-impl ToSql<Text, sqlite::Sqlite> for Language {
-    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, sqlite::Sqlite>) -> serialize::Result {
-        out.set_value(self.to_string());
-        Ok(IsNull::No)
+impl Language {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Language::Rust => "Rust",
+            Language::Golang => "Golang",
+        }
     }
 }
 
-impl FromSql<Text, sqlite::Sqlite> for Language {
-    fn from_sql(bytes: <sqlite::Sqlite as Backend>::RawValue<'_>) -> deserialize::Result<Self> {
-        let value = <String as FromSql<Text, sqlite::Sqlite>>::from_sql(bytes)?;
+impl<DB> ToSql<Text, DB> for Language
+where
+    DB: Backend,
+    str: ToSql<Text, DB>,
+{
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, DB>) -> serialize::Result {
+        self.as_str().to_sql(out)
+    }
+}
+
+impl<DB> FromSql<Text, DB> for Language
+where
+    DB: Backend,
+    String: FromSql<Text, DB>,
+{
+    fn from_sql(bytes: DB::RawValue<'_>) -> deserialize::Result<Self> {
+        let value = <String as FromSql<Text, DB>>::from_sql(bytes)?;
 
         match value.as_str() {
             "Rust" => Ok(Language::Rust),
@@ -55,6 +78,13 @@ pub struct CreateFunctionDTO {
     pub name: String,
     pub language: Language,
     pub wasm: Vec<u8>,
+    /// Deploys the handler as a canary release receiving this percentage of the traffic
+    #[serde(default)]
+    pub canary: Option<u8>,
+    /// The hex encoded Ed25519 signature of the module. Required once the project has trusted
+    /// keys.
+    #[serde(default)]
+    pub signature: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Default)]
@@ -68,21 +98,500 @@ pub struct GetHandlerDTO {
     pub name: String,
     pub language: Language,
     pub hash: String,
+    /// The SHA-256 digest of the deployed module as `sha256:<hex>`. Missing for handlers
+    /// deployed before the modules were hashed with SHA-256.
+    #[serde(default)]
+    pub digest: Option<String>,
     pub link: String,
+    pub version: i32,
+    pub canary: Option<CanaryDTO>,
+    pub stage: String,
+}
+
+#[derive(Serialize, Deserialize, Eq, PartialEq, Debug, Clone, Default, Hash, PartialOrd, Ord)]
+pub struct CanaryDTO {
+    pub version: i32,
+    pub weight: u8,
+}
+
+#[derive(Serialize, Deserialize, Eq, PartialEq, Debug, Clone, Default)]
+pub struct GetCanaryDTO {
+    pub weight: u8,
+    pub stable: VersionStatusCodesDTO,
+    pub canary: VersionStatusCodesDTO,
+}
+
+#[derive(Serialize, Deserialize, Eq, PartialEq, Debug, Clone, Default)]
+pub struct VersionStatusCodesDTO {
+    pub number: i32,
+    pub status_codes: BTreeMap<u16, i64>,
+}
+
+#[derive(Serialize, Deserialize, Eq, PartialEq, Debug, Clone, Default)]
+pub struct GetHandlerVersionDTO {
+    pub number: i32,
+    pub hash: String,
+    /// The login of the user who deployed the version, none once they are deleted
+    pub deployed_by: Option<String>,
+    pub deployed_at: NaiveDateTime,
+    pub active: bool,
+}
+
+/// A public key whose signatures the project accepts
+#[derive(Serialize, Deserialize, Eq, PartialEq, Debug, Clone, Default)]
+pub struct CreateTrustedKeyDTO {
+    pub name: String,
+    /// The hex encoded Ed25519 public key
+    pub public_key: String,
+}
+
+#[derive(Serialize, Deserialize, Eq, PartialEq, Debug, Clone, Default)]
+pub struct GetTrustedKeyDTO {
+    pub name: String,
+    pub public_key: String,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Default)]
+pub struct RollbackDTO {
+    pub version: Option<i32>,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+pub struct StageDTO {
+    #[serde(default = "default_stage")]
+    pub stage: String,
+}
+
+impl Default for StageDTO {
+    fn default() -> Self {
+        Self {
+            stage: default_stage(),
+        }
+    }
+}
+
+fn default_stage() -> String {
+    DEFAULT_STAGE.to_string()
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Default)]
+pub struct PromoteDTO {
+    pub target: String,
+}
+
+/// The usage of a quota and its limit
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Copy, Default)]
+pub struct QuotaDTO {
+    pub used: i64,
+    pub limit: i64,
+}
+
+/// The quotas of the user, the handlers are counted in the requested project
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Default)]
+pub struct GetQuotasDTO {
+    pub projects: QuotaDTO,
+    pub handlers: QuotaDTO,
+    /// The largest module in bytes that can be deployed
+    pub artifact_size: i64,
+    /// The bytes of the components of all versions
+    pub storage: QuotaDTO,
+    /// The invocations of all handlers of the user today (UTC)
+    pub invocations: QuotaDTO,
+}
+
+/// The query of an import, without a project name the name stored in the archive is used
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Default)]
+pub struct ImportDTO {
+    pub project: Option<String>,
+    /// The hex encoded Ed25519 signature of the archive. Required if the archive has trusted
+    /// keys, by one of them.
+    pub signature: Option<String>,
 }
 
 impl Display for Language {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// What an API token permits
+#[derive(
+    AsExpression, FromSqlRow, Serialize, Deserialize, PartialEq, Debug, Clone, Copy, Default, Eq,
+)]
+#[diesel(sql_type = Text)]
+#[serde(rename_all = "lowercase")]
+pub enum TokenScope {
+    /// Reading projects and handlers, but not the variables of stages
+    Read,
+    /// Reading, deploying, promoting and rolling back, but not deleting, restoring, managing
+    /// keys or variables, exporting or importing
+    #[default]
+    Deploy,
+    /// Everything except managing tokens and organizations
+    Full,
+}
+
+impl TokenScope {
+    pub fn as_str(&self) -> &'static str {
         match self {
-            Language::Rust => f.write_str("Rust"),
-            Language::Golang => f.write_str("Golang"),
+            TokenScope::Read => "read",
+            TokenScope::Deploy => "deploy",
+            TokenScope::Full => "full",
+        }
+    }
+}
+
+impl Display for TokenScope {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for TokenScope {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "read" => Ok(TokenScope::Read),
+            "deploy" => Ok(TokenScope::Deploy),
+            "full" => Ok(TokenScope::Full),
+            _ => Err(format!(
+                "Invalid scope \"{}\", expected read, deploy or full",
+                value
+            )),
         }
     }
 }
 
+impl<DB> ToSql<Text, DB> for TokenScope
+where
+    DB: Backend,
+    str: ToSql<Text, DB>,
+{
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, DB>) -> serialize::Result {
+        self.as_str().to_sql(out)
+    }
+}
+
+impl<DB> FromSql<Text, DB> for TokenScope
+where
+    DB: Backend,
+    String: FromSql<Text, DB>,
+{
+    fn from_sql(bytes: DB::RawValue<'_>) -> deserialize::Result<Self> {
+        let value = <String as FromSql<Text, DB>>::from_sql(bytes)?;
+        Ok(value.parse()?)
+    }
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Default)]
+pub struct CreateTokenDTO {
+    pub name: String,
+    pub scope: TokenScope,
+    /// Limits the token to the project
+    pub project: Option<String>,
+}
+
+/// A created API token, the server only returns the secret once
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Default)]
+pub struct CreatedTokenDTO {
+    pub name: String,
+    pub token: String,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Default)]
+pub struct GetTokenDTO {
+    pub name: String,
+    pub scope: TokenScope,
+    pub project: Option<String>,
+    pub created_at: NaiveDateTime,
+    pub last_used_at: Option<NaiveDateTime>,
+}
+
+/// The role of a member of an organization, each role may do everything the roles before it
+/// may
+#[derive(
+    AsExpression,
+    FromSqlRow,
+    Serialize,
+    Deserialize,
+    PartialEq,
+    Debug,
+    Clone,
+    Copy,
+    Default,
+    Eq,
+    PartialOrd,
+    Ord,
+)]
+#[diesel(sql_type = Text)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    /// Reading projects and handlers
+    #[default]
+    Viewer,
+    /// Deploying, rolling back and promoting handlers and changing stage variables
+    Maintainer,
+    /// Creating and deleting projects, trusting keys, exporting and managing members
+    Owner,
+}
+
+impl Role {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Role::Viewer => "viewer",
+            Role::Maintainer => "maintainer",
+            Role::Owner => "owner",
+        }
+    }
+}
+
+impl Display for Role {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Role {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "viewer" => Ok(Role::Viewer),
+            "maintainer" => Ok(Role::Maintainer),
+            "owner" => Ok(Role::Owner),
+            _ => Err(format!(
+                "Invalid role \"{}\", expected viewer, maintainer or owner",
+                value
+            )),
+        }
+    }
+}
+
+impl<DB> ToSql<Text, DB> for Role
+where
+    DB: Backend,
+    str: ToSql<Text, DB>,
+{
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, DB>) -> serialize::Result {
+        self.as_str().to_sql(out)
+    }
+}
+
+impl<DB> FromSql<Text, DB> for Role
+where
+    DB: Backend,
+    String: FromSql<Text, DB>,
+{
+    fn from_sql(bytes: DB::RawValue<'_>) -> deserialize::Result<Self> {
+        let value = <String as FromSql<Text, DB>>::from_sql(bytes)?;
+        Ok(value.parse()?)
+    }
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Default)]
+pub struct CreateOrgDTO {
+    pub name: String,
+}
+
+/// An organization of the user with the role of the user in it
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Default)]
+pub struct GetOrgDTO {
+    pub name: String,
+    pub role: Role,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Default)]
+pub struct GetMemberDTO {
+    pub login: String,
+    /// The identity provider the member logs in with
+    pub provider: String,
+    pub role: Role,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Default)]
+pub struct SetMemberDTO {
+    pub role: Role,
+}
+
+/// A management action recorded in the audit log
+#[derive(AsExpression, FromSqlRow, Serialize, Deserialize, PartialEq, Debug, Clone, Copy, Eq)]
+#[diesel(sql_type = Text)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditAction {
+    Login,
+    Logout,
+    ProjectCreate,
+    ProjectDelete,
+    ProjectRestore,
+    ProjectImport,
+    ProjectExport,
+    VariablesUpdate,
+    KeyAdd,
+    KeyRemove,
+    HandlerDeploy,
+    HandlerDelete,
+    HandlerRestore,
+    HandlerRollback,
+    CanaryPromote,
+    CanaryAbort,
+    StagePromote,
+    TokenCreate,
+    TokenRevoke,
+    OrgCreate,
+    MemberSet,
+    MemberRemove,
+}
+
+impl AuditAction {
+    pub const ALL: [AuditAction; 22] = [
+        AuditAction::Login,
+        AuditAction::Logout,
+        AuditAction::ProjectCreate,
+        AuditAction::ProjectDelete,
+        AuditAction::ProjectRestore,
+        AuditAction::ProjectImport,
+        AuditAction::ProjectExport,
+        AuditAction::VariablesUpdate,
+        AuditAction::KeyAdd,
+        AuditAction::KeyRemove,
+        AuditAction::HandlerDeploy,
+        AuditAction::HandlerDelete,
+        AuditAction::HandlerRestore,
+        AuditAction::HandlerRollback,
+        AuditAction::CanaryPromote,
+        AuditAction::CanaryAbort,
+        AuditAction::StagePromote,
+        AuditAction::TokenCreate,
+        AuditAction::TokenRevoke,
+        AuditAction::OrgCreate,
+        AuditAction::MemberSet,
+        AuditAction::MemberRemove,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AuditAction::Login => "login",
+            AuditAction::Logout => "logout",
+            AuditAction::ProjectCreate => "project_create",
+            AuditAction::ProjectDelete => "project_delete",
+            AuditAction::ProjectRestore => "project_restore",
+            AuditAction::ProjectImport => "project_import",
+            AuditAction::ProjectExport => "project_export",
+            AuditAction::VariablesUpdate => "variables_update",
+            AuditAction::KeyAdd => "key_add",
+            AuditAction::KeyRemove => "key_remove",
+            AuditAction::HandlerDeploy => "handler_deploy",
+            AuditAction::HandlerDelete => "handler_delete",
+            AuditAction::HandlerRestore => "handler_restore",
+            AuditAction::HandlerRollback => "handler_rollback",
+            AuditAction::CanaryPromote => "canary_promote",
+            AuditAction::CanaryAbort => "canary_abort",
+            AuditAction::StagePromote => "stage_promote",
+            AuditAction::TokenCreate => "token_create",
+            AuditAction::TokenRevoke => "token_revoke",
+            AuditAction::OrgCreate => "org_create",
+            AuditAction::MemberSet => "member_set",
+            AuditAction::MemberRemove => "member_remove",
+        }
+    }
+}
+
+impl Display for AuditAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for AuditAction {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        AuditAction::ALL
+            .into_iter()
+            .find(|action| action.as_str() == value)
+            .ok_or(format!("Invalid action \"{}\"", value))
+    }
+}
+
+impl<DB> ToSql<Text, DB> for AuditAction
+where
+    DB: Backend,
+    str: ToSql<Text, DB>,
+{
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, DB>) -> serialize::Result {
+        self.as_str().to_sql(out)
+    }
+}
+
+impl<DB> FromSql<Text, DB> for AuditAction
+where
+    DB: Backend,
+    String: FromSql<Text, DB>,
+{
+    fn from_sql(bytes: DB::RawValue<'_>) -> deserialize::Result<Self> {
+        let value = <String as FromSql<Text, DB>>::from_sql(bytes)?;
+        Ok(value.parse()?)
+    }
+}
+
+/// An entry of the audit log. The hashes are those of the handler before and after the
+/// action, the details describe what else changed.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+pub struct GetAuditEventDTO {
+    pub created_at: NaiveDateTime,
+    pub login: String,
+    /// The API token the request was sent with
+    pub token: Option<String>,
+    pub ip: Option<String>,
+    pub action: AuditAction,
+    pub project: Option<String>,
+    pub stage: Option<String>,
+    /// The handler, key, token, organization or member the action was applied to
+    pub target: Option<String>,
+    pub old_hash: Option<String>,
+    pub new_hash: Option<String>,
+    pub details: Option<String>,
+}
+
+/// Narrows the audit log down, without a project it holds the events of the user only
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Default)]
+pub struct AuditFilterDTO {
+    pub project: Option<String>,
+    pub stage: Option<String>,
+    pub target: Option<String>,
+    pub action: Option<AuditAction>,
+    /// The login of the user who acted
+    pub user: Option<String>,
+    pub since: Option<NaiveDateTime>,
+    pub until: Option<NaiveDateTime>,
+    pub limit: Option<i64>,
+}
+
 #[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Default)]
 pub struct GetJWTDTO {
     pub jwt: String,
+    /// Exchanged for new tokens at `/api/auth/refresh` once the access token expired
+    #[serde(default)]
+    pub refresh_token: String,
+}
+
+/// The identity provider of the server and how the CLI obtains an access token from it with
+/// the OAuth device flow
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Default)]
+pub struct AuthProviderDTO {
+    pub name: String,
+    pub client_id: String,
+    pub authorization_url: String,
+    pub device_authorization_url: String,
+    pub token_url: String,
+    pub scopes: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Default)]
+pub struct RefreshTokenDTO {
+    pub refresh_token: String,
 }
 
 #[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Default)]
@@ -1,10 +1,35 @@
-use std::{
-    collections::hash_map::DefaultHasher,
-    hash::{Hash, Hasher},
-};
+use sha2::{Digest, Sha256};
 
+/// The hex encoded SHA-256 hash of the module
 pub fn hash(wasm: &[u8]) -> String {
-    let mut hasher = DefaultHasher::new();
-    wasm.hash(&mut hasher);
-    hasher.finish().to_string()
+    format!("{:x}", Sha256::digest(wasm))
+}
+
+/// Older releases hashed the modules with the `DefaultHasher` of the standard library, which is
+/// neither stable nor collision resistant
+pub fn is_sha256(hash: &str) -> bool {
+    hash.len() == 64 && hash.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'))
+}
+
+/// The hash prefixed with its algorithm, like `sha256:<hex>`
+pub fn digest(hash: &str) -> Option<String> {
+    is_sha256(hash).then(|| format!("sha256:{}", hash))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EMPTY_HASH: &str = "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+
+    #[test]
+    fn hash_ok() {
+        assert_eq!(EMPTY_HASH, hash(b""));
+        assert_eq!(Some(format!("sha256:{}", EMPTY_HASH)), digest(EMPTY_HASH));
+    }
+
+    #[test]
+    fn digest_legacy_hash() {
+        assert_eq!(None, digest("14627903435432386541"));
+    }
 }
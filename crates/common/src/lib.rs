@@ -1,2 +1,3 @@
 pub mod dtos;
 pub mod hash;
+pub mod signature;
@@ -0,0 +1,78 @@
+use ed25519_dalek::{Signature, Signer, Verifier, VerifyingKey};
+use rand_core::OsRng;
+
+pub use ed25519_dalek::SigningKey;
+
+/// Creates a new random Ed25519 key
+pub fn generate_key() -> SigningKey {
+    SigningKey::generate(&mut OsRng)
+}
+
+/// The hex encoded secret of the key, as stored in key files
+pub fn encode_signing_key(key: &SigningKey) -> String {
+    hex::encode(key.to_bytes())
+}
+
+pub fn decode_signing_key(encoded: &str) -> Option<SigningKey> {
+    let bytes = hex::decode(encoded.trim()).ok()?;
+    Some(SigningKey::from_bytes(&bytes.try_into().ok()?))
+}
+
+/// The hex encoded public key, which is registered as trusted key of a project
+pub fn public_key(key: &SigningKey) -> String {
+    hex::encode(key.verifying_key().as_bytes())
+}
+
+pub fn is_public_key(public_key: &str) -> bool {
+    decode_public_key(public_key).is_some()
+}
+
+/// Signs the module and returns the hex encoded detached signature
+pub fn sign(key: &SigningKey, wasm: &[u8]) -> String {
+    hex::encode(key.sign(wasm).to_bytes())
+}
+
+/// Whether the signature of the module was created by the key belonging to `public_key`
+pub fn verify(public_key: &str, wasm: &[u8], signature: &str) -> bool {
+    let Some(public_key) = decode_public_key(public_key) else {
+        return false;
+    };
+    let Some(signature) = hex::decode(signature)
+        .ok()
+        .and_then(|bytes| Signature::from_slice(&bytes).ok())
+    else {
+        return false;
+    };
+    public_key.verify(wasm, &signature).is_ok()
+}
+
+fn decode_public_key(public_key: &str) -> Option<VerifyingKey> {
+    let bytes = hex::decode(public_key).ok()?;
+    VerifyingKey::from_bytes(&bytes.try_into().ok()?).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WASM: &[u8] = b"\0asm";
+
+    #[test]
+    fn verify_ok() {
+        let key = generate_key();
+        let signature = sign(&key, WASM);
+
+        assert!(verify(&public_key(&key), WASM, &signature));
+        assert!(!verify(&public_key(&key), b"\0asm\0", &signature));
+        assert!(!verify(&public_key(&generate_key()), WASM, &signature));
+    }
+
+    #[test]
+    fn decode_signing_key_ok() {
+        let key = generate_key();
+        let decoded = decode_signing_key(&format!("{}\n", encode_signing_key(&key)));
+
+        assert_eq!(Some(public_key(&key)), decoded.as_ref().map(public_key));
+        assert!(decode_signing_key("invalid").is_none());
+    }
+}
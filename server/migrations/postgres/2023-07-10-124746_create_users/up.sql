@@ -0,0 +1,12 @@
+-- Your SQL goes here
+CREATE TABLE users (
+  id VARCHAR NOT NULL PRIMARY KEY,
+  email VARCHAR NOT NULL,
+  name VARCHAR,
+  location VARCHAR,
+  company VARCHAR,
+  github_login VARCHAR NOT NULL,
+  github_id INTEGER NOT NULL,
+  github_access_token VARCHAR NOT NULL,
+  UNIQUE(github_id)
+);
@@ -0,0 +1,8 @@
+-- Your SQL goes here
+CREATE TABLE projects (
+  id VARCHAR PRIMARY KEY NOT NULL,
+  name VARCHAR NOT NULL,
+  user_id VARCHAR NOT NULL,
+  UNIQUE(name, user_id),
+  FOREIGN KEY (user_id) REFERENCES users(id)
+);
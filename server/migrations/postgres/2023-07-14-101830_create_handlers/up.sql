@@ -0,0 +1,10 @@
+-- Your SQL goes here
+CREATE TABLE handlers (
+  id VARCHAR PRIMARY KEY NOT NULL,
+  name VARCHAR NOT NULL,
+  language VARCHAR NOT NULL,
+  hash VARCHAR NOT NULL,
+  project_id VARCHAR NOT NULL,
+  UNIQUE(name, project_id),
+  FOREIGN KEY (project_id) REFERENCES projects(id)
+);
@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE handlers DROP COLUMN version;
+DROP TABLE handler_versions;
@@ -0,0 +1,14 @@
+-- Your SQL goes here
+CREATE TABLE handler_versions (
+  id VARCHAR PRIMARY KEY NOT NULL,
+  handler_id VARCHAR NOT NULL,
+  number INTEGER NOT NULL,
+  hash VARCHAR NOT NULL,
+  user_id VARCHAR NOT NULL,
+  created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  UNIQUE(handler_id, number),
+  FOREIGN KEY (handler_id) REFERENCES handlers(id),
+  FOREIGN KEY (user_id) REFERENCES users(id)
+);
+
+ALTER TABLE handlers ADD COLUMN version INTEGER NOT NULL DEFAULT 1;
@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE version_status_codes;
+ALTER TABLE handlers DROP COLUMN canary_weight;
+ALTER TABLE handlers DROP COLUMN canary_version;
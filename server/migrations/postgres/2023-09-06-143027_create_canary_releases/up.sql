@@ -0,0 +1,11 @@
+-- Your SQL goes here
+ALTER TABLE handlers ADD COLUMN canary_version INTEGER;
+ALTER TABLE handlers ADD COLUMN canary_weight INTEGER NOT NULL DEFAULT 0;
+
+CREATE TABLE version_status_codes (
+  version_id VARCHAR NOT NULL,
+  status_code INTEGER NOT NULL,
+  count BIGINT NOT NULL DEFAULT 0,
+  PRIMARY KEY(version_id, status_code),
+  FOREIGN KEY (version_id) REFERENCES handler_versions(id)
+);
@@ -0,0 +1,7 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE stage_variables;
+
+DELETE FROM handlers WHERE stage <> 'prod';
+ALTER TABLE handlers DROP CONSTRAINT handlers_name_project_id_stage_key;
+ALTER TABLE handlers ADD CONSTRAINT handlers_name_project_id_key UNIQUE (name, project_id);
+ALTER TABLE handlers DROP COLUMN stage;
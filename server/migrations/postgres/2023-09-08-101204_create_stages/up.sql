@@ -0,0 +1,13 @@
+-- Your SQL goes here
+ALTER TABLE handlers ADD COLUMN stage VARCHAR NOT NULL DEFAULT 'prod';
+ALTER TABLE handlers DROP CONSTRAINT handlers_name_project_id_key;
+ALTER TABLE handlers ADD CONSTRAINT handlers_name_project_id_stage_key UNIQUE (name, project_id, stage);
+
+CREATE TABLE stage_variables (
+  project_id VARCHAR NOT NULL,
+  stage VARCHAR NOT NULL,
+  name VARCHAR NOT NULL,
+  value VARCHAR NOT NULL,
+  PRIMARY KEY (project_id, stage, name),
+  FOREIGN KEY (project_id) REFERENCES projects(id)
+);
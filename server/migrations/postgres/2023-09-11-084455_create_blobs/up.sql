@@ -0,0 +1,2 @@
+-- Your SQL goes here
+ALTER TABLE handler_versions ADD COLUMN blob VARCHAR NOT NULL DEFAULT '';
@@ -0,0 +1,24 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE projects DROP CONSTRAINT projects_user_id_fkey;
+ALTER TABLE projects ADD CONSTRAINT projects_user_id_fkey
+  FOREIGN KEY (user_id) REFERENCES users(id);
+
+ALTER TABLE handlers DROP CONSTRAINT handlers_project_id_fkey;
+ALTER TABLE handlers ADD CONSTRAINT handlers_project_id_fkey
+  FOREIGN KEY (project_id) REFERENCES projects(id);
+
+ALTER TABLE handler_versions DROP CONSTRAINT handler_versions_handler_id_fkey;
+ALTER TABLE handler_versions ADD CONSTRAINT handler_versions_handler_id_fkey
+  FOREIGN KEY (handler_id) REFERENCES handlers(id);
+
+ALTER TABLE handler_versions DROP CONSTRAINT handler_versions_user_id_fkey;
+ALTER TABLE handler_versions ADD CONSTRAINT handler_versions_user_id_fkey
+  FOREIGN KEY (user_id) REFERENCES users(id);
+
+ALTER TABLE version_status_codes DROP CONSTRAINT version_status_codes_version_id_fkey;
+ALTER TABLE version_status_codes ADD CONSTRAINT version_status_codes_version_id_fkey
+  FOREIGN KEY (version_id) REFERENCES handler_versions(id);
+
+ALTER TABLE stage_variables DROP CONSTRAINT stage_variables_project_id_fkey;
+ALTER TABLE stage_variables ADD CONSTRAINT stage_variables_project_id_fkey
+  FOREIGN KEY (project_id) REFERENCES projects(id);
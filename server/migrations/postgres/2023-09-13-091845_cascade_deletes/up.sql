@@ -0,0 +1,25 @@
+-- Your SQL goes here
+ALTER TABLE projects DROP CONSTRAINT projects_user_id_fkey;
+ALTER TABLE projects ADD CONSTRAINT projects_user_id_fkey
+  FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE;
+
+ALTER TABLE handlers DROP CONSTRAINT handlers_project_id_fkey;
+ALTER TABLE handlers ADD CONSTRAINT handlers_project_id_fkey
+  FOREIGN KEY (project_id) REFERENCES projects(id) ON DELETE CASCADE;
+
+ALTER TABLE handler_versions DROP CONSTRAINT handler_versions_handler_id_fkey;
+ALTER TABLE handler_versions ADD CONSTRAINT handler_versions_handler_id_fkey
+  FOREIGN KEY (handler_id) REFERENCES handlers(id) ON DELETE CASCADE;
+
+-- Deleting a user deletes the versions they deployed to handlers of other users as well
+ALTER TABLE handler_versions DROP CONSTRAINT handler_versions_user_id_fkey;
+ALTER TABLE handler_versions ADD CONSTRAINT handler_versions_user_id_fkey
+  FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE;
+
+ALTER TABLE version_status_codes DROP CONSTRAINT version_status_codes_version_id_fkey;
+ALTER TABLE version_status_codes ADD CONSTRAINT version_status_codes_version_id_fkey
+  FOREIGN KEY (version_id) REFERENCES handler_versions(id) ON DELETE CASCADE;
+
+ALTER TABLE stage_variables DROP CONSTRAINT stage_variables_project_id_fkey;
+ALTER TABLE stage_variables ADD CONSTRAINT stage_variables_project_id_fkey
+  FOREIGN KEY (project_id) REFERENCES projects(id) ON DELETE CASCADE;
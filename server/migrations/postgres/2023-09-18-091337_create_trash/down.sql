@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE handlers DROP COLUMN deleted_at;
+ALTER TABLE projects DROP COLUMN deleted_at;
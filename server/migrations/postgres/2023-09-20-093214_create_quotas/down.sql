@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE invocations;
+DROP TABLE user_quotas;
+ALTER TABLE handler_versions DROP COLUMN size;
@@ -0,0 +1,12 @@
+-- Your SQL goes here
+
+-- A login of a user. The access tokens name their session, and the refresh token is stored
+-- hashed. Deleting the session revokes both.
+CREATE TABLE sessions (
+  id VARCHAR PRIMARY KEY NOT NULL,
+  user_id VARCHAR NOT NULL,
+  refresh_token_hash VARCHAR NOT NULL UNIQUE,
+  created_at TIMESTAMP NOT NULL,
+  expires_at TIMESTAMP NOT NULL,
+  FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
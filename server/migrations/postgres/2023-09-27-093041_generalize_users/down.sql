@@ -0,0 +1,10 @@
+-- This file should undo anything in `up.sql`
+-- Users of other providers have no GitHub id
+DELETE FROM users WHERE provider <> 'github';
+ALTER TABLE users DROP CONSTRAINT users_provider_subject_key;
+ALTER TABLE users DROP COLUMN provider;
+ALTER TABLE users RENAME COLUMN subject TO github_id;
+ALTER TABLE users ALTER COLUMN github_id TYPE INTEGER USING github_id::INTEGER;
+ALTER TABLE users ADD CONSTRAINT users_github_id_key UNIQUE (github_id);
+ALTER TABLE users RENAME COLUMN access_token TO github_access_token;
+ALTER TABLE users RENAME COLUMN login TO github_login;
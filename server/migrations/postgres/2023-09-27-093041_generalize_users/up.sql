@@ -0,0 +1,11 @@
+-- Your SQL goes here
+-- Users log in with an identity provider, GitHub or an OpenID Connect issuer, and are
+-- identified by the provider and their subject there
+ALTER TABLE users RENAME COLUMN github_login TO login;
+ALTER TABLE users RENAME COLUMN github_access_token TO access_token;
+ALTER TABLE users ALTER COLUMN github_id TYPE VARCHAR USING github_id::VARCHAR;
+ALTER TABLE users RENAME COLUMN github_id TO subject;
+ALTER TABLE users ADD COLUMN provider VARCHAR NOT NULL DEFAULT 'github';
+ALTER TABLE users ALTER COLUMN provider DROP DEFAULT;
+ALTER TABLE users DROP CONSTRAINT users_github_id_key;
+ALTER TABLE users ADD CONSTRAINT users_provider_subject_key UNIQUE (provider, subject);
@@ -0,0 +1,5 @@
+-- Your SQL goes here
+-- The access tokens of the identity providers were never used after the login, and a leaked
+-- database gave access to the accounts of the users
+UPDATE users SET access_token = '';
+ALTER TABLE users DROP COLUMN access_token;
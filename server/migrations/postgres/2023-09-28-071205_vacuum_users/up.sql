@@ -0,0 +1,6 @@
+-- Your SQL goes here
+-- Dropping the access tokens left them in the dead and rewritten rows of the table until
+-- autovacuum reuses the space, which may never overwrite them. VACUUM FULL rewrites the table
+-- without them. It must be the only statement of a migration, because Postgres runs the
+-- statements of one query string in a transaction, and VACUUM refuses to run in one.
+VACUUM FULL users;
@@ -0,0 +1,9 @@
+-- This file should undo anything in `up.sql`
+-- Projects of organizations don't fit into the namespaces of their creators
+DELETE FROM projects WHERE org_id IS NOT NULL;
+DROP INDEX projects_org_id_name;
+DROP INDEX projects_user_id_name;
+ALTER TABLE projects ADD CONSTRAINT projects_name_user_id_key UNIQUE (name, user_id);
+ALTER TABLE projects DROP COLUMN org_id;
+DROP TABLE org_members;
+DROP TABLE orgs;
@@ -0,0 +1,24 @@
+-- Your SQL goes here
+
+-- Organizations own projects their members work on together
+CREATE TABLE orgs (
+  id VARCHAR PRIMARY KEY NOT NULL,
+  name VARCHAR NOT NULL UNIQUE,
+  created_at TIMESTAMP NOT NULL
+);
+
+CREATE TABLE org_members (
+  org_id VARCHAR NOT NULL,
+  user_id VARCHAR NOT NULL,
+  role VARCHAR NOT NULL,
+  PRIMARY KEY (org_id, user_id),
+  FOREIGN KEY (org_id) REFERENCES orgs(id) ON DELETE CASCADE,
+  FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+
+-- A project of an organization keeps the member who created it in user_id. Project names are
+-- unique per user for personal projects and per organization otherwise.
+ALTER TABLE projects ADD COLUMN org_id VARCHAR REFERENCES orgs(id) ON DELETE CASCADE;
+ALTER TABLE projects DROP CONSTRAINT projects_name_user_id_key;
+CREATE UNIQUE INDEX projects_user_id_name ON projects (user_id, name) WHERE org_id IS NULL;
+CREATE UNIQUE INDEX projects_org_id_name ON projects (org_id, name) WHERE org_id IS NOT NULL;
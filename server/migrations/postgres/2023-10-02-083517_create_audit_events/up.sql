@@ -0,0 +1,35 @@
+-- Your SQL goes here
+
+-- The audit log. It references neither users nor projects, so the events outlive them.
+CREATE TABLE audit_events (
+  id VARCHAR PRIMARY KEY NOT NULL,
+  created_at TIMESTAMP NOT NULL,
+  user_id VARCHAR NOT NULL,
+  login VARCHAR NOT NULL,
+  token VARCHAR,
+  ip VARCHAR,
+  action VARCHAR NOT NULL,
+  project_id VARCHAR,
+  project VARCHAR,
+  stage VARCHAR,
+  target VARCHAR,
+  old_hash VARCHAR,
+  new_hash VARCHAR,
+  details VARCHAR
+);
+
+CREATE INDEX audit_events_project_id ON audit_events (project_id, created_at);
+CREATE INDEX audit_events_user_id ON audit_events (user_id, created_at);
+
+-- Events are only ever appended
+CREATE FUNCTION audit_events_append_only() RETURNS trigger AS $$
+BEGIN
+  RAISE EXCEPTION 'The audit log is append-only';
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER audit_events_no_change BEFORE UPDATE OR DELETE ON audit_events
+  FOR EACH ROW EXECUTE FUNCTION audit_events_append_only();
+
+CREATE TRIGGER audit_events_no_truncate BEFORE TRUNCATE ON audit_events
+  FOR EACH STATEMENT EXECUTE FUNCTION audit_events_append_only();
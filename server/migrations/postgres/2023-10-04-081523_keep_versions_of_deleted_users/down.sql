@@ -0,0 +1,6 @@
+-- This file should undo anything in `up.sql`
+DELETE FROM handler_versions WHERE user_id IS NULL;
+ALTER TABLE handler_versions DROP CONSTRAINT handler_versions_user_id_fkey;
+ALTER TABLE handler_versions ADD CONSTRAINT handler_versions_user_id_fkey
+  FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE;
+ALTER TABLE handler_versions ALTER COLUMN user_id SET NOT NULL;
@@ -0,0 +1,7 @@
+-- Your SQL goes here
+-- Versions outlive the user who deployed them, a handler of another user or of an
+-- organization keeps running them
+ALTER TABLE handler_versions ALTER COLUMN user_id DROP NOT NULL;
+ALTER TABLE handler_versions DROP CONSTRAINT handler_versions_user_id_fkey;
+ALTER TABLE handler_versions ADD CONSTRAINT handler_versions_user_id_fkey
+  FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE SET NULL;
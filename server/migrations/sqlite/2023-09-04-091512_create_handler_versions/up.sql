@@ -0,0 +1,20 @@
+-- Your SQL goes here
+CREATE TABLE handler_versions (
+  id CHAR(21) PRIMARY KEY NOT NULL,
+  handler_id CHAR(21) NOT NULL,
+  number INTEGER NOT NULL,
+  hash VARCHAR NOT NULL,
+  user_id CHAR(21) NOT NULL,
+  created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  UNIQUE(handler_id, number),
+  foreign key (handler_id) references handlers(id),
+  foreign key (user_id) references users(id)
+);
+
+ALTER TABLE handlers ADD COLUMN version INTEGER NOT NULL DEFAULT 1;
+
+-- Existing artifacts are stored as {handler_id}.wasm, so their first
+-- version reuses the handler id to keep pointing at the same file.
+INSERT INTO handler_versions (id, handler_id, number, hash, user_id)
+SELECT handlers.id, handlers.id, 1, handlers.hash, projects.user_id
+FROM handlers INNER JOIN projects ON handlers.project_id = projects.id;
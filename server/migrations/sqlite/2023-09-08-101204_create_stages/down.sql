@@ -0,0 +1,22 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE stage_variables;
+
+CREATE TABLE handlers_without_stage (
+  id CHAR(21) PRIMARY KEY NOT NULL,
+  name VARCHAR NOT NULL,
+  language VARCHAR NOT NULL,
+  hash VARCHAR NOT NULL,
+  project_id CHAR(21) NOT NULL,
+  version INTEGER NOT NULL DEFAULT 1,
+  canary_version INTEGER,
+  canary_weight INTEGER NOT NULL DEFAULT 0,
+  UNIQUE(name, project_id),
+  FOREIGN KEY (project_id) REFERENCES projects(id)
+);
+
+INSERT INTO handlers_without_stage (id, name, language, hash, project_id, version, canary_version, canary_weight)
+SELECT id, name, language, hash, project_id, version, canary_version, canary_weight FROM handlers
+WHERE stage = 'prod';
+
+DROP TABLE handlers;
+ALTER TABLE handlers_without_stage RENAME TO handlers;
@@ -0,0 +1,29 @@
+-- Your SQL goes here
+CREATE TABLE handlers_with_stage (
+  id CHAR(21) PRIMARY KEY NOT NULL,
+  name VARCHAR NOT NULL,
+  language VARCHAR NOT NULL,
+  hash VARCHAR NOT NULL,
+  project_id CHAR(21) NOT NULL,
+  version INTEGER NOT NULL DEFAULT 1,
+  canary_version INTEGER,
+  canary_weight INTEGER NOT NULL DEFAULT 0,
+  stage VARCHAR NOT NULL DEFAULT 'prod',
+  UNIQUE(name, project_id, stage),
+  FOREIGN KEY (project_id) REFERENCES projects(id)
+);
+
+INSERT INTO handlers_with_stage (id, name, language, hash, project_id, version, canary_version, canary_weight)
+SELECT id, name, language, hash, project_id, version, canary_version, canary_weight FROM handlers;
+
+DROP TABLE handlers;
+ALTER TABLE handlers_with_stage RENAME TO handlers;
+
+CREATE TABLE stage_variables (
+  project_id CHAR(21) NOT NULL,
+  stage VARCHAR NOT NULL,
+  name VARCHAR NOT NULL,
+  value VARCHAR NOT NULL,
+  PRIMARY KEY (project_id, stage, name),
+  FOREIGN KEY (project_id) REFERENCES projects(id)
+);
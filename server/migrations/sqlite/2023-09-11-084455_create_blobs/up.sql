@@ -0,0 +1,6 @@
+-- Your SQL goes here
+ALTER TABLE handler_versions ADD COLUMN blob VARCHAR NOT NULL DEFAULT '';
+
+-- Until the server moved the legacy `{version_id}.wasm` files to their content address on
+-- startup, the blob of a version is its id
+UPDATE handler_versions SET blob = id;
@@ -0,0 +1,79 @@
+-- This file should undo anything in `up.sql`
+PRAGMA foreign_keys = OFF;
+
+BEGIN;
+
+CREATE TABLE projects_old (
+  id CHAR(21) PRIMARY KEY NOT NULL,
+  name VARCHAR NOT NULL,
+  user_id CHAR(21) NOT NULL,
+  UNIQUE(name, user_id),
+  FOREIGN KEY (user_id) REFERENCES users(id)
+);
+INSERT INTO projects_old SELECT id, name, user_id FROM projects;
+
+CREATE TABLE handlers_old (
+  id CHAR(21) PRIMARY KEY NOT NULL,
+  name VARCHAR NOT NULL,
+  language VARCHAR NOT NULL,
+  hash VARCHAR NOT NULL,
+  project_id CHAR(21) NOT NULL,
+  version INTEGER NOT NULL DEFAULT 1,
+  canary_version INTEGER,
+  canary_weight INTEGER NOT NULL DEFAULT 0,
+  stage VARCHAR NOT NULL DEFAULT 'prod',
+  UNIQUE(name, project_id, stage),
+  FOREIGN KEY (project_id) REFERENCES projects(id)
+);
+INSERT INTO handlers_old
+SELECT id, name, language, hash, project_id, version, canary_version, canary_weight, stage FROM handlers;
+
+CREATE TABLE handler_versions_old (
+  id CHAR(21) PRIMARY KEY NOT NULL,
+  handler_id CHAR(21) NOT NULL,
+  number INTEGER NOT NULL,
+  hash VARCHAR NOT NULL,
+  user_id CHAR(21) NOT NULL,
+  created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  blob VARCHAR NOT NULL DEFAULT '',
+  UNIQUE(handler_id, number),
+  FOREIGN KEY (handler_id) REFERENCES handlers(id),
+  FOREIGN KEY (user_id) REFERENCES users(id)
+);
+INSERT INTO handler_versions_old
+SELECT id, handler_id, number, hash, user_id, created_at, blob FROM handler_versions;
+
+CREATE TABLE version_status_codes_old (
+  version_id CHAR(21) NOT NULL,
+  status_code INTEGER NOT NULL,
+  count BIGINT NOT NULL DEFAULT 0,
+  PRIMARY KEY(version_id, status_code),
+  FOREIGN KEY (version_id) REFERENCES handler_versions(id)
+);
+INSERT INTO version_status_codes_old SELECT version_id, status_code, count FROM version_status_codes;
+
+CREATE TABLE stage_variables_old (
+  project_id CHAR(21) NOT NULL,
+  stage VARCHAR NOT NULL,
+  name VARCHAR NOT NULL,
+  value VARCHAR NOT NULL,
+  PRIMARY KEY (project_id, stage, name),
+  FOREIGN KEY (project_id) REFERENCES projects(id)
+);
+INSERT INTO stage_variables_old SELECT project_id, stage, name, value FROM stage_variables;
+
+DROP TABLE version_status_codes;
+DROP TABLE handler_versions;
+DROP TABLE stage_variables;
+DROP TABLE handlers;
+DROP TABLE projects;
+
+ALTER TABLE projects_old RENAME TO projects;
+ALTER TABLE handlers_old RENAME TO handlers;
+ALTER TABLE handler_versions_old RENAME TO handler_versions;
+ALTER TABLE version_status_codes_old RENAME TO version_status_codes;
+ALTER TABLE stage_variables_old RENAME TO stage_variables;
+
+COMMIT;
+
+PRAGMA foreign_keys = ON;
@@ -0,0 +1,98 @@
+-- Your SQL goes here
+-- SQLite cannot alter constraints, so every table with a foreign key is recreated. Rows
+-- whose parent is already gone are not copied, they would violate the constraints. The new
+-- tables reference the old ones until they are renamed, so dropping the old tables with
+-- foreign keys switched on would cascade into the copied rows.
+PRAGMA foreign_keys = OFF;
+
+BEGIN;
+
+CREATE TABLE projects_new (
+  id CHAR(21) PRIMARY KEY NOT NULL,
+  name VARCHAR NOT NULL,
+  user_id CHAR(21) NOT NULL,
+  UNIQUE(name, user_id),
+  FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+
+INSERT INTO projects_new (id, name, user_id)
+SELECT id, name, user_id FROM projects
+WHERE user_id IN (SELECT id FROM users);
+
+CREATE TABLE handlers_new (
+  id CHAR(21) PRIMARY KEY NOT NULL,
+  name VARCHAR NOT NULL,
+  language VARCHAR NOT NULL,
+  hash VARCHAR NOT NULL,
+  project_id CHAR(21) NOT NULL,
+  version INTEGER NOT NULL DEFAULT 1,
+  canary_version INTEGER,
+  canary_weight INTEGER NOT NULL DEFAULT 0,
+  stage VARCHAR NOT NULL DEFAULT 'prod',
+  UNIQUE(name, project_id, stage),
+  FOREIGN KEY (project_id) REFERENCES projects(id) ON DELETE CASCADE
+);
+
+INSERT INTO handlers_new (id, name, language, hash, project_id, version, canary_version, canary_weight, stage)
+SELECT id, name, language, hash, project_id, version, canary_version, canary_weight, stage FROM handlers
+WHERE project_id IN (SELECT id FROM projects_new);
+
+-- Deleting a user deletes the versions they deployed to handlers of other users as well
+CREATE TABLE handler_versions_new (
+  id CHAR(21) PRIMARY KEY NOT NULL,
+  handler_id CHAR(21) NOT NULL,
+  number INTEGER NOT NULL,
+  hash VARCHAR NOT NULL,
+  user_id CHAR(21) NOT NULL,
+  created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  blob VARCHAR NOT NULL DEFAULT '',
+  UNIQUE(handler_id, number),
+  FOREIGN KEY (handler_id) REFERENCES handlers(id) ON DELETE CASCADE,
+  FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+
+INSERT INTO handler_versions_new (id, handler_id, number, hash, user_id, created_at, blob)
+SELECT id, handler_id, number, hash, user_id, created_at, blob FROM handler_versions
+WHERE handler_id IN (SELECT id FROM handlers_new)
+AND user_id IN (SELECT id FROM users);
+
+CREATE TABLE version_status_codes_new (
+  version_id CHAR(21) NOT NULL,
+  status_code INTEGER NOT NULL,
+  count BIGINT NOT NULL DEFAULT 0,
+  PRIMARY KEY(version_id, status_code),
+  FOREIGN KEY (version_id) REFERENCES handler_versions(id) ON DELETE CASCADE
+);
+
+INSERT INTO version_status_codes_new (version_id, status_code, count)
+SELECT version_id, status_code, count FROM version_status_codes
+WHERE version_id IN (SELECT id FROM handler_versions_new);
+
+CREATE TABLE stage_variables_new (
+  project_id CHAR(21) NOT NULL,
+  stage VARCHAR NOT NULL,
+  name VARCHAR NOT NULL,
+  value VARCHAR NOT NULL,
+  PRIMARY KEY (project_id, stage, name),
+  FOREIGN KEY (project_id) REFERENCES projects(id) ON DELETE CASCADE
+);
+
+INSERT INTO stage_variables_new (project_id, stage, name, value)
+SELECT project_id, stage, name, value FROM stage_variables
+WHERE project_id IN (SELECT id FROM projects_new);
+
+DROP TABLE version_status_codes;
+DROP TABLE handler_versions;
+DROP TABLE stage_variables;
+DROP TABLE handlers;
+DROP TABLE projects;
+
+ALTER TABLE projects_new RENAME TO projects;
+ALTER TABLE handlers_new RENAME TO handlers;
+ALTER TABLE handler_versions_new RENAME TO handler_versions;
+ALTER TABLE version_status_codes_new RENAME TO version_status_codes;
+ALTER TABLE stage_variables_new RENAME TO stage_variables;
+
+COMMIT;
+
+PRAGMA foreign_keys = ON;
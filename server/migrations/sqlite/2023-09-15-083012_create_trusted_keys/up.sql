@@ -0,0 +1,9 @@
+-- Your SQL goes here
+CREATE TABLE trusted_keys (
+  project_id CHAR(21) NOT NULL,
+  name VARCHAR NOT NULL,
+  public_key VARCHAR NOT NULL,
+  created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  PRIMARY KEY (project_id, name),
+  FOREIGN KEY (project_id) REFERENCES projects(id) ON DELETE CASCADE
+);
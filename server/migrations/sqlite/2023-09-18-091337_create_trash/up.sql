@@ -0,0 +1,4 @@
+-- Your SQL goes here
+-- Deleted projects and handlers stay in the trash until the retention period ends
+ALTER TABLE projects ADD COLUMN deleted_at TIMESTAMP;
+ALTER TABLE handlers ADD COLUMN deleted_at TIMESTAMP;
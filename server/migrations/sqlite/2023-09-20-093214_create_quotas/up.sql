@@ -0,0 +1,24 @@
+-- Your SQL goes here
+
+-- The size of the component in bytes. Versions deployed before are measured by the server on
+-- startup.
+ALTER TABLE handler_versions ADD COLUMN size BIGINT NOT NULL DEFAULT 0;
+
+-- Limits of a user which differ from the defaults of the server, NULL keeps the default
+CREATE TABLE user_quotas (
+  user_id CHAR(21) PRIMARY KEY NOT NULL,
+  projects BIGINT,
+  handlers_per_project BIGINT,
+  artifact_size BIGINT,
+  storage BIGINT,
+  invocations_per_day BIGINT,
+  FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+
+CREATE TABLE invocations (
+  user_id CHAR(21) NOT NULL,
+  day DATE NOT NULL,
+  count BIGINT NOT NULL DEFAULT 0,
+  PRIMARY KEY (user_id, day),
+  FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
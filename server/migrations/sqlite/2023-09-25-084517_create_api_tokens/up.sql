@@ -0,0 +1,14 @@
+-- Your SQL goes here
+
+-- A long-lived token of a user for CI. Only the hash of the token is stored.
+CREATE TABLE api_tokens (
+  user_id CHAR(21) NOT NULL,
+  name VARCHAR NOT NULL,
+  token_hash VARCHAR NOT NULL UNIQUE,
+  scope VARCHAR NOT NULL,
+  project VARCHAR,
+  created_at TIMESTAMP NOT NULL,
+  last_used_at TIMESTAMP,
+  PRIMARY KEY (user_id, name),
+  FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
@@ -0,0 +1,29 @@
+-- This file should undo anything in `up.sql`
+-- Users of other providers have no GitHub id
+DELETE FROM users WHERE provider <> 'github';
+
+PRAGMA foreign_keys = OFF;
+
+BEGIN;
+
+CREATE TABLE users_old (
+  id CHAR(21) NOT NULL PRIMARY KEY,
+  email VARCHAR NOT NULL,
+  name VARCHAR,
+  location VARCHAR,
+  company VARCHAR,
+  github_login VARCHAR NOT NULL,
+  github_id INTEGER NOT NULL,
+  github_access_token VARCHAR NOT NULL,
+  UNIQUE(github_id)
+);
+
+INSERT INTO users_old (id, email, name, location, company, github_login, github_id, github_access_token)
+SELECT id, email, name, location, company, login, CAST(subject AS INTEGER), access_token FROM users;
+
+DROP TABLE users;
+ALTER TABLE users_old RENAME TO users;
+
+COMMIT;
+
+PRAGMA foreign_keys = ON;
@@ -0,0 +1,31 @@
+-- Your SQL goes here
+-- Users log in with an identity provider, GitHub or an OpenID Connect issuer, and are
+-- identified by the provider and their subject there. SQLite cannot alter the unique
+-- constraint, so the table is recreated. Dropping it with foreign keys switched on would
+-- cascade to every project.
+PRAGMA foreign_keys = OFF;
+
+BEGIN;
+
+CREATE TABLE users_new (
+  id CHAR(21) NOT NULL PRIMARY KEY,
+  email VARCHAR NOT NULL,
+  name VARCHAR,
+  location VARCHAR,
+  company VARCHAR,
+  login VARCHAR NOT NULL,
+  provider VARCHAR NOT NULL,
+  subject VARCHAR NOT NULL,
+  access_token VARCHAR NOT NULL,
+  UNIQUE(provider, subject)
+);
+
+INSERT INTO users_new (id, email, name, location, company, login, provider, subject, access_token)
+SELECT id, email, name, location, company, github_login, 'github', CAST(github_id AS VARCHAR), github_access_token FROM users;
+
+DROP TABLE users;
+ALTER TABLE users_new RENAME TO users;
+
+COMMIT;
+
+PRAGMA foreign_keys = ON;
@@ -0,0 +1,2 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE users ADD COLUMN access_token VARCHAR NOT NULL DEFAULT '';
@@ -0,0 +1,34 @@
+-- Your SQL goes here
+-- The access tokens of the identity providers were never used after the login, and a leaked
+-- database gave access to the accounts of the users. Older SQLite versions cannot drop a
+-- column, so the table is recreated like in generalize_users.
+PRAGMA foreign_keys = OFF;
+
+BEGIN;
+
+CREATE TABLE users_new (
+  id CHAR(21) NOT NULL PRIMARY KEY,
+  email VARCHAR NOT NULL,
+  name VARCHAR,
+  location VARCHAR,
+  company VARCHAR,
+  login VARCHAR NOT NULL,
+  provider VARCHAR NOT NULL,
+  subject VARCHAR NOT NULL,
+  UNIQUE(provider, subject)
+);
+
+INSERT INTO users_new (id, email, name, location, company, login, provider, subject)
+SELECT id, email, name, location, company, login, provider, subject FROM users;
+
+DROP TABLE users;
+ALTER TABLE users_new RENAME TO users;
+
+COMMIT;
+
+PRAGMA foreign_keys = ON;
+
+-- The dropped table stays in the free pages of the file until they are reused, and in
+-- write-ahead log mode the old pages stay in the database file until a checkpoint
+VACUUM;
+PRAGMA wal_checkpoint(TRUNCATE);
@@ -0,0 +1,2 @@
+-- Your SQL goes here
+-- Kept in sync with the Postgres migrations, drop_access_tokens already vacuums the database
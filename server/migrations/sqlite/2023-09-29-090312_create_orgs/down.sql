@@ -0,0 +1,29 @@
+-- This file should undo anything in `up.sql`
+PRAGMA foreign_keys = OFF;
+
+BEGIN;
+
+-- Projects of organizations don't fit into the namespaces of their creators
+DELETE FROM projects WHERE org_id IS NOT NULL;
+
+CREATE TABLE projects_old (
+  id CHAR(21) PRIMARY KEY NOT NULL,
+  name VARCHAR NOT NULL,
+  user_id CHAR(21) NOT NULL,
+  deleted_at TIMESTAMP,
+  UNIQUE(name, user_id),
+  FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+
+INSERT INTO projects_old (id, name, user_id, deleted_at)
+SELECT id, name, user_id, deleted_at FROM projects;
+
+DROP TABLE projects;
+ALTER TABLE projects_old RENAME TO projects;
+
+DROP TABLE org_members;
+DROP TABLE orgs;
+
+COMMIT;
+
+PRAGMA foreign_keys = ON;
@@ -0,0 +1,47 @@
+-- Your SQL goes here
+
+-- Organizations own projects their members work on together
+CREATE TABLE orgs (
+  id CHAR(21) PRIMARY KEY NOT NULL,
+  name VARCHAR NOT NULL UNIQUE,
+  created_at TIMESTAMP NOT NULL
+);
+
+CREATE TABLE org_members (
+  org_id CHAR(21) NOT NULL,
+  user_id CHAR(21) NOT NULL,
+  role VARCHAR NOT NULL,
+  PRIMARY KEY (org_id, user_id),
+  FOREIGN KEY (org_id) REFERENCES orgs(id) ON DELETE CASCADE,
+  FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+
+-- A project of an organization keeps the member who created it in user_id. Project names are
+-- unique per user for personal projects and per organization otherwise. SQLite cannot alter
+-- the unique constraint, so the table is recreated.
+PRAGMA foreign_keys = OFF;
+
+BEGIN;
+
+CREATE TABLE projects_new (
+  id CHAR(21) PRIMARY KEY NOT NULL,
+  name VARCHAR NOT NULL,
+  user_id CHAR(21) NOT NULL,
+  deleted_at TIMESTAMP,
+  org_id CHAR(21),
+  FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE,
+  FOREIGN KEY (org_id) REFERENCES orgs(id) ON DELETE CASCADE
+);
+
+INSERT INTO projects_new (id, name, user_id, deleted_at)
+SELECT id, name, user_id, deleted_at FROM projects;
+
+DROP TABLE projects;
+ALTER TABLE projects_new RENAME TO projects;
+
+CREATE UNIQUE INDEX projects_user_id_name ON projects (user_id, name) WHERE org_id IS NULL;
+CREATE UNIQUE INDEX projects_org_id_name ON projects (org_id, name) WHERE org_id IS NOT NULL;
+
+COMMIT;
+
+PRAGMA foreign_keys = ON;
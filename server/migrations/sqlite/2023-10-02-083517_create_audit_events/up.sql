@@ -0,0 +1,33 @@
+-- Your SQL goes here
+
+-- The audit log. It references neither users nor projects, so the events outlive them.
+CREATE TABLE audit_events (
+  id CHAR(21) PRIMARY KEY NOT NULL,
+  created_at TIMESTAMP NOT NULL,
+  user_id CHAR(21) NOT NULL,
+  login VARCHAR NOT NULL,
+  token VARCHAR,
+  ip VARCHAR,
+  action VARCHAR NOT NULL,
+  project_id CHAR(21),
+  project VARCHAR,
+  stage VARCHAR,
+  target VARCHAR,
+  old_hash VARCHAR,
+  new_hash VARCHAR,
+  details VARCHAR
+);
+
+CREATE INDEX audit_events_project_id ON audit_events (project_id, created_at);
+CREATE INDEX audit_events_user_id ON audit_events (user_id, created_at);
+
+-- Events are only ever appended
+CREATE TRIGGER audit_events_no_update BEFORE UPDATE ON audit_events
+BEGIN
+  SELECT RAISE(ABORT, 'The audit log is append-only');
+END;
+
+CREATE TRIGGER audit_events_no_delete BEFORE DELETE ON audit_events
+BEGIN
+  SELECT RAISE(ABORT, 'The audit log is append-only');
+END;
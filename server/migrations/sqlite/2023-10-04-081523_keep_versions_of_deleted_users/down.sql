@@ -0,0 +1,29 @@
+-- This file should undo anything in `up.sql`
+PRAGMA foreign_keys = OFF;
+
+BEGIN;
+
+CREATE TABLE handler_versions_new (
+  id CHAR(21) PRIMARY KEY NOT NULL,
+  handler_id CHAR(21) NOT NULL,
+  number INTEGER NOT NULL,
+  hash VARCHAR NOT NULL,
+  user_id CHAR(21) NOT NULL,
+  created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  blob VARCHAR NOT NULL DEFAULT '',
+  size BIGINT NOT NULL DEFAULT 0,
+  UNIQUE(handler_id, number),
+  FOREIGN KEY (handler_id) REFERENCES handlers(id) ON DELETE CASCADE,
+  FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+
+INSERT INTO handler_versions_new (id, handler_id, number, hash, user_id, created_at, blob, size)
+SELECT id, handler_id, number, hash, user_id, created_at, blob, size FROM handler_versions
+WHERE user_id IS NOT NULL;
+
+DROP TABLE handler_versions;
+ALTER TABLE handler_versions_new RENAME TO handler_versions;
+
+COMMIT;
+
+PRAGMA foreign_keys = ON;
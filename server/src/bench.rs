@@ -0,0 +1,178 @@
+//! Throughput of the server under concurrent traffic. The database work runs on the blocking
+//! pool, so management requests must not starve the execution of handlers. Run with
+//! `cargo test -p noops-server --release bench -- --ignored --nocapture`.
+
+use crate::{
+    config::Limits,
+    controller,
+    identity::{github::Github, Identity, IdentityClient},
+    jwt::{JwtConfig, JwtKeys},
+    repository::quota::QuotaRepository,
+    repository::{self, connection::DatabaseOptions, user::User, Repository},
+    service::{
+        audit::Actor,
+        auth::AuthService,
+        handler::HandlerService,
+        project::ProjectService,
+        quota::{QuotaService, Quotas},
+    },
+    wasmstore::{filesystem::FileSystem, WasmStore},
+};
+use axum::{extract::FromRef, Server};
+use common::dtos::DEFAULT_STAGE;
+use std::{
+    net::SocketAddr,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+const PROJECT_NAME: &str = "bench";
+const HANDLER_NAME: &str = "status";
+const DURATION: Duration = Duration::from_secs(5);
+const CONCURRENCY: usize = 32;
+
+struct Target {
+    addr: SocketAddr,
+    handler_id: String,
+    jwt: String,
+    _temp_dir: tempfile::TempDir,
+}
+
+async fn start() -> anyhow::Result<Target> {
+    let temp_dir = tempfile::tempdir()?;
+    let url = temp_dir.path().join("noops.sqlite");
+    repository::migrate(url.to_str().unwrap(), DatabaseOptions::default())?;
+    let pool = repository::create_pool(url.to_str().unwrap(), DatabaseOptions::default())?;
+    let wasmstore = WasmStore::new(Arc::new(FileSystem::new(
+        &temp_dir.path().join("wasmstore"),
+    )?));
+
+    let (users, _, _, _, _, _, _) = repository::new(pool.clone());
+    let user = User::new(Identity {
+        provider: "github".to_string(),
+        subject: "42".to_string(),
+        login: "bench".to_string(),
+        email: "bench@example.com".to_string(),
+        ..Default::default()
+    });
+    users.create(&user)?;
+
+    let (_, projects, handlers, versions, _, _, _) = repository::new(pool.clone());
+    let quotas = QuotaService::new(
+        QuotaRepository::new(pool.clone()),
+        projects,
+        handlers,
+        versions,
+        wasmstore.clone(),
+        Quotas {
+            invocations_per_day: i64::MAX,
+            ..Quotas::default()
+        },
+    );
+    let jwt = JwtConfig::new(JwtKeys::ephemeral(), "noops.io".to_string(), 60 * 60);
+    let state = crate::create_app_state(
+        pool,
+        wasmstore,
+        Duration::ZERO,
+        quotas,
+        jwt,
+        chrono::Duration::days(1),
+        IdentityClient::new(Arc::new(Github::new(String::new()))),
+    );
+    let projects = ProjectService::from_ref(&state);
+    let handlers = HandlerService::from_ref(&state);
+    let actor = Actor::from(user.clone());
+    projects.create(&actor, PROJECT_NAME.to_string())?;
+    let wasm = std::fs::read(env!("CARGO_CDYLIB_FILE_RETURN_STATUS_CODE_200"))?;
+    handlers.create(
+        &actor,
+        PROJECT_NAME,
+        DEFAULT_STAGE,
+        HANDLER_NAME.to_string(),
+        &wasm,
+        None,
+        None,
+    )?;
+    let project = projects.read(&user, PROJECT_NAME)?;
+    let link = &project.handlers[0].link;
+    let handler_id = link.rsplit('/').next().unwrap().to_string();
+    let jwt = AuthService::from_ref(&state).issue_token(&user, None)?.jwt;
+
+    let server = Server::bind(&SocketAddr::from(([127, 0, 0, 1], 0)))
+        .serve(controller::routes(state, &Limits::default()).into_make_service());
+    let addr = server.local_addr();
+    tokio::spawn(server);
+
+    Ok(Target {
+        addr,
+        handler_id,
+        jwt,
+        _temp_dir: temp_dir,
+    })
+}
+
+/// Sends requests to the URL from `concurrency` workers until the time is up and returns the
+/// number of successful responses
+async fn drive(url: String, jwt: Option<String>, concurrency: usize) -> u64 {
+    let client = reqwest::Client::new();
+    let completed = Arc::new(AtomicU64::new(0));
+    let deadline = Instant::now() + DURATION;
+
+    let workers: Vec<_> = (0..concurrency)
+        .map(|_| {
+            let (client, url, jwt) = (client.clone(), url.clone(), jwt.clone());
+            let completed = completed.clone();
+            tokio::spawn(async move {
+                while Instant::now() < deadline {
+                    let mut request = client.get(&url);
+                    if let Some(jwt) = &jwt {
+                        request = request.bearer_auth(jwt);
+                    }
+                    if let Ok(response) = request.send().await {
+                        if response.status().is_success() {
+                            completed.fetch_add(1, Ordering::Relaxed);
+                        }
+                    }
+                }
+            })
+        })
+        .collect();
+    for worker in workers {
+        worker.await.unwrap();
+    }
+
+    completed.load(Ordering::Relaxed)
+}
+
+fn report(name: &str, completed: u64) {
+    println!(
+        "{:<12} {:>8} requests {:>10.1} req/s",
+        name,
+        completed,
+        completed as f64 / DURATION.as_secs_f64()
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+#[ignore = "benchmark"]
+async fn throughput() -> anyhow::Result<()> {
+    let target = start().await?;
+    let execute_url = format!("http://{}/{}", target.addr, target.handler_id);
+    let management_url = format!("http://{}/api/{}", target.addr, PROJECT_NAME);
+
+    let completed = drive(execute_url.clone(), None, CONCURRENCY).await;
+    report("execute", completed);
+
+    let (execute, management) = tokio::join!(
+        drive(execute_url, None, CONCURRENCY / 2),
+        drive(management_url, Some(target.jwt.clone()), CONCURRENCY / 2),
+    );
+    report("execute", execute);
+    report("management", management);
+
+    assert!(completed > 0 && execute > 0 && management > 0);
+    Ok(())
+}
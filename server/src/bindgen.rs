@@ -1,13 +1,12 @@
-use std::fs;
 use wasmtime::component::bindgen;
 use wit_component::ComponentEncoder;
 
-const ADAPTER_PATH: &str = "../wit/wasi_snapshot_preview1.reactor.wasm";
 const ADAPTER_NAME: &str = "wasi_snapshot_preview1";
-
-lazy_static::lazy_static! {
-    static ref ADAPTER: Vec<u8> = fs::read(ADAPTER_PATH).unwrap();
-}
+/// Embedded, so the server doesn't depend on the working directory it is started from
+const ADAPTER: &[u8] = include_bytes!(concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/../wit/wasi_snapshot_preview1.reactor.wasm"
+));
 
 bindgen!({
     world: "handler",
@@ -27,7 +26,7 @@ impl Default for Request {
 pub fn create_component(wasm_module: &[u8]) -> anyhow::Result<Vec<u8>> {
     let component = ComponentEncoder::default()
         .module(wasm_module)?
-        .adapter(ADAPTER_NAME, &ADAPTER)?
+        .adapter(ADAPTER_NAME, ADAPTER)?
         .validate(true)
         .encode()?;
     Ok(component)
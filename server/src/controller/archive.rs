@@ -0,0 +1,49 @@
+use super::AppState;
+use crate::{
+    errors::Error,
+    service::{archive::ArchiveService, audit::Actor, blocking},
+};
+use axum::{
+    body::Bytes,
+    extract::{DefaultBodyLimit, Path, Query, State},
+    http::{header, StatusCode},
+    response::IntoResponse,
+    routing::{get, post},
+    Extension, Json, Router,
+};
+use common::dtos;
+
+const ARCHIVE_CONTENT_TYPE: &str = "application/zstd";
+pub fn routes(state: AppState, body_limit: usize) -> Router {
+    Router::new()
+        .route("/api/:project_name/export", get(export))
+        .route("/api/import", post(import))
+        .with_state(state)
+        .layer(DefaultBodyLimit::max(body_limit))
+}
+
+async fn export(
+    Path(project_name): Path<String>,
+    State(archives): State<ArchiveService>,
+    Extension(user): Extension<Actor>,
+) -> Result<impl IntoResponse, Error> {
+    let archive = blocking(move || archives.export(&user, &project_name)).await?;
+    Ok((
+        StatusCode::OK,
+        [(header::CONTENT_TYPE, ARCHIVE_CONTENT_TYPE)],
+        archive,
+    ))
+}
+
+async fn import(
+    Query(query): Query<dtos::ImportDTO>,
+    State(archives): State<ArchiveService>,
+    Extension(user): Extension<Actor>,
+    archive: Bytes,
+) -> Result<impl IntoResponse, Error> {
+    let project = blocking(move || {
+        archives.import(&user, &archive, query.project, query.signature.as_deref())
+    })
+    .await?;
+    Ok((StatusCode::CREATED, Json(project)))
+}
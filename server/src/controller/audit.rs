@@ -0,0 +1,31 @@
+use super::AppState;
+use crate::{
+    errors::Error,
+    service::{
+        audit::{Actor, AuditService},
+        blocking,
+    },
+};
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+    routing::get,
+    Extension, Json, Router,
+};
+use common::dtos::AuditFilterDTO;
+
+pub fn routes(state: AppState) -> Router {
+    Router::new()
+        .route("/api/audit", get(list_events))
+        .with_state(state)
+}
+
+async fn list_events(
+    Query(filter): Query<AuditFilterDTO>,
+    State(audit): State<AuditService>,
+    Extension(user): Extension<Actor>,
+) -> Result<impl IntoResponse, Error> {
+    let events = blocking(move || audit.search(&user, &filter)).await?;
+    Ok((StatusCode::OK, Json(events)))
+}
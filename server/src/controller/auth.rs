@@ -1,19 +1,32 @@
 use super::AppState;
-use crate::{errors::Error, service::auth::AuthService};
+use crate::{
+    errors::Error,
+    service::{
+        audit::Actor,
+        auth::AuthService,
+        blocking,
+        token::{self, Access, TokenService},
+    },
+};
 use axum::{
-    extract::{Query, State, TypedHeader},
+    extract::{ConnectInfo, MatchedPath, Query, State, TypedHeader},
     headers::authorization::{Authorization, Bearer},
-    http::Request,
+    http::{Request, StatusCode},
     middleware::Next,
     response::{IntoResponse, Response},
-    routing::get,
+    routing::{get, post},
     Json, Router,
 };
+use common::dtos;
 use serde::Deserialize;
+use std::net::SocketAddr;
 
 pub fn routes(state: AppState) -> Router {
     Router::new()
+        .route("/api/auth/provider", get(provider))
         .route("/api/auth/login", get(login))
+        .route("/api/auth/refresh", post(refresh))
+        .route("/api/auth/logout", post(logout))
         .with_state(state)
 }
 
@@ -22,23 +35,74 @@ pub struct LoginQuery {
     token: String,
 }
 
+async fn provider(State(auth): State<AuthService>) -> impl IntoResponse {
+    Json(auth.provider())
+}
+
+/// The address of the client as seen by the server, which is a proxy's if there is one
+fn client_ip(connect_info: Option<ConnectInfo<SocketAddr>>) -> Option<String> {
+    connect_info.map(|ConnectInfo(address)| address.ip().to_string())
+}
+
 async fn login(
     Query(login_query): Query<LoginQuery>,
+    connect_info: Option<ConnectInfo<SocketAddr>>,
     State(auth): State<AuthService>,
 ) -> Result<impl IntoResponse, Error> {
-    let github_access_token = login_query.token;
-    let jwt = auth.login(github_access_token).await?;
+    let jwt = auth
+        .login(login_query.token, client_ip(connect_info))
+        .await?;
     Ok(Json(jwt))
 }
 
+async fn refresh(
+    State(auth): State<AuthService>,
+    Json(refresh): Json<dtos::RefreshTokenDTO>,
+) -> Result<impl IntoResponse, Error> {
+    let tokens = blocking(move || auth.refresh(&refresh.refresh_token)).await?;
+    Ok(Json(tokens))
+}
+
+async fn logout(
+    connect_info: Option<ConnectInfo<SocketAddr>>,
+    State(auth): State<AuthService>,
+    Json(refresh): Json<dtos::RefreshTokenDTO>,
+) -> Result<impl IntoResponse, Error> {
+    let ip = client_ip(connect_info);
+    blocking(move || auth.logout(&refresh.refresh_token, ip)).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Authenticates the request with an access token or an API token, whose scope must permit it.
+/// The handlers get the [`Actor`] of the request.
 pub async fn auth_middleware<B>(
     State(auth): State<AuthService>,
+    State(tokens): State<TokenService>,
     TypedHeader(header): TypedHeader<Authorization<Bearer>>,
     mut request: Request<B>,
     next: Next<B>,
 ) -> Result<Response, Error> {
-    let user = auth.authenticate(header.token())?;
-    request.extensions_mut().insert(user);
+    let token = header.token().to_string();
+    let ip = client_ip(
+        request
+            .extensions()
+            .get::<ConnectInfo<SocketAddr>>()
+            .cloned(),
+    );
+    let actor = if token::is_api_token(&token) {
+        let route = request
+            .extensions()
+            .get::<MatchedPath>()
+            .map(|path| path.as_str().to_string())
+            .unwrap_or_default();
+        let access = Access::new(request.method().clone(), &route, request.uri().path());
+        let (user, name) = blocking(move || tokens.authenticate(&token, &access)).await?;
+        Actor::new(user, ip, Some(name))
+    } else {
+        let user = blocking(move || auth.authenticate(&token)).await?;
+        Actor::new(user, ip, None)
+    };
+    request.extensions_mut().insert(actor);
 
     Ok(next.run(request).await)
 }
@@ -1,14 +1,25 @@
 use super::AppState;
-use crate::{bindgen, errors::Error, executor, wasmstore::WasmStore};
+use crate::{
+    bindgen,
+    errors::Error,
+    executor,
+    service::{
+        blocking,
+        handler::{CanaryRouting, HandlerService},
+    },
+};
 use axum::{
     extract::{Path, Query, State},
-    http::StatusCode,
+    http::{header::COOKIE, HeaderMap, StatusCode},
     response::{IntoResponse, Response},
     routing::get,
     Router,
 };
 use std::collections::HashMap;
 
+const CANARY_HEADER: &str = "x-noops-canary";
+const CANARY_COOKIE: &str = "noops-canary";
+
 pub fn routes(state: AppState) -> Router {
     Router::new()
         .route("/:function", get(execute))
@@ -18,9 +29,14 @@ pub fn routes(state: AppState) -> Router {
 async fn execute(
     Path(function): Path<String>,
     Query(query_map): Query<HashMap<String, String>>,
-    State(wasmstore): State<WasmStore>,
+    State(handlers): State<HandlerService>,
+    headers: HeaderMap,
 ) -> Result<Response, Error> {
-    let function = wasmstore.read(&function)?;
+    let routing = canary_routing(&headers);
+    let component = {
+        let handlers = handlers.clone();
+        blocking(move || handlers.read_component(&function, routing)).await?
+    };
     let mut query_list: Vec<(String, String)> = Vec::new();
     for (key, value) in query_map.into_iter() {
         query_list.push((key, value));
@@ -30,7 +46,14 @@ async fn execute(
     let request = bindgen::Request {
         query_params: result,
     };
-    let response = executor::execute(function, request).await?;
+    let response = executor::execute(component.wasm, &component.variables, request).await;
+
+    let status = response.as_ref().map_or(500, |response| response.status);
+    let version = component.version;
+    if let Err(err) = blocking(move || handlers.record_status_code(&version, status)).await {
+        tracing::warn!("Recording status code failed: {}", err);
+    }
+    let response = response?;
 
     Ok((
         StatusCode::from_u16(response.status).unwrap(),
@@ -39,6 +62,28 @@ async fn execute(
         .into_response())
 }
 
+/// Requests can pin the version of a handler with a canary release by setting the
+/// `x-noops-canary` header or the `noops-canary` cookie to `always` or `never`.
+fn canary_routing(headers: &HeaderMap) -> CanaryRouting {
+    let header = headers
+        .get(CANARY_HEADER)
+        .and_then(|value| value.to_str().ok());
+    let cookie = headers
+        .get_all(COOKIE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|cookies| cookies.split(';'))
+        .filter_map(|cookie| cookie.trim().split_once('='))
+        .find(|(name, _)| *name == CANARY_COOKIE)
+        .map(|(_, value)| value);
+
+    match header.or(cookie) {
+        Some("always") => CanaryRouting::Canary,
+        Some("never") => CanaryRouting::Stable,
+        _ => CanaryRouting::Weighted,
+    }
+}
+
 /*
 #[cfg(test)]
 mod tests {
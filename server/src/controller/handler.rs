@@ -1,54 +1,166 @@
 use super::AppState;
-use crate::{errors::Error, repository::user::User, service::handler::HandlerService};
+use crate::{
+    errors::Error,
+    service::{audit::Actor, blocking, handler::HandlerService},
+};
 use axum::{
-    extract::{DefaultBodyLimit, Json, Path, State},
+    extract::{DefaultBodyLimit, Json, Path, Query, State},
     http::StatusCode,
     response::IntoResponse,
-    routing::put,
+    routing::{get, post, put},
     Extension, Router,
 };
 use common::dtos;
 
-const MAX_CONTENT_SIZE_IN_BYTES: usize = 10_000_000;
-
-pub fn routes(state: AppState) -> Router {
+pub fn routes(state: AppState, body_limit: usize) -> Router {
     Router::new()
         .route(
             "/api/:project_name/:function_name",
             put(create).delete(delete).get(read),
         )
+        .route(
+            "/api/:project_name/:function_name/versions",
+            get(read_versions),
+        )
+        .route("/api/:project_name/:function_name/rollback", post(rollback))
+        .route("/api/:project_name/:function_name/restore", post(restore))
+        .route("/api/:project_name/:function_name/canary", get(read_canary))
+        .route(
+            "/api/:project_name/:function_name/canary/promote",
+            post(promote_canary),
+        )
+        .route(
+            "/api/:project_name/:function_name/canary/abort",
+            post(abort_canary),
+        )
         .with_state(state)
-        .layer(DefaultBodyLimit::max(MAX_CONTENT_SIZE_IN_BYTES))
+        .layer(DefaultBodyLimit::max(body_limit))
 }
 
 async fn create(
     Path((project_name, handler_name)): Path<(String, String)>,
+    Query(stage): Query<dtos::StageDTO>,
     State(functions): State<HandlerService>,
-    Extension(user): Extension<User>,
+    Extension(user): Extension<Actor>,
     Json(function_dto): Json<dtos::CreateFunctionDTO>,
 ) -> Result<StatusCode, Error> {
-    functions.create(&user, &project_name, handler_name, &function_dto.wasm)?;
+    blocking(move || {
+        functions.create(
+            &user,
+            &project_name,
+            &stage.stage,
+            handler_name,
+            &function_dto.wasm,
+            function_dto.signature.as_deref(),
+            function_dto.canary,
+        )
+    })
+    .await?;
     Ok(StatusCode::NO_CONTENT)
 }
 
 async fn read(
     Path((project_name, handler_name)): Path<(String, String)>,
+    Query(stage): Query<dtos::StageDTO>,
     State(functions): State<HandlerService>,
-    Extension(user): Extension<User>,
+    Extension(user): Extension<Actor>,
 ) -> Result<impl IntoResponse, Error> {
-    let function = functions.read(&user, &project_name, handler_name)?;
+    let function =
+        blocking(move || functions.read(&user, &project_name, &stage.stage, &handler_name)).await?;
     Ok((StatusCode::OK, Json(function)))
 }
 
 async fn delete(
     Path((project_name, handler_name)): Path<(String, String)>,
+    Query(stage): Query<dtos::StageDTO>,
     State(functions): State<HandlerService>,
-    Extension(user): Extension<User>,
+    Extension(user): Extension<Actor>,
 ) -> Result<StatusCode, Error> {
-    functions.delete(&user, &project_name, &handler_name)?;
+    blocking(move || functions.delete(&user, &project_name, &stage.stage, &handler_name)).await?;
     Ok(StatusCode::NO_CONTENT)
 }
 
+async fn read_versions(
+    Path((project_name, handler_name)): Path<(String, String)>,
+    Query(stage): Query<dtos::StageDTO>,
+    State(functions): State<HandlerService>,
+    Extension(user): Extension<Actor>,
+) -> Result<impl IntoResponse, Error> {
+    let versions =
+        blocking(move || functions.versions(&user, &project_name, &stage.stage, &handler_name))
+            .await?;
+    Ok((StatusCode::OK, Json(versions)))
+}
+
+async fn rollback(
+    Path((project_name, handler_name)): Path<(String, String)>,
+    Query(stage): Query<dtos::StageDTO>,
+    State(functions): State<HandlerService>,
+    Extension(user): Extension<Actor>,
+    Json(rollback_dto): Json<dtos::RollbackDTO>,
+) -> Result<impl IntoResponse, Error> {
+    let function = blocking(move || {
+        functions.rollback(
+            &user,
+            &project_name,
+            &stage.stage,
+            &handler_name,
+            rollback_dto.version,
+        )
+    })
+    .await?;
+    Ok((StatusCode::OK, Json(function)))
+}
+
+async fn restore(
+    Path((project_name, handler_name)): Path<(String, String)>,
+    Query(stage): Query<dtos::StageDTO>,
+    State(functions): State<HandlerService>,
+    Extension(user): Extension<Actor>,
+) -> Result<impl IntoResponse, Error> {
+    let function =
+        blocking(move || functions.restore(&user, &project_name, &stage.stage, &handler_name))
+            .await?;
+    Ok((StatusCode::OK, Json(function)))
+}
+
+async fn read_canary(
+    Path((project_name, handler_name)): Path<(String, String)>,
+    Query(stage): Query<dtos::StageDTO>,
+    State(functions): State<HandlerService>,
+    Extension(user): Extension<Actor>,
+) -> Result<impl IntoResponse, Error> {
+    let canary =
+        blocking(move || functions.canary(&user, &project_name, &stage.stage, &handler_name))
+            .await?;
+    Ok((StatusCode::OK, Json(canary)))
+}
+
+async fn promote_canary(
+    Path((project_name, handler_name)): Path<(String, String)>,
+    Query(stage): Query<dtos::StageDTO>,
+    State(functions): State<HandlerService>,
+    Extension(user): Extension<Actor>,
+) -> Result<impl IntoResponse, Error> {
+    let function = blocking(move || {
+        functions.promote_canary(&user, &project_name, &stage.stage, &handler_name)
+    })
+    .await?;
+    Ok((StatusCode::OK, Json(function)))
+}
+
+async fn abort_canary(
+    Path((project_name, handler_name)): Path<(String, String)>,
+    Query(stage): Query<dtos::StageDTO>,
+    State(functions): State<HandlerService>,
+    Extension(user): Extension<Actor>,
+) -> Result<impl IntoResponse, Error> {
+    let function =
+        blocking(move || functions.abort_canary(&user, &project_name, &stage.stage, &handler_name))
+            .await?;
+    Ok((StatusCode::OK, Json(function)))
+}
+
 /*
 
 #[cfg(test)]
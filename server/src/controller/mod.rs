@@ -1,14 +1,25 @@
 // https://docs.rs/axum/0.6.10/axum/extract/struct.State.html#substates
 
+mod archive;
+mod audit;
 mod auth;
 mod execute;
 mod handler;
+mod org;
 mod project;
+mod quota;
+mod stage;
+mod token;
 
+use crate::config::Limits;
+use crate::service::archive::ArchiveService;
+use crate::service::audit::AuditService;
 use crate::service::auth::AuthService;
 use crate::service::handler::HandlerService;
+use crate::service::org::OrgService;
 use crate::service::project::ProjectService;
-use crate::wasmstore::WasmStore;
+use crate::service::quota::QuotaService;
+use crate::service::token::TokenService;
 use axum::{extract::FromRef, middleware, Router};
 
 #[derive(Debug, Clone)]
@@ -16,31 +27,38 @@ pub struct AppState {
     auth: AuthService,
     projects: ProjectService,
     handlers: HandlerService,
-    wasmstore: WasmStore,
+    archives: ArchiveService,
+    quotas: QuotaService,
+    tokens: TokenService,
+    orgs: OrgService,
+    audit: AuditService,
 }
 
 impl AppState {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         auth: AuthService,
         projects: ProjectService,
         handlers: HandlerService,
-        wasmstore: WasmStore,
+        archives: ArchiveService,
+        quotas: QuotaService,
+        tokens: TokenService,
+        orgs: OrgService,
+        audit: AuditService,
     ) -> Self {
         Self {
             auth,
             projects,
             handlers,
-            wasmstore,
+            archives,
+            quotas,
+            tokens,
+            orgs,
+            audit,
         }
     }
 }
 
-impl FromRef<AppState> for WasmStore {
-    fn from_ref(app_state: &AppState) -> WasmStore {
-        app_state.wasmstore.clone()
-    }
-}
-
 impl FromRef<AppState> for AuthService {
     fn from_ref(app_state: &AppState) -> AuthService {
         app_state.auth.clone()
@@ -59,10 +77,46 @@ impl FromRef<AppState> for HandlerService {
     }
 }
 
-pub fn routes(state: AppState) -> Router {
+impl FromRef<AppState> for ArchiveService {
+    fn from_ref(app_state: &AppState) -> ArchiveService {
+        app_state.archives.clone()
+    }
+}
+
+impl FromRef<AppState> for QuotaService {
+    fn from_ref(app_state: &AppState) -> QuotaService {
+        app_state.quotas.clone()
+    }
+}
+
+impl FromRef<AppState> for TokenService {
+    fn from_ref(app_state: &AppState) -> TokenService {
+        app_state.tokens.clone()
+    }
+}
+
+impl FromRef<AppState> for OrgService {
+    fn from_ref(app_state: &AppState) -> OrgService {
+        app_state.orgs.clone()
+    }
+}
+
+impl FromRef<AppState> for AuditService {
+    fn from_ref(app_state: &AppState) -> AuditService {
+        app_state.audit.clone()
+    }
+}
+
+pub fn routes(state: AppState, limits: &Limits) -> Router {
     Router::new()
         .merge(project::routes(state.clone()))
-        .merge(handler::routes(state.clone()))
+        .merge(handler::routes(state.clone(), limits.upload_size))
+        .merge(stage::routes(state.clone()))
+        .merge(archive::routes(state.clone(), limits.archive_size))
+        .merge(quota::routes(state.clone()))
+        .merge(token::routes(state.clone()))
+        .merge(org::routes(state.clone()))
+        .merge(audit::routes(state.clone()))
         .route_layer(middleware::from_fn_with_state(
             state.clone(),
             auth::auth_middleware,
@@ -0,0 +1,69 @@
+use super::AppState;
+use crate::{
+    errors::Error,
+    service::{audit::Actor, blocking, org::OrgService},
+};
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+    routing::{get, put},
+    Extension, Json, Router,
+};
+use common::dtos::{CreateOrgDTO, SetMemberDTO};
+
+pub fn routes(state: AppState) -> Router {
+    Router::new()
+        .route("/api/orgs", get(list_orgs).post(create_org))
+        .route("/api/orgs/:org_name/members", get(list_members))
+        .route(
+            "/api/orgs/:org_name/members/:login",
+            put(set_member).delete(remove_member),
+        )
+        .with_state(state)
+}
+
+async fn list_orgs(
+    State(orgs): State<OrgService>,
+    Extension(user): Extension<Actor>,
+) -> Result<impl IntoResponse, Error> {
+    let orgs = blocking(move || orgs.list(&user)).await?;
+    Ok((StatusCode::OK, Json(orgs)))
+}
+
+async fn create_org(
+    State(orgs): State<OrgService>,
+    Extension(user): Extension<Actor>,
+    Json(org): Json<CreateOrgDTO>,
+) -> Result<impl IntoResponse, Error> {
+    blocking(move || orgs.create(&user, &org.name)).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn list_members(
+    Path(org_name): Path<String>,
+    State(orgs): State<OrgService>,
+    Extension(user): Extension<Actor>,
+) -> Result<impl IntoResponse, Error> {
+    let members = blocking(move || orgs.members(&user, &org_name)).await?;
+    Ok((StatusCode::OK, Json(members)))
+}
+
+async fn set_member(
+    Path((org_name, login)): Path<(String, String)>,
+    State(orgs): State<OrgService>,
+    Extension(user): Extension<Actor>,
+    Json(member): Json<SetMemberDTO>,
+) -> Result<impl IntoResponse, Error> {
+    blocking(move || orgs.set_member(&user, &org_name, &login, member.role)).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn remove_member(
+    Path((org_name, login)): Path<(String, String)>,
+    State(orgs): State<OrgService>,
+    Extension(user): Extension<Actor>,
+) -> Result<impl IntoResponse, Error> {
+    blocking(move || orgs.remove_member(&user, &org_name, &login)).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -1,16 +1,16 @@
 use super::AppState;
 use crate::{
     errors::Error::{self},
-    repository::user::User,
-    service::project::ProjectService,
+    service::{audit::Actor, blocking, project::ProjectService},
 };
 use axum::{
     extract::{Path, State},
     http::StatusCode,
     response::IntoResponse,
-    routing::get,
+    routing::{delete, get, post},
     Extension, Json, Router,
 };
+use common::dtos;
 
 pub fn routes(state: AppState) -> Router {
     Router::new()
@@ -18,35 +18,81 @@ pub fn routes(state: AppState) -> Router {
             "/api/:project_name",
             get(get_project).post(create_project).delete(delete_project),
         )
+        .route("/api/:project_name/restore", post(restore_project))
+        .route(
+            "/api/:project_name/keys",
+            get(read_trusted_keys).post(add_trusted_key),
+        )
+        .route(
+            "/api/:project_name/keys/:key_name",
+            delete(remove_trusted_key),
+        )
         .with_state(state)
 }
 
 async fn create_project(
     Path(project_name): Path<String>,
     State(projects): State<ProjectService>,
-    Extension(user): Extension<User>,
+    Extension(user): Extension<Actor>,
 ) -> Result<StatusCode, Error> {
-    projects.create(user.id, project_name)?;
+    blocking(move || projects.create(&user, project_name)).await?;
     Ok(StatusCode::NO_CONTENT)
 }
 async fn get_project(
     Path(project_name): Path<String>,
     State(projects): State<ProjectService>,
-    Extension(user): Extension<User>,
+    Extension(user): Extension<Actor>,
 ) -> Result<impl IntoResponse, Error> {
-    let project = projects.read(&user, &project_name)?;
+    let project = blocking(move || projects.read(&user, &project_name)).await?;
     Ok((StatusCode::OK, Json(project)))
 }
 
 async fn delete_project(
     Path(project_name): Path<String>,
     State(projects): State<ProjectService>,
-    Extension(user): Extension<User>,
+    Extension(user): Extension<Actor>,
 ) -> Result<StatusCode, Error> {
-    projects.delete(&user, &project_name)?;
+    blocking(move || projects.delete(&user, &project_name)).await?;
     Ok(StatusCode::OK)
 }
 
+async fn restore_project(
+    Path(project_name): Path<String>,
+    State(projects): State<ProjectService>,
+    Extension(user): Extension<Actor>,
+) -> Result<impl IntoResponse, Error> {
+    let project = blocking(move || projects.restore(&user, &project_name)).await?;
+    Ok((StatusCode::OK, Json(project)))
+}
+
+async fn read_trusted_keys(
+    Path(project_name): Path<String>,
+    State(projects): State<ProjectService>,
+    Extension(user): Extension<Actor>,
+) -> Result<impl IntoResponse, Error> {
+    let keys = blocking(move || projects.trusted_keys(&user, &project_name)).await?;
+    Ok((StatusCode::OK, Json(keys)))
+}
+
+async fn add_trusted_key(
+    Path(project_name): Path<String>,
+    State(projects): State<ProjectService>,
+    Extension(user): Extension<Actor>,
+    Json(key): Json<dtos::CreateTrustedKeyDTO>,
+) -> Result<StatusCode, Error> {
+    blocking(move || projects.add_trusted_key(&user, &project_name, key)).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn remove_trusted_key(
+    Path((project_name, key_name)): Path<(String, String)>,
+    State(projects): State<ProjectService>,
+    Extension(user): Extension<Actor>,
+) -> Result<StatusCode, Error> {
+    blocking(move || projects.remove_trusted_key(&user, &project_name, &key_name)).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
 /*
 #[cfg(test)]
 mod tests {
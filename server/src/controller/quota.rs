@@ -0,0 +1,27 @@
+use super::AppState;
+use crate::{
+    errors::Error,
+    service::{audit::Actor, blocking, quota::QuotaService},
+};
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+    routing::get,
+    Extension, Json, Router,
+};
+
+pub fn routes(state: AppState) -> Router {
+    Router::new()
+        .route("/api/:project_name/quotas", get(get_quotas))
+        .with_state(state)
+}
+
+async fn get_quotas(
+    Path(project_name): Path<String>,
+    State(quotas): State<QuotaService>,
+    Extension(user): Extension<Actor>,
+) -> Result<impl IntoResponse, Error> {
+    let usage = blocking(move || quotas.usage(&user, &project_name)).await?;
+    Ok((StatusCode::OK, Json(usage)))
+}
@@ -0,0 +1,54 @@
+use super::AppState;
+use crate::{
+    errors::Error,
+    service::{audit::Actor, blocking, handler::HandlerService, project::ProjectService},
+};
+use axum::{
+    extract::{Json, Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+    routing::{get, post},
+    Extension, Router,
+};
+use common::dtos;
+
+pub fn routes(state: AppState) -> Router {
+    Router::new()
+        .route(
+            "/api/:project_name/stages/:stage/variables",
+            get(read_variables).put(update_variables),
+        )
+        .route("/api/:project_name/stages/:stage/promote", post(promote))
+        .with_state(state)
+}
+
+async fn read_variables(
+    Path((project_name, stage)): Path<(String, String)>,
+    State(projects): State<ProjectService>,
+    Extension(user): Extension<Actor>,
+) -> Result<impl IntoResponse, Error> {
+    let variables = blocking(move || projects.variables(&user, &project_name, &stage)).await?;
+    Ok((StatusCode::OK, Json(variables)))
+}
+
+async fn update_variables(
+    Path((project_name, stage)): Path<(String, String)>,
+    State(projects): State<ProjectService>,
+    Extension(user): Extension<Actor>,
+    Json(variables): Json<dtos::StageVariablesDTO>,
+) -> Result<StatusCode, Error> {
+    blocking(move || projects.update_variables(&user, &project_name, &stage, variables)).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn promote(
+    Path((project_name, stage)): Path<(String, String)>,
+    State(handlers): State<HandlerService>,
+    Extension(user): Extension<Actor>,
+    Json(promote_dto): Json<dtos::PromoteDTO>,
+) -> Result<impl IntoResponse, Error> {
+    let promoted =
+        blocking(move || handlers.promote(&user, &project_name, &stage, &promote_dto.target))
+            .await?;
+    Ok((StatusCode::OK, Json(promoted)))
+}
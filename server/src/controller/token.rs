@@ -0,0 +1,46 @@
+use super::AppState;
+use crate::{
+    errors::Error,
+    service::{audit::Actor, blocking, token::TokenService},
+};
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+    routing::{delete, get},
+    Extension, Json, Router,
+};
+use common::dtos::CreateTokenDTO;
+
+pub fn routes(state: AppState) -> Router {
+    Router::new()
+        .route("/api/tokens", get(list_tokens).post(create_token))
+        .route("/api/tokens/:token_name", delete(revoke_token))
+        .with_state(state)
+}
+
+async fn list_tokens(
+    State(tokens): State<TokenService>,
+    Extension(user): Extension<Actor>,
+) -> Result<impl IntoResponse, Error> {
+    let tokens = blocking(move || tokens.list(&user)).await?;
+    Ok((StatusCode::OK, Json(tokens)))
+}
+
+async fn create_token(
+    State(tokens): State<TokenService>,
+    Extension(user): Extension<Actor>,
+    Json(token): Json<CreateTokenDTO>,
+) -> Result<impl IntoResponse, Error> {
+    let token = blocking(move || tokens.create(&user, &token)).await?;
+    Ok((StatusCode::CREATED, Json(token)))
+}
+
+async fn revoke_token(
+    Path(token_name): Path<String>,
+    State(tokens): State<TokenService>,
+    Extension(user): Extension<Actor>,
+) -> Result<impl IntoResponse, Error> {
+    blocking(move || tokens.revoke(&user, &token_name)).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -1,9 +1,10 @@
+use crate::service::quota::Quota;
 use axum::{
     http::StatusCode,
     response::{IntoResponse, Response},
     Json,
 };
-use common::dtos::ErrorDTO;
+use common::dtos::{ErrorDTO, Role};
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -16,14 +17,95 @@ pub enum Error {
     #[error("User not registered")]
     UserNotRegistered,
 
+    #[error("Invalid refresh token")]
+    InvalidRefreshToken,
+
+    #[error("Token revoked")]
+    TokenRevoked,
+
+    #[error("Invalid API token")]
+    InvalidApiToken,
+
+    #[error("The token doesn't permit the request")]
+    TokenForbidden,
+
+    #[error("Token not found")]
+    TokenNotFound,
+
+    #[error("Token already exists")]
+    TokenExists,
+
     #[error("Project not found")]
     ProjectNotFound,
 
     #[error("Function not found")]
     HandlerNotFound,
 
-    #[error("Function already exists")]
-    FunctionAlreadyExists,
+    #[error("Version not found")]
+    VersionNotFound,
+
+    #[error("No canary release")]
+    CanaryNotFound,
+
+    #[error("Invalid canary weight")]
+    InvalidCanaryWeight,
+
+    #[error("Stage not found")]
+    StageNotFound,
+
+    #[error("Invalid stage name")]
+    InvalidStage,
+
+    #[error("The name {} is reserved", .0)]
+    ReservedName(String),
+
+    #[error("Source and target stage are the same")]
+    SameStage,
+
+    #[error("Invalid public key")]
+    InvalidPublicKey,
+
+    #[error("Trusted key not found")]
+    TrustedKeyNotFound,
+
+    #[error("Trusted key already exists")]
+    TrustedKeyExists,
+
+    #[error("Handler not signed by a trusted key")]
+    UntrustedSignature,
+
+    #[error("Project already exists")]
+    ProjectExists,
+
+    #[error("Invalid archive: {}", .0)]
+    InvalidArchive(String),
+
+    #[error("Quota exceeded: {} (limit {})", .quota, .limit)]
+    QuotaExceeded { quota: Quota, limit: i64 },
+
+    #[error("Requires the role {}", .0)]
+    RoleForbidden(Role),
+
+    #[error("Organization not found")]
+    OrgNotFound,
+
+    #[error("Organization already exists")]
+    OrgExists,
+
+    #[error("Invalid organization name")]
+    InvalidOrgName,
+
+    #[error("Member not found")]
+    MemberNotFound,
+
+    #[error("The organization needs an owner")]
+    LastOwner,
+
+    #[error("User not found")]
+    UserNotFound,
+
+    #[error("Several users have the login {}", .0)]
+    AmbiguousLogin(String),
 }
 
 impl IntoResponse for Error {
@@ -54,12 +136,109 @@ impl IntoResponse for Error {
             Error::UserNotRegistered => {
                 (StatusCode::UNAUTHORIZED, "User not registered".to_string())
             }
+            Error::InvalidRefreshToken => (
+                StatusCode::UNAUTHORIZED,
+                "Invalid refresh token".to_string(),
+            ),
+            Error::TokenRevoked => (StatusCode::UNAUTHORIZED, "Token revoked".to_string()),
+            Error::InvalidApiToken => (StatusCode::UNAUTHORIZED, "Invalid token".to_string()),
+            Error::TokenForbidden => (
+                StatusCode::FORBIDDEN,
+                "The token doesn't permit the request".to_string(),
+            ),
+            Error::TokenNotFound => (StatusCode::NOT_FOUND, "Token not found".to_string()),
+            Error::TokenExists => (
+                StatusCode::CONFLICT,
+                "A token with this name already exists".to_string(),
+            ),
             Error::ProjectNotFound => (StatusCode::NOT_FOUND, "Project not found".to_string()),
             Error::HandlerNotFound => (StatusCode::NOT_FOUND, "Function not found".to_string()),
-
-            Error::FunctionAlreadyExists => {
-                (StatusCode::CONFLICT, "Function already exists".to_string())
+            Error::VersionNotFound => (StatusCode::NOT_FOUND, "Version not found".to_string()),
+            Error::CanaryNotFound => (StatusCode::NOT_FOUND, "No canary release".to_string()),
+            Error::InvalidCanaryWeight => (
+                StatusCode::BAD_REQUEST,
+                "Canary weight must be between 0 and 100".to_string(),
+            ),
+            Error::StageNotFound => (StatusCode::NOT_FOUND, "Stage not found".to_string()),
+            Error::InvalidStage => (
+                StatusCode::BAD_REQUEST,
+                "Stage names must not be empty or contain a slash".to_string(),
+            ),
+            Error::ReservedName(name) => (
+                StatusCode::BAD_REQUEST,
+                format!("The name {} is reserved", name),
+            ),
+            Error::SameStage => (
+                StatusCode::BAD_REQUEST,
+                "Source and target stage must differ".to_string(),
+            ),
+            Error::InvalidPublicKey => (
+                StatusCode::BAD_REQUEST,
+                "Public key must be a hex encoded Ed25519 key".to_string(),
+            ),
+            Error::TrustedKeyNotFound => {
+                (StatusCode::NOT_FOUND, "Trusted key not found".to_string())
             }
+            Error::TrustedKeyExists => (
+                StatusCode::CONFLICT,
+                "A trusted key with this name already exists".to_string(),
+            ),
+            Error::UntrustedSignature => (
+                StatusCode::FORBIDDEN,
+                "The project requires handlers signed by one of its trusted keys".to_string(),
+            ),
+            Error::ProjectExists => (
+                StatusCode::CONFLICT,
+                "A project with this name already exists".to_string(),
+            ),
+            Error::QuotaExceeded {
+                quota: Quota::InvocationsPerDay,
+                limit,
+            } => (
+                StatusCode::TOO_MANY_REQUESTS,
+                format!(
+                    "Quota exceeded: {} (limit {})",
+                    Quota::InvocationsPerDay,
+                    limit
+                ),
+            ),
+            Error::QuotaExceeded { quota, limit } => (
+                StatusCode::FORBIDDEN,
+                format!("Quota exceeded: {} (limit {})", quota, limit),
+            ),
+            Error::InvalidArchive(reason) => (
+                StatusCode::BAD_REQUEST,
+                format!("Invalid archive: {}", reason),
+            ),
+            Error::RoleForbidden(role) => (
+                StatusCode::FORBIDDEN,
+                format!("Requires the role {} in the organization", role),
+            ),
+            Error::OrgNotFound => (StatusCode::NOT_FOUND, "Organization not found".to_string()),
+            Error::OrgExists => (
+                StatusCode::CONFLICT,
+                "An organization with this name already exists".to_string(),
+            ),
+            Error::InvalidOrgName => (
+                StatusCode::BAD_REQUEST,
+                "Organization names must not be empty or contain a slash".to_string(),
+            ),
+            Error::MemberNotFound => (StatusCode::NOT_FOUND, "Member not found".to_string()),
+            Error::LastOwner => (
+                StatusCode::CONFLICT,
+                "The organization needs at least one owner".to_string(),
+            ),
+            Error::UserNotFound => (
+                StatusCode::NOT_FOUND,
+                "User not found, they must log in once before".to_string(),
+            ),
+            Error::AmbiguousLogin(login) => (
+                StatusCode::CONFLICT,
+                format!(
+                    "Several users have the login {}, qualify it with their identity provider like github:{}",
+                    login, login
+                ),
+            ),
 
             _ => (
                 StatusCode::INTERNAL_SERVER_ERROR,
@@ -36,15 +36,26 @@ impl WasiView for CommandCtx {
     }
 }
 
+/// Compiles the component and checks that the imports it needs are provided when executing it
+pub fn validate(wasm: &[u8]) -> anyhow::Result<()> {
+    let component = Component::from_binary(&ENGINE, wasm)?;
+
+    let mut linker = Linker::<CommandCtx>::new(&ENGINE);
+    preview2::command::add_to_linker(&mut linker)?;
+    linker.instantiate_pre(&component)?;
+    Ok(())
+}
+
 pub async fn execute(
     wasm: Vec<u8>,
+    env: &[(String, String)],
     request: bindgen::Request,
 ) -> anyhow::Result<bindgen::Response> {
     let component = Component::from_binary(&ENGINE, &wasm)?;
 
     let mut linker = Linker::new(&ENGINE);
     let mut table = Table::new();
-    let wasi = WasiCtxBuilder::new().build(&mut table)?;
+    let wasi = WasiCtxBuilder::new().set_env(env).build(&mut table)?;
     preview2::command::add_to_linker(&mut linker)?;
 
     let linker = linker;
@@ -69,7 +80,7 @@ mod tests {
         let component =
             bindgen::create_component(&module).expect("Unable to create component from module");
         let request = bindgen::Request::default();
-        let response = executor::execute(component, request).await?;
+        let response = executor::execute(component, &[], request).await?;
 
         assert_eq!(200, response.status);
         Ok(())
@@ -88,7 +99,7 @@ mod tests {
                 ("key3".to_string(), "value3".to_string()),
             ],
         };
-        let response = executor::execute(component, request).await?;
+        let response = executor::execute(component, &[], request).await?;
         assert_eq!(200, response.status);
         assert_eq!(
             format!("key1=value1\nkey2=value2\nkey3=value3\n"),
@@ -96,4 +107,14 @@ mod tests {
         );
         Ok(())
     }
+
+    #[test]
+    fn validate_ok() -> anyhow::Result<()> {
+        let module = std::fs::read(env!("CARGO_CDYLIB_FILE_RETURN_STATUS_CODE_200"))?;
+        let component = bindgen::create_component(&module)?;
+
+        executor::validate(&component)?;
+        assert!(executor::validate(&module).is_err());
+        Ok(())
+    }
 }
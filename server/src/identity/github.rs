@@ -1,3 +1,6 @@
+use super::{Identity, IdentityProvider};
+use async_trait::async_trait;
+use common::dtos::AuthProviderDTO;
 use reqwest::{
     header::{HeaderMap, ACCEPT, AUTHORIZATION, USER_AGENT},
     Client,
@@ -5,23 +8,20 @@ use reqwest::{
 
 use serde::Deserialize;
 
+pub const PROVIDER: &str = "github";
+
+/// The OAuth app of the noops cloud
+pub const DEFAULT_CLIENT_ID: &str = "213ab154663f83fa7e80";
+
 const GITHUB_API_USER: &str = "https://api.github.com/user";
 const GITHUB_API_EMAIL: &str = "https://api.github.com/user/emails";
-
-#[derive(Debug, Clone, Default)]
-pub struct GithubUser {
-    pub id: i32,
-    pub name: Option<String>,
-    pub email: String,
-    pub login: String,
-    pub location: Option<String>,
-    pub company: Option<String>,
-    pub access_token: String,
-}
+const DEVICE_AUTHORIZATION_URL: &str = "https://github.com/login/device/code";
+const AUTHORIZATION_URL: &str = "https://github.com/login/oauth/authorize";
+const TOKEN_URL: &str = "https://github.com/login/oauth/access_token";
 
 #[derive(Debug, Clone, Default, Deserialize)]
 struct RawGithubUser {
-    pub id: i32,
+    pub id: i64,
     pub login: String,
     pub name: Option<String>,
     pub location: Option<String>,
@@ -34,37 +34,21 @@ struct Email {
     primary: bool,
 }
 
-#[cfg_attr(test, faux::create)]
+/// Logs users in with their GitHub account
 #[derive(Debug, Clone)]
-pub struct GithubClient {
+pub struct Github {
     client: Client,
+    client_id: String,
 }
 
-#[cfg_attr(test, faux::methods)]
-impl GithubClient {
-    pub fn new() -> Self {
+impl Github {
+    pub fn new(client_id: String) -> Self {
         Self {
             client: reqwest::Client::new(),
+            client_id,
         }
     }
 
-    pub async fn get_user(&self, access_token: String) -> anyhow::Result<GithubUser> {
-        let headers = self.create_headers(&access_token)?;
-
-        let user_info = self.get_user_infos(headers.clone()).await?;
-        let email = self.get_primary_email(headers).await?;
-
-        Ok(GithubUser {
-            email: email.email,
-            name: user_info.name,
-            location: user_info.location,
-            company: user_info.company,
-            login: user_info.login,
-            id: user_info.id,
-            access_token,
-        })
-    }
-
     async fn get_user_infos(&self, headers: HeaderMap) -> anyhow::Result<RawGithubUser> {
         let user = self
             .client
@@ -72,6 +56,7 @@ impl GithubClient {
             .headers(headers)
             .send()
             .await?
+            .error_for_status()?
             .json()
             .await?;
 
@@ -85,10 +70,14 @@ impl GithubClient {
             .headers(headers)
             .send()
             .await?
+            .error_for_status()?
             .json()
             .await?;
 
-        let email = emails.into_iter().find(|email| email.primary).unwrap();
+        let email = emails
+            .into_iter()
+            .find(|email| email.primary)
+            .ok_or(anyhow::anyhow!("The GitHub account has no primary email"))?;
         Ok(email)
     }
 
@@ -102,3 +91,34 @@ impl GithubClient {
         Ok(headers)
     }
 }
+
+#[async_trait]
+impl IdentityProvider for Github {
+    fn device_flow(&self) -> AuthProviderDTO {
+        AuthProviderDTO {
+            name: PROVIDER.to_string(),
+            client_id: self.client_id.clone(),
+            authorization_url: AUTHORIZATION_URL.to_string(),
+            device_authorization_url: DEVICE_AUTHORIZATION_URL.to_string(),
+            token_url: TOKEN_URL.to_string(),
+            scopes: vec!["read:user".to_string(), "user:email".to_string()],
+        }
+    }
+
+    async fn identify(&self, access_token: &str) -> anyhow::Result<Identity> {
+        let headers = self.create_headers(access_token)?;
+
+        let user_info = self.get_user_infos(headers.clone()).await?;
+        let email = self.get_primary_email(headers).await?;
+
+        Ok(Identity {
+            provider: PROVIDER.to_string(),
+            subject: user_info.id.to_string(),
+            login: user_info.login,
+            email: email.email,
+            name: user_info.name,
+            location: user_info.location,
+            company: user_info.company,
+        })
+    }
+}
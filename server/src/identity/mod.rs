@@ -0,0 +1,54 @@
+pub mod dev;
+pub mod github;
+pub mod oidc;
+
+use async_trait::async_trait;
+use common::dtos::AuthProviderDTO;
+use std::{fmt::Debug, sync::Arc};
+
+/// A user as the identity provider knows them
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Identity {
+    /// The name of the provider, like `github`
+    pub provider: String,
+    /// The id of the user at the provider, which never changes
+    pub subject: String,
+    pub login: String,
+    pub email: String,
+    pub name: Option<String>,
+    pub location: Option<String>,
+    pub company: Option<String>,
+}
+
+/// A service users log in with. The CLI obtains an access token from the provider with the
+/// OAuth device flow, the server looks the user up with it.
+#[async_trait]
+pub trait IdentityProvider: Debug + Send + Sync {
+    /// Where and how the CLI obtains an access token
+    fn device_flow(&self) -> AuthProviderDTO;
+
+    /// The user the access token was issued to
+    async fn identify(&self, access_token: &str) -> anyhow::Result<Identity>;
+}
+
+/// The identity provider configured for the server
+#[cfg_attr(test, faux::create)]
+#[derive(Debug, Clone)]
+pub struct IdentityClient {
+    provider: Arc<dyn IdentityProvider>,
+}
+
+#[cfg_attr(test, faux::methods)]
+impl IdentityClient {
+    pub fn new(provider: Arc<dyn IdentityProvider>) -> Self {
+        Self { provider }
+    }
+
+    pub fn device_flow(&self) -> AuthProviderDTO {
+        self.provider.device_flow()
+    }
+
+    pub async fn identify(&self, access_token: String) -> anyhow::Result<Identity> {
+        self.provider.identify(&access_token).await
+    }
+}
@@ -0,0 +1,211 @@
+use super::{Identity, IdentityProvider};
+use async_trait::async_trait;
+use common::dtos::AuthProviderDTO;
+use reqwest::Client;
+use serde::Deserialize;
+
+const DISCOVERY_PATH: &str = ".well-known/openid-configuration";
+
+/// The endpoints of an issuer, see
+/// https://openid.net/specs/openid-connect-discovery-1_0.html#ProviderMetadata
+#[derive(Debug, Clone, Deserialize)]
+struct Discovery {
+    issuer: String,
+    authorization_endpoint: String,
+    token_endpoint: String,
+    device_authorization_endpoint: Option<String>,
+    userinfo_endpoint: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct UserInfo {
+    sub: String,
+    email: Option<String>,
+    name: Option<String>,
+    preferred_username: Option<String>,
+}
+
+/// Logs users in with an OpenID Connect provider supporting the device flow, like Keycloak,
+/// GitLab or Google. Users are identified by the issuer and their subject, so the subjects of
+/// different issuers never clash.
+#[derive(Debug, Clone)]
+pub struct Oidc {
+    client: Client,
+    client_id: String,
+    scopes: Vec<String>,
+    discovery: Discovery,
+}
+
+impl Oidc {
+    /// Reads the endpoints from the discovery document of the issuer
+    pub async fn discover(
+        issuer: &str,
+        client_id: String,
+        scopes: Vec<String>,
+    ) -> anyhow::Result<Self> {
+        let client = Client::new();
+        let issuer = issuer.trim_end_matches('/');
+        let discovery: Discovery = client
+            .get(format!("{}/{}", issuer, DISCOVERY_PATH))
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        if discovery.issuer.trim_end_matches('/') != issuer {
+            anyhow::bail!(
+                "The discovery document of {} names the issuer {}",
+                issuer,
+                discovery.issuer
+            );
+        }
+        if discovery.device_authorization_endpoint.is_none() {
+            anyhow::bail!("The issuer {} doesn't support the device flow", issuer);
+        }
+
+        Ok(Self {
+            client,
+            client_id,
+            scopes,
+            discovery,
+        })
+    }
+}
+
+#[async_trait]
+impl IdentityProvider for Oidc {
+    fn device_flow(&self) -> AuthProviderDTO {
+        AuthProviderDTO {
+            name: self.discovery.issuer.clone(),
+            client_id: self.client_id.clone(),
+            authorization_url: self.discovery.authorization_endpoint.clone(),
+            device_authorization_url: self
+                .discovery
+                .device_authorization_endpoint
+                .clone()
+                .unwrap_or_default(),
+            token_url: self.discovery.token_endpoint.clone(),
+            scopes: self.scopes.clone(),
+        }
+    }
+
+    async fn identify(&self, access_token: &str) -> anyhow::Result<Identity> {
+        let user_info: UserInfo = self
+            .client
+            .get(&self.discovery.userinfo_endpoint)
+            .bearer_auth(access_token)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        let email = user_info.email.ok_or(anyhow::anyhow!(
+            "The issuer returned no email, request the \"email\" scope"
+        ))?;
+        Ok(Identity {
+            provider: self.discovery.issuer.clone(),
+            subject: user_info.sub,
+            login: user_info.preferred_username.unwrap_or(email.clone()),
+            email,
+            name: user_info.name,
+            location: None,
+            company: None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{
+        headers::{authorization::Bearer, Authorization},
+        http::StatusCode,
+        routing::get,
+        Json, Router, Server, TypedHeader,
+    };
+    use serde_json::{json, Value};
+    use std::net::TcpListener;
+
+    const CLIENT_ID: &str = "noops-cli";
+    const ACCESS_TOKEN: &str = "eing4Ohpheefoh1ahc8Oocha";
+    const SUBJECT: &str = "f81d4fae-7dec-11d0-a765-00a0c91e6bf6";
+
+    /// Serves the discovery document and the user info of an issuer on a random port
+    fn mock_issuer(issuer: Option<&str>) -> anyhow::Result<String> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let url = format!("http://{}", listener.local_addr()?);
+        let discovery = json!({
+            "issuer": issuer.unwrap_or(&url),
+            "authorization_endpoint": format!("{}/auth", url),
+            "token_endpoint": format!("{}/token", url),
+            "device_authorization_endpoint": format!("{}/device", url),
+            "userinfo_endpoint": format!("{}/userinfo", url),
+        });
+        let app = Router::new()
+            .route(
+                "/.well-known/openid-configuration",
+                get(move || async move { Json(discovery) }),
+            )
+            .route("/userinfo", get(user_info));
+        tokio::spawn(Server::from_tcp(listener)?.serve(app.into_make_service()));
+        Ok(url)
+    }
+
+    async fn user_info(
+        TypedHeader(header): TypedHeader<Authorization<Bearer>>,
+    ) -> Result<Json<Value>, StatusCode> {
+        if header.token() != ACCESS_TOKEN {
+            return Err(StatusCode::UNAUTHORIZED);
+        }
+        Ok(Json(json!({
+            "sub": SUBJECT,
+            "email": "jane@example.com",
+            "name": "Jane Doe",
+            "preferred_username": "jane",
+        })))
+    }
+
+    async fn discover(issuer: &str) -> anyhow::Result<Oidc> {
+        Oidc::discover(issuer, CLIENT_ID.to_string(), vec!["openid".to_string()]).await
+    }
+
+    #[tokio::test]
+    async fn identify_ok() -> anyhow::Result<()> {
+        let issuer = mock_issuer(None)?;
+        let oidc = discover(&issuer).await?;
+
+        let identity = oidc.identify(ACCESS_TOKEN).await?;
+        assert_eq!(issuer, identity.provider);
+        assert_eq!(SUBJECT, identity.subject);
+        assert_eq!("jane", identity.login);
+        assert_eq!("jane@example.com", identity.email);
+        assert_eq!(Some("Jane Doe".to_string()), identity.name);
+
+        let device_flow = oidc.device_flow();
+        assert_eq!(CLIENT_ID, device_flow.client_id);
+        assert_eq!(
+            format!("{}/device", issuer),
+            device_flow.device_authorization_url
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn identify_invalid_token() -> anyhow::Result<()> {
+        let issuer = mock_issuer(None)?;
+        let oidc = discover(&issuer).await?;
+
+        assert!(oidc.identify("invalid").await.is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn discover_other_issuer() -> anyhow::Result<()> {
+        let issuer = mock_issuer(Some("https://evil.example.com"))?;
+
+        assert!(discover(&issuer).await.is_err());
+        Ok(())
+    }
+}
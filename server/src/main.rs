@@ -1,68 +1,473 @@
+#[cfg(test)]
+mod bench;
 mod bindgen;
+mod config;
 mod controller;
 mod errors;
 mod executor;
-mod github;
+mod identity;
 mod jwt;
 mod repository;
 mod service;
 mod wasmstore;
 
 use crate::controller::AppState;
+use anyhow::Context;
 use axum::Server;
-use diesel::prelude::*;
-use diesel::sqlite::SqliteConnection;
-use diesel_migrations::{embed_migrations, EmbeddedMigrations, MigrationHarness};
-use github::GithubClient;
-use service::{auth::AuthService, handler::HandlerService, project::ProjectService};
-use std::{net::SocketAddr, path::Path};
+use clap::{Parser, Subcommand};
+use config::{
+    AuthConfig, AuthProvider, Config, ConfigArgs, DatabaseConfig, JwtSettings, LogConfig,
+    LogFormat, StorageBackend, StorageConfig,
+};
+use identity::{
+    dev::Dev,
+    github::{self, Github},
+    oidc::Oidc,
+    IdentityClient, IdentityProvider,
+};
+use jwt::{JwtConfig, JwtKeys};
+use repository::{
+    api_token::ApiTokenRepository,
+    audit::AuditRepository,
+    org::OrgRepository,
+    quota::{QuotaOverride, QuotaRepository},
+    session::SessionRepository,
+    DatabasePool, Repository,
+};
+use service::{
+    archive::ArchiveService,
+    audit::AuditService,
+    auth::AuthService,
+    blocking,
+    fsck::ConsistencyChecker,
+    gc::GarbageCollector,
+    handler::HandlerService,
+    org::OrgService,
+    project::ProjectService,
+    quota::{QuotaService, Quotas},
+    token::TokenService,
+    trash::TrashService,
+};
+use std::{net::SocketAddr, sync::Arc, time::Duration};
 use tower_http::trace::TraceLayer;
-use tracing_subscriber::{self, layer::SubscriberExt, util::SubscriberInitExt};
+use tracing_subscriber::{self, layer::SubscriberExt, util::SubscriberInitExt, EnvFilter};
+use wasmstore::{filesystem::FileSystem, s3::S3, Storage, WasmStore};
 
-const WASMSTORE_PREFIX: &str = "./wasmstore";
-const DATABASE_CONNECTION: &str = "./noops.sqlite";
-const MIGRATIONS: EmbeddedMigrations = embed_migrations!("migrations");
+const PURGE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+#[derive(Parser)]
+#[command(author, version, about = "noops server", long_about = None)]
+struct Cli {
+    #[command(flatten)]
+    config: ConfigArgs,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Run the server (default)
+    Serve,
+
+    /// Remove stored components no handler version references anymore
+    Gc {
+        /// Only list the components which would be removed
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Cross-check the database against the stored components
+    Fsck {
+        /// Fix the inconsistencies which can be fixed
+        #[arg(long)]
+        repair: bool,
+    },
+
+    /// Show or override the quotas of a user
+    Quota {
+        #[command(subcommand)]
+        command: QuotaCommand,
+    },
+}
+
+#[derive(Subcommand)]
+enum QuotaCommand {
+    /// Show the quotas of a user
+    Show {
+        /// Login of the user, qualified like github:alice if users of several providers share it
+        login: String,
+    },
+
+    /// Override quotas of a user, the quotas not given stay unchanged
+    Set {
+        /// Login of the user, qualified like github:alice if users of several providers share it
+        login: String,
+
+        #[arg(long)]
+        projects: Option<i64>,
+
+        #[arg(long)]
+        handlers_per_project: Option<i64>,
+
+        /// In bytes
+        #[arg(long)]
+        artifact_size: Option<i64>,
+
+        /// In bytes
+        #[arg(long)]
+        storage: Option<i64>,
+
+        #[arg(long)]
+        invocations_per_day: Option<i64>,
+    },
+
+    /// Remove the overrides of a user, the defaults of the server apply again
+    Reset {
+        /// Login of the user, qualified like github:alice if users of several providers share it
+        login: String,
+    },
+}
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    tracing_subscriber::registry()
-        .with(
-            tracing_subscriber::EnvFilter::try_from_default_env()
-                .unwrap_or_else(|_| "noops_server=debug,tower_http=debug".into()),
-        )
-        .with(tracing_subscriber::fmt::layer())
-        .init();
-
-    let state = create_app_state(Path::new(DATABASE_CONNECTION), Path::new(WASMSTORE_PREFIX))?;
-    run_database_migration()?;
-    let app = controller::routes(state).layer(TraceLayer::new_for_http());
-    let addr = SocketAddr::from(([0, 0, 0, 0], 8080));
-    tracing::info!("listening on {}", addr);
-    Server::bind(&addr)
-        .serve(app.into_make_service())
+    let cli = Cli::parse();
+    let config = cli.config.load()?;
+    init_logging(&config.log);
+
+    run_database_migration(&config.database)?;
+    let pool = repository::create_pool(&config.database.url, config.database.options())?;
+    let wasmstore = WasmStore::new(create_storage(&config.storage)?);
+    let gc = create_garbage_collector(pool.clone(), wasmstore.clone());
+    let migrated = gc.migrate_legacy_files()?;
+    if migrated > 0 {
+        tracing::info!("Moved {} components to their content address", migrated);
+    }
+    let quotas = create_quota_service(pool.clone(), wasmstore.clone(), config.quotas);
+    let measured = quotas.measure_components()?;
+    if measured > 0 {
+        tracing::info!("Recorded the sizes of {} components", measured);
+    }
+
+    match cli.command.unwrap_or(Command::Serve) {
+        Command::Serve => {
+            let jwt = create_jwt_config(&config.jwt)?;
+            let identities = IdentityClient::new(create_identity_provider(&config.auth).await?);
+            serve(&config, pool, wasmstore, quotas, jwt, identities).await?
+        }
+        Command::Gc { dry_run } => collect_garbage(&gc, dry_run)?,
+        Command::Fsck { repair } => {
+            let checker = create_consistency_checker(pool, wasmstore);
+            check_consistency(&checker, repair)?
+        }
+        Command::Quota { command } => manage_quotas(pool, &quotas, command)?,
+    }
+
+    Ok(())
+}
+
+async fn serve(
+    config: &Config,
+    pool: DatabasePool,
+    wasmstore: WasmStore,
+    quotas: QuotaService,
+    jwt: JwtConfig,
+    identities: IdentityClient,
+) -> anyhow::Result<()> {
+    let trash = create_trash_service(pool.clone(), wasmstore.clone(), config.trash_retention());
+    tokio::spawn(purge_trash(trash));
+
+    service::set_public_url(config.public_url());
+    let state = create_app_state(
+        pool,
+        wasmstore,
+        config.trash_retention(),
+        quotas,
+        jwt,
+        chrono::Duration::days(config.jwt.session_expiration_days),
+        identities,
+    );
+    let app = controller::routes(state, &config.limits).layer(TraceLayer::new_for_http());
+    tracing::info!("listening on {}", config.listen);
+    Server::bind(&config.listen)
+        .serve(app.into_make_service_with_connect_info::<SocketAddr>())
         .await
         .unwrap();
 
     Ok(())
 }
 
-fn create_app_state(database_path: &Path, wasmstore_path: &Path) -> anyhow::Result<AppState> {
-    let (users, projects, handlers) = repository::new(database_path);
-    let wasmstore = wasmstore::WasmStore::new(wasmstore_path)?;
+/// Purges the expired items from the trash periodically
+async fn purge_trash(trash: TrashService) {
+    let mut interval = tokio::time::interval(PURGE_INTERVAL);
+    loop {
+        interval.tick().await;
+        let trash = trash.clone();
+        match blocking(move || trash.purge()).await {
+            Ok(purged) if purged.projects > 0 || purged.handlers > 0 => tracing::info!(
+                "Purged {} projects and {} handlers from the trash",
+                purged.projects,
+                purged.handlers
+            ),
+            Ok(_) => {}
+            Err(err) => tracing::error!("Purging the trash failed: {}", err),
+        }
+    }
+}
 
-    let auth_service = AuthService::new(GithubClient::new(), users);
-    let project_service = ProjectService::new(projects.clone(), handlers.clone());
-    let handler_service = HandlerService::new(projects, handlers, wasmstore.clone());
+fn collect_garbage(gc: &GarbageCollector, dry_run: bool) -> anyhow::Result<()> {
+    let garbage = gc.collect(dry_run)?;
+    for name in garbage.removed.iter().chain(&garbage.temp_files) {
+        if dry_run {
+            println!("Would remove {}", name);
+        } else {
+            println!("Removed {}", name);
+        }
+    }
+    println!(
+        "{} components removed, {} kept, {} temporary files removed",
+        garbage.removed.len(),
+        garbage.kept,
+        garbage.temp_files.len()
+    );
+    Ok(())
+}
 
-    let state = AppState::new(auth_service, project_service, handler_service, wasmstore);
+fn check_consistency(checker: &ConsistencyChecker, repair: bool) -> anyhow::Result<()> {
+    let inconsistencies = checker.check()?;
+    let mut remaining = 0;
+    for inconsistency in &inconsistencies {
+        println!("{}", inconsistency);
+        if !repair {
+            remaining += 1;
+        } else if checker.repair(inconsistency)? {
+            println!("  Repaired");
+        } else {
+            println!("  Cannot be repaired, the handler has to be deployed again");
+            remaining += 1;
+        }
+    }
+
+    println!(
+        "{} inconsistencies found, {} remaining",
+        inconsistencies.len(),
+        remaining
+    );
+    if remaining > 0 {
+        anyhow::bail!("The database and the wasmstore are inconsistent");
+    }
+    Ok(())
+}
+
+fn manage_quotas(
+    pool: DatabasePool,
+    quotas: &QuotaService,
+    command: QuotaCommand,
+) -> anyhow::Result<()> {
+    let (users, _, _, _, _, _, _) = repository::new(pool);
+    let login = match &command {
+        QuotaCommand::Show { login }
+        | QuotaCommand::Set { login, .. }
+        | QuotaCommand::Reset { login } => login,
+    };
+    let user = service::org::user_by_login(&users, login)?
+        .with_context(|| format!("User {} not found", login))?;
+
+    match command {
+        QuotaCommand::Show { .. } => {}
+        QuotaCommand::Set {
+            projects,
+            handlers_per_project,
+            artifact_size,
+            storage,
+            invocations_per_day,
+            ..
+        } => quotas.set(&QuotaOverride {
+            user_id: user.id.clone(),
+            projects,
+            handlers_per_project,
+            artifact_size,
+            storage,
+            invocations_per_day,
+        })?,
+        QuotaCommand::Reset { .. } => quotas.reset(&user.id)?,
+    }
+
+    let limits = quotas.limits(&user.id)?;
+    println!("projects:             {}", limits.projects);
+    println!("handlers per project: {}", limits.handlers_per_project);
+    println!("artifact size:        {} bytes", limits.artifact_size);
+    println!("storage:              {} bytes", limits.storage);
+    println!("invocations per day:  {}", limits.invocations_per_day);
+    Ok(())
+}
+
+fn init_logging(log: &LogConfig) {
+    let registry = tracing_subscriber::registry().with(EnvFilter::new(&log.filter));
+    match log.format {
+        LogFormat::Text => registry.with(tracing_subscriber::fmt::layer()).init(),
+        LogFormat::Json => registry
+            .with(tracing_subscriber::fmt::layer().json())
+            .init(),
+    }
+}
+
+fn create_jwt_config(settings: &JwtSettings) -> anyhow::Result<JwtConfig> {
+    let keys = match &settings.signing_key {
+        Some(signing_key) => JwtKeys::load(signing_key, &settings.verification_keys)?,
+        None => {
+            tracing::warn!(
+                "No --jwt-signing-key configured, the tokens become invalid when the server restarts"
+            );
+            JwtKeys::ephemeral()
+        }
+    };
+    Ok(JwtConfig::new(
+        keys,
+        settings.issuer.clone(),
+        settings.expiration_minutes * 60,
+    ))
+}
+
+async fn create_identity_provider(auth: &AuthConfig) -> anyhow::Result<Arc<dyn IdentityProvider>> {
+    if auth.dev {
+        tracing::warn!(
+            "--dev-auth is enabled, everyone is logged in as {} without authentication",
+            auth.dev_user
+        );
+        return Ok(Arc::new(Dev::new(auth.dev_user.clone())));
+    }
+    match auth.provider {
+        AuthProvider::Github => {
+            let client_id = auth
+                .client_id
+                .clone()
+                .unwrap_or(github::DEFAULT_CLIENT_ID.to_string());
+            Ok(Arc::new(Github::new(client_id)))
+        }
+        AuthProvider::Oidc => {
+            let (issuer, client_id) = auth.oidc()?;
+            let oidc = Oidc::discover(&issuer, client_id, auth.oidc_scopes.clone())
+                .await
+                .with_context(|| format!("Discovering the issuer {} failed", issuer))?;
+            Ok(Arc::new(oidc))
+        }
+    }
+}
+
+fn create_storage(storage: &StorageConfig) -> anyhow::Result<Arc<dyn Storage>> {
+    match storage.backend {
+        StorageBackend::Filesystem => Ok(Arc::new(FileSystem::new(&storage.path)?)),
+        StorageBackend::S3 => Ok(Arc::new(S3::new(storage.s3()?)?)),
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn create_app_state(
+    pool: DatabasePool,
+    wasmstore: WasmStore,
+    trash_retention: Duration,
+    quotas: QuotaService,
+    jwt: JwtConfig,
+    session_lifetime: chrono::Duration,
+    identities: IdentityClient,
+) -> AppState {
+    let trash = create_trash_service(pool.clone(), wasmstore.clone(), trash_retention);
+    let sessions = SessionRepository::new(pool.clone());
+    let api_tokens = ApiTokenRepository::new(pool.clone());
+    let orgs = OrgRepository::new(pool.clone());
+    let audit_events = AuditRepository::new(pool.clone());
+    let (users, projects, handlers, versions, status_codes, variables, trusted_keys) =
+        repository::new(pool);
+
+    let audit_service = AuditService::new(audit_events, projects.clone());
+    let token_service = TokenService::new(api_tokens, users.clone(), projects.clone());
+    let org_service = OrgService::new(orgs, users.clone());
+    let auth_service = AuthService::new(identities, users, sessions, jwt, session_lifetime);
+    let project_service = ProjectService::new(
+        projects.clone(),
+        handlers.clone(),
+        variables.clone(),
+        trusted_keys.clone(),
+        trash.clone(),
+        quotas.clone(),
+        org_service.clone(),
+    );
+    let archive_service = ArchiveService::new(
+        projects.clone(),
+        handlers.clone(),
+        versions.clone(),
+        variables.clone(),
+        trusted_keys.clone(),
+        wasmstore.clone(),
+        trash.clone(),
+        quotas.clone(),
+        org_service.clone(),
+        audit_service.clone(),
+    );
+    let handler_service = HandlerService::new(
+        projects,
+        handlers,
+        versions,
+        status_codes,
+        variables,
+        trusted_keys,
+        wasmstore,
+        trash,
+        quotas.clone(),
+    );
+
+    AppState::new(
+        auth_service,
+        project_service,
+        handler_service,
+        archive_service,
+        quotas,
+        token_service,
+        org_service,
+        audit_service,
+    )
+}
+
+fn create_garbage_collector(pool: DatabasePool, wasmstore: WasmStore) -> GarbageCollector {
+    let (_, _, _, versions, _, _, _) = repository::new(pool);
+    GarbageCollector::new(versions, wasmstore)
+}
+
+fn create_trash_service(
+    pool: DatabasePool,
+    wasmstore: WasmStore,
+    retention: Duration,
+) -> TrashService {
+    let (_, projects, handlers, versions, _, _, _) = repository::new(pool.clone());
+    let gc = create_garbage_collector(pool, wasmstore);
+    TrashService::new(projects, handlers, versions, gc, retention)
+}
+
+fn create_quota_service(
+    pool: DatabasePool,
+    wasmstore: WasmStore,
+    defaults: Quotas,
+) -> QuotaService {
+    let (_, projects, handlers, versions, _, _, _) = repository::new(pool.clone());
+    QuotaService::new(
+        QuotaRepository::new(pool),
+        projects,
+        handlers,
+        versions,
+        wasmstore,
+        defaults,
+    )
+}
 
-    Ok(state)
+fn create_consistency_checker(pool: DatabasePool, wasmstore: WasmStore) -> ConsistencyChecker {
+    let (users, projects, handlers, versions, _, _, _) = repository::new(pool);
+    ConsistencyChecker::new(users, projects, handlers, versions, wasmstore)
 }
 
-fn run_database_migration() -> anyhow::Result<()> {
+fn run_database_migration(database: &DatabaseConfig) -> anyhow::Result<()> {
     tracing::info!("Running Database Migrations");
-    let mut connection = SqliteConnection::establish(DATABASE_CONNECTION)?;
-    connection.run_pending_migrations(MIGRATIONS).unwrap();
+    repository::migrate(&database.url, database.options())?;
     tracing::info!("Database Migrations Successful");
     Ok(())
 }
@@ -0,0 +1,217 @@
+use super::{
+    audit::{self, AuditEvent},
+    connection::execute_native,
+    schema::api_tokens::{self, dsl},
+    DatabasePool,
+};
+use chrono::{NaiveDateTime, Utc};
+use common::dtos::{GetTokenDTO, TokenScope};
+use diesel::prelude::*;
+
+/// A long-lived token of a user, e.g. for deployments from CI. Only the hash of the token is
+/// stored, the scope and the project limit what it permits.
+#[derive(Identifiable, Insertable, Queryable, Selectable, Debug, Clone, PartialEq)]
+#[diesel(table_name = crate::repository::schema::api_tokens)]
+#[diesel(primary_key(user_id, name))]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite, diesel::pg::Pg))]
+pub struct ApiToken {
+    pub user_id: String,
+    pub name: String,
+    pub token_hash: String,
+    pub scope: TokenScope,
+    pub project: Option<String>,
+    pub created_at: NaiveDateTime,
+    pub last_used_at: Option<NaiveDateTime>,
+}
+
+impl ApiToken {
+    pub fn new(
+        user_id: String,
+        name: String,
+        token_hash: String,
+        scope: TokenScope,
+        project: Option<String>,
+    ) -> Self {
+        Self {
+            user_id,
+            name,
+            token_hash,
+            scope,
+            project,
+            created_at: Utc::now().naive_utc(),
+            last_used_at: None,
+        }
+    }
+}
+
+impl From<ApiToken> for GetTokenDTO {
+    fn from(token: ApiToken) -> Self {
+        Self {
+            name: token.name,
+            scope: token.scope,
+            project: token.project,
+            created_at: token.created_at,
+            last_used_at: token.last_used_at,
+        }
+    }
+}
+
+#[cfg_attr(test, faux::create)]
+#[derive(Debug, Clone)]
+pub struct ApiTokenRepository {
+    pool: DatabasePool,
+}
+
+#[cfg_attr(test, faux::methods)]
+impl ApiTokenRepository {
+    pub fn new(pool: DatabasePool) -> Self {
+        Self { pool }
+    }
+
+    pub fn create(&self, token: &ApiToken, event: &AuditEvent) -> anyhow::Result<()> {
+        let mut connection = self.pool.get()?;
+
+        connection.transaction(|connection| {
+            execute_native!(
+                connection,
+                diesel::insert_into(api_tokens::table).values(token)
+            )?;
+            audit::insert(connection, event)
+        })?;
+
+        Ok(())
+    }
+
+    pub fn read(&self, user_id: &str, name: &str) -> anyhow::Result<Option<ApiToken>> {
+        let mut connection = self.pool.get()?;
+
+        let token = api_tokens::table
+            .find((user_id, name))
+            .first::<ApiToken>(&mut connection)
+            .optional()?;
+
+        Ok(token)
+    }
+
+    pub fn read_by_hash(&self, token_hash: &str) -> anyhow::Result<Option<ApiToken>> {
+        let mut connection = self.pool.get()?;
+
+        let token = api_tokens::table
+            .filter(dsl::token_hash.eq(token_hash))
+            .first::<ApiToken>(&mut connection)
+            .optional()?;
+
+        Ok(token)
+    }
+
+    pub fn belonging_to(&self, user_id: &str) -> anyhow::Result<Vec<ApiToken>> {
+        let mut connection = self.pool.get()?;
+
+        let tokens = api_tokens::table
+            .filter(dsl::user_id.eq(user_id))
+            .order(dsl::name.asc())
+            .load::<ApiToken>(&mut connection)?;
+
+        Ok(tokens)
+    }
+
+    /// Records that the token was used now
+    pub fn touch(&self, token: &ApiToken) -> anyhow::Result<()> {
+        let mut connection = self.pool.get()?;
+
+        diesel::update(api_tokens::table.find((&token.user_id, &token.name)))
+            .set(dsl::last_used_at.eq(Utc::now().naive_utc()))
+            .execute(&mut connection)?;
+
+        Ok(())
+    }
+
+    /// Deletes the token and returns whether it existed. The event is only recorded if it did.
+    pub fn delete(&self, user_id: &str, name: &str, event: &AuditEvent) -> anyhow::Result<bool> {
+        let mut connection = self.pool.get()?;
+
+        let deleted = connection.transaction(|connection| {
+            let deleted =
+                diesel::delete(api_tokens::table.find((user_id, name))).execute(connection)?;
+            if deleted > 0 {
+                audit::insert(connection, event)?;
+            }
+            diesel::result::QueryResult::Ok(deleted)
+        })?;
+
+        Ok(deleted > 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repository::fixtures::{self, TestDatabase};
+
+    const USER_ID: &str = "Eeghaeng8ohxeiqu7Aek";
+
+    fn setup() -> anyhow::Result<(TestDatabase, ApiTokenRepository)> {
+        let database = fixtures::database()?;
+        let pool = database.pool.clone();
+        fixtures::insert_user(&pool, USER_ID)?;
+        Ok((database, ApiTokenRepository::new(pool)))
+    }
+
+    fn token(name: &str, hash: &str) -> ApiToken {
+        ApiToken::new(
+            USER_ID.to_string(),
+            name.to_string(),
+            hash.to_string(),
+            TokenScope::Deploy,
+            Some("PROJECT_NAME".to_string()),
+        )
+    }
+
+    #[test]
+    fn create_ok() -> anyhow::Result<()> {
+        let (_database, tokens) = setup()?;
+        tokens.create(&token("github", "HASH"), &fixtures::event())?;
+        tokens.create(&token("gitlab", "OTHER_HASH"), &fixtures::event())?;
+
+        let read = tokens.read_by_hash("HASH")?.unwrap();
+        assert_eq!("github", read.name);
+        assert_eq!(TokenScope::Deploy, read.scope);
+        assert_eq!(Some("PROJECT_NAME".to_string()), read.project);
+        assert_eq!(None, tokens.read_by_hash("UNKNOWN")?);
+        let names: Vec<String> = tokens
+            .belonging_to(USER_ID)?
+            .into_iter()
+            .map(|token| token.name)
+            .collect();
+        assert_eq!(vec!["github", "gitlab"], names);
+        Ok(())
+    }
+
+    #[test]
+    fn create_same_name_fails() -> anyhow::Result<()> {
+        let (_database, tokens) = setup()?;
+        tokens.create(&token("github", "HASH"), &fixtures::event())?;
+        assert!(tokens
+            .create(&token("github", "OTHER_HASH"), &fixtures::event())
+            .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn touch_and_delete_ok() -> anyhow::Result<()> {
+        let (_database, tokens) = setup()?;
+        let github = token("github", "HASH");
+        tokens.create(&github, &fixtures::event())?;
+
+        tokens.touch(&github)?;
+        assert!(tokens
+            .read(USER_ID, "github")?
+            .unwrap()
+            .last_used_at
+            .is_some());
+        assert!(tokens.delete(USER_ID, "github", &fixtures::event())?);
+        assert!(!tokens.delete(USER_ID, "github", &fixtures::event())?);
+        assert_eq!(None, tokens.read(USER_ID, "github")?);
+        Ok(())
+    }
+}
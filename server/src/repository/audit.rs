@@ -0,0 +1,272 @@
+use super::{
+    connection::{execute_native, DbConnection},
+    create_id,
+    schema::audit_events::{self, dsl},
+    DatabasePool,
+};
+use chrono::{NaiveDateTime, Utc};
+use common::dtos::{AuditAction, AuditFilterDTO, GetAuditEventDTO};
+use diesel::{connection::Connection, prelude::*};
+
+type Backend = <DbConnection as Connection>::Backend;
+
+/// An action recorded in the audit log. Events are never changed or deleted, the database
+/// rejects it.
+#[derive(Identifiable, Insertable, Queryable, Selectable, Debug, Clone, PartialEq)]
+#[diesel(table_name = crate::repository::schema::audit_events)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite, diesel::pg::Pg))]
+pub struct AuditEvent {
+    pub id: String,
+    pub created_at: NaiveDateTime,
+    pub user_id: String,
+    pub login: String,
+    pub token: Option<String>,
+    pub ip: Option<String>,
+    pub action: AuditAction,
+    pub project_id: Option<String>,
+    pub project: Option<String>,
+    pub stage: Option<String>,
+    pub target: Option<String>,
+    pub old_hash: Option<String>,
+    pub new_hash: Option<String>,
+    pub details: Option<String>,
+}
+
+impl AuditEvent {
+    pub fn new(user_id: String, login: String, action: AuditAction) -> Self {
+        Self {
+            id: create_id(),
+            created_at: Utc::now().naive_utc(),
+            user_id,
+            login,
+            token: None,
+            ip: None,
+            action,
+            project_id: None,
+            project: None,
+            stage: None,
+            target: None,
+            old_hash: None,
+            new_hash: None,
+            details: None,
+        }
+    }
+}
+
+impl From<AuditEvent> for GetAuditEventDTO {
+    fn from(event: AuditEvent) -> Self {
+        Self {
+            created_at: event.created_at,
+            login: event.login,
+            token: event.token,
+            ip: event.ip,
+            action: event.action,
+            project: event.project,
+            stage: event.stage,
+            target: event.target,
+            old_hash: event.old_hash,
+            new_hash: event.new_hash,
+            details: event.details,
+        }
+    }
+}
+
+/// Whose events a search covers
+#[derive(Debug, Clone, PartialEq)]
+pub enum AuditScope {
+    /// The events of the project with the id, whoever acted
+    Project(String),
+    /// The events of the user with the id
+    User(String),
+}
+
+/// Appends the event in the transaction of the action it records, so the action is rolled
+/// back if the event can't be written
+pub(super) fn insert(connection: &mut DbConnection, event: &AuditEvent) -> QueryResult<()> {
+    execute_native!(
+        connection,
+        diesel::insert_into(audit_events::table).values(event)
+    )?;
+    Ok(())
+}
+
+#[cfg_attr(test, faux::create)]
+#[derive(Debug, Clone)]
+pub struct AuditRepository {
+    pool: DatabasePool,
+}
+
+#[cfg_attr(test, faux::methods)]
+impl AuditRepository {
+    pub fn new(pool: DatabasePool) -> Self {
+        Self { pool }
+    }
+
+    /// Records an action which changes nothing in the database, others record their event
+    /// with the change
+    pub fn create(&self, event: &AuditEvent) -> anyhow::Result<()> {
+        let mut connection = self.pool.get()?;
+        insert(&mut connection, event)?;
+        Ok(())
+    }
+
+    /// The newest events in the scope matching the filter, at most `limit`. The project of
+    /// the filter is resolved to the scope by the caller.
+    pub fn search(
+        &self,
+        scope: &AuditScope,
+        filter: &AuditFilterDTO,
+        limit: i64,
+    ) -> anyhow::Result<Vec<AuditEvent>> {
+        let mut connection = self.pool.get()?;
+
+        let mut query = audit_events::table.into_boxed::<Backend>();
+        query = match scope {
+            AuditScope::Project(project_id) => query.filter(dsl::project_id.eq(project_id)),
+            AuditScope::User(user_id) => query.filter(dsl::user_id.eq(user_id)),
+        };
+        if let Some(stage) = &filter.stage {
+            query = query.filter(dsl::stage.eq(stage));
+        }
+        if let Some(target) = &filter.target {
+            query = query.filter(dsl::target.eq(target));
+        }
+        if let Some(action) = filter.action {
+            query = query.filter(dsl::action.eq(action));
+        }
+        if let Some(login) = &filter.user {
+            query = query.filter(dsl::login.eq(login));
+        }
+        if let Some(since) = filter.since {
+            query = query.filter(dsl::created_at.ge(since));
+        }
+        if let Some(until) = filter.until {
+            query = query.filter(dsl::created_at.lt(until));
+        }
+
+        let events = query
+            .order((dsl::created_at.desc(), dsl::id.desc()))
+            .limit(limit)
+            .load::<AuditEvent>(&mut connection)?;
+
+        Ok(events)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repository::fixtures::{self, TestDatabase};
+    use chrono::Duration;
+
+    const USER_ID: &str = "Eiph9ahh6ohwoo1eiCh4a";
+    const PROJECT_ID: &str = "Ahng3eezoh4Ahcaeng2ie";
+
+    fn setup() -> anyhow::Result<(TestDatabase, AuditRepository)> {
+        let database = fixtures::database()?;
+        let events = AuditRepository::new(database.pool.clone());
+        Ok((database, events))
+    }
+
+    fn event(action: AuditAction, stage: &str, minutes_ago: i64) -> AuditEvent {
+        AuditEvent {
+            created_at: Utc::now().naive_utc() - Duration::minutes(minutes_ago),
+            project_id: Some(PROJECT_ID.to_string()),
+            project: Some("shop".to_string()),
+            stage: Some(stage.to_string()),
+            target: Some("api".to_string()),
+            ..AuditEvent::new(USER_ID.to_string(), "alice".to_string(), action)
+        }
+    }
+
+    fn actions(events: Vec<AuditEvent>) -> Vec<AuditAction> {
+        events.into_iter().map(|event| event.action).collect()
+    }
+
+    #[test]
+    fn search_ok() -> anyhow::Result<()> {
+        let (_database, events) = setup()?;
+        events.create(&event(AuditAction::HandlerDeploy, "prod", 30))?;
+        events.create(&event(AuditAction::HandlerRollback, "prod", 20))?;
+        events.create(&event(AuditAction::HandlerDeploy, "dev", 10))?;
+        events.create(&AuditEvent::new(
+            USER_ID.to_string(),
+            "alice".to_string(),
+            AuditAction::Login,
+        ))?;
+        let project = AuditScope::Project(PROJECT_ID.to_string());
+
+        assert_eq!(
+            vec![
+                AuditAction::HandlerDeploy,
+                AuditAction::HandlerRollback,
+                AuditAction::HandlerDeploy
+            ],
+            actions(events.search(&project, &AuditFilterDTO::default(), 100)?)
+        );
+        assert_eq!(
+            vec![AuditAction::HandlerRollback, AuditAction::HandlerDeploy],
+            actions(events.search(
+                &project,
+                &AuditFilterDTO {
+                    stage: Some("prod".to_string()),
+                    ..Default::default()
+                },
+                100
+            )?)
+        );
+        assert_eq!(
+            vec![AuditAction::HandlerDeploy],
+            actions(events.search(
+                &project,
+                &AuditFilterDTO {
+                    action: Some(AuditAction::HandlerDeploy),
+                    until: Some(Utc::now().naive_utc() - Duration::minutes(15)),
+                    ..Default::default()
+                },
+                100
+            )?)
+        );
+        assert_eq!(
+            vec![AuditAction::Login, AuditAction::HandlerDeploy],
+            actions(events.search(
+                &AuditScope::User(USER_ID.to_string()),
+                &AuditFilterDTO::default(),
+                2
+            )?)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn events_append_only() -> anyhow::Result<()> {
+        let (database, events) = setup()?;
+        let event = event(AuditAction::HandlerDeploy, "prod", 0);
+        events.create(&event)?;
+        let mut connection = database.pool.get()?;
+
+        assert!(diesel::update(audit_events::table.find(&event.id))
+            .set(dsl::login.eq("mallory"))
+            .execute(&mut connection)
+            .is_err());
+        assert!(diesel::delete(audit_events::table)
+            .execute(&mut connection)
+            .is_err());
+        assert_eq!(
+            vec![event.clone()],
+            events
+                .search(
+                    &AuditScope::User(USER_ID.to_string()),
+                    &AuditFilterDTO::default(),
+                    100
+                )?
+                .into_iter()
+                .map(|found| AuditEvent {
+                    created_at: event.created_at,
+                    ..found
+                })
+                .collect::<Vec<_>>()
+        );
+        Ok(())
+    }
+}
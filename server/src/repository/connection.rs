@@ -0,0 +1,336 @@
+use diesel::{
+    connection::SimpleConnection,
+    pg::PgConnection,
+    prelude::*,
+    r2d2::{self, ManageConnection, R2D2Connection},
+    sqlite::SqliteConnection,
+};
+use diesel_migrations::{embed_migrations, EmbeddedMigrations, MigrationHarness};
+use std::time::Duration;
+
+const SQLITE_MIGRATIONS: EmbeddedMigrations = embed_migrations!("migrations/sqlite");
+const POSTGRES_MIGRATIONS: EmbeddedMigrations = embed_migrations!("migrations/postgres");
+
+/// A connection to one of the supported databases
+#[derive(diesel::MultiConnection)]
+pub enum DbConnection {
+    Postgresql(PgConnection),
+    Sqlite(SqliteConnection),
+}
+
+/// Whether the database URL points to a PostgreSQL server, anything else is the path of a
+/// SQLite database
+pub fn is_postgres_url(url: &str) -> bool {
+    url.starts_with("postgres://") || url.starts_with("postgresql://")
+}
+
+/// Tuning of the connection pool and the SQLite connections
+#[derive(Debug, Clone, PartialEq)]
+pub struct DatabaseOptions {
+    /// The maximum number of connections, which caps the concurrent database work
+    pub pool_size: u32,
+    /// How long a SQLite connection waits for the lock of another connection before failing
+    pub busy_timeout: Duration,
+    /// Whether SQLite uses its write-ahead log, which lets reads proceed during a write
+    pub wal: bool,
+}
+
+impl Default for DatabaseOptions {
+    fn default() -> Self {
+        Self {
+            pool_size: 10,
+            busy_timeout: Duration::from_secs(5),
+            wal: true,
+        }
+    }
+}
+
+/// Opens the connections of the pool with the backend the database URL selects. Unlike
+/// `ConnectionManager<DbConnection>`, which tries every backend in turn, this reports why
+/// connecting failed.
+#[derive(Debug)]
+pub struct DatabaseManager {
+    url: String,
+    options: DatabaseOptions,
+}
+
+impl DatabaseManager {
+    pub fn new(url: &str, options: DatabaseOptions) -> Self {
+        Self {
+            url: url.to_string(),
+            options,
+        }
+    }
+
+    /// Runs the migrations of the backend which are not applied yet. SQLite cannot alter
+    /// constraints, so its migrations recreate tables. They run on a connection outside of the
+    /// pool with foreign keys switched off, else dropping a table would cascade into the rows
+    /// referencing it. The foreign keys are checked after migrations ran.
+    pub fn run_migrations(&self) -> anyhow::Result<()> {
+        if is_postgres_url(&self.url) {
+            let mut connection = PgConnection::establish(&self.url)?;
+            connection
+                .run_pending_migrations(POSTGRES_MIGRATIONS)
+                .map_err(|err| anyhow::anyhow!(err))?;
+            return Ok(());
+        }
+
+        let mut connection = SqliteConnection::establish(&self.url)?;
+        connection.batch_execute(&format!(
+            "PRAGMA busy_timeout = {};",
+            self.options.busy_timeout.as_millis()
+        ))?;
+        let migrations = connection
+            .pending_migrations(SQLITE_MIGRATIONS)
+            .map_err(|err| anyhow::anyhow!(err))?;
+        if migrations.is_empty() {
+            return Ok(());
+        }
+        for migration in migrations {
+            // A migration may switch foreign keys on again when it is done
+            connection.batch_execute("PRAGMA foreign_keys = OFF;")?;
+            connection
+                .run_migration(&migration)
+                .map_err(|err| anyhow::anyhow!(err))?;
+        }
+
+        let violations = diesel::sql_query("PRAGMA foreign_key_check")
+            .load::<ForeignKeyViolation>(&mut connection)?;
+        if let Some(violation) = violations.first() {
+            anyhow::bail!(
+                "{} rows violate foreign keys after the migrations, e.g. a row of {} referencing {}",
+                violations.len(),
+                violation.table,
+                violation.parent
+            );
+        }
+        Ok(())
+    }
+}
+
+/// The result of `PRAGMA foreign_keys`, 1 if SQLite enforces foreign keys
+#[derive(QueryableByName)]
+struct ForeignKeys {
+    #[diesel(sql_type = diesel::sql_types::Integer)]
+    foreign_keys: i32,
+}
+
+/// A row `PRAGMA foreign_key_check` reports, whose parent row does not exist
+#[derive(QueryableByName)]
+struct ForeignKeyViolation {
+    #[diesel(sql_type = diesel::sql_types::Text)]
+    table: String,
+    #[diesel(sql_type = diesel::sql_types::Text)]
+    parent: String,
+}
+
+impl ManageConnection for DatabaseManager {
+    type Connection = DbConnection;
+    type Error = r2d2::Error;
+
+    fn connect(&self) -> Result<DbConnection, r2d2::Error> {
+        if is_postgres_url(&self.url) {
+            let connection =
+                PgConnection::establish(&self.url).map_err(r2d2::Error::ConnectionError)?;
+            return Ok(DbConnection::Postgresql(connection));
+        }
+
+        let mut connection =
+            SqliteConnection::establish(&self.url).map_err(r2d2::Error::ConnectionError)?;
+        // SQLite turns foreign key enforcement off for every new connection
+        let mut pragmas = format!(
+            "PRAGMA foreign_keys = ON; PRAGMA busy_timeout = {};",
+            self.options.busy_timeout.as_millis()
+        );
+        if self.options.wal {
+            pragmas.push_str(" PRAGMA journal_mode = WAL;");
+        }
+        connection
+            .batch_execute(&pragmas)
+            .map_err(r2d2::Error::QueryError)?;
+        // SQLite ignores the pragma if it was built without foreign key support
+        let foreign_keys = diesel::sql_query("PRAGMA foreign_keys")
+            .get_result::<ForeignKeys>(&mut connection)
+            .map_err(r2d2::Error::QueryError)?;
+        if foreign_keys.foreign_keys != 1 {
+            return Err(r2d2::Error::QueryError(
+                diesel::result::Error::QueryBuilderError(
+                    "SQLite does not enforce foreign keys".into(),
+                ),
+            ));
+        }
+        Ok(DbConnection::Sqlite(connection))
+    }
+
+    fn is_valid(&self, connection: &mut DbConnection) -> Result<(), r2d2::Error> {
+        connection.ping().map_err(r2d2::Error::QueryError)
+    }
+
+    fn has_broken(&self, connection: &mut DbConnection) -> bool {
+        std::thread::panicking() || connection.is_broken()
+    }
+}
+
+/// Executes the query on the backend specific connection. Needed for the statements the
+/// common backend cannot express: upserts, inserts of whole rows and updates setting a
+/// timestamp to NULL, which the common backend binds as an integer.
+macro_rules! execute_native {
+    ($connection:expr, $query:expr) => {
+        match $connection as &mut $crate::repository::connection::DbConnection {
+            $crate::repository::connection::DbConnection::Postgresql(connection) => {
+                $query.execute(connection)
+            }
+            $crate::repository::connection::DbConnection::Sqlite(connection) => {
+                $query.execute(connection)
+            }
+        }
+    };
+}
+
+pub(crate) use execute_native;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repository::{create_pool, migrate};
+    use diesel::{
+        migration::{MigrationConnection, MigrationSource},
+        sqlite::Sqlite,
+    };
+    use tempfile::{tempdir, TempDir};
+
+    /// The migration which recreates the handlers to add stages
+    const CREATE_STAGES: &str = "2023-09-08-101204";
+    /// The first migration which recreates all tables with foreign keys
+    const CASCADE_DELETES: &str = "2023-09-13-091845";
+    /// The migration which recreates the users
+    const GENERALIZE_USERS: &str = "2023-09-27-093041";
+
+    /// Creates a SQLite database of a server released before the migration `until`
+    fn legacy_database(until: &str) -> anyhow::Result<(TempDir, String, SqliteConnection)> {
+        let temp_dir = tempdir()?;
+        let path = temp_dir.path().join("noops.sqlite");
+        let url = path.to_string_lossy().to_string();
+        let mut connection = SqliteConnection::establish(&url)?;
+        connection.setup()?;
+        let migrations = MigrationSource::<Sqlite>::migrations(&SQLITE_MIGRATIONS)
+            .map_err(|err| anyhow::anyhow!(err))?;
+        for migration in migrations
+            .iter()
+            .filter(|migration| migration.name().to_string().as_str() < until)
+        {
+            connection
+                .run_migration(&**migration)
+                .map_err(|err| anyhow::anyhow!(err))?;
+        }
+        Ok((temp_dir, url, connection))
+    }
+
+    #[derive(QueryableByName)]
+    struct Count {
+        #[diesel(sql_type = diesel::sql_types::BigInt)]
+        count: i64,
+    }
+
+    fn count(connection: &mut DbConnection, table: &str) -> anyhow::Result<i64> {
+        let query = format!("SELECT COUNT(*) AS count FROM {}", table);
+        Ok(diesel::sql_query(query)
+            .get_result::<Count>(connection)?
+            .count)
+    }
+
+    #[test]
+    fn migrate_keeps_rows_of_recreated_tables() -> anyhow::Result<()> {
+        let (_temp_dir, url, mut connection) = legacy_database(CASCADE_DELETES)?;
+        connection.batch_execute(
+            "INSERT INTO users (id, email, github_login, github_id, github_access_token)
+             VALUES ('user', 'user@example.com', 'user', 1, 'token');
+             INSERT INTO projects (id, name, user_id) VALUES ('project', 'project', 'user');
+             INSERT INTO handlers (id, name, language, hash, project_id)
+             VALUES ('handler', 'handler', 'Rust', 'hash', 'project');
+             INSERT INTO handler_versions (id, handler_id, number, hash, user_id, blob)
+             VALUES ('version', 'handler', 1, 'hash', 'user', 'blob');
+             INSERT INTO version_status_codes (version_id, status_code, count)
+             VALUES ('version', 200, 1);
+             INSERT INTO stage_variables (project_id, stage, name, value)
+             VALUES ('project', 'prod', 'NAME', 'value');",
+        )?;
+        drop(connection);
+
+        migrate(&url, DatabaseOptions::default())?;
+
+        let mut connection = create_pool(&url, DatabaseOptions::default())?.get()?;
+        assert_eq!(count(&mut connection, "users")?, 1);
+        assert_eq!(count(&mut connection, "projects")?, 1);
+        assert_eq!(count(&mut connection, "handlers")?, 1);
+        assert_eq!(count(&mut connection, "handler_versions")?, 1);
+        assert_eq!(count(&mut connection, "version_status_codes")?, 1);
+        assert_eq!(count(&mut connection, "stage_variables")?, 1);
+        Ok(())
+    }
+
+    #[test]
+    fn migrate_recreates_referenced_tables_without_foreign_keys() -> anyhow::Result<()> {
+        // Adding stages drops the handlers, which the versions reference
+        let (_temp_dir, url, mut connection) = legacy_database(CREATE_STAGES)?;
+        connection.batch_execute(
+            "INSERT INTO users (id, email, github_login, github_id, github_access_token)
+             VALUES ('user', 'user@example.com', 'user', 1, 'token');
+             INSERT INTO projects (id, name, user_id) VALUES ('project', 'project', 'user');
+             INSERT INTO handlers (id, name, language, hash, project_id)
+             VALUES ('handler', 'handler', 'Rust', 'hash', 'project');
+             INSERT INTO handler_versions (id, handler_id, number, hash, user_id)
+             VALUES ('version', 'handler', 1, 'hash', 'user');
+             INSERT INTO version_status_codes (version_id, status_code, count)
+             VALUES ('version', 200, 1);",
+        )?;
+        drop(connection);
+
+        migrate(&url, DatabaseOptions::default())?;
+
+        let mut connection = create_pool(&url, DatabaseOptions::default())?.get()?;
+        assert_eq!(count(&mut connection, "handlers")?, 1);
+        assert_eq!(count(&mut connection, "handler_versions")?, 1);
+        assert_eq!(count(&mut connection, "version_status_codes")?, 1);
+        Ok(())
+    }
+
+    #[test]
+    fn migrate_fails_on_foreign_key_violations() -> anyhow::Result<()> {
+        let (_temp_dir, url, mut connection) = legacy_database(GENERALIZE_USERS)?;
+        // A session of a user who is gone, which an older server could have left behind
+        connection.batch_execute(
+            "PRAGMA foreign_keys = OFF;
+             INSERT INTO sessions (id, user_id, refresh_token_hash, created_at, expires_at)
+             VALUES ('session', 'unknown', 'hash', '2023-09-26 10:00:00', '2023-10-26 10:00:00');",
+        )?;
+        drop(connection);
+
+        let result = migrate(&url, DatabaseOptions::default());
+
+        let err = result.unwrap_err().to_string();
+        assert!(err.contains("sessions"), "{}", err);
+        Ok(())
+    }
+
+    #[test]
+    fn pooled_connections_enforce_foreign_keys_after_migrations() -> anyhow::Result<()> {
+        // The migrations recreating the users switch foreign keys off and on again
+        let (_temp_dir, url, connection) = legacy_database(GENERALIZE_USERS)?;
+        drop(connection);
+        migrate(&url, DatabaseOptions::default())?;
+
+        let pool = create_pool(&url, DatabaseOptions::default())?;
+        let connections = (0..3).map(|_| pool.get()).collect::<Result<Vec<_>, _>>()?;
+        for mut connection in connections {
+            let result = diesel::sql_query("PRAGMA foreign_keys")
+                .get_result::<ForeignKeys>(&mut *connection)?;
+            assert_eq!(result.foreign_keys, 1);
+            let result = connection.batch_execute(
+                "INSERT INTO projects (id, name, user_id) VALUES ('project', 'project', 'unknown')",
+            );
+            assert!(result.is_err());
+        }
+        Ok(())
+    }
+}
@@ -1,15 +1,19 @@
 use super::{
+    audit::{self, AuditEvent},
+    connection::execute_native,
     create_id,
     project::Project,
-    schema::handlers::{self, dsl},
-    Repository,
+    schema::{
+        handler_versions,
+        handlers::{self, dsl},
+    },
+    version::HandlerVersion,
+    DatabasePool, Repository,
 };
 use anyhow;
+use chrono::{NaiveDateTime, Utc};
 use common::dtos::Language;
-use diesel::{
-    prelude::*,
-    r2d2::{ConnectionManager, Pool},
-};
+use diesel::prelude::*;
 
 #[derive(
     Identifiable,
@@ -24,23 +28,41 @@ use diesel::{
 )]
 #[diesel(table_name = crate::repository::schema::handlers)]
 #[diesel(belongs_to(Project))]
-#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+#[diesel(treat_none_as_null = true)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite, diesel::pg::Pg))]
 pub struct Handler {
     pub id: String,
     pub name: String,
     pub language: Language,
     pub hash: String,
     pub project_id: String,
+    pub version: i32,
+    pub canary_version: Option<i32>,
+    pub canary_weight: i32,
+    pub stage: String,
+    /// When the handler was moved to the trash
+    pub deleted_at: Option<NaiveDateTime>,
 }
 
 impl Handler {
-    pub fn new(name: String, language: Language, hash: String, project_id: String) -> Self {
+    pub fn new(
+        name: String,
+        language: Language,
+        hash: String,
+        project_id: String,
+        stage: String,
+    ) -> Self {
         Self {
             id: create_id(),
             name,
             language,
             hash,
             project_id,
+            version: 1,
+            canary_version: None,
+            canary_weight: 0,
+            stage,
+            deleted_at: None,
         }
     }
 }
@@ -48,24 +70,26 @@ impl Handler {
 #[cfg_attr(test, faux::create)]
 #[derive(Debug, Clone)]
 pub struct HandlerRepository {
-    pool: Pool<ConnectionManager<SqliteConnection>>,
+    pool: DatabasePool,
 }
 
 #[cfg_attr(test, faux::methods)]
 impl Repository<Handler> for HandlerRepository {
-    fn new(pool: Pool<ConnectionManager<SqliteConnection>>) -> Self {
+    fn new(pool: DatabasePool) -> Self {
         Self { pool }
     }
 
     fn create(&self, handler: &Handler) -> anyhow::Result<()> {
         let mut connection = self.pool.get()?;
 
-        diesel::insert_into(handlers::table)
-            .values(handler)
-            .on_conflict((dsl::name, dsl::project_id))
-            .do_update()
-            .set(handler)
-            .execute(&mut connection)?;
+        execute_native!(
+            &mut connection,
+            diesel::insert_into(handlers::table)
+                .values(handler)
+                .on_conflict((dsl::name, dsl::project_id, dsl::stage))
+                .do_update()
+                .set(handler)
+        )?;
 
         Ok(())
     }
@@ -75,6 +99,7 @@ impl Repository<Handler> for HandlerRepository {
 
         let handler = handlers::table
             .find(id)
+            .filter(dsl::deleted_at.is_null())
             .first::<Handler>(&mut connection)
             .optional()?;
 
@@ -94,68 +119,295 @@ impl Repository<Handler> for HandlerRepository {
 
 #[cfg_attr(test, faux::methods)]
 impl HandlerRepository {
+    pub fn all(&self) -> anyhow::Result<Vec<Handler>> {
+        let mut connection = self.pool.get()?;
+        let handlers = handlers::table.load::<Handler>(&mut connection)?;
+        Ok(handlers)
+    }
+
     pub fn belonging_to(&self, project: &Project) -> anyhow::Result<Vec<Handler>> {
         let mut connection = self.pool.get()?;
-        let handlers = Handler::belonging_to(project).load::<Handler>(&mut connection)?;
+        let handlers = Handler::belonging_to(project)
+            .filter(dsl::deleted_at.is_null())
+            .load::<Handler>(&mut connection)?;
+        Ok(handlers)
+    }
+
+    pub fn belonging_to_stage(
+        &self,
+        project: &Project,
+        stage: &str,
+    ) -> anyhow::Result<Vec<Handler>> {
+        let mut connection = self.pool.get()?;
+        let handlers = Handler::belonging_to(project)
+            .filter(dsl::stage.eq(stage))
+            .filter(dsl::deleted_at.is_null())
+            .load::<Handler>(&mut connection)?;
         Ok(handlers)
     }
 
+    /// The number of handlers of the project in all stages
+    pub fn count_belonging_to(&self, project: &Project) -> anyhow::Result<i64> {
+        let mut connection = self.pool.get()?;
+        let count = Handler::belonging_to(project)
+            .filter(dsl::deleted_at.is_null())
+            .count()
+            .get_result::<i64>(&mut connection)?;
+        Ok(count)
+    }
+
     pub fn belonging_to_by_name(
         &self,
         project: &Project,
+        stage: &str,
+        handler_name: &str,
+    ) -> anyhow::Result<Option<Handler>> {
+        let mut connection = self.pool.get()?;
+
+        let handler = Handler::belonging_to(project)
+            .filter(dsl::stage.eq(stage))
+            .filter(dsl::name.eq(handler_name))
+            .filter(dsl::deleted_at.is_null())
+            .first::<Handler>(&mut connection)
+            .optional()?;
+
+        Ok(handler)
+    }
+
+    pub fn trashed_by_name(
+        &self,
+        project: &Project,
+        stage: &str,
         handler_name: &str,
     ) -> anyhow::Result<Option<Handler>> {
         let mut connection = self.pool.get()?;
 
         let handler = Handler::belonging_to(project)
+            .filter(dsl::stage.eq(stage))
             .filter(dsl::name.eq(handler_name))
+            .filter(dsl::deleted_at.is_not_null())
             .first::<Handler>(&mut connection)
             .optional()?;
 
         Ok(handler)
     }
+
+    /// Handlers moved to the trash before the given time
+    pub fn trashed_before(&self, before: NaiveDateTime) -> anyhow::Result<Vec<Handler>> {
+        let mut connection = self.pool.get()?;
+        let handlers = handlers::table
+            .filter(dsl::deleted_at.lt(before))
+            .load::<Handler>(&mut connection)?;
+        Ok(handlers)
+    }
+
+    pub fn trash(&self, handler: &Handler, event: &AuditEvent) -> anyhow::Result<()> {
+        let mut connection = self.pool.get()?;
+
+        connection.transaction(|connection| {
+            diesel::update(handlers::table.find(&handler.id))
+                .set(dsl::deleted_at.eq(Utc::now().naive_utc()))
+                .execute(connection)?;
+            audit::insert(connection, event)
+        })?;
+
+        Ok(())
+    }
+
+    pub fn restore(&self, handler: &Handler, event: &AuditEvent) -> anyhow::Result<()> {
+        let mut connection = self.pool.get()?;
+
+        connection.transaction(|connection| {
+            execute_native!(
+                connection,
+                diesel::update(handlers::table.find(&handler.id))
+                    .set(dsl::deleted_at.eq(None::<NaiveDateTime>))
+            )?;
+            audit::insert(connection, event)
+        })?;
+
+        Ok(())
+    }
+
+    /// The repairs of fsck pass no event, no user requested them
+    pub fn activate(
+        &self,
+        handler: &Handler,
+        version: &HandlerVersion,
+        event: Option<&AuditEvent>,
+    ) -> anyhow::Result<()> {
+        let mut connection = self.pool.get()?;
+
+        connection.transaction(|connection| {
+            diesel::update(handlers::table.find(&handler.id))
+                .set((dsl::version.eq(version.number), dsl::hash.eq(&version.hash)))
+                .execute(connection)?;
+            event.map_or(Ok(()), |event| audit::insert(connection, event))
+        })?;
+
+        Ok(())
+    }
+
+    /// The repairs of fsck pass no event, no user requested them
+    pub fn stop_canary(&self, handler: &Handler, event: Option<&AuditEvent>) -> anyhow::Result<()> {
+        let mut connection = self.pool.get()?;
+
+        connection.transaction(|connection| {
+            diesel::update(handlers::table.find(&handler.id))
+                .set((
+                    dsl::canary_version.eq(None::<i32>),
+                    dsl::canary_weight.eq(0),
+                ))
+                .execute(connection)?;
+            event.map_or(Ok(()), |event| audit::insert(connection, event))
+        })?;
+
+        Ok(())
+    }
+
+    /// Activates the canary version and ends the canary release in a single statement
+    pub fn promote_canary(
+        &self,
+        handler: &Handler,
+        canary: &HandlerVersion,
+        event: &AuditEvent,
+    ) -> anyhow::Result<()> {
+        let mut connection = self.pool.get()?;
+
+        connection.transaction(|connection| {
+            diesel::update(handlers::table.find(&handler.id))
+                .set((
+                    dsl::version.eq(canary.number),
+                    dsl::hash.eq(&canary.hash),
+                    dsl::canary_version.eq(None::<i32>),
+                    dsl::canary_weight.eq(0),
+                ))
+                .execute(connection)?;
+            audit::insert(connection, event)
+        })?;
+
+        Ok(())
+    }
+
+    /// Stores the handler together with its new active version in one transaction. The
+    /// handler is matched by its id, so a handler with the same name deployed concurrently
+    /// under another id fails the transaction instead of leaving the version orphaned.
+    pub fn deploy(
+        &self,
+        handler: &Handler,
+        version: &HandlerVersion,
+        event: &AuditEvent,
+    ) -> anyhow::Result<()> {
+        let mut connection = self.pool.get()?;
+
+        connection.transaction(|connection| {
+            execute_native!(
+                connection,
+                diesel::insert_into(handlers::table)
+                    .values(handler)
+                    .on_conflict(dsl::id)
+                    .do_update()
+                    .set(handler)
+            )?;
+            execute_native!(
+                connection,
+                diesel::insert_into(handler_versions::table).values(version)
+            )?;
+            audit::insert(connection, event)
+        })?;
+
+        Ok(())
+    }
+
+    /// Stores the new version and starts a canary release of it in one transaction
+    pub fn deploy_canary(
+        &self,
+        handler: &Handler,
+        version: &HandlerVersion,
+        weight: i32,
+        event: &AuditEvent,
+    ) -> anyhow::Result<()> {
+        let mut connection = self.pool.get()?;
+
+        connection.transaction(|connection| {
+            execute_native!(
+                connection,
+                diesel::insert_into(handler_versions::table).values(version)
+            )?;
+            diesel::update(handlers::table.find(&handler.id))
+                .set((
+                    dsl::canary_version.eq(version.number),
+                    dsl::canary_weight.eq(weight),
+                ))
+                .execute(connection)?;
+            audit::insert(connection, event)
+        })?;
+
+        Ok(())
+    }
 }
 
 #[cfg(test)]
 mod tests {
 
-    use crate::repository::create_pool;
+    use crate::repository::{
+        fixtures::{self, TestDatabase},
+        status_code::StatusCodeRepository,
+        version::HandlerVersionRepository,
+    };
 
     use super::*;
-    use diesel_migrations::{FileBasedMigrations, MigrationHarness};
     use lazy_static::lazy_static;
-    use tempfile::{tempdir, TempDir};
 
-    const DATABASE_NAME: &str = "noops_test.sqlite";
     const HANDLER_NAME: &str = "FUNCTION_NAME";
     const HANDLER_HASH: &str = "aeHae9shaiquu4Iey8phishohqu1ON9y";
     const PROJECT_ID: &str = "xiekaiphoe7Luk3zeuNie";
     const PROJECT_NAME: &str = "PROJECT_NAME";
     const USER_ID: &str = "puphoonoh1bae6Binaixu";
     const HANDLER_LANGUAGE: Language = Language::Rust;
+    const STAGE: &str = "prod";
 
     lazy_static! {
+        static ref PROJECT: Project = Project {
+            id: PROJECT_ID.to_string(),
+            name: PROJECT_NAME.to_string(),
+            user_id: USER_ID.to_string(),
+            deleted_at: None,
+            org_id: None,
+        };
         static ref HANDLER: Handler = Handler::new(
             HANDLER_NAME.to_string(),
             HANDLER_LANGUAGE,
             HANDLER_HASH.to_string(),
-            PROJECT_ID.to_string()
+            PROJECT_ID.to_string(),
+            STAGE.to_string()
         );
     }
 
-    fn setup() -> anyhow::Result<(TempDir, HandlerRepository)> {
-        let temp_dir = tempdir()?;
-        let pool = create_pool(&temp_dir.path().join(DATABASE_NAME));
-        let mut connection = pool.get()?;
-        let projects = HandlerRepository::new(pool);
-        let migrations = FileBasedMigrations::find_migrations_directory_in_path("./server")?;
-        connection.run_pending_migrations(migrations).unwrap();
-        Ok((temp_dir, projects))
+    fn setup() -> anyhow::Result<(TestDatabase, HandlerRepository)> {
+        let (database, handlers, _, _) = setup_with_versions()?;
+        Ok((database, handlers))
+    }
+
+    fn setup_with_versions() -> anyhow::Result<(
+        TestDatabase,
+        HandlerRepository,
+        HandlerVersionRepository,
+        StatusCodeRepository,
+    )> {
+        let database = fixtures::database()?;
+        let pool = database.pool.clone();
+        fixtures::insert_user(&pool, USER_ID)?;
+        fixtures::insert_project(&pool, PROJECT_ID, USER_ID)?;
+        let handlers = HandlerRepository::new(pool.clone());
+        let versions = HandlerVersionRepository::new(pool.clone());
+        let status_codes = StatusCodeRepository::new(pool);
+        Ok((database, handlers, versions, status_codes))
     }
 
     #[test]
     fn create_ok() -> anyhow::Result<()> {
-        let (_temp_dir, handlers) = setup()?;
+        let (_database, handlers) = setup()?;
         let result = handlers.create(&HANDLER);
         assert!(result.is_ok());
         Ok(())
@@ -163,7 +415,7 @@ mod tests {
 
     #[test]
     fn create_conflict() -> anyhow::Result<()> {
-        let (_temp_dir, handlers) = setup()?;
+        let (_database, handlers) = setup()?;
         handlers.create(&HANDLER)?;
         let result = handlers.create(&HANDLER);
         assert!(result.is_ok());
@@ -172,13 +424,14 @@ mod tests {
 
     #[test]
     fn create_name_conflict() -> anyhow::Result<()> {
-        let (_temp_dir, handlers) = setup()?;
+        let (_database, handlers) = setup()?;
         handlers.create(&HANDLER)?;
         let handler = Handler::new(
             HANDLER_NAME.to_string(),
             HANDLER_LANGUAGE,
             HANDLER_HASH.to_string(),
             PROJECT_ID.to_string(),
+            STAGE.to_string(),
         );
         let result = handlers.create(&handler);
         assert!(result.is_ok());
@@ -187,7 +440,7 @@ mod tests {
 
     #[test]
     fn read_ok() -> anyhow::Result<()> {
-        let (_temp_dir, handlers) = setup()?;
+        let (_database, handlers) = setup()?;
         handlers.create(&HANDLER)?;
         let handler = handlers.read(&HANDLER.id)?;
 
@@ -199,7 +452,7 @@ mod tests {
 
     #[test]
     fn read_not_found() -> anyhow::Result<()> {
-        let (_temp_dir, handlers) = setup()?;
+        let (_database, handlers) = setup()?;
         let handler = handlers.read(&HANDLER.id)?;
 
         assert!(handler.is_none());
@@ -208,7 +461,7 @@ mod tests {
 
     #[test]
     fn delete_ok() -> anyhow::Result<()> {
-        let (_temp_dir, handlers) = setup()?;
+        let (_database, handlers) = setup()?;
         handlers.create(&HANDLER)?;
         handlers.delete(&HANDLER.id)?;
         let result = handlers.read(&HANDLER.id)?;
@@ -220,7 +473,7 @@ mod tests {
     #[test]
     #[ignore]
     fn delete_not_found() -> anyhow::Result<()> {
-        let (_temp_dir, handlers) = setup()?;
+        let (_database, handlers) = setup()?;
         let result = handlers.delete(&HANDLER.id);
 
         assert!(result.is_err());
@@ -229,8 +482,8 @@ mod tests {
 
     #[test]
     fn belonging_to_ok() -> anyhow::Result<()> {
-        let (_temp_dir, handlers) = setup()?;
-        let project: Project = Project::new(PROJECT_NAME.to_string(), USER_ID.to_string());
+        let (_database, handlers) = setup()?;
+        let project = PROJECT.clone();
         let mut handler = HANDLER.clone();
         handler.project_id = project.id.clone();
         handlers.create(&handler)?;
@@ -244,8 +497,8 @@ mod tests {
 
     #[test]
     fn belonging_to_not_found() -> anyhow::Result<()> {
-        let (_temp_dir, handlers) = setup()?;
-        let project = Project::new(PROJECT_NAME.to_string(), USER_ID.to_string());
+        let (_database, handlers) = setup()?;
+        let project = PROJECT.clone();
         let project_handlers = handlers.belonging_to(&project)?;
         assert!(project_handlers.is_empty());
 
@@ -254,13 +507,13 @@ mod tests {
 
     #[test]
     fn belonging_to_by_name_ok() -> anyhow::Result<()> {
-        let (_temp_dir, handlers) = setup()?;
-        let project = Project::new(PROJECT_NAME.to_string(), USER_ID.to_string());
+        let (_database, handlers) = setup()?;
+        let project = PROJECT.clone();
         let mut handler = HANDLER.clone();
         handler.project_id = project.id.clone();
         handlers.create(&handler)?;
 
-        let project_handler = handlers.belonging_to_by_name(&project, &HANDLER.name)?;
+        let project_handler = handlers.belonging_to_by_name(&project, STAGE, &HANDLER.name)?;
         assert!(project_handler.is_some());
         let project_handler = project_handler.unwrap();
         assert_eq!(handler, project_handler);
@@ -269,11 +522,261 @@ mod tests {
 
     #[test]
     fn belonging_to_by_name_not_found() -> anyhow::Result<()> {
-        let (_temp_dir, handlers) = setup()?;
-        let project = Project::new(PROJECT_NAME.to_string(), USER_ID.to_string());
+        let (_database, handlers) = setup()?;
+        let project = PROJECT.clone();
+
+        let project_handler = handlers.belonging_to_by_name(&project, STAGE, &HANDLER.name)?;
+        assert!(project_handler.is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn belonging_to_by_name_other_stage() -> anyhow::Result<()> {
+        let (_database, handlers) = setup()?;
+        let project = PROJECT.clone();
+        let mut handler = HANDLER.clone();
+        handler.project_id = project.id.clone();
+        handlers.create(&handler)?;
 
-        let project_handler = handlers.belonging_to_by_name(&project, &HANDLER.name)?;
+        let project_handler = handlers.belonging_to_by_name(&project, "staging", &HANDLER.name)?;
         assert!(project_handler.is_none());
         Ok(())
     }
+
+    #[test]
+    fn belonging_to_stage_ok() -> anyhow::Result<()> {
+        let (_database, handlers) = setup()?;
+        let project = PROJECT.clone();
+        let mut handler = HANDLER.clone();
+        handler.project_id = project.id.clone();
+        handlers.create(&handler)?;
+        let staging = Handler::new(
+            HANDLER_NAME.to_string(),
+            HANDLER_LANGUAGE,
+            HANDLER_HASH.to_string(),
+            project.id.clone(),
+            "staging".to_string(),
+        );
+        handlers.create(&staging)?;
+
+        let stage_handlers = handlers.belonging_to_stage(&project, "staging")?;
+        assert_eq!(vec![staging], stage_handlers);
+        assert_eq!(2, handlers.belonging_to(&project)?.len());
+        Ok(())
+    }
+
+    #[test]
+    fn activate_ok() -> anyhow::Result<()> {
+        let (_database, handlers) = setup()?;
+        handlers.create(&HANDLER)?;
+        let version = HandlerVersion::new(
+            HANDLER.id.clone(),
+            2,
+            "Aeb0eethoh2ai".to_string(),
+            "Aeb0eethoh2ai".to_string(),
+            0,
+            USER_ID.to_string(),
+        );
+        handlers.activate(&HANDLER, &version, Some(&fixtures::event()))?;
+
+        let handler = handlers.read(&HANDLER.id)?.unwrap();
+        assert_eq!(version.number, handler.version);
+        assert_eq!(version.hash, handler.hash);
+        Ok(())
+    }
+
+    #[test]
+    fn stop_canary_ok() -> anyhow::Result<()> {
+        let (_database, handlers) = setup()?;
+        let mut handler = HANDLER.clone();
+        handler.canary_version = Some(2);
+        handler.canary_weight = 10;
+        handlers.create(&handler)?;
+        handlers.stop_canary(&handler, Some(&fixtures::event()))?;
+
+        let handler = handlers.read(&HANDLER.id)?.unwrap();
+        assert_eq!(None, handler.canary_version);
+        assert_eq!(0, handler.canary_weight);
+        Ok(())
+    }
+
+    #[test]
+    fn promote_canary_ok() -> anyhow::Result<()> {
+        let (_database, handlers) = setup()?;
+        let mut handler = HANDLER.clone();
+        handler.canary_version = Some(2);
+        handler.canary_weight = 10;
+        handlers.create(&handler)?;
+        let canary = HandlerVersion::new(
+            HANDLER.id.clone(),
+            2,
+            "Aeb0eethoh2ai".to_string(),
+            "Aeb0eethoh2ai".to_string(),
+            0,
+            USER_ID.to_string(),
+        );
+        handlers.promote_canary(&handler, &canary, &fixtures::event())?;
+
+        let handler = handlers.read(&HANDLER.id)?.unwrap();
+        assert_eq!(canary.number, handler.version);
+        assert_eq!(canary.hash, handler.hash);
+        assert_eq!(None, handler.canary_version);
+        Ok(())
+    }
+
+    fn version(handler: &Handler, number: i32) -> HandlerVersion {
+        HandlerVersion::new(
+            handler.id.clone(),
+            number,
+            "Aeb0eethoh2ai".to_string(),
+            "Aeb0eethoh2ai".to_string(),
+            0,
+            USER_ID.to_string(),
+        )
+    }
+
+    #[test]
+    fn deploy_ok() -> anyhow::Result<()> {
+        let (_database, handlers, versions, _) = setup_with_versions()?;
+        handlers.deploy(&HANDLER, &version(&HANDLER, 1), &fixtures::event())?;
+
+        assert_eq!(Some(HANDLER.clone()), handlers.read(&HANDLER.id)?);
+        assert_eq!(Some(1), versions.latest(&HANDLER)?.map(|v| v.number));
+        Ok(())
+    }
+
+    #[test]
+    fn deploy_name_conflict_rolls_back() -> anyhow::Result<()> {
+        let (_database, handlers, versions, _) = setup_with_versions()?;
+        handlers.deploy(&HANDLER, &version(&HANDLER, 1), &fixtures::event())?;
+        let handler = Handler::new(
+            HANDLER_NAME.to_string(),
+            HANDLER_LANGUAGE,
+            HANDLER_HASH.to_string(),
+            PROJECT_ID.to_string(),
+            STAGE.to_string(),
+        );
+
+        let result = handlers.deploy(&handler, &version(&handler, 1), &fixtures::event());
+
+        assert!(result.is_err());
+        assert!(handlers.read(&handler.id)?.is_none());
+        assert!(versions.latest(&handler)?.is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn deploy_version_conflict_rolls_back() -> anyhow::Result<()> {
+        let (_database, handlers) = setup()?;
+        handlers.deploy(&HANDLER, &version(&HANDLER, 1), &fixtures::event())?;
+        let mut handler = HANDLER.clone();
+        handler.hash = "Ohqu8aiy".to_string();
+
+        let result = handlers.deploy(&handler, &version(&handler, 1), &fixtures::event());
+
+        assert!(result.is_err());
+        assert_eq!(Some(HANDLER.clone()), handlers.read(&HANDLER.id)?);
+        Ok(())
+    }
+
+    #[test]
+    fn deploy_audit_failure_rolls_back() -> anyhow::Result<()> {
+        let (database, handlers, versions, _) = setup_with_versions()?;
+        let event = fixtures::event();
+        handlers.deploy(&HANDLER, &version(&HANDLER, 1), &event)?;
+        let mut handler = HANDLER.clone();
+        handler.hash = "Ohqu8aiy".to_string();
+        handler.version = 2;
+
+        // The event exists already, so recording it again fails
+        let result = handlers.deploy(&handler, &version(&handler, 2), &event);
+
+        assert!(result.is_err());
+        assert_eq!(Some(HANDLER.clone()), handlers.read(&HANDLER.id)?);
+        assert_eq!(Some(1), versions.latest(&HANDLER)?.map(|v| v.number));
+        assert_eq!(1, fixtures::count_events(&database.pool)?);
+        Ok(())
+    }
+
+    #[test]
+    fn trash_audit_failure_rolls_back() -> anyhow::Result<()> {
+        let (database, handlers) = setup()?;
+        handlers.create(&HANDLER)?;
+        let event = fixtures::event();
+        handlers.trash(&HANDLER, &event)?;
+        handlers.restore(&HANDLER, &fixtures::event())?;
+
+        let result = handlers.trash(&HANDLER, &event);
+
+        assert!(result.is_err());
+        assert_eq!(Some(HANDLER.clone()), handlers.read(&HANDLER.id)?);
+        assert_eq!(2, fixtures::count_events(&database.pool)?);
+        Ok(())
+    }
+
+    #[test]
+    fn deploy_canary_ok() -> anyhow::Result<()> {
+        let (_database, handlers, versions, _) = setup_with_versions()?;
+        handlers.deploy(&HANDLER, &version(&HANDLER, 1), &fixtures::event())?;
+        handlers.deploy_canary(&HANDLER, &version(&HANDLER, 2), 10, &fixtures::event())?;
+
+        let handler = handlers.read(&HANDLER.id)?.unwrap();
+        assert_eq!(1, handler.version);
+        assert_eq!(Some(2), handler.canary_version);
+        assert_eq!(10, handler.canary_weight);
+        assert_eq!(Some(2), versions.latest(&HANDLER)?.map(|v| v.number));
+        Ok(())
+    }
+
+    #[test]
+    fn delete_cascades_to_versions() -> anyhow::Result<()> {
+        let (_database, handlers, versions, status_codes) = setup_with_versions()?;
+        let version = version(&HANDLER, 1);
+        handlers.deploy(&HANDLER, &version, &fixtures::event())?;
+        status_codes.increment(&version, 200)?;
+
+        handlers.delete(&HANDLER.id)?;
+
+        assert!(handlers.read(&HANDLER.id)?.is_none());
+        assert!(versions.latest(&HANDLER)?.is_none());
+        assert!(status_codes.belonging_to(&version)?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn trash_ok() -> anyhow::Result<()> {
+        let (_database, handlers) = setup()?;
+        handlers.create(&HANDLER)?;
+        handlers.trash(&HANDLER, &fixtures::event())?;
+
+        assert!(handlers.read(&HANDLER.id)?.is_none());
+        assert!(handlers.belonging_to(&PROJECT)?.is_empty());
+        assert!(handlers.belonging_to_stage(&PROJECT, STAGE)?.is_empty());
+        assert!(handlers
+            .belonging_to_by_name(&PROJECT, STAGE, HANDLER_NAME)?
+            .is_none());
+        let trashed = handlers
+            .trashed_by_name(&PROJECT, STAGE, HANDLER_NAME)?
+            .unwrap();
+        assert!(trashed.deleted_at.is_some());
+        assert_eq!(
+            vec![trashed],
+            handlers.trashed_before(Utc::now().naive_utc() + chrono::Duration::seconds(1))?
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn restore_ok() -> anyhow::Result<()> {
+        let (_database, handlers) = setup()?;
+        handlers.create(&HANDLER)?;
+        handlers.trash(&HANDLER, &fixtures::event())?;
+        handlers.restore(&HANDLER, &fixtures::event())?;
+
+        assert_eq!(Some(HANDLER.clone()), handlers.read(&HANDLER.id)?);
+        assert!(handlers
+            .trashed_by_name(&PROJECT, STAGE, HANDLER_NAME)?
+            .is_none());
+        Ok(())
+    }
 }
@@ -0,0 +1,287 @@
+use super::{
+    audit::{self, AuditEvent},
+    connection::execute_native,
+    create_id,
+    schema::{org_members, orgs, users},
+    user::User,
+    DatabasePool, Repository,
+};
+use chrono::{NaiveDateTime, Utc};
+use common::dtos::Role;
+use diesel::prelude::*;
+
+/// An organization, whose members work on its projects together
+#[derive(Identifiable, Insertable, Queryable, Selectable, Debug, Clone, PartialEq)]
+#[diesel(table_name = crate::repository::schema::orgs)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite, diesel::pg::Pg))]
+pub struct Org {
+    pub id: String,
+    pub name: String,
+    pub created_at: NaiveDateTime,
+}
+
+impl Org {
+    pub fn new(name: String) -> Self {
+        Self {
+            id: create_id(),
+            name,
+            created_at: Utc::now().naive_utc(),
+        }
+    }
+}
+
+#[derive(Identifiable, Insertable, Queryable, Selectable, Debug, Clone, PartialEq)]
+#[diesel(table_name = crate::repository::schema::org_members)]
+#[diesel(primary_key(org_id, user_id))]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite, diesel::pg::Pg))]
+pub struct OrgMember {
+    pub org_id: String,
+    pub user_id: String,
+    pub role: Role,
+}
+
+impl OrgMember {
+    pub fn new(org_id: String, user_id: String, role: Role) -> Self {
+        Self {
+            org_id,
+            user_id,
+            role,
+        }
+    }
+}
+
+#[cfg_attr(test, faux::create)]
+#[derive(Debug, Clone)]
+pub struct OrgRepository {
+    pool: DatabasePool,
+}
+
+#[cfg_attr(test, faux::methods)]
+impl Repository<Org> for OrgRepository {
+    fn new(pool: DatabasePool) -> Self {
+        Self { pool }
+    }
+
+    fn read(&self, id: &str) -> anyhow::Result<Option<Org>> {
+        let mut connection = self.pool.get()?;
+        let org = orgs::table
+            .find(id)
+            .first::<Org>(&mut connection)
+            .optional()?;
+        Ok(org)
+    }
+
+    fn create(&self, org: &Org) -> anyhow::Result<()> {
+        let mut connection = self.pool.get()?;
+        execute_native!(
+            &mut connection,
+            diesel::insert_into(orgs::table).values(org)
+        )?;
+        Ok(())
+    }
+
+    fn delete(&self, id: &str) -> anyhow::Result<()> {
+        let mut connection = self.pool.get()?;
+        diesel::delete(orgs::table.find(id)).execute(&mut connection)?;
+        Ok(())
+    }
+}
+
+#[cfg_attr(test, faux::methods)]
+impl OrgRepository {
+    /// Creates the organization with its first member in one transaction
+    pub fn create_with_owner(
+        &self,
+        org: &Org,
+        owner: &User,
+        event: &AuditEvent,
+    ) -> anyhow::Result<()> {
+        let mut connection = self.pool.get()?;
+
+        connection.transaction(|connection| {
+            execute_native!(connection, diesel::insert_into(orgs::table).values(org))?;
+            execute_native!(
+                connection,
+                diesel::insert_into(org_members::table).values(&OrgMember::new(
+                    org.id.clone(),
+                    owner.id.clone(),
+                    Role::Owner
+                ))
+            )?;
+            audit::insert(connection, event)
+        })?;
+
+        Ok(())
+    }
+
+    pub fn read_by_name(&self, name: &str) -> anyhow::Result<Option<Org>> {
+        let mut connection = self.pool.get()?;
+        let org = orgs::table
+            .filter(orgs::dsl::name.eq(name))
+            .first::<Org>(&mut connection)
+            .optional()?;
+        Ok(org)
+    }
+
+    /// The organizations of the user with the role of the user in them
+    pub fn belonging_to(&self, user: &User) -> anyhow::Result<Vec<(Org, Role)>> {
+        let mut connection = self.pool.get()?;
+        let orgs = orgs::table
+            .inner_join(org_members::table)
+            .filter(org_members::dsl::user_id.eq(&user.id))
+            .order(orgs::dsl::name.asc())
+            .select((Org::as_select(), org_members::dsl::role))
+            .load::<(Org, Role)>(&mut connection)?;
+        Ok(orgs)
+    }
+
+    /// The members of the organization ordered by their login
+    pub fn members(&self, org: &Org) -> anyhow::Result<Vec<(User, Role)>> {
+        let mut connection = self.pool.get()?;
+        let members = org_members::table
+            .inner_join(users::table)
+            .filter(org_members::dsl::org_id.eq(&org.id))
+            .order(users::dsl::login.asc())
+            .select((User::as_select(), org_members::dsl::role))
+            .load::<(User, Role)>(&mut connection)?;
+        Ok(members)
+    }
+
+    pub fn role(&self, org_id: &str, user_id: &str) -> anyhow::Result<Option<Role>> {
+        let mut connection = self.pool.get()?;
+        let role = org_members::table
+            .find((org_id, user_id))
+            .select(org_members::dsl::role)
+            .first::<Role>(&mut connection)
+            .optional()?;
+        Ok(role)
+    }
+
+    /// Adds the member or changes the role of an existing member
+    pub fn save_member(&self, member: &OrgMember, event: &AuditEvent) -> anyhow::Result<()> {
+        let mut connection = self.pool.get()?;
+        connection.transaction(|connection| {
+            execute_native!(
+                connection,
+                diesel::insert_into(org_members::table)
+                    .values(member)
+                    .on_conflict((org_members::dsl::org_id, org_members::dsl::user_id))
+                    .do_update()
+                    .set(org_members::dsl::role.eq(member.role))
+            )?;
+            audit::insert(connection, event)
+        })?;
+        Ok(())
+    }
+
+    /// Removes the member and returns whether they were a member. The event is only recorded
+    /// if they were.
+    pub fn delete_member(
+        &self,
+        org_id: &str,
+        user_id: &str,
+        event: &AuditEvent,
+    ) -> anyhow::Result<bool> {
+        let mut connection = self.pool.get()?;
+        let deleted = connection.transaction(|connection| {
+            let deleted =
+                diesel::delete(org_members::table.find((org_id, user_id))).execute(connection)?;
+            if deleted > 0 {
+                audit::insert(connection, event)?;
+            }
+            diesel::result::QueryResult::Ok(deleted)
+        })?;
+        Ok(deleted > 0)
+    }
+
+    pub fn count_owners(&self, org_id: &str) -> anyhow::Result<i64> {
+        let mut connection = self.pool.get()?;
+        let count = org_members::table
+            .filter(org_members::dsl::org_id.eq(org_id))
+            .filter(org_members::dsl::role.eq(Role::Owner))
+            .count()
+            .get_result::<i64>(&mut connection)?;
+        Ok(count)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repository::fixtures::{self, TestDatabase};
+
+    const OWNER_ID: &str = "Ohngoo4eiPhaiph4ieTh8";
+    const MEMBER_ID: &str = "Quai3aeLoh4ieng7Iechu";
+
+    fn setup() -> anyhow::Result<(TestDatabase, OrgRepository, User)> {
+        let database = fixtures::database()?;
+        let pool = database.pool.clone();
+        fixtures::insert_user(&pool, OWNER_ID)?;
+        fixtures::insert_user(&pool, MEMBER_ID)?;
+        let owner = User {
+            id: OWNER_ID.to_string(),
+            ..Default::default()
+        };
+        Ok((database, OrgRepository::new(pool), owner))
+    }
+
+    #[test]
+    fn create_with_owner_ok() -> anyhow::Result<()> {
+        let (_database, orgs, owner) = setup()?;
+        let org = Org::new("acme".to_string());
+        orgs.create_with_owner(&org, &owner, &fixtures::event())?;
+
+        assert_eq!(
+            Some(org.id.clone()),
+            orgs.read_by_name("acme")?.map(|org| org.id)
+        );
+        let roles: Vec<(String, Role)> = orgs
+            .belonging_to(&owner)?
+            .into_iter()
+            .map(|(org, role)| (org.name, role))
+            .collect();
+        assert_eq!(vec![("acme".to_string(), Role::Owner)], roles);
+        assert_eq!(Some(Role::Owner), orgs.role(&org.id, OWNER_ID)?);
+        assert_eq!(None, orgs.role(&org.id, MEMBER_ID)?);
+        assert!(orgs
+            .create_with_owner(&Org::new("acme".to_string()), &owner, &fixtures::event())
+            .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn save_and_delete_member_ok() -> anyhow::Result<()> {
+        let (database, orgs, owner) = setup()?;
+        let org = Org::new("acme".to_string());
+        orgs.create_with_owner(&org, &owner, &fixtures::event())?;
+
+        orgs.save_member(
+            &OrgMember::new(org.id.clone(), MEMBER_ID.to_string(), Role::Viewer),
+            &fixtures::event(),
+        )?;
+        orgs.save_member(
+            &OrgMember::new(org.id.clone(), MEMBER_ID.to_string(), Role::Maintainer),
+            &fixtures::event(),
+        )?;
+        let roles: Vec<(String, Role)> = orgs
+            .members(&org)?
+            .into_iter()
+            .map(|(user, role)| (user.id, role))
+            .collect();
+        assert_eq!(
+            vec![
+                (OWNER_ID.to_string(), Role::Owner),
+                (MEMBER_ID.to_string(), Role::Maintainer)
+            ],
+            roles
+        );
+        assert_eq!(1, orgs.count_owners(&org.id)?);
+
+        assert!(orgs.delete_member(&org.id, MEMBER_ID, &fixtures::event())?);
+        assert!(!orgs.delete_member(&org.id, MEMBER_ID, &fixtures::event())?);
+        assert_eq!(None, orgs.role(&org.id, MEMBER_ID)?);
+        // Removing someone who isn't a member records nothing
+        assert_eq!(4, fixtures::count_events(&database.pool)?);
+        Ok(())
+    }
+}
@@ -1,24 +1,35 @@
 use super::{
+    audit::{self, AuditEvent},
+    connection::{execute_native, DbConnection},
     create_id,
-    schema::projects::{self},
+    handler::Handler,
+    schema::{
+        handler_versions, handlers, org_members, orgs, projects, stage_variables, trusted_keys,
+    },
+    trusted_key::TrustedKey,
     user::User,
-    Repository,
-};
-use diesel::{
-    prelude::*,
-    r2d2::{ConnectionManager, Pool},
+    variable::StageVariable,
+    version::HandlerVersion,
+    DatabasePool, Repository,
 };
+use chrono::{NaiveDateTime, Utc};
+use common::dtos::Role;
+use diesel::prelude::*;
 
 #[derive(
     Identifiable, Insertable, Queryable, Selectable, Associations, Debug, PartialEq, Clone,
 )]
 #[diesel(table_name = crate::repository::schema::projects)]
 #[diesel(belongs_to(User))]
-#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite, diesel::pg::Pg))]
 pub struct Project {
     pub id: String,
     pub name: String,
     pub user_id: String,
+    /// When the project was moved to the trash
+    pub deleted_at: Option<NaiveDateTime>,
+    /// The organization owning the project, `user_id` is the member who created it then
+    pub org_id: Option<String>,
 }
 
 impl Project {
@@ -27,19 +38,57 @@ impl Project {
             id: create_id(),
             name,
             user_id,
+            deleted_at: None,
+            org_id: None,
         }
     }
 }
 
+/// Splits the name of a project of an organization, like `acme/shop`, into the name of the
+/// organization and the name of the project
+pub fn split_name(name: &str) -> (Option<&str>, &str) {
+    match name.split_once('/') {
+        Some((org, project)) => (Some(org), project),
+        None => (None, name),
+    }
+}
+
+/// The project of the user, or of one of the organizations of the user if the name is
+/// qualified with it
+fn find_by_name(
+    connection: &mut DbConnection,
+    user: &User,
+    name: &str,
+    trashed: bool,
+) -> QueryResult<Option<Project>> {
+    match split_name(name) {
+        (Some(org), project_name) => projects::table
+            .inner_join(orgs::table.inner_join(org_members::table))
+            .filter(orgs::dsl::name.eq(org))
+            .filter(org_members::dsl::user_id.eq(&user.id))
+            .filter(projects::dsl::name.eq(project_name))
+            .filter(projects::dsl::deleted_at.is_not_null().eq(trashed))
+            .select(Project::as_select())
+            .first::<Project>(connection)
+            .optional(),
+        (None, project_name) => Project::belonging_to(user)
+            .filter(projects::dsl::org_id.is_null())
+            .filter(projects::dsl::name.eq(project_name))
+            .filter(projects::dsl::deleted_at.is_not_null().eq(trashed))
+            .first::<Project>(connection)
+            .optional(),
+    }
+}
+
 #[cfg_attr(test, faux::create)]
 #[derive(Debug, Clone)]
 pub struct ProjectRepository {
-    pool: Pool<ConnectionManager<SqliteConnection>>,
+    pool: DatabasePool,
 }
 
 #[cfg_attr(test, faux::methods)]
 impl Repository<Project> for ProjectRepository {
-    fn new(pool: Pool<ConnectionManager<SqliteConnection>>) -> Self {
+    fn new(pool: DatabasePool) -> Self {
         Self { pool }
     }
 
@@ -47,6 +96,7 @@ impl Repository<Project> for ProjectRepository {
         let mut connection = self.pool.get()?;
         let project = projects::dsl::projects
             .find(id)
+            .filter(projects::dsl::deleted_at.is_null())
             .first::<Project>(&mut connection)
             .optional()?;
 
@@ -56,9 +106,10 @@ impl Repository<Project> for ProjectRepository {
     fn create(&self, project: &Project) -> anyhow::Result<()> {
         let mut connection = self.pool.get()?;
 
-        diesel::insert_into(projects::table)
-            .values(project)
-            .execute(&mut connection)?;
+        execute_native!(
+            &mut connection,
+            diesel::insert_into(projects::table).values(project)
+        )?;
 
         Ok(())
     }
@@ -75,32 +126,192 @@ impl Repository<Project> for ProjectRepository {
 
 #[cfg_attr(test, faux::methods)]
 impl ProjectRepository {
+    /// Creates the project and records the event in one transaction
+    pub fn create_with_audit(&self, project: &Project, event: &AuditEvent) -> anyhow::Result<()> {
+        let mut connection = self.pool.get()?;
+
+        connection.transaction(|connection| {
+            execute_native!(
+                connection,
+                diesel::insert_into(projects::table).values(project)
+            )?;
+            audit::insert(connection, event)
+        })?;
+
+        Ok(())
+    }
+
+    /// The project of the user or, with a name like `acme/shop`, the project of an
+    /// organization the user is a member of
     pub fn belonging_to_by_name(
         &self,
         user: &User,
         project_name: &str,
     ) -> anyhow::Result<Option<Project>> {
         let mut connection = self.pool.get()?;
-        let project = Project::belonging_to(user)
-            .filter(projects::dsl::name.eq(project_name))
-            .first::<Project>(&mut connection)
+        let project = find_by_name(&mut connection, user, project_name, false)?;
+
+        Ok(project)
+    }
+
+    /// The role of the user in the project, users own their personal projects
+    pub fn role(&self, user: &User, project: &Project) -> anyhow::Result<Option<Role>> {
+        let Some(org_id) = &project.org_id else {
+            return Ok((project.user_id == user.id).then_some(Role::Owner));
+        };
+        let mut connection = self.pool.get()?;
+        let role = org_members::table
+            .find((org_id, &user.id))
+            .select(org_members::dsl::role)
+            .first::<Role>(&mut connection)
             .optional()?;
 
+        Ok(role)
+    }
+
+    /// Counts the projects the user created, including those of organizations
+    pub fn count_belonging_to(&self, user_id: &str) -> anyhow::Result<i64> {
+        let mut connection = self.pool.get()?;
+        let count = projects::table
+            .filter(projects::dsl::user_id.eq(user_id))
+            .filter(projects::dsl::deleted_at.is_null())
+            .count()
+            .get_result::<i64>(&mut connection)?;
+        Ok(count)
+    }
+
+    pub fn trashed_by_name(
+        &self,
+        user: &User,
+        project_name: &str,
+    ) -> anyhow::Result<Option<Project>> {
+        let mut connection = self.pool.get()?;
+        let project = find_by_name(&mut connection, user, project_name, true)?;
+
         Ok(project)
     }
+
+    /// Projects moved to the trash before the given time
+    pub fn trashed_before(&self, before: NaiveDateTime) -> anyhow::Result<Vec<Project>> {
+        let mut connection = self.pool.get()?;
+        let projects = projects::table
+            .filter(projects::dsl::deleted_at.lt(before))
+            .load::<Project>(&mut connection)?;
+        Ok(projects)
+    }
+
+    /// Moves the project together with its handlers to the trash. The handlers get the same
+    /// deletion time as the project, which tells them apart from handlers deleted before.
+    pub fn trash(&self, project: &Project, event: &AuditEvent) -> anyhow::Result<()> {
+        let mut connection = self.pool.get()?;
+        let now = Utc::now().naive_utc();
+
+        connection.transaction(|connection| {
+            diesel::update(projects::table.find(&project.id))
+                .set(projects::dsl::deleted_at.eq(now))
+                .execute(connection)?;
+            diesel::update(
+                handlers::table
+                    .filter(handlers::dsl::project_id.eq(&project.id))
+                    .filter(handlers::dsl::deleted_at.is_null()),
+            )
+            .set(handlers::dsl::deleted_at.eq(now))
+            .execute(connection)?;
+            audit::insert(connection, event)
+        })?;
+
+        Ok(())
+    }
+
+    /// Takes the project out of the trash together with the handlers trashed with it
+    pub fn restore(&self, project: &Project, event: &AuditEvent) -> anyhow::Result<()> {
+        let mut connection = self.pool.get()?;
+
+        connection.transaction(|connection| {
+            if let Some(deleted_at) = project.deleted_at {
+                execute_native!(
+                    connection,
+                    diesel::update(
+                        handlers::table
+                            .filter(handlers::dsl::project_id.eq(&project.id))
+                            .filter(handlers::dsl::deleted_at.eq(deleted_at)),
+                    )
+                    .set(handlers::dsl::deleted_at.eq(None::<NaiveDateTime>))
+                )?;
+            }
+            execute_native!(
+                connection,
+                diesel::update(projects::table.find(&project.id))
+                    .set(projects::dsl::deleted_at.eq(None::<NaiveDateTime>))
+            )?;
+            audit::insert(connection, event)
+        })?;
+
+        Ok(())
+    }
+
+    /// Creates the project together with its handlers, versions, variables and trusted keys in
+    /// one transaction, so a failed import leaves nothing behind. The trashed project the import
+    /// replaces is deleted in the same transaction, a failed import keeps it.
+    #[allow(clippy::too_many_arguments)]
+    pub fn import(
+        &self,
+        project: &Project,
+        handlers: &[Handler],
+        versions: &[HandlerVersion],
+        variables: &[StageVariable],
+        keys: &[TrustedKey],
+        trashed: Option<&Project>,
+        event: &AuditEvent,
+    ) -> anyhow::Result<()> {
+        let mut connection = self.pool.get()?;
+
+        connection.transaction(|connection| {
+            if let Some(trashed) = trashed {
+                diesel::delete(projects::table.find(&trashed.id)).execute(connection)?;
+            }
+            execute_native!(
+                connection,
+                diesel::insert_into(projects::table).values(project)
+            )?;
+            execute_native!(
+                connection,
+                diesel::insert_into(handlers::table).values(handlers)
+            )?;
+            execute_native!(
+                connection,
+                diesel::insert_into(handler_versions::table).values(versions)
+            )?;
+            execute_native!(
+                connection,
+                diesel::insert_into(stage_variables::table).values(variables)
+            )?;
+            execute_native!(
+                connection,
+                diesel::insert_into(trusted_keys::table).values(keys)
+            )?;
+            audit::insert(connection, event)
+        })?;
+
+        Ok(())
+    }
+
+    pub fn all(&self) -> anyhow::Result<Vec<Project>> {
+        let mut connection = self.pool.get()?;
+        let projects = projects::table.load::<Project>(&mut connection)?;
+        Ok(projects)
+    }
 }
 
 #[cfg(test)]
 mod tests {
 
-    use crate::repository::create_pool;
+    use crate::repository::fixtures::{self, TestDatabase};
 
     use super::*;
-    use diesel_migrations::{FileBasedMigrations, MigrationHarness};
+    use crate::identity::Identity;
     use lazy_static::lazy_static;
-    use tempfile::{tempdir, TempDir};
 
-    const DATABASE_NAME: &str = "noops_test.sqlite";
     const PROJECT_NAME: &str = "TEST_PROJECT";
     const USER_ID: &str = "oveethai3oophaV6Aiwei";
 
@@ -108,27 +319,24 @@ mod tests {
     const USER_NAME: &str = "user_name";
     const USER_LOCATION: &str = "Hamburg";
     const USER_COMPANY: &str = "Noops.io";
-    const USER_GH_ACCESS_TOKEN: &str = "Yiu0Hae4ietheereij4OhneuNe6tae0e";
-    const USER_GH_LOGIN: &str = "login_name";
-    const USER_GH_ID: i32 = 42;
+    const USER_LOGIN: &str = "login_name";
+    const USER_SUBJECT: &str = "42";
 
     lazy_static! {
         static ref PROJECT: Project = Project::new(PROJECT_NAME.to_string(), USER_ID.to_string());
     }
 
-    fn setup() -> anyhow::Result<(TempDir, ProjectRepository)> {
-        let temp_dir = tempdir()?;
-        let pool = create_pool(&temp_dir.path().join(DATABASE_NAME));
-        let mut connection = pool.get()?;
+    fn setup() -> anyhow::Result<(TestDatabase, ProjectRepository)> {
+        let database = fixtures::database()?;
+        let pool = database.pool.clone();
+        fixtures::insert_user(&pool, USER_ID)?;
         let projects = ProjectRepository::new(pool);
-        let migrations = FileBasedMigrations::find_migrations_directory_in_path("./server")?;
-        connection.run_pending_migrations(migrations).unwrap();
-        Ok((temp_dir, projects))
+        Ok((database, projects))
     }
 
     #[test]
     fn create_ok() -> anyhow::Result<()> {
-        let (_temp_dir, projects) = setup()?;
+        let (_database, projects) = setup()?;
         projects.create(&PROJECT)?;
 
         Ok(())
@@ -136,7 +344,7 @@ mod tests {
 
     #[test]
     fn create_conflict() -> anyhow::Result<()> {
-        let (_temp_dir, projects) = setup()?;
+        let (_database, projects) = setup()?;
         projects.create(&PROJECT)?;
         let result = projects.create(&PROJECT);
 
@@ -146,7 +354,7 @@ mod tests {
 
     #[test]
     fn create_name_conflict() -> anyhow::Result<()> {
-        let (_temp_dir, projects) = setup()?;
+        let (_database, projects) = setup()?;
         projects.create(&PROJECT)?;
         let project = Project::new(PROJECT_NAME.to_string(), USER_ID.to_string());
         let result = projects.create(&project);
@@ -157,7 +365,7 @@ mod tests {
 
     #[test]
     fn read_by_id_ok() -> anyhow::Result<()> {
-        let (_temp_dir, projects) = setup()?;
+        let (_database, projects) = setup()?;
         projects.create(&PROJECT)?;
         let result = projects.read(&PROJECT.id)?;
 
@@ -170,7 +378,7 @@ mod tests {
 
     #[test]
     fn delete_ok() -> anyhow::Result<()> {
-        let (_temp_dir, projects) = setup()?;
+        let (_database, projects) = setup()?;
         projects.create(&PROJECT)?;
         projects.delete(&PROJECT.id)?;
         let result = projects.read(&PROJECT.id)?;
@@ -182,7 +390,7 @@ mod tests {
     #[test]
     #[ignore]
     fn delete_not_found() -> anyhow::Result<()> {
-        let (_temp_dir, projects) = setup()?;
+        let (_database, projects) = setup()?;
         let result = projects.delete("UNKNOWN_PROJECT_ID");
         assert!(result.is_err());
         Ok(())
@@ -190,18 +398,21 @@ mod tests {
 
     #[test]
     fn belonging_to_ok() -> anyhow::Result<()> {
-        let (_temp_dir, projects) = setup()?;
-        let user = User::new(
-            USER_EMAIL.to_string(),
-            Some(USER_NAME.to_string()),
-            Some(USER_LOCATION.to_string()),
-            Some(USER_COMPANY.to_string()),
-            USER_GH_ID,
-            USER_GH_LOGIN.to_string(),
-            USER_GH_ACCESS_TOKEN.to_string(),
-        );
-        let mut project = PROJECT.clone();
-        project.user_id = user.id.clone();
+        let (_database, projects) = setup()?;
+        let user = User::new(Identity {
+            provider: "github".to_string(),
+            subject: USER_SUBJECT.to_string(),
+            login: USER_LOGIN.to_string(),
+            email: USER_EMAIL.to_string(),
+            name: Some(USER_NAME.to_string()),
+            location: Some(USER_LOCATION.to_string()),
+            company: Some(USER_COMPANY.to_string()),
+        });
+        let user = User {
+            id: USER_ID.to_string(),
+            ..user
+        };
+        let project = PROJECT.clone();
         projects.create(&project)?;
 
         let result = projects.belonging_to_by_name(&user, &project.name)?;
@@ -214,16 +425,16 @@ mod tests {
 
     #[test]
     fn belonging_to_not_found() -> anyhow::Result<()> {
-        let (_temp_dir, projects) = setup()?;
-        let user = User::new(
-            USER_EMAIL.to_string(),
-            Some(USER_NAME.to_string()),
-            Some(USER_LOCATION.to_string()),
-            Some(USER_COMPANY.to_string()),
-            USER_GH_ID,
-            USER_GH_LOGIN.to_string(),
-            USER_GH_ACCESS_TOKEN.to_string(),
-        );
+        let (_database, projects) = setup()?;
+        let user = User::new(Identity {
+            provider: "github".to_string(),
+            subject: USER_SUBJECT.to_string(),
+            login: USER_LOGIN.to_string(),
+            email: USER_EMAIL.to_string(),
+            name: Some(USER_NAME.to_string()),
+            location: Some(USER_LOCATION.to_string()),
+            company: Some(USER_COMPANY.to_string()),
+        });
         projects.create(&PROJECT)?;
 
         let result = projects.belonging_to_by_name(&user, &PROJECT.name)?;
@@ -231,4 +442,221 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn count_belonging_to_ok() -> anyhow::Result<()> {
+        let (_database, projects) = setup()?;
+        projects.create(&PROJECT)?;
+        let trashed = Project::new("TRASHED".to_string(), USER_ID.to_string());
+        projects.create(&trashed)?;
+        projects.trash(&trashed, &fixtures::event())?;
+
+        assert_eq!(1, projects.count_belonging_to(USER_ID)?);
+        Ok(())
+    }
+
+    fn user() -> User {
+        let user = User::new(Identity {
+            provider: "github".to_string(),
+            subject: USER_SUBJECT.to_string(),
+            login: USER_LOGIN.to_string(),
+            email: USER_EMAIL.to_string(),
+            name: Some(USER_NAME.to_string()),
+            location: Some(USER_LOCATION.to_string()),
+            company: Some(USER_COMPANY.to_string()),
+        });
+        User {
+            id: USER_ID.to_string(),
+            ..user
+        }
+    }
+
+    fn handler_deleted_at(
+        database: &TestDatabase,
+        handler_id: &str,
+    ) -> anyhow::Result<Option<NaiveDateTime>> {
+        let deleted_at = handlers::table
+            .find(handler_id)
+            .select(handlers::dsl::deleted_at)
+            .first(&mut database.pool.get()?)?;
+        Ok(deleted_at)
+    }
+
+    #[test]
+    fn trash_ok() -> anyhow::Result<()> {
+        let (database, projects) = setup()?;
+        projects.create(&PROJECT)?;
+        fixtures::insert_handler(&database.pool, "HANDLER", &PROJECT.id)?;
+        projects.trash(&PROJECT, &fixtures::event())?;
+
+        assert!(projects.read(&PROJECT.id)?.is_none());
+        assert!(projects
+            .belonging_to_by_name(&user(), PROJECT_NAME)?
+            .is_none());
+        let trashed = projects.trashed_by_name(&user(), PROJECT_NAME)?.unwrap();
+        assert_eq!(
+            trashed.deleted_at,
+            handler_deleted_at(&database, "HANDLER")?
+        );
+        assert_eq!(
+            vec![trashed],
+            projects.trashed_before(Utc::now().naive_utc() + chrono::Duration::seconds(1))?
+        );
+        assert!(projects
+            .trashed_before(Utc::now().naive_utc() - chrono::Duration::days(1))?
+            .is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn restore_ok() -> anyhow::Result<()> {
+        let (database, projects) = setup()?;
+        projects.create(&PROJECT)?;
+        fixtures::insert_handler(&database.pool, "TRASHED_WITH_PROJECT", &PROJECT.id)?;
+        fixtures::insert_handler(&database.pool, "TRASHED_BEFORE", &PROJECT.id)?;
+        let deleted_before = Utc::now().naive_utc() - chrono::Duration::hours(1);
+        diesel::update(handlers::table.find("TRASHED_BEFORE"))
+            .set(handlers::dsl::deleted_at.eq(deleted_before))
+            .execute(&mut database.pool.get()?)?;
+
+        projects.trash(&PROJECT, &fixtures::event())?;
+        let trashed = projects.trashed_by_name(&user(), PROJECT_NAME)?.unwrap();
+        projects.restore(&trashed, &fixtures::event())?;
+
+        assert_eq!(Some(PROJECT.clone()), projects.read(&PROJECT.id)?);
+        assert!(projects.trashed_by_name(&user(), PROJECT_NAME)?.is_none());
+        assert!(handler_deleted_at(&database, "TRASHED_WITH_PROJECT")?.is_none());
+        assert!(handler_deleted_at(&database, "TRASHED_BEFORE")?.is_some());
+        Ok(())
+    }
+
+    fn import_rows() -> (Vec<Handler>, Vec<HandlerVersion>, Vec<StageVariable>) {
+        let handler = Handler::new(
+            "HANDLER".to_string(),
+            common::dtos::Language::Rust,
+            "HASH".to_string(),
+            PROJECT.id.clone(),
+            "prod".to_string(),
+        );
+        let version = HandlerVersion::new(
+            handler.id.clone(),
+            1,
+            "HASH".to_string(),
+            "BLOB".to_string(),
+            0,
+            USER_ID.to_string(),
+        );
+        let variable = StageVariable::new(
+            PROJECT.id.clone(),
+            "prod".to_string(),
+            "NAME".to_string(),
+            "VALUE".to_string(),
+        );
+        (vec![handler], vec![version], vec![variable])
+    }
+
+    #[test]
+    fn import_ok() -> anyhow::Result<()> {
+        let (database, projects) = setup()?;
+        let (handlers, versions, variables) = import_rows();
+        let key = TrustedKey::new(PROJECT.id.clone(), "ci".to_string(), "KEY".to_string());
+        projects.import(
+            &PROJECT,
+            &handlers,
+            &versions,
+            &variables,
+            &[key],
+            None,
+            &fixtures::event(),
+        )?;
+
+        let mut connection = database.pool.get()?;
+        assert_eq!(Some(PROJECT.clone()), projects.read(&PROJECT.id)?);
+        assert_eq!(handlers, handlers::table.load::<Handler>(&mut connection)?);
+        assert_eq!(
+            Ok(1),
+            handler_versions::table
+                .count()
+                .get_result::<i64>(&mut connection)
+        );
+        assert_eq!(
+            Ok(1),
+            stage_variables::table
+                .count()
+                .get_result::<i64>(&mut connection)
+        );
+        assert_eq!(
+            Ok(1),
+            trusted_keys::table
+                .count()
+                .get_result::<i64>(&mut connection)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn import_rolls_back() -> anyhow::Result<()> {
+        let (database, projects) = setup()?;
+        let (handlers, versions, variables) = import_rows();
+        let key = TrustedKey::new(PROJECT.id.clone(), "ci".to_string(), "KEY".to_string());
+        let result = projects.import(
+            &PROJECT,
+            &handlers,
+            &versions,
+            &variables,
+            &[key.clone(), key],
+            None,
+            &fixtures::event(),
+        );
+
+        assert!(result.is_err());
+        assert!(projects.read(&PROJECT.id)?.is_none());
+        assert_eq!(
+            Ok(0),
+            handlers::table
+                .count()
+                .get_result::<i64>(&mut database.pool.get()?)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn import_replaces_trashed() -> anyhow::Result<()> {
+        let (database, projects) = setup()?;
+        let trashed = Project {
+            id: "TRASHED_PROJECT".to_string(),
+            ..PROJECT.clone()
+        };
+        projects.create(&trashed)?;
+        fixtures::insert_handler(&database.pool, "TRASHED_HANDLER", &trashed.id)?;
+        projects.trash(&trashed, &fixtures::event())?;
+        let (handlers, versions, variables) = import_rows();
+        let key = TrustedKey::new(PROJECT.id.clone(), "ci".to_string(), "KEY".to_string());
+
+        let result = projects.import(
+            &PROJECT,
+            &handlers,
+            &versions,
+            &variables,
+            &[key.clone(), key.clone()],
+            Some(&trashed),
+            &fixtures::event(),
+        );
+        assert!(result.is_err());
+        assert!(projects.trashed_by_name(&user(), PROJECT_NAME)?.is_some());
+
+        projects.import(
+            &PROJECT,
+            &handlers,
+            &versions,
+            &variables,
+            &[key],
+            Some(&trashed),
+            &fixtures::event(),
+        )?;
+        assert!(projects.trashed_by_name(&user(), PROJECT_NAME)?.is_none());
+        assert!(projects.read(&trashed.id)?.is_none());
+        assert_eq!(Some(PROJECT.clone()), projects.read(&PROJECT.id)?);
+        Ok(())
+    }
 }
@@ -0,0 +1,207 @@
+use super::{
+    connection::execute_native,
+    schema::{
+        invocations,
+        user_quotas::{self, dsl},
+    },
+    DatabasePool,
+};
+use chrono::NaiveDate;
+use diesel::prelude::*;
+
+/// Limits of a user which differ from the defaults of the server, `None` keeps the default
+#[derive(
+    Identifiable, Insertable, Queryable, Selectable, AsChangeset, Debug, Clone, PartialEq, Default,
+)]
+#[diesel(table_name = crate::repository::schema::user_quotas)]
+#[diesel(primary_key(user_id))]
+#[diesel(treat_none_as_null = true)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite, diesel::pg::Pg))]
+pub struct QuotaOverride {
+    pub user_id: String,
+    pub projects: Option<i64>,
+    pub handlers_per_project: Option<i64>,
+    pub artifact_size: Option<i64>,
+    pub storage: Option<i64>,
+    pub invocations_per_day: Option<i64>,
+}
+
+#[cfg_attr(test, faux::create)]
+#[derive(Debug, Clone)]
+pub struct QuotaRepository {
+    pool: DatabasePool,
+}
+
+#[cfg_attr(test, faux::methods)]
+impl QuotaRepository {
+    pub fn new(pool: DatabasePool) -> Self {
+        Self { pool }
+    }
+
+    pub fn read(&self, user_id: &str) -> anyhow::Result<Option<QuotaOverride>> {
+        let mut connection = self.pool.get()?;
+
+        let quota = user_quotas::table
+            .find(user_id)
+            .first::<QuotaOverride>(&mut connection)
+            .optional()?;
+
+        Ok(quota)
+    }
+
+    /// Creates or replaces the overrides of the user
+    pub fn save(&self, quota: &QuotaOverride) -> anyhow::Result<()> {
+        let mut connection = self.pool.get()?;
+
+        execute_native!(
+            &mut connection,
+            diesel::insert_into(user_quotas::table)
+                .values(quota)
+                .on_conflict(dsl::user_id)
+                .do_update()
+                .set(quota)
+        )?;
+
+        Ok(())
+    }
+
+    pub fn delete(&self, user_id: &str) -> anyhow::Result<()> {
+        let mut connection = self.pool.get()?;
+        diesel::delete(user_quotas::table.find(user_id)).execute(&mut connection)?;
+        Ok(())
+    }
+
+    /// The invocations of the handlers of the user on the day
+    pub fn invocations(&self, user_id: &str, day: NaiveDate) -> anyhow::Result<i64> {
+        let mut connection = self.pool.get()?;
+
+        let count = invocations::table
+            .find((user_id, day))
+            .select(invocations::dsl::count)
+            .first::<i64>(&mut connection)
+            .optional()?;
+
+        Ok(count.unwrap_or(0))
+    }
+
+    /// Counts an invocation of the handlers of the user on the day, unless the user has reached
+    /// the limit already. The limit is checked by the update counting the invocation, so
+    /// concurrent invocations can't exceed it. Returns whether the invocation was counted.
+    pub fn count_invocation(
+        &self,
+        user_id: &str,
+        day: NaiveDate,
+        limit: i64,
+    ) -> anyhow::Result<bool> {
+        let mut connection = self.pool.get()?;
+
+        execute_native!(
+            &mut connection,
+            diesel::insert_into(invocations::table)
+                .values((
+                    invocations::dsl::user_id.eq(user_id),
+                    invocations::dsl::day.eq(day),
+                    invocations::dsl::count.eq(0),
+                ))
+                .on_conflict((invocations::dsl::user_id, invocations::dsl::day))
+                .do_nothing()
+        )?;
+        let counted = diesel::update(
+            invocations::table
+                .find((user_id, day))
+                .filter(invocations::dsl::count.lt(limit)),
+        )
+        .set(invocations::dsl::count.eq(invocations::dsl::count + 1))
+        .execute(&mut connection)?;
+
+        Ok(counted == 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repository::fixtures::{self, TestDatabase};
+
+    const USER_ID: &str = "Ohshie5iepaeShoo7aeng";
+
+    fn setup() -> anyhow::Result<(TestDatabase, QuotaRepository)> {
+        let database = fixtures::database()?;
+        fixtures::insert_user(&database.pool, USER_ID)?;
+        let quotas = QuotaRepository::new(database.pool.clone());
+        Ok((database, quotas))
+    }
+
+    #[test]
+    fn save_ok() -> anyhow::Result<()> {
+        let (_database, quotas) = setup()?;
+        let quota = QuotaOverride {
+            user_id: USER_ID.to_string(),
+            projects: Some(20),
+            storage: Some(1_000),
+            ..Default::default()
+        };
+        quotas.save(&quota)?;
+        assert_eq!(Some(quota.clone()), quotas.read(USER_ID)?);
+
+        let quota = QuotaOverride {
+            projects: None,
+            ..quota
+        };
+        quotas.save(&quota)?;
+        assert_eq!(Some(quota), quotas.read(USER_ID)?);
+
+        quotas.delete(USER_ID)?;
+        assert!(quotas.read(USER_ID)?.is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn count_invocation_ok() -> anyhow::Result<()> {
+        let (_database, quotas) = setup()?;
+        let today = NaiveDate::from_ymd_opt(2023, 9, 20).unwrap();
+        let yesterday = today.pred_opt().unwrap();
+
+        assert_eq!(0, quotas.invocations(USER_ID, today)?);
+        assert!(quotas.count_invocation(USER_ID, yesterday, 2)?);
+        assert!(quotas.count_invocation(USER_ID, today, 2)?);
+        assert!(quotas.count_invocation(USER_ID, today, 2)?);
+
+        assert_eq!(2, quotas.invocations(USER_ID, today)?);
+        assert_eq!(1, quotas.invocations(USER_ID, yesterday)?);
+        Ok(())
+    }
+
+    #[test]
+    fn count_invocation_limit() -> anyhow::Result<()> {
+        let (_database, quotas) = setup()?;
+        let today = NaiveDate::from_ymd_opt(2023, 9, 20).unwrap();
+
+        assert!(!quotas.count_invocation(USER_ID, today, 0)?);
+        assert!(quotas.count_invocation(USER_ID, today, 1)?);
+        assert!(!quotas.count_invocation(USER_ID, today, 1)?);
+
+        assert_eq!(1, quotas.invocations(USER_ID, today)?);
+        Ok(())
+    }
+
+    #[test]
+    fn count_invocation_concurrent() -> anyhow::Result<()> {
+        let (_database, quotas) = setup()?;
+        let today = NaiveDate::from_ymd_opt(2023, 9, 20).unwrap();
+
+        let counted = std::thread::scope(|scope| {
+            let threads: Vec<_> = (0..8)
+                .map(|_| scope.spawn(|| quotas.count_invocation(USER_ID, today, 5)))
+                .collect();
+            threads
+                .into_iter()
+                .map(|thread| thread.join().unwrap())
+                .collect::<anyhow::Result<Vec<bool>>>()
+        })?;
+
+        assert_eq!(5, counted.into_iter().filter(|counted| *counted).count());
+        assert_eq!(5, quotas.invocations(USER_ID, today)?);
+        Ok(())
+    }
+}
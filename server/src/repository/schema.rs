@@ -1,5 +1,49 @@
 // @generated automatically by Diesel CLI.
 
+diesel::table! {
+    api_tokens (user_id, name) {
+        user_id -> Text,
+        name -> Text,
+        token_hash -> Text,
+        scope -> Text,
+        project -> Nullable<Text>,
+        created_at -> Timestamp,
+        last_used_at -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    audit_events (id) {
+        id -> Text,
+        created_at -> Timestamp,
+        user_id -> Text,
+        login -> Text,
+        token -> Nullable<Text>,
+        ip -> Nullable<Text>,
+        action -> Text,
+        project_id -> Nullable<Text>,
+        project -> Nullable<Text>,
+        stage -> Nullable<Text>,
+        target -> Nullable<Text>,
+        old_hash -> Nullable<Text>,
+        new_hash -> Nullable<Text>,
+        details -> Nullable<Text>,
+    }
+}
+
+diesel::table! {
+    handler_versions (id) {
+        id -> Text,
+        handler_id -> Text,
+        number -> Integer,
+        hash -> Text,
+        user_id -> Nullable<Text>,
+        created_at -> Timestamp,
+        blob -> Text,
+        size -> BigInt,
+    }
+}
+
 diesel::table! {
     handlers (id) {
         id -> Text,
@@ -7,6 +51,35 @@ diesel::table! {
         language -> Text,
         hash -> Text,
         project_id -> Text,
+        version -> Integer,
+        canary_version -> Nullable<Integer>,
+        canary_weight -> Integer,
+        stage -> Text,
+        deleted_at -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    invocations (user_id, day) {
+        user_id -> Text,
+        day -> Date,
+        count -> BigInt,
+    }
+}
+
+diesel::table! {
+    org_members (org_id, user_id) {
+        org_id -> Text,
+        user_id -> Text,
+        role -> Text,
+    }
+}
+
+diesel::table! {
+    orgs (id) {
+        id -> Text,
+        name -> Text,
+        created_at -> Timestamp,
     }
 }
 
@@ -15,6 +88,47 @@ diesel::table! {
         id -> Text,
         name -> Text,
         user_id -> Text,
+        deleted_at -> Nullable<Timestamp>,
+        org_id -> Nullable<Text>,
+    }
+}
+
+diesel::table! {
+    sessions (id) {
+        id -> Text,
+        user_id -> Text,
+        refresh_token_hash -> Text,
+        created_at -> Timestamp,
+        expires_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    stage_variables (project_id, stage, name) {
+        project_id -> Text,
+        stage -> Text,
+        name -> Text,
+        value -> Text,
+    }
+}
+
+diesel::table! {
+    trusted_keys (project_id, name) {
+        project_id -> Text,
+        name -> Text,
+        public_key -> Text,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    user_quotas (user_id) {
+        user_id -> Text,
+        projects -> Nullable<BigInt>,
+        handlers_per_project -> Nullable<BigInt>,
+        artifact_size -> Nullable<BigInt>,
+        storage -> Nullable<BigInt>,
+        invocations_per_day -> Nullable<BigInt>,
     }
 }
 
@@ -25,13 +139,48 @@ diesel::table! {
         name -> Nullable<Text>,
         location -> Nullable<Text>,
         company -> Nullable<Text>,
-        github_login -> Text,
-        github_id -> Integer,
-        github_access_token -> Text,
+        login -> Text,
+        provider -> Text,
+        subject -> Text,
+    }
+}
+
+diesel::table! {
+    version_status_codes (version_id, status_code) {
+        version_id -> Text,
+        status_code -> Integer,
+        count -> BigInt,
     }
 }
 
+diesel::joinable!(api_tokens -> users (user_id));
+diesel::joinable!(handler_versions -> handlers (handler_id));
+diesel::joinable!(handler_versions -> users (user_id));
 diesel::joinable!(handlers -> projects (project_id));
+diesel::joinable!(invocations -> users (user_id));
+diesel::joinable!(org_members -> orgs (org_id));
+diesel::joinable!(org_members -> users (user_id));
+diesel::joinable!(projects -> orgs (org_id));
 diesel::joinable!(projects -> users (user_id));
+diesel::joinable!(sessions -> users (user_id));
+diesel::joinable!(stage_variables -> projects (project_id));
+diesel::joinable!(trusted_keys -> projects (project_id));
+diesel::joinable!(user_quotas -> users (user_id));
+diesel::joinable!(version_status_codes -> handler_versions (version_id));
 
-diesel::allow_tables_to_appear_in_same_query!(handlers, projects, users,);
+diesel::allow_tables_to_appear_in_same_query!(
+    api_tokens,
+    audit_events,
+    handler_versions,
+    handlers,
+    invocations,
+    org_members,
+    orgs,
+    projects,
+    sessions,
+    stage_variables,
+    trusted_keys,
+    user_quotas,
+    users,
+    version_status_codes,
+);
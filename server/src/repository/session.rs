@@ -0,0 +1,203 @@
+use super::{
+    audit::{self, AuditEvent},
+    connection::execute_native,
+    schema::sessions::{self, dsl},
+    DatabasePool,
+};
+use chrono::{Duration, NaiveDateTime, Utc};
+use diesel::prelude::*;
+
+/// A login of a user. The access tokens carry the id of their session, the refresh token is
+/// only stored as its hash. Deleting the session revokes both.
+#[derive(Identifiable, Insertable, Queryable, Selectable, Debug, Clone, PartialEq)]
+#[diesel(table_name = crate::repository::schema::sessions)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite, diesel::pg::Pg))]
+pub struct Session {
+    pub id: String,
+    pub user_id: String,
+    pub refresh_token_hash: String,
+    pub created_at: NaiveDateTime,
+    pub expires_at: NaiveDateTime,
+}
+
+impl Session {
+    pub fn new(user_id: String, refresh_token_hash: String, lifetime: Duration) -> Self {
+        let now = Utc::now().naive_utc();
+        Self {
+            id: super::create_id(),
+            user_id,
+            refresh_token_hash,
+            created_at: now,
+            expires_at: now + lifetime,
+        }
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.expires_at <= Utc::now().naive_utc()
+    }
+}
+
+#[cfg_attr(test, faux::create)]
+#[derive(Debug, Clone)]
+pub struct SessionRepository {
+    pool: DatabasePool,
+}
+
+#[cfg_attr(test, faux::methods)]
+impl SessionRepository {
+    pub fn new(pool: DatabasePool) -> Self {
+        Self { pool }
+    }
+
+    /// Starts the session, together with the event of the login if there is one
+    pub fn create(&self, session: &Session, event: Option<&AuditEvent>) -> anyhow::Result<()> {
+        let mut connection = self.pool.get()?;
+
+        connection.transaction(|connection| {
+            execute_native!(
+                connection,
+                diesel::insert_into(sessions::table).values(session)
+            )?;
+            event.map_or(Ok(()), |event| audit::insert(connection, event))
+        })?;
+
+        Ok(())
+    }
+
+    pub fn read(&self, id: &str) -> anyhow::Result<Option<Session>> {
+        let mut connection = self.pool.get()?;
+
+        let session = sessions::table
+            .find(id)
+            .first::<Session>(&mut connection)
+            .optional()?;
+
+        Ok(session)
+    }
+
+    pub fn read_by_refresh_token(
+        &self,
+        refresh_token_hash: &str,
+    ) -> anyhow::Result<Option<Session>> {
+        let mut connection = self.pool.get()?;
+
+        let session = sessions::table
+            .filter(dsl::refresh_token_hash.eq(refresh_token_hash))
+            .first::<Session>(&mut connection)
+            .optional()?;
+
+        Ok(session)
+    }
+
+    /// Replaces the refresh token of the session, unless it was replaced since the session was
+    /// read. Returns whether it was replaced, so a refresh token can only be used once.
+    pub fn rotate(
+        &self,
+        session: &Session,
+        refresh_token_hash: &str,
+        expires_at: NaiveDateTime,
+    ) -> anyhow::Result<bool> {
+        let mut connection = self.pool.get()?;
+
+        let updated = diesel::update(
+            sessions::table
+                .find(&session.id)
+                .filter(dsl::refresh_token_hash.eq(&session.refresh_token_hash)),
+        )
+        .set((
+            dsl::refresh_token_hash.eq(refresh_token_hash),
+            dsl::expires_at.eq(expires_at),
+        ))
+        .execute(&mut connection)?;
+
+        Ok(updated > 0)
+    }
+
+    /// Deletes the session and returns whether it existed. The event of the logout is only
+    /// recorded if it did.
+    pub fn delete(&self, id: &str, event: Option<&AuditEvent>) -> anyhow::Result<bool> {
+        let mut connection = self.pool.get()?;
+
+        let deleted = connection.transaction(|connection| {
+            let deleted = diesel::delete(sessions::table.find(id)).execute(connection)?;
+            if let (true, Some(event)) = (deleted > 0, event) {
+                audit::insert(connection, event)?;
+            }
+            diesel::result::QueryResult::Ok(deleted)
+        })?;
+
+        Ok(deleted > 0)
+    }
+
+    /// Deletes the expired sessions and returns their number
+    pub fn delete_expired(&self) -> anyhow::Result<usize> {
+        let mut connection = self.pool.get()?;
+
+        let deleted =
+            diesel::delete(sessions::table.filter(dsl::expires_at.le(Utc::now().naive_utc())))
+                .execute(&mut connection)?;
+
+        Ok(deleted)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repository::fixtures::{self, TestDatabase};
+
+    const USER_ID: &str = "Eeghaeng8ohxeiqu7Aek";
+
+    fn setup() -> anyhow::Result<(TestDatabase, SessionRepository)> {
+        let database = fixtures::database()?;
+        let pool = database.pool.clone();
+        fixtures::insert_user(&pool, USER_ID)?;
+        Ok((database, SessionRepository::new(pool)))
+    }
+
+    #[test]
+    fn create_ok() -> anyhow::Result<()> {
+        let (_database, sessions) = setup()?;
+        let session = Session::new(USER_ID.to_string(), "HASH".to_string(), Duration::days(1));
+        sessions.create(&session, None)?;
+
+        let read = sessions.read(&session.id)?.unwrap();
+        assert_eq!(USER_ID, read.user_id);
+        assert_eq!("HASH", read.refresh_token_hash);
+        let read = sessions.read_by_refresh_token("HASH")?.unwrap();
+        assert_eq!(session.id, read.id);
+        assert_eq!(None, sessions.read_by_refresh_token("OTHER_HASH")?);
+        Ok(())
+    }
+
+    #[test]
+    fn rotate_once() -> anyhow::Result<()> {
+        let (_database, sessions) = setup()?;
+        let session = Session::new(USER_ID.to_string(), "HASH".to_string(), Duration::days(1));
+        sessions.create(&session, None)?;
+
+        let expires_at = session.expires_at + Duration::hours(1);
+        assert!(sessions.rotate(&session, "NEW_HASH", expires_at)?);
+        assert!(!sessions.rotate(&session, "OTHER_HASH", expires_at)?);
+
+        let rotated = sessions.read_by_refresh_token("NEW_HASH")?.unwrap();
+        assert_eq!(session.id, rotated.id);
+        assert_eq!(None, sessions.read_by_refresh_token("HASH")?);
+        Ok(())
+    }
+
+    #[test]
+    fn delete_expired_ok() -> anyhow::Result<()> {
+        let (_database, sessions) = setup()?;
+        let expired = Session::new(USER_ID.to_string(), "EXPIRED".to_string(), Duration::zero());
+        let valid = Session::new(USER_ID.to_string(), "VALID".to_string(), Duration::days(1));
+        sessions.create(&expired, None)?;
+        sessions.create(&valid, None)?;
+
+        assert_eq!(1, sessions.delete_expired()?);
+        assert!(sessions.read(&valid.id)?.is_some());
+        assert!(sessions.delete(&valid.id, None)?);
+        assert!(!sessions.delete(&valid.id, None)?);
+        Ok(())
+    }
+}
@@ -0,0 +1,124 @@
+use super::{
+    connection::execute_native,
+    schema::version_status_codes::{self, dsl},
+    version::HandlerVersion,
+    DatabasePool,
+};
+use diesel::prelude::*;
+
+#[derive(
+    Identifiable, Insertable, Queryable, Selectable, Associations, Debug, Clone, PartialEq,
+)]
+#[diesel(table_name = crate::repository::schema::version_status_codes)]
+#[diesel(primary_key(version_id, status_code))]
+#[diesel(belongs_to(HandlerVersion, foreign_key = version_id))]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite, diesel::pg::Pg))]
+pub struct StatusCodeCount {
+    pub version_id: String,
+    pub status_code: i32,
+    pub count: i64,
+}
+
+#[cfg_attr(test, faux::create)]
+#[derive(Debug, Clone)]
+pub struct StatusCodeRepository {
+    pool: DatabasePool,
+}
+
+#[cfg_attr(test, faux::methods)]
+impl StatusCodeRepository {
+    pub fn new(pool: DatabasePool) -> Self {
+        Self { pool }
+    }
+
+    pub fn increment(&self, version: &HandlerVersion, status_code: u16) -> anyhow::Result<()> {
+        let mut connection = self.pool.get()?;
+
+        execute_native!(
+            &mut connection,
+            diesel::insert_into(version_status_codes::table)
+                .values((
+                    dsl::version_id.eq(&version.id),
+                    dsl::status_code.eq(i32::from(status_code)),
+                    dsl::count.eq(1),
+                ))
+                .on_conflict((dsl::version_id, dsl::status_code))
+                .do_update()
+                .set(dsl::count.eq(dsl::count + 1))
+        )?;
+
+        Ok(())
+    }
+
+    pub fn belonging_to(&self, version: &HandlerVersion) -> anyhow::Result<Vec<StatusCodeCount>> {
+        let mut connection = self.pool.get()?;
+
+        let status_codes = StatusCodeCount::belonging_to(version)
+            .order(dsl::status_code.asc())
+            .load::<StatusCodeCount>(&mut connection)?;
+
+        Ok(status_codes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repository::{
+        fixtures::{self, TestDatabase},
+        version::HandlerVersionRepository,
+        Repository,
+    };
+    use lazy_static::lazy_static;
+
+    const HANDLER_ID: &str = "Phai5ohquoh7thohmeeT2";
+    const PROJECT_ID: &str = "xiekaiphoe7Luk3zeuNie";
+    const USER_ID: &str = "puphoonoh1bae6Binaixu";
+
+    lazy_static! {
+        static ref VERSION: HandlerVersion = HandlerVersion::new(
+            HANDLER_ID.to_string(),
+            1,
+            "Eiy3aiph".to_string(),
+            "Eiy3aiph".to_string(),
+            0,
+            USER_ID.to_string()
+        );
+    }
+
+    fn setup() -> anyhow::Result<(TestDatabase, StatusCodeRepository)> {
+        let database = fixtures::database()?;
+        let pool = database.pool.clone();
+        fixtures::insert_user(&pool, USER_ID)?;
+        fixtures::insert_project(&pool, PROJECT_ID, USER_ID)?;
+        fixtures::insert_handler(&pool, HANDLER_ID, PROJECT_ID)?;
+        HandlerVersionRepository::new(pool.clone()).create(&VERSION)?;
+        let status_codes = StatusCodeRepository::new(pool);
+        Ok((database, status_codes))
+    }
+
+    #[test]
+    fn increment_ok() -> anyhow::Result<()> {
+        let (_database, status_codes) = setup()?;
+        status_codes.increment(&VERSION, 200)?;
+        status_codes.increment(&VERSION, 200)?;
+        status_codes.increment(&VERSION, 500)?;
+
+        let counts: Vec<(i32, i64)> = status_codes
+            .belonging_to(&VERSION)?
+            .into_iter()
+            .map(|count| (count.status_code, count.count))
+            .collect();
+        assert_eq!(vec![(200, 2), (500, 1)], counts);
+        Ok(())
+    }
+
+    #[test]
+    fn belonging_to_not_found() -> anyhow::Result<()> {
+        let (_database, status_codes) = setup()?;
+        let counts = status_codes.belonging_to(&VERSION)?;
+
+        assert!(counts.is_empty());
+        Ok(())
+    }
+}
@@ -0,0 +1,155 @@
+use super::{
+    audit::{self, AuditEvent},
+    connection::execute_native,
+    project::Project,
+    schema::trusted_keys::{self, dsl},
+    DatabasePool,
+};
+use chrono::{NaiveDateTime, Utc};
+use diesel::prelude::*;
+
+/// A public key whose signatures the project accepts for uploaded handlers
+#[derive(
+    Identifiable, Insertable, Queryable, Selectable, Associations, Debug, Clone, PartialEq,
+)]
+#[diesel(table_name = crate::repository::schema::trusted_keys)]
+#[diesel(primary_key(project_id, name))]
+#[diesel(belongs_to(Project))]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite, diesel::pg::Pg))]
+pub struct TrustedKey {
+    pub project_id: String,
+    pub name: String,
+    pub public_key: String,
+    pub created_at: NaiveDateTime,
+}
+
+impl TrustedKey {
+    pub fn new(project_id: String, name: String, public_key: String) -> Self {
+        Self {
+            project_id,
+            name,
+            public_key,
+            created_at: Utc::now().naive_utc(),
+        }
+    }
+}
+
+#[cfg_attr(test, faux::create)]
+#[derive(Debug, Clone)]
+pub struct TrustedKeyRepository {
+    pool: DatabasePool,
+}
+
+#[cfg_attr(test, faux::methods)]
+impl TrustedKeyRepository {
+    pub fn new(pool: DatabasePool) -> Self {
+        Self { pool }
+    }
+
+    pub fn belonging_to(&self, project: &Project) -> anyhow::Result<Vec<TrustedKey>> {
+        let mut connection = self.pool.get()?;
+
+        let keys = TrustedKey::belonging_to(project)
+            .order(dsl::name.asc())
+            .load::<TrustedKey>(&mut connection)?;
+
+        Ok(keys)
+    }
+
+    pub fn create(&self, key: &TrustedKey, event: &AuditEvent) -> anyhow::Result<()> {
+        let mut connection = self.pool.get()?;
+
+        connection.transaction(|connection| {
+            execute_native!(
+                connection,
+                diesel::insert_into(trusted_keys::table).values(key)
+            )?;
+            audit::insert(connection, event)
+        })?;
+
+        Ok(())
+    }
+
+    /// Deletes the key and returns whether it existed. The event is only recorded if it did.
+    pub fn delete(
+        &self,
+        project: &Project,
+        name: &str,
+        event: &AuditEvent,
+    ) -> anyhow::Result<bool> {
+        let mut connection = self.pool.get()?;
+
+        let deleted = connection.transaction(|connection| {
+            let deleted = diesel::delete(trusted_keys::table.find((&project.id, name)))
+                .execute(connection)?;
+            if deleted > 0 {
+                audit::insert(connection, event)?;
+            }
+            diesel::result::QueryResult::Ok(deleted)
+        })?;
+
+        Ok(deleted > 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repository::fixtures::{self, TestDatabase};
+    use lazy_static::lazy_static;
+
+    const PROJECT_NAME: &str = "PROJECT_NAME";
+    const USER_ID: &str = "Eeghaeng8ohxeiqu7Aek";
+    const PUBLIC_KEY: &str = "d75a980182b10ab7d54bfed3c964073a0ee172f3daa62325af021a68f707511a";
+
+    lazy_static! {
+        static ref PROJECT: Project = Project::new(PROJECT_NAME.to_string(), USER_ID.to_string());
+    }
+
+    fn setup() -> anyhow::Result<(TestDatabase, TrustedKeyRepository)> {
+        let database = fixtures::database()?;
+        let pool = database.pool.clone();
+        fixtures::insert_user(&pool, USER_ID)?;
+        fixtures::insert_project(&pool, &PROJECT.id, USER_ID)?;
+        Ok((database, TrustedKeyRepository::new(pool)))
+    }
+
+    fn key(name: &str) -> TrustedKey {
+        TrustedKey::new(PROJECT.id.clone(), name.to_string(), PUBLIC_KEY.to_string())
+    }
+
+    #[test]
+    fn create_ok() -> anyhow::Result<()> {
+        let (_database, keys) = setup()?;
+        keys.create(&key("laptop"), &fixtures::event())?;
+        keys.create(&key("ci"), &fixtures::event())?;
+
+        let names: Vec<String> = keys
+            .belonging_to(&PROJECT)?
+            .into_iter()
+            .map(|key| key.name)
+            .collect();
+        assert_eq!(vec!["ci".to_string(), "laptop".to_string()], names);
+        Ok(())
+    }
+
+    #[test]
+    fn create_conflict() -> anyhow::Result<()> {
+        let (_database, keys) = setup()?;
+        keys.create(&key("ci"), &fixtures::event())?;
+
+        assert!(keys.create(&key("ci"), &fixtures::event()).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn delete_ok() -> anyhow::Result<()> {
+        let (_database, keys) = setup()?;
+        keys.create(&key("ci"), &fixtures::event())?;
+
+        assert!(keys.delete(&PROJECT, "ci", &fixtures::event())?);
+        assert!(!keys.delete(&PROJECT, "ci", &fixtures::event())?);
+        assert!(keys.belonging_to(&PROJECT)?.is_empty());
+        Ok(())
+    }
+}
@@ -1,46 +1,48 @@
 use super::{
+    connection::execute_native,
     create_id,
-    schema::users::{self},
-    Repository,
-};
-use diesel::{
-    prelude::*,
-    r2d2::{ConnectionManager, Pool},
+    schema::{projects, users},
+    DatabasePool, Repository,
 };
+use crate::identity::Identity;
+use diesel::prelude::*;
 
 #[derive(Identifiable, Insertable, Queryable, Selectable, Debug, Clone, PartialEq, Default)]
 #[diesel(table_name = crate::repository::schema::users)]
-#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite, diesel::pg::Pg))]
 pub struct User {
     pub id: String,
     pub email: String,
     pub name: Option<String>,
     pub location: Option<String>,
     pub company: Option<String>,
-    pub github_login: String,
-    pub github_id: i32,
-    pub github_access_token: String,
+    pub login: String,
+    /// The identity provider the user logs in with
+    pub provider: String,
+    /// The id of the user at the identity provider
+    pub subject: String,
+}
+
+/// Splits a login qualified with the identity provider, like `github:alice` or
+/// `https://sso.example.com:alice`, into the provider and the login
+pub fn split_login(login: &str) -> (Option<&str>, &str) {
+    match login.rsplit_once(':') {
+        Some((provider, login)) => (Some(provider), login),
+        None => (None, login),
+    }
 }
 
 impl User {
-    pub fn new(
-        email: String,
-        name: Option<String>,
-        location: Option<String>,
-        company: Option<String>,
-        github_id: i32,
-        github_login: String,
-        github_access_token: String,
-    ) -> Self {
+    pub fn new(identity: Identity) -> Self {
         Self {
             id: create_id(),
-            email,
-            name,
-            location,
-            company,
-            github_id,
-            github_login,
-            github_access_token,
+            email: identity.email,
+            name: identity.name,
+            location: identity.location,
+            company: identity.company,
+            login: identity.login,
+            provider: identity.provider,
+            subject: identity.subject,
         }
     }
 }
@@ -48,12 +50,12 @@ impl User {
 #[cfg_attr(test, faux::create)]
 #[derive(Debug, Clone)]
 pub struct UserRepository {
-    pool: Pool<ConnectionManager<SqliteConnection>>,
+    pool: DatabasePool,
 }
 
 #[cfg_attr(test, faux::methods)]
 impl Repository<User> for UserRepository {
-    fn new(pool: Pool<ConnectionManager<SqliteConnection>>) -> Self {
+    fn new(pool: DatabasePool) -> Self {
         Self { pool }
     }
 
@@ -70,75 +72,117 @@ impl Repository<User> for UserRepository {
     fn create(&self, user: &User) -> anyhow::Result<()> {
         let mut connection = self.pool.get()?;
 
-        diesel::insert_into(users::table)
-            .values(user)
-            .execute(&mut connection)?;
+        execute_native!(
+            &mut connection,
+            diesel::insert_into(users::table).values(user)
+        )?;
         Ok(())
     }
 
-    fn delete(&self, _id: &str) -> anyhow::Result<()> {
-        unimplemented!()
+    /// Deletes the user, the database cascades to their projects. The versions they deployed
+    /// to projects of others are kept without them. A user who created projects of
+    /// organizations is refused, the organizations would lose them.
+    fn delete(&self, id: &str) -> anyhow::Result<()> {
+        let mut connection = self.pool.get()?;
+
+        connection.transaction(|connection| {
+            let org_projects: i64 = projects::table
+                .filter(projects::dsl::user_id.eq(id))
+                .filter(projects::dsl::org_id.is_not_null())
+                .count()
+                .get_result(connection)?;
+            if org_projects > 0 {
+                anyhow::bail!(
+                    "The user created {} projects of organizations, which must be deleted first",
+                    org_projects
+                );
+            }
+            diesel::delete(users::table.find(id)).execute(connection)?;
+            Ok(())
+        })
     }
 }
 
 #[cfg_attr(test, faux::methods)]
 impl UserRepository {
-    pub fn read_by_gh_id(&self, github_id: i32) -> anyhow::Result<Option<User>> {
+    pub fn read_by_subject(&self, provider: &str, subject: &str) -> anyhow::Result<Option<User>> {
         let mut connection = self.pool.get()?;
 
         let user = users::dsl::users
-            .filter(users::dsl::github_id.eq(github_id))
+            .filter(users::dsl::provider.eq(provider))
+            .filter(users::dsl::subject.eq(subject))
             .first::<User>(&mut connection)
             .optional()?;
 
         Ok(user)
     }
+
+    /// The users with the login, only at the identity provider if one is given. Logins are
+    /// only unique per provider.
+    pub fn read_by_login(&self, provider: Option<&str>, login: &str) -> anyhow::Result<Vec<User>> {
+        let mut connection = self.pool.get()?;
+
+        let mut query = users::dsl::users
+            .filter(users::dsl::login.eq(login))
+            .into_boxed();
+        if let Some(provider) = provider {
+            query = query.filter(users::dsl::provider.eq(provider));
+        }
+        let users = query
+            .order(users::dsl::provider)
+            .load::<User>(&mut connection)?;
+
+        Ok(users)
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::repository::create_pool;
+    use crate::repository::{
+        fixtures::{self, TestDatabase},
+        org::{Org, OrgRepository},
+        project::Project,
+        project::ProjectRepository,
+        version::{HandlerVersion, HandlerVersionRepository},
+    };
 
     use super::*;
-    use diesel_migrations::{FileBasedMigrations, MigrationHarness};
     use lazy_static::lazy_static;
-    use tempfile::{tempdir, TempDir};
-
-    const DATABASE_NAME: &str = "noops_test.sqlite";
 
     const USER_EMAIL: &str = "test@example.com";
     const USER_NAME: &str = "user_name";
     const USER_LOCATION: &str = "Hamburg";
     const USER_COMPANY: &str = "Noops.io";
-    const USER_GH_ACCESS_TOKEN: &str = "Yiu0Hae4ietheereij4OhneuNe6tae0e";
-    const USER_GH_LOGIN: &str = "login_name";
-    const USER_GH_ID: i32 = 42;
+    const USER_LOGIN: &str = "login_name";
+    const USER_SUBJECT: &str = "42";
 
     lazy_static! {
-        static ref USER: User = User::new(
-            USER_EMAIL.to_string(),
-            Some(USER_NAME.to_string()),
-            Some(USER_LOCATION.to_string()),
-            Some(USER_COMPANY.to_string()),
-            USER_GH_ID,
-            USER_GH_LOGIN.to_string(),
-            USER_GH_ACCESS_TOKEN.to_string()
-        );
+        static ref USER: User = User::new(Identity {
+            provider: "github".to_string(),
+            subject: USER_SUBJECT.to_string(),
+            login: USER_LOGIN.to_string(),
+            email: USER_EMAIL.to_string(),
+            name: Some(USER_NAME.to_string()),
+            location: Some(USER_LOCATION.to_string()),
+            company: Some(USER_COMPANY.to_string()),
+        });
+    }
+
+    fn setup() -> anyhow::Result<(TestDatabase, UserRepository)> {
+        let (database, users, _) = setup_with_projects()?;
+        Ok((database, users))
     }
 
-    fn setup() -> anyhow::Result<(TempDir, UserRepository)> {
-        let temp_dir = tempdir()?;
-        let pool = create_pool(&temp_dir.path().join(DATABASE_NAME));
-        let mut connection = pool.get()?;
-        let users = UserRepository::new(pool);
-        let migrations = FileBasedMigrations::find_migrations_directory_in_path("./server")?;
-        connection.run_pending_migrations(migrations).unwrap();
-        Ok((temp_dir, users))
+    fn setup_with_projects() -> anyhow::Result<(TestDatabase, UserRepository, ProjectRepository)> {
+        let database = fixtures::database()?;
+        let users = UserRepository::new(database.pool.clone());
+        let projects = ProjectRepository::new(database.pool.clone());
+        Ok((database, users, projects))
     }
 
     #[test]
     fn create_ok() -> anyhow::Result<()> {
-        let (_temp_dir, users) = setup()?;
+        let (_database, users) = setup()?;
         let result = users.create(&USER);
 
         assert!(result.is_ok());
@@ -147,7 +191,7 @@ mod tests {
 
     #[test]
     fn create_id_conflict() -> anyhow::Result<()> {
-        let (_temp_dir, users) = setup()?;
+        let (_database, users) = setup()?;
         users.create(&USER)?;
         let result = users.create(&USER);
         assert!(result.is_err());
@@ -155,17 +199,17 @@ mod tests {
     }
 
     #[test]
-    fn create_gh_id_conflict() -> anyhow::Result<()> {
-        let (_temp_dir, users) = setup()?;
-        let user = User::new(
-            USER_EMAIL.to_string(),
-            Some(USER_NAME.to_string()),
-            Some(USER_LOCATION.to_string()),
-            Some(USER_COMPANY.to_string()),
-            USER_GH_ID,
-            USER_GH_LOGIN.to_string(),
-            USER_GH_ACCESS_TOKEN.to_string(),
-        );
+    fn create_subject_conflict() -> anyhow::Result<()> {
+        let (_database, users) = setup()?;
+        let user = User::new(Identity {
+            provider: "github".to_string(),
+            subject: USER_SUBJECT.to_string(),
+            login: USER_LOGIN.to_string(),
+            email: USER_EMAIL.to_string(),
+            name: Some(USER_NAME.to_string()),
+            location: Some(USER_LOCATION.to_string()),
+            company: Some(USER_COMPANY.to_string()),
+        });
         users.create(&user)?;
         let result = users.create(&USER);
         assert!(result.is_err());
@@ -174,7 +218,7 @@ mod tests {
 
     #[test]
     fn read_by_id_ok() -> anyhow::Result<()> {
-        let (_temp_dir, users) = setup()?;
+        let (_database, users) = setup()?;
         users.create(&USER)?;
         let result = users.read(&USER.id)?;
 
@@ -184,19 +228,69 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn read_by_login_ok() -> anyhow::Result<()> {
+        let (_database, users) = setup()?;
+        users.create(&USER)?;
+
+        assert_eq!(vec![USER.clone()], users.read_by_login(None, USER_LOGIN)?);
+        assert_eq!(
+            vec![USER.clone()],
+            users.read_by_login(Some("github"), USER_LOGIN)?
+        );
+        assert!(users.read_by_login(None, "unknown")?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn read_by_login_of_several_providers() -> anyhow::Result<()> {
+        let (_database, users) = setup()?;
+        users.create(&USER)?;
+        let other = User::new(Identity {
+            provider: "https://sso.example.com".to_string(),
+            subject: "sso-subject".to_string(),
+            login: USER_LOGIN.to_string(),
+            email: "other@example.com".to_string(),
+            name: None,
+            location: None,
+            company: None,
+        });
+        users.create(&other)?;
+
+        assert_eq!(
+            vec![USER.clone(), other.clone()],
+            users.read_by_login(None, USER_LOGIN)?
+        );
+        assert_eq!(
+            vec![other],
+            users.read_by_login(Some("https://sso.example.com"), USER_LOGIN)?
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn split_login_ok() {
+        assert_eq!((None, "alice"), split_login("alice"));
+        assert_eq!((Some("github"), "alice"), split_login("github:alice"));
+        assert_eq!(
+            (Some("https://sso.example.com:8443"), "alice"),
+            split_login("https://sso.example.com:8443:alice")
+        );
+    }
+
     #[test]
     fn read_by_id_not_found() -> anyhow::Result<()> {
-        let (_temp_dir, database) = setup()?;
+        let (_database, database) = setup()?;
         let result = database.read(&USER.id)?;
         assert!(result.is_none());
         Ok(())
     }
 
     #[test]
-    fn read_by_gh_id_ok() -> anyhow::Result<()> {
-        let (_temp_dir, users) = setup()?;
+    fn read_by_subject_ok() -> anyhow::Result<()> {
+        let (_database, users) = setup()?;
         users.create(&USER)?;
-        let result = users.read_by_gh_id(USER.github_id)?;
+        let result = users.read_by_subject(&USER.provider, &USER.subject)?;
 
         assert!(result.is_some());
         let user = result.unwrap();
@@ -205,10 +299,76 @@ mod tests {
     }
 
     #[test]
-    fn read_by_gh_id_not_found() -> anyhow::Result<()> {
-        let (_temp_dir, users) = setup()?;
-        let result = users.read_by_gh_id(USER_GH_ID)?;
+    fn read_by_subject_not_found() -> anyhow::Result<()> {
+        let (_database, users) = setup()?;
+        users.create(&USER)?;
+        let result = users.read_by_subject("https://sso.example.com", USER_SUBJECT)?;
         assert!(result.is_none());
         Ok(())
     }
+
+    #[test]
+    fn delete_cascades_to_projects() -> anyhow::Result<()> {
+        let (_database, users, projects) = setup_with_projects()?;
+        users.create(&USER)?;
+        let project = Project::new("project".to_string(), USER.id.clone());
+        projects.create(&project)?;
+
+        users.delete(&USER.id)?;
+
+        assert!(users.read(&USER.id)?.is_none());
+        assert!(projects.read(&project.id)?.is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn delete_keeps_versions_of_other_projects() -> anyhow::Result<()> {
+        let (database, users) = setup()?;
+        users.create(&USER)?;
+        fixtures::insert_user(&database.pool, "owner")?;
+        fixtures::insert_project(&database.pool, "project", "owner")?;
+        fixtures::insert_handler(&database.pool, "handler", "project")?;
+        let versions = HandlerVersionRepository::new(database.pool.clone());
+        let version = HandlerVersion::new(
+            "handler".to_string(),
+            1,
+            "hash".to_string(),
+            "blob".to_string(),
+            0,
+            USER.id.clone(),
+        );
+        versions.create(&version)?;
+
+        users.delete(&USER.id)?;
+
+        assert!(users.read(&USER.id)?.is_none());
+        let kept = versions.read(&version.id)?;
+        assert_eq!(None, kept.and_then(|version| version.user_id));
+        assert!(users.read("owner")?.is_some());
+        Ok(())
+    }
+
+    #[test]
+    fn delete_refused_with_org_projects() -> anyhow::Result<()> {
+        let (database, users, projects) = setup_with_projects()?;
+        users.create(&USER)?;
+        let org = Org::new("acme".to_string());
+        OrgRepository::new(database.pool.clone()).create_with_owner(
+            &org,
+            &USER,
+            &fixtures::event(),
+        )?;
+        let project = Project {
+            org_id: Some(org.id.clone()),
+            ..Project::new("shop".to_string(), USER.id.clone())
+        };
+        projects.create(&project)?;
+
+        let result = users.delete(&USER.id);
+
+        assert!(result.is_err());
+        assert!(users.read(&USER.id)?.is_some());
+        assert!(projects.read(&project.id)?.is_some());
+        Ok(())
+    }
 }
@@ -0,0 +1,213 @@
+use super::{
+    audit::{self, AuditEvent},
+    connection::execute_native,
+    project::Project,
+    schema::stage_variables::{self, dsl},
+    DatabasePool,
+};
+use diesel::prelude::*;
+
+/// An environment variable passed to the handlers of a stage
+#[derive(
+    Identifiable, Insertable, Queryable, Selectable, Associations, Debug, Clone, PartialEq,
+)]
+#[diesel(table_name = crate::repository::schema::stage_variables)]
+#[diesel(primary_key(project_id, stage, name))]
+#[diesel(belongs_to(Project))]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite, diesel::pg::Pg))]
+pub struct StageVariable {
+    pub project_id: String,
+    pub stage: String,
+    pub name: String,
+    pub value: String,
+}
+
+impl StageVariable {
+    pub fn new(project_id: String, stage: String, name: String, value: String) -> Self {
+        Self {
+            project_id,
+            stage,
+            name,
+            value,
+        }
+    }
+}
+
+#[cfg_attr(test, faux::create)]
+#[derive(Debug, Clone)]
+pub struct StageVariableRepository {
+    pool: DatabasePool,
+}
+
+#[cfg_attr(test, faux::methods)]
+impl StageVariableRepository {
+    pub fn new(pool: DatabasePool) -> Self {
+        Self { pool }
+    }
+
+    pub fn belonging_to_stage(
+        &self,
+        project_id: &str,
+        stage: &str,
+    ) -> anyhow::Result<Vec<StageVariable>> {
+        let mut connection = self.pool.get()?;
+
+        let variables = stage_variables::table
+            .filter(dsl::project_id.eq(project_id))
+            .filter(dsl::stage.eq(stage))
+            .order(dsl::name.asc())
+            .load::<StageVariable>(&mut connection)?;
+
+        Ok(variables)
+    }
+
+    /// The variables of all stages of the project
+    pub fn belonging_to(&self, project: &Project) -> anyhow::Result<Vec<StageVariable>> {
+        let mut connection = self.pool.get()?;
+
+        let variables = StageVariable::belonging_to(project)
+            .order((dsl::stage.asc(), dsl::name.asc()))
+            .load::<StageVariable>(&mut connection)?;
+
+        Ok(variables)
+    }
+
+    /// Replaces all variables of a stage
+    pub fn replace(
+        &self,
+        project: &Project,
+        stage: &str,
+        variables: &[StageVariable],
+        event: &AuditEvent,
+    ) -> anyhow::Result<()> {
+        let mut connection = self.pool.get()?;
+
+        connection.transaction(|connection| {
+            diesel::delete(StageVariable::belonging_to(project).filter(dsl::stage.eq(stage)))
+                .execute(connection)?;
+            execute_native!(
+                connection,
+                diesel::insert_into(stage_variables::table).values(variables)
+            )?;
+            audit::insert(connection, event)
+        })?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repository::{
+        fixtures::{self, TestDatabase},
+        project::ProjectRepository,
+        Repository,
+    };
+    use lazy_static::lazy_static;
+
+    const PROJECT_NAME: &str = "PROJECT_NAME";
+    const USER_ID: &str = "puphoonoh1bae6Binaixu";
+    const STAGE: &str = "staging";
+
+    lazy_static! {
+        static ref PROJECT: Project = Project::new(PROJECT_NAME.to_string(), USER_ID.to_string());
+    }
+
+    fn setup() -> anyhow::Result<(TestDatabase, StageVariableRepository)> {
+        let (database, variables, _) = setup_with_projects()?;
+        Ok((database, variables))
+    }
+
+    fn setup_with_projects(
+    ) -> anyhow::Result<(TestDatabase, StageVariableRepository, ProjectRepository)> {
+        let database = fixtures::database()?;
+        let pool = database.pool.clone();
+        fixtures::insert_user(&pool, USER_ID)?;
+        fixtures::insert_project(&pool, &PROJECT.id, USER_ID)?;
+        let variables = StageVariableRepository::new(pool.clone());
+        let projects = ProjectRepository::new(pool);
+        Ok((database, variables, projects))
+    }
+
+    fn variable(stage: &str, name: &str, value: &str) -> StageVariable {
+        StageVariable::new(
+            PROJECT.id.clone(),
+            stage.to_string(),
+            name.to_string(),
+            value.to_string(),
+        )
+    }
+
+    #[test]
+    fn replace_ok() -> anyhow::Result<()> {
+        let (_database, variables) = setup()?;
+        variables.replace(
+            &PROJECT,
+            STAGE,
+            &[variable(STAGE, "OLD", "1")],
+            &fixtures::event(),
+        )?;
+        let expected = vec![variable(STAGE, "A", "1"), variable(STAGE, "B", "2")];
+        variables.replace(&PROJECT, STAGE, &expected, &fixtures::event())?;
+
+        let result = variables.belonging_to_stage(&PROJECT.id, STAGE)?;
+        assert_eq!(expected, result);
+        Ok(())
+    }
+
+    #[test]
+    fn replace_keeps_other_stages() -> anyhow::Result<()> {
+        let (_database, variables) = setup()?;
+        variables.replace(
+            &PROJECT,
+            "prod",
+            &[variable("prod", "A", "1")],
+            &fixtures::event(),
+        )?;
+        variables.replace(&PROJECT, STAGE, &[], &fixtures::event())?;
+
+        let result = variables.belonging_to_stage(&PROJECT.id, "prod")?;
+        assert_eq!(vec![variable("prod", "A", "1")], result);
+        Ok(())
+    }
+
+    #[test]
+    fn belonging_to_ok() -> anyhow::Result<()> {
+        let (_database, variables) = setup()?;
+        variables.replace(
+            &PROJECT,
+            STAGE,
+            &[variable(STAGE, "B", "2")],
+            &fixtures::event(),
+        )?;
+        variables.replace(
+            &PROJECT,
+            "prod",
+            &[variable("prod", "A", "1")],
+            &fixtures::event(),
+        )?;
+
+        let result = variables.belonging_to(&PROJECT)?;
+        assert_eq!(
+            vec![variable("prod", "A", "1"), variable(STAGE, "B", "2")],
+            result
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn delete_project_cascades_to_variables() -> anyhow::Result<()> {
+        let (_database, variables, projects) = setup_with_projects()?;
+        variables.replace(
+            &PROJECT,
+            STAGE,
+            &[variable(STAGE, "A", "1")],
+            &fixtures::event(),
+        )?;
+        projects.delete(&PROJECT.id)?;
+
+        assert!(variables.belonging_to_stage(&PROJECT.id, STAGE)?.is_empty());
+        Ok(())
+    }
+}
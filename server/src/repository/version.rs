@@ -0,0 +1,513 @@
+use super::{
+    connection::execute_native,
+    create_id,
+    handler::Handler,
+    project::Project,
+    schema::{
+        handler_versions::{self, dsl},
+        handlers, projects, users,
+    },
+    user::User,
+    DatabasePool, Repository,
+};
+use chrono::{NaiveDateTime, Utc};
+use diesel::prelude::*;
+use std::collections::{HashMap, HashSet};
+
+#[derive(
+    Identifiable, Insertable, Queryable, Selectable, Associations, Debug, Clone, PartialEq,
+)]
+#[diesel(table_name = crate::repository::schema::handler_versions)]
+#[diesel(belongs_to(Handler))]
+#[diesel(belongs_to(User))]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite, diesel::pg::Pg))]
+pub struct HandlerVersion {
+    pub id: String,
+    pub handler_id: String,
+    pub number: i32,
+    pub hash: String,
+    /// The user who deployed the version, none once they are deleted
+    pub user_id: Option<String>,
+    pub created_at: NaiveDateTime,
+    /// The digest of the component in the wasmstore
+    pub blob: String,
+    /// The size of the component in bytes
+    pub size: i64,
+}
+
+impl HandlerVersion {
+    pub fn new(
+        handler_id: String,
+        number: i32,
+        hash: String,
+        blob: String,
+        size: i64,
+        user_id: String,
+    ) -> Self {
+        Self {
+            id: create_id(),
+            handler_id,
+            number,
+            hash,
+            user_id: Some(user_id),
+            created_at: Utc::now().naive_utc(),
+            blob,
+            size,
+        }
+    }
+}
+
+#[cfg_attr(test, faux::create)]
+#[derive(Debug, Clone)]
+pub struct HandlerVersionRepository {
+    pool: DatabasePool,
+}
+
+#[cfg_attr(test, faux::methods)]
+impl Repository<HandlerVersion> for HandlerVersionRepository {
+    fn new(pool: DatabasePool) -> Self {
+        Self { pool }
+    }
+
+    fn create(&self, version: &HandlerVersion) -> anyhow::Result<()> {
+        let mut connection = self.pool.get()?;
+
+        execute_native!(
+            &mut connection,
+            diesel::insert_into(handler_versions::table).values(version)
+        )?;
+
+        Ok(())
+    }
+
+    fn read(&self, id: &str) -> anyhow::Result<Option<HandlerVersion>> {
+        let mut connection = self.pool.get()?;
+
+        let version = handler_versions::table
+            .find(id)
+            .first::<HandlerVersion>(&mut connection)
+            .optional()?;
+
+        Ok(version)
+    }
+
+    fn delete(&self, id: &str) -> anyhow::Result<()> {
+        let mut connection = self.pool.get()?;
+
+        diesel::delete(handler_versions::table.find(id)).execute(&mut connection)?;
+
+        Ok(())
+    }
+}
+
+#[cfg_attr(test, faux::methods)]
+impl HandlerVersionRepository {
+    pub fn all(&self) -> anyhow::Result<Vec<HandlerVersion>> {
+        let mut connection = self.pool.get()?;
+
+        let versions = handler_versions::table
+            .order((dsl::handler_id, dsl::number.desc()))
+            .load::<HandlerVersion>(&mut connection)?;
+
+        Ok(versions)
+    }
+
+    /// The versions of the handler with the login of the user who deployed them, none once
+    /// they are deleted
+    pub fn belonging_to_with_login(
+        &self,
+        handler: &Handler,
+    ) -> anyhow::Result<Vec<(HandlerVersion, Option<String>)>> {
+        let mut connection = self.pool.get()?;
+
+        let versions = HandlerVersion::belonging_to(handler)
+            .left_join(users::table)
+            .order(dsl::number.desc())
+            .select((HandlerVersion::as_select(), users::login.nullable()))
+            .load::<(HandlerVersion, Option<String>)>(&mut connection)?;
+
+        Ok(versions)
+    }
+
+    pub fn belonging_to_by_number(
+        &self,
+        handler: &Handler,
+        number: i32,
+    ) -> anyhow::Result<Option<HandlerVersion>> {
+        let mut connection = self.pool.get()?;
+
+        let version = HandlerVersion::belonging_to(handler)
+            .filter(dsl::number.eq(number))
+            .first::<HandlerVersion>(&mut connection)
+            .optional()?;
+
+        Ok(version)
+    }
+
+    pub fn latest(&self, handler: &Handler) -> anyhow::Result<Option<HandlerVersion>> {
+        let mut connection = self.pool.get()?;
+
+        let version = HandlerVersion::belonging_to(handler)
+            .order(dsl::number.desc())
+            .first::<HandlerVersion>(&mut connection)
+            .optional()?;
+
+        Ok(version)
+    }
+
+    /// All blobs referenced by any version
+    pub fn blobs(&self) -> anyhow::Result<HashSet<String>> {
+        let mut connection = self.pool.get()?;
+
+        let blobs = handler_versions::table
+            .select(dsl::blob)
+            .distinct()
+            .load::<String>(&mut connection)?;
+
+        Ok(blobs.into_iter().collect())
+    }
+
+    /// The blobs referenced by the versions of the handlers of the project
+    pub fn blobs_belonging_to(&self, project: &Project) -> anyhow::Result<HashSet<String>> {
+        let mut connection = self.pool.get()?;
+
+        let blobs = handler_versions::table
+            .inner_join(handlers::table)
+            .filter(handlers::project_id.eq(&project.id))
+            .select(dsl::blob)
+            .distinct()
+            .load::<String>(&mut connection)?;
+
+        Ok(blobs.into_iter().collect())
+    }
+
+    /// The sizes of the blobs referenced by the versions of all projects of the user, including
+    /// the projects and handlers in the trash
+    pub fn sizes_belonging_to(&self, user_id: &str) -> anyhow::Result<HashMap<String, i64>> {
+        let mut connection = self.pool.get()?;
+
+        let sizes = handler_versions::table
+            .inner_join(handlers::table.inner_join(projects::table))
+            .filter(projects::user_id.eq(user_id))
+            .select((dsl::blob, dsl::size))
+            .distinct()
+            .load::<(String, i64)>(&mut connection)?;
+
+        Ok(sizes.into_iter().collect())
+    }
+
+    /// The blobs of the versions deployed before their size was recorded
+    pub fn unmeasured_blobs(&self) -> anyhow::Result<Vec<String>> {
+        let mut connection = self.pool.get()?;
+
+        let blobs = handler_versions::table
+            .filter(dsl::size.eq(0))
+            .select(dsl::blob)
+            .distinct()
+            .load::<String>(&mut connection)?;
+
+        Ok(blobs)
+    }
+
+    /// Records the size of the blob on all versions referencing it
+    pub fn set_size(&self, blob: &str, size: i64) -> anyhow::Result<usize> {
+        let mut connection = self.pool.get()?;
+
+        let updated = diesel::update(handler_versions::table.filter(dsl::blob.eq(blob)))
+            .set(dsl::size.eq(size))
+            .execute(&mut connection)?;
+
+        Ok(updated)
+    }
+
+    /// Points all versions referencing the blob `old` to the blob `new` and returns their number
+    pub fn replace_blob(&self, old: &str, new: &str) -> anyhow::Result<usize> {
+        let mut connection = self.pool.get()?;
+
+        let replaced = diesel::update(handler_versions::table.filter(dsl::blob.eq(old)))
+            .set(dsl::blob.eq(new))
+            .execute(&mut connection)?;
+
+        Ok(replaced)
+    }
+
+    /// The newest version deployed before the currently active one
+    pub fn previous(&self, handler: &Handler) -> anyhow::Result<Option<HandlerVersion>> {
+        let mut connection = self.pool.get()?;
+
+        let version = HandlerVersion::belonging_to(handler)
+            .filter(dsl::number.lt(handler.version))
+            .order(dsl::number.desc())
+            .first::<HandlerVersion>(&mut connection)
+            .optional()?;
+
+        Ok(version)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::identity::Identity;
+    use crate::repository::{
+        fixtures::{self, TestDatabase},
+        handler::HandlerRepository,
+        user::UserRepository,
+    };
+    use common::dtos::Language;
+    use lazy_static::lazy_static;
+
+    const HANDLER_NAME: &str = "HANDLER_NAME";
+    const PROJECT_ID: &str = "xiekaiphoe7Luk3zeuNie";
+    const BLOB: &str = "2c26b46b68ffc68ff99b453c1d30413413422d706483bfa0f98a5e886266e7ae";
+
+    const USER_EMAIL: &str = "test@example.com";
+    const USER_NAME: &str = "user_name";
+    const USER_LOCATION: &str = "Hamburg";
+    const USER_COMPANY: &str = "Noops.io";
+    const USER_LOGIN: &str = "login_name";
+    const USER_SUBJECT: &str = "42";
+
+    lazy_static! {
+        static ref USER: User = User::new(Identity {
+            provider: "github".to_string(),
+            subject: USER_SUBJECT.to_string(),
+            login: USER_LOGIN.to_string(),
+            email: USER_EMAIL.to_string(),
+            name: Some(USER_NAME.to_string()),
+            location: Some(USER_LOCATION.to_string()),
+            company: Some(USER_COMPANY.to_string()),
+        });
+        static ref HANDLER: Handler = Handler::new(
+            HANDLER_NAME.to_string(),
+            Language::Rust,
+            "ooKae9ah".to_string(),
+            PROJECT_ID.to_string(),
+            "prod".to_string()
+        );
+    }
+
+    fn setup() -> anyhow::Result<(TestDatabase, HandlerVersionRepository)> {
+        let database = fixtures::database()?;
+        let pool = database.pool.clone();
+        UserRepository::new(pool.clone()).create(&USER)?;
+        fixtures::insert_project(&pool, PROJECT_ID, &USER.id)?;
+        HandlerRepository::new(pool.clone()).create(&HANDLER)?;
+        let versions = HandlerVersionRepository::new(pool);
+        Ok((database, versions))
+    }
+
+    fn create_versions(
+        versions: &HandlerVersionRepository,
+        count: i32,
+    ) -> anyhow::Result<Vec<HandlerVersion>> {
+        let mut created = Vec::new();
+        for number in 1..=count {
+            let version = HandlerVersion::new(
+                HANDLER.id.clone(),
+                number,
+                format!("hash_{}", number),
+                format!("blob_{}", number % 2),
+                0,
+                USER.id.clone(),
+            );
+            versions.create(&version)?;
+            created.push(version);
+        }
+        Ok(created)
+    }
+
+    #[test]
+    fn create_ok() -> anyhow::Result<()> {
+        let (_database, versions) = setup()?;
+        let version = HandlerVersion::new(
+            HANDLER.id.clone(),
+            1,
+            HANDLER.hash.clone(),
+            BLOB.to_string(),
+            0,
+            USER.id.clone(),
+        );
+        let result = versions.create(&version);
+        assert!(result.is_ok());
+        Ok(())
+    }
+
+    #[test]
+    fn create_number_conflict() -> anyhow::Result<()> {
+        let (_database, versions) = setup()?;
+        create_versions(&versions, 1)?;
+        let version = HandlerVersion::new(
+            HANDLER.id.clone(),
+            1,
+            HANDLER.hash.clone(),
+            BLOB.to_string(),
+            0,
+            USER.id.clone(),
+        );
+        let result = versions.create(&version);
+        assert!(result.is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn read_ok() -> anyhow::Result<()> {
+        let (_database, versions) = setup()?;
+        let created = create_versions(&versions, 1)?;
+        let version = versions.read(&created[0].id)?;
+
+        assert!(version.is_some());
+        assert_eq!(created[0].id, version.unwrap().id);
+        Ok(())
+    }
+
+    #[test]
+    fn delete_ok() -> anyhow::Result<()> {
+        let (_database, versions) = setup()?;
+        let created = create_versions(&versions, 1)?;
+        versions.delete(&created[0].id)?;
+        let version = versions.read(&created[0].id)?;
+
+        assert!(version.is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn belonging_to_with_login_ok() -> anyhow::Result<()> {
+        let (_database, versions) = setup()?;
+        create_versions(&versions, 3)?;
+        let result = versions.belonging_to_with_login(&HANDLER)?;
+
+        let numbers: Vec<i32> = result.iter().map(|(version, _)| version.number).collect();
+        assert_eq!(vec![3, 2, 1], numbers);
+        assert!(result
+            .iter()
+            .all(|(_, login)| login.as_ref() == Some(&USER.login)));
+        Ok(())
+    }
+
+    #[test]
+    fn belonging_to_by_number_ok() -> anyhow::Result<()> {
+        let (_database, versions) = setup()?;
+        create_versions(&versions, 2)?;
+        let version = versions.belonging_to_by_number(&HANDLER, 2)?;
+
+        assert!(version.is_some());
+        assert_eq!("hash_2", version.unwrap().hash);
+        Ok(())
+    }
+
+    #[test]
+    fn belonging_to_by_number_not_found() -> anyhow::Result<()> {
+        let (_database, versions) = setup()?;
+        create_versions(&versions, 2)?;
+        let version = versions.belonging_to_by_number(&HANDLER, 3)?;
+
+        assert!(version.is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn latest_ok() -> anyhow::Result<()> {
+        let (_database, versions) = setup()?;
+        create_versions(&versions, 3)?;
+        let version = versions.latest(&HANDLER)?;
+
+        assert_eq!(3, version.unwrap().number);
+        Ok(())
+    }
+
+    #[test]
+    fn blobs_ok() -> anyhow::Result<()> {
+        let (_database, versions) = setup()?;
+        create_versions(&versions, 3)?;
+        let blobs = versions.blobs()?;
+
+        assert_eq!(
+            HashSet::from(["blob_0".to_string(), "blob_1".to_string()]),
+            blobs
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn blobs_belonging_to_ok() -> anyhow::Result<()> {
+        let (_database, versions) = setup()?;
+        create_versions(&versions, 3)?;
+        let project = Project {
+            id: PROJECT_ID.to_string(),
+            name: PROJECT_ID.to_string(),
+            user_id: USER.id.clone(),
+            deleted_at: None,
+            org_id: None,
+        };
+        let other = Project::new("OTHER".to_string(), USER.id.clone());
+
+        assert_eq!(
+            HashSet::from(["blob_0".to_string(), "blob_1".to_string()]),
+            versions.blobs_belonging_to(&project)?
+        );
+        assert!(versions.blobs_belonging_to(&other)?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn sizes_ok() -> anyhow::Result<()> {
+        let (_database, versions) = setup()?;
+        create_versions(&versions, 3)?;
+        assert_eq!(2, versions.set_size("blob_1", 10)?);
+
+        assert_eq!(vec!["blob_0".to_string()], versions.unmeasured_blobs()?);
+        assert_eq!(
+            HashMap::from([("blob_0".to_string(), 0), ("blob_1".to_string(), 10)]),
+            versions.sizes_belonging_to(&USER.id)?
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn replace_blob_ok() -> anyhow::Result<()> {
+        let (_database, versions) = setup()?;
+        create_versions(&versions, 3)?;
+        let replaced = versions.replace_blob("blob_1", BLOB)?;
+
+        assert_eq!(2, replaced);
+        assert_eq!(
+            HashSet::from(["blob_0".to_string(), BLOB.to_string()]),
+            versions.blobs()?
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn all_ok() -> anyhow::Result<()> {
+        let (_database, versions) = setup()?;
+        create_versions(&versions, 3)?;
+
+        let numbers: Vec<i32> = versions.all()?.iter().map(|v| v.number).collect();
+        assert_eq!(vec![3, 2, 1], numbers);
+        Ok(())
+    }
+
+    #[test]
+    fn previous_ok() -> anyhow::Result<()> {
+        let (_database, versions) = setup()?;
+        create_versions(&versions, 3)?;
+        let mut handler = HANDLER.clone();
+        handler.version = 3;
+        let version = versions.previous(&handler)?;
+
+        assert_eq!(2, version.unwrap().number);
+        Ok(())
+    }
+
+    #[test]
+    fn previous_not_found() -> anyhow::Result<()> {
+        let (_database, versions) = setup()?;
+        create_versions(&versions, 1)?;
+        let version = versions.previous(&HANDLER)?;
+
+        assert!(version.is_none());
+        Ok(())
+    }
+}
@@ -0,0 +1,1058 @@
+use super::{
+    audit::{Actor, AuditService},
+    handler::{validate_canary_weight, validate_handler_name, validate_stage, verify_signature},
+    org::{authorized_project, OrgService},
+    project::validate_project_name,
+    quota::QuotaService,
+    trash::TrashService,
+};
+use crate::{
+    errors::Error::{self, InvalidArchive, ProjectExists},
+    executor,
+    repository::{
+        audit::AuditEvent,
+        create_id,
+        handler::{Handler, HandlerRepository},
+        project::ProjectRepository,
+        trusted_key::{TrustedKey, TrustedKeyRepository},
+        variable::{StageVariable, StageVariableRepository},
+        version::{HandlerVersion, HandlerVersionRepository},
+    },
+    wasmstore::WasmStore,
+};
+use anyhow::Context;
+use chrono::NaiveDateTime;
+use common::{
+    dtos::{AuditAction, GetHandlerDTO, GetProjectDTO, Language, Role},
+    hash, signature,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeSet, HashMap},
+    io::Read,
+};
+
+/// The version of the archive layout, archives of another version are rejected
+const ARCHIVE_FORMAT: u32 = 1;
+const PROJECT_PATH: &str = "project.json";
+const COMPONENTS_DIRECTORY: &str = "components";
+const WASM_EXTENSION: &str = "wasm";
+/// Caps the unpacked size of an archive, so a small upload can't expand without bounds
+const MAX_UNPACKED_SIZE_IN_BYTES: u64 = 1_000_000_000;
+
+/// The records of a project in an archive. Ids and users are left out, they are assigned anew
+/// by the server importing the archive.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+struct ArchivedProject {
+    format: u32,
+    name: String,
+    handlers: Vec<ArchivedHandler>,
+    variables: Vec<ArchivedVariable>,
+    trusted_keys: Vec<ArchivedKey>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+struct ArchivedHandler {
+    name: String,
+    language: Language,
+    stage: String,
+    version: i32,
+    canary_version: Option<i32>,
+    canary_weight: i32,
+    versions: Vec<ArchivedVersion>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+struct ArchivedVersion {
+    number: i32,
+    hash: String,
+    /// The digest of the component in the `components` directory of the archive
+    blob: String,
+    created_at: NaiveDateTime,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+struct ArchivedVariable {
+    stage: String,
+    name: String,
+    value: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+struct ArchivedKey {
+    name: String,
+    public_key: String,
+    created_at: NaiveDateTime,
+}
+
+/// Moves projects between servers. An archive is a zstd compressed tar file with the records of
+/// the project in `project.json` and the components of all its handler versions in
+/// `components/<digest>.wasm`.
+#[derive(Debug, Clone)]
+pub struct ArchiveService {
+    projects: ProjectRepository,
+    handlers: HandlerRepository,
+    versions: HandlerVersionRepository,
+    variables: StageVariableRepository,
+    trusted_keys: TrustedKeyRepository,
+    wasmstore: WasmStore,
+    trash: TrashService,
+    quotas: QuotaService,
+    orgs: OrgService,
+    audit: AuditService,
+}
+
+impl ArchiveService {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        projects: ProjectRepository,
+        handlers: HandlerRepository,
+        versions: HandlerVersionRepository,
+        variables: StageVariableRepository,
+        trusted_keys: TrustedKeyRepository,
+        wasmstore: WasmStore,
+        trash: TrashService,
+        quotas: QuotaService,
+        orgs: OrgService,
+        audit: AuditService,
+    ) -> Self {
+        Self {
+            projects,
+            handlers,
+            versions,
+            variables,
+            trusted_keys,
+            wasmstore,
+            trash,
+            quotas,
+            orgs,
+            audit,
+        }
+    }
+
+    /// Bundles the project with its handlers, all their versions, the variables of all stages
+    /// and the trusted keys into an archive
+    pub fn export(&self, user: &Actor, project_name: &str) -> Result<Vec<u8>, Error> {
+        let project = authorized_project(&self.projects, user, project_name, Role::Owner)?;
+
+        let mut blobs = BTreeSet::new();
+        let mut handlers = Vec::new();
+        for handler in self.handlers.belonging_to(&project)? {
+            let mut versions: Vec<ArchivedVersion> = self
+                .versions
+                .belonging_to_with_login(&handler)?
+                .into_iter()
+                .map(|(version, _)| ArchivedVersion {
+                    number: version.number,
+                    hash: version.hash,
+                    blob: version.blob,
+                    created_at: version.created_at,
+                })
+                .collect();
+            versions.sort_by_key(|version| version.number);
+            blobs.extend(versions.iter().map(|version| version.blob.clone()));
+
+            handlers.push(ArchivedHandler {
+                name: handler.name,
+                language: handler.language,
+                stage: handler.stage,
+                version: handler.version,
+                canary_version: handler.canary_version,
+                canary_weight: handler.canary_weight,
+                versions,
+            });
+        }
+
+        let archived = ArchivedProject {
+            format: ARCHIVE_FORMAT,
+            name: project.name.clone(),
+            handlers,
+            variables: self
+                .variables
+                .belonging_to(&project)?
+                .into_iter()
+                .map(|variable| ArchivedVariable {
+                    stage: variable.stage,
+                    name: variable.name,
+                    value: variable.value,
+                })
+                .collect(),
+            trusted_keys: self
+                .trusted_keys
+                .belonging_to(&project)?
+                .into_iter()
+                .map(|key| ArchivedKey {
+                    name: key.name,
+                    public_key: key.public_key,
+                    created_at: key.created_at,
+                })
+                .collect(),
+        };
+
+        let components = blobs
+            .into_iter()
+            .map(|digest| {
+                let wasm = self.wasmstore.read(&digest)?;
+                Ok((digest, wasm))
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+        let archive = pack(&archived, &components)?;
+
+        self.audit.record(user.project_event(
+            AuditAction::ProjectExport,
+            &project,
+            project_name,
+        ))?;
+        Ok(archive)
+    }
+
+    /// Creates a project of the user from an archive, named like the exported project unless
+    /// another name is given. The versions are attributed to the importing user. A project with
+    /// the same name in the trash is purged once the import succeeds, it can't be restored
+    /// anymore. The project must fit into the quotas of the user.
+    ///
+    /// The archive is held to the rules of deployments: names, stages and canary weights must
+    /// be valid and every component must compile. If the archive has trusted keys, it must be
+    /// signed by one of them, as the server doesn't keep the signed modules of the versions.
+    /// The hashes of the modules can't be computed from the components, so every component
+    /// must be recorded with a single hash.
+    pub fn import(
+        &self,
+        user: &Actor,
+        archive: &[u8],
+        project_name: Option<String>,
+        signature: Option<&str>,
+    ) -> Result<GetProjectDTO, Error> {
+        let (archived, mut components) =
+            unpack(archive).map_err(|err| InvalidArchive(format!("{:#}", err)))?;
+        if archived.format != ARCHIVE_FORMAT {
+            return Err(InvalidArchive(format!(
+                "Unsupported format {}",
+                archived.format
+            )));
+        }
+
+        let project_name = project_name.unwrap_or(archived.name);
+        validate_project_name(&project_name)?;
+        if self
+            .projects
+            .belonging_to_by_name(user, &project_name)?
+            .is_some()
+        {
+            return Err(ProjectExists);
+        }
+        let project = self.orgs.new_project(user, &project_name)?;
+
+        let mut keys = Vec::new();
+        for key in archived.trusted_keys {
+            if !signature::is_public_key(&key.public_key) {
+                return Err(InvalidArchive(format!(
+                    "Trusted key {} is not a public key",
+                    key.name
+                )));
+            }
+            keys.push(TrustedKey {
+                project_id: project.id.clone(),
+                name: key.name,
+                public_key: key.public_key,
+                created_at: key.created_at,
+            });
+        }
+        verify_signature(&keys, archive, signature)?;
+
+        let mut handlers = Vec::new();
+        let mut versions = Vec::new();
+        let mut blobs = BTreeSet::new();
+        let mut hashes = HashMap::new();
+        for archived_handler in archived.handlers {
+            validate_handler_name(&archived_handler.name)?;
+            validate_stage(&archived_handler.stage)?;
+            validate_canary_weight(archived_handler.canary_weight)?;
+            let active = archived_handler
+                .versions
+                .iter()
+                .find(|version| version.number == archived_handler.version)
+                .ok_or(InvalidArchive(format!(
+                    "Handler {} misses its active version",
+                    archived_handler.name
+                )))?;
+            if archived_handler.canary_version.is_some_and(|canary| {
+                !archived_handler
+                    .versions
+                    .iter()
+                    .any(|version| version.number == canary)
+            }) {
+                return Err(InvalidArchive(format!(
+                    "Handler {} misses its canary version",
+                    archived_handler.name
+                )));
+            }
+
+            let handler = Handler {
+                id: create_id(),
+                name: archived_handler.name,
+                language: archived_handler.language,
+                hash: active.hash.clone(),
+                project_id: project.id.clone(),
+                version: archived_handler.version,
+                canary_version: archived_handler.canary_version,
+                canary_weight: archived_handler.canary_weight,
+                stage: archived_handler.stage,
+                deleted_at: None,
+            };
+            for version in archived_handler.versions {
+                let Some(component) = components.get(&version.blob) else {
+                    return Err(InvalidArchive(format!(
+                        "Component {} is missing",
+                        version.blob
+                    )));
+                };
+                if version.hash.is_empty()
+                    || hashes
+                        .insert(version.blob.clone(), version.hash.clone())
+                        .is_some_and(|hash| hash != version.hash)
+                {
+                    return Err(InvalidArchive(format!(
+                        "Version {} of handler {} doesn't match the hash of its component",
+                        version.number, handler.name
+                    )));
+                }
+                let size = component.len() as i64;
+                blobs.insert(version.blob.clone());
+                versions.push(HandlerVersion {
+                    id: create_id(),
+                    handler_id: handler.id.clone(),
+                    number: version.number,
+                    hash: version.hash,
+                    user_id: Some(user.id.clone()),
+                    created_at: version.created_at,
+                    blob: version.blob,
+                    size,
+                });
+            }
+            handlers.push(handler);
+        }
+
+        let mut variables = Vec::new();
+        for variable in archived.variables {
+            validate_stage(&variable.stage)?;
+            variables.push(StageVariable::new(
+                project.id.clone(),
+                variable.stage,
+                variable.name,
+                variable.value,
+            ));
+        }
+
+        self.quotas.check_projects(user)?;
+        self.quotas
+            .check_handlers(&project, handlers.len() as i64)?;
+        let sizes = blobs
+            .iter()
+            .filter_map(|digest| Some((digest.clone(), components.get(digest)?.len() as i64)))
+            .collect();
+        self.quotas.check_storage(&project, &sizes)?;
+
+        for digest in &blobs {
+            if let Some(wasm) = components.get(digest) {
+                executor::validate(wasm).map_err(|err| {
+                    InvalidArchive(format!("Component {} is invalid: {:#}", digest, err))
+                })?;
+            }
+        }
+
+        // The project in the trash is only purged together with the import
+        let trashed = self.projects.trashed_by_name(user, &project_name)?;
+        let trashed_blobs = trashed
+            .as_ref()
+            .map(|trashed| self.versions.blobs_belonging_to(trashed))
+            .transpose()?;
+
+        // The components are stored before the versions referencing them. If the import fails,
+        // the garbage collection removes them again.
+        for digest in &blobs {
+            if let Some(wasm) = components.remove(digest) {
+                self.wasmstore.create(&wasm)?;
+            }
+        }
+
+        self.projects.import(
+            &project,
+            &handlers,
+            &versions,
+            &variables,
+            &keys,
+            trashed.as_ref(),
+            &AuditEvent {
+                details: Some(format!("{} handlers", handlers.len())),
+                ..user.project_event(AuditAction::ProjectImport, &project, &project_name)
+            },
+        )?;
+        if let Some(blobs) = trashed_blobs {
+            self.trash.remove_components(&blobs);
+        }
+
+        Ok(GetProjectDTO {
+            name: project.name,
+            handlers: handlers.into_iter().map(GetHandlerDTO::from).collect(),
+        })
+    }
+}
+
+fn component_path(digest: &str) -> String {
+    format!("{}/{}.{}", COMPONENTS_DIRECTORY, digest, WASM_EXTENSION)
+}
+
+fn pack(archived: &ArchivedProject, components: &[(String, Vec<u8>)]) -> anyhow::Result<Vec<u8>> {
+    let mut builder = tar::Builder::new(zstd::Encoder::new(Vec::new(), 0)?);
+
+    let mut append = |path: &str, data: &[u8]| {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(data.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+        builder.append_data(&mut header, path, data)
+    };
+    append(PROJECT_PATH, &serde_json::to_vec_pretty(archived)?)?;
+    for (digest, wasm) in components {
+        append(&component_path(digest), wasm)?;
+    }
+
+    Ok(builder.into_inner()?.finish()?)
+}
+
+/// Reads the project and the components of an archive. A component is only accepted if its
+/// content matches the digest it's stored under.
+fn unpack(archive: &[u8]) -> anyhow::Result<(ArchivedProject, HashMap<String, Vec<u8>>)> {
+    let decoder = zstd::Decoder::new(archive)?.take(MAX_UNPACKED_SIZE_IN_BYTES);
+    let mut archive = tar::Archive::new(decoder);
+
+    let mut project = None;
+    let mut components = HashMap::new();
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let path = entry.path()?.to_string_lossy().into_owned();
+        let mut data = Vec::new();
+        entry.read_to_end(&mut data)?;
+
+        if path == PROJECT_PATH {
+            project = Some(serde_json::from_slice(&data).context("Reading project.json failed")?);
+            continue;
+        }
+        let Some(digest) = path
+            .strip_prefix(&format!("{}/", COMPONENTS_DIRECTORY))
+            .and_then(|name| name.strip_suffix(&format!(".{}", WASM_EXTENSION)))
+        else {
+            anyhow::bail!("Unexpected file {}", path);
+        };
+        if hash::hash(&data) != digest {
+            anyhow::bail!("Component {} doesn't match its digest", digest);
+        }
+        components.insert(digest.to_string(), data);
+    }
+
+    let project = project.context("project.json is missing")?;
+    Ok((project, components))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        bindgen,
+        errors::Error::{
+            InvalidCanaryWeight, InvalidStage, QuotaExceeded, ReservedName, UntrustedSignature,
+        },
+        identity::Identity,
+        repository::{
+            org::OrgRepository,
+            project::Project,
+            user::{User, UserRepository},
+        },
+        service::quota::Quota,
+    };
+    use faux::when;
+    use lazy_static::lazy_static;
+    use std::collections::HashSet;
+
+    /// The arguments of [`ProjectRepository::import`]
+    type ImportArgs<'a> = (
+        &'a Project,
+        &'a [Handler],
+        &'a [HandlerVersion],
+        &'a [StageVariable],
+        &'a [TrustedKey],
+        Option<&'a Project>,
+        &'a AuditEvent,
+    );
+
+    const PROJECT_NAME: &str = "PROJECT_NAME";
+    const USER_ID: &str = "Jai3ohr5ahghoo6Ohjeih";
+
+    lazy_static! {
+        static ref USER: User = User {
+            id: USER_ID.to_string(),
+            ..User::new(Identity {
+                provider: "github".to_string(),
+                subject: "42".to_string(),
+                login: "login_name".to_string(),
+                email: "test@example.com".to_string(),
+                ..Default::default()
+            })
+        };
+        static ref ACTOR: Actor = Actor::from(USER.clone());
+        static ref STABLE: Vec<u8> = component(env!("CARGO_CDYLIB_FILE_RETURN_STATUS_CODE_200"));
+        static ref CANARY: Vec<u8> = component(env!("CARGO_CDYLIB_FILE_RETURN_PARAMS"));
+        static ref STABLE_DIGEST: String = hash::hash(&STABLE);
+        static ref CANARY_DIGEST: String = hash::hash(&CANARY);
+        static ref SIGNING_KEY: signature::SigningKey = signature::generate_key();
+        static ref PUBLIC_KEY: String = signature::public_key(&SIGNING_KEY);
+    }
+
+    fn component(path: &str) -> Vec<u8> {
+        bindgen::create_component(&std::fs::read(path).unwrap()).unwrap()
+    }
+
+    fn service(
+        projects: ProjectRepository,
+        handlers: HandlerRepository,
+        versions: HandlerVersionRepository,
+        variables: StageVariableRepository,
+        trusted_keys: TrustedKeyRepository,
+        wasmstore: WasmStore,
+        quotas: QuotaService,
+    ) -> ArchiveService {
+        ArchiveService::new(
+            projects,
+            handlers,
+            versions,
+            variables,
+            trusted_keys,
+            wasmstore,
+            TrashService::faux(),
+            quotas,
+            OrgService::new(OrgRepository::faux(), UserRepository::faux()),
+            audit_mock(),
+        )
+    }
+
+    /// Exports a project with a handler running a canary release, a variable and a trusted key
+    fn export() -> anyhow::Result<Vec<u8>> {
+        let project = Project::new(PROJECT_NAME.to_string(), USER_ID.to_string());
+        let handler = Handler {
+            version: 1,
+            canary_version: Some(2),
+            canary_weight: 10,
+            ..Handler::new(
+                "HANDLER_NAME".to_string(),
+                Language::Rust,
+                "STABLE_HASH".to_string(),
+                project.id.clone(),
+                "prod".to_string(),
+            )
+        };
+        let version = |number, hash: &str, blob: &str| {
+            let version = HandlerVersion::new(
+                handler.id.clone(),
+                number,
+                hash.to_string(),
+                blob.to_string(),
+                0,
+                "EXPORTING_USER".to_string(),
+            );
+            (version, Some(USER.login.clone()))
+        };
+
+        let mut projects_mock = ProjectRepository::faux();
+        when!(projects_mock.belonging_to_by_name)
+            .once()
+            .then_return(Ok(Some(project.clone())));
+        let mut handlers_mock = HandlerRepository::faux();
+        when!(handlers_mock.belonging_to)
+            .once()
+            .then_return(Ok(vec![handler.clone()]));
+        let mut versions_mock = HandlerVersionRepository::faux();
+        when!(versions_mock.belonging_to_with_login)
+            .once()
+            .then_return(Ok(vec![
+                version(2, "CANARY_HASH", &CANARY_DIGEST),
+                version(1, "STABLE_HASH", &STABLE_DIGEST),
+            ]));
+        let mut variables_mock = StageVariableRepository::faux();
+        when!(variables_mock.belonging_to)
+            .once()
+            .then_return(Ok(vec![StageVariable::new(
+                project.id.clone(),
+                "prod".to_string(),
+                "NAME".to_string(),
+                "VALUE".to_string(),
+            )]));
+        let mut trusted_keys_mock = TrustedKeyRepository::faux();
+        when!(trusted_keys_mock.belonging_to)
+            .once()
+            .then_return(Ok(vec![TrustedKey::new(
+                project.id.clone(),
+                "ci".to_string(),
+                PUBLIC_KEY.clone(),
+            )]));
+        let mut wasmstore_mock = WasmStore::faux();
+        when!(wasmstore_mock.read(STABLE_DIGEST.as_str()))
+            .once()
+            .then_return(Ok(STABLE.clone()));
+        when!(wasmstore_mock.read(CANARY_DIGEST.as_str()))
+            .once()
+            .then_return(Ok(CANARY.clone()));
+
+        let archives = service(
+            projects_mock,
+            handlers_mock,
+            versions_mock,
+            variables_mock,
+            trusted_keys_mock,
+            wasmstore_mock,
+            quotas_mock(),
+        );
+        Ok(archives.export(&ACTOR, PROJECT_NAME)?)
+    }
+
+    /// An audit log accepting every event
+    fn audit_mock() -> AuditService {
+        let mut audit_mock = AuditService::faux();
+        when!(audit_mock.record).then(|_| Ok(()));
+        audit_mock
+    }
+
+    /// Quotas which are never exceeded
+    fn quotas_mock() -> QuotaService {
+        let mut quotas_mock = QuotaService::faux();
+        when!(quotas_mock.check_projects).then(|_| Ok(()));
+        when!(quotas_mock.check_handlers).then(|_| Ok(()));
+        when!(quotas_mock.check_artifact).then(|_| Ok(()));
+        when!(quotas_mock.check_storage).then(|_| Ok(()));
+        when!(quotas_mock.count_invocation).then(|_| Ok(()));
+        quotas_mock
+    }
+
+    #[test]
+    fn export_import_ok() -> anyhow::Result<()> {
+        let archive = export()?;
+
+        let mut projects_mock = ProjectRepository::faux();
+        when!(projects_mock.belonging_to_by_name)
+            .once()
+            .then_return(Ok(None));
+        when!(projects_mock.trashed_by_name)
+            .once()
+            .then_return(Ok(None));
+        when!(projects_mock.import).once().then(
+            |(project, handlers, versions, variables, keys, trashed, _): ImportArgs<'_>| {
+                assert!(trashed.is_none());
+                assert_eq!("IMPORTED", project.name);
+                assert_eq!(USER_ID, project.user_id);
+                assert_eq!(1, handlers.len());
+                assert_eq!(project.id, handlers[0].project_id);
+                assert_eq!("STABLE_HASH", handlers[0].hash);
+                assert_eq!((1, Some(2), 10), {
+                    let handler = &handlers[0];
+                    (
+                        handler.version,
+                        handler.canary_version,
+                        handler.canary_weight,
+                    )
+                });
+                let numbers: Vec<(i32, &str)> = versions
+                    .iter()
+                    .map(|version| (version.number, version.blob.as_str()))
+                    .collect();
+                assert_eq!(
+                    vec![(1, STABLE_DIGEST.as_str()), (2, CANARY_DIGEST.as_str())],
+                    numbers
+                );
+                assert!(versions
+                    .iter()
+                    .all(|version| version.user_id.as_deref() == Some(USER_ID)
+                        && version.handler_id == handlers[0].id));
+                assert_eq!(
+                    vec![StageVariable::new(
+                        project.id.clone(),
+                        "prod".to_string(),
+                        "NAME".to_string(),
+                        "VALUE".to_string(),
+                    )],
+                    variables
+                );
+                assert_eq!(
+                    vec![("ci", PUBLIC_KEY.as_str())],
+                    keys.iter()
+                        .map(|key| (key.name.as_str(), key.public_key.as_str()))
+                        .collect::<Vec<_>>()
+                );
+                Ok(())
+            },
+        );
+        let mut wasmstore_mock = WasmStore::faux();
+        when!(wasmstore_mock.create(STABLE.as_slice()))
+            .once()
+            .then_return(Ok(STABLE_DIGEST.clone()));
+        when!(wasmstore_mock.create(CANARY.as_slice()))
+            .once()
+            .then_return(Ok(CANARY_DIGEST.clone()));
+
+        // -------------------------------------------------------------------------------------
+
+        let archives = service(
+            projects_mock,
+            HandlerRepository::faux(),
+            HandlerVersionRepository::faux(),
+            StageVariableRepository::faux(),
+            TrustedKeyRepository::faux(),
+            wasmstore_mock,
+            quotas_mock(),
+        );
+        let project = archives.import(
+            &ACTOR,
+            &archive,
+            Some("IMPORTED".to_string()),
+            Some(&signature::sign(&SIGNING_KEY, &archive)),
+        )?;
+
+        assert_eq!("IMPORTED", project.name);
+        assert_eq!(1, project.handlers.len());
+        Ok(())
+    }
+
+    #[test]
+    fn import_project_exists() -> anyhow::Result<()> {
+        let archive = export()?;
+
+        let mut projects_mock = ProjectRepository::faux();
+        when!(projects_mock.belonging_to_by_name)
+            .once()
+            .then_return(Ok(Some(Project::new(
+                PROJECT_NAME.to_string(),
+                USER_ID.to_string(),
+            ))));
+
+        // -------------------------------------------------------------------------------------
+
+        let archives = service(
+            projects_mock,
+            HandlerRepository::faux(),
+            HandlerVersionRepository::faux(),
+            StageVariableRepository::faux(),
+            TrustedKeyRepository::faux(),
+            WasmStore::faux(),
+            quotas_mock(),
+        );
+        let result = archives.import(&ACTOR, &archive, None, None);
+
+        assert!(matches!(result, Err(ProjectExists)));
+        Ok(())
+    }
+
+    #[test]
+    fn import_missing_component() -> anyhow::Result<()> {
+        let archived = ArchivedProject {
+            format: ARCHIVE_FORMAT,
+            name: PROJECT_NAME.to_string(),
+            handlers: vec![ArchivedHandler {
+                name: "HANDLER_NAME".to_string(),
+                language: Language::Rust,
+                stage: "prod".to_string(),
+                version: 1,
+                canary_version: None,
+                canary_weight: 0,
+                versions: vec![ArchivedVersion {
+                    number: 1,
+                    hash: "STABLE_HASH".to_string(),
+                    blob: STABLE_DIGEST.clone(),
+                    created_at: chrono::Utc::now().naive_utc(),
+                }],
+            }],
+            variables: vec![],
+            trusted_keys: vec![],
+        };
+        let archive = pack(&archived, &[])?;
+
+        let mut projects_mock = ProjectRepository::faux();
+        when!(projects_mock.belonging_to_by_name)
+            .once()
+            .then_return(Ok(None));
+
+        // -------------------------------------------------------------------------------------
+
+        let archives = service(
+            projects_mock,
+            HandlerRepository::faux(),
+            HandlerVersionRepository::faux(),
+            StageVariableRepository::faux(),
+            TrustedKeyRepository::faux(),
+            WasmStore::faux(),
+            quotas_mock(),
+        );
+        let result = archives.import(&ACTOR, &archive, None, None);
+
+        assert!(matches!(result, Err(InvalidArchive(_))));
+        Ok(())
+    }
+
+    #[test]
+    fn unpack_tampered_component() -> anyhow::Result<()> {
+        let archived = ArchivedProject {
+            format: ARCHIVE_FORMAT,
+            name: PROJECT_NAME.to_string(),
+            handlers: vec![],
+            variables: vec![],
+            trusted_keys: vec![],
+        };
+        let archive = pack(&archived, &[(STABLE_DIGEST.clone(), CANARY.clone())])?;
+
+        assert!(unpack(&archive).is_err());
+        Ok(())
+    }
+
+    /// A handler whose only version runs the stable component
+    fn archived_handler() -> ArchivedHandler {
+        ArchivedHandler {
+            name: "HANDLER_NAME".to_string(),
+            language: Language::Rust,
+            stage: "prod".to_string(),
+            version: 1,
+            canary_version: None,
+            canary_weight: 0,
+            versions: vec![ArchivedVersion {
+                number: 1,
+                hash: "STABLE_HASH".to_string(),
+                blob: STABLE_DIGEST.clone(),
+                created_at: chrono::Utc::now().naive_utc(),
+            }],
+        }
+    }
+
+    fn archive_of(
+        handler: ArchivedHandler,
+        trusted_keys: Vec<ArchivedKey>,
+        components: &[(String, Vec<u8>)],
+    ) -> anyhow::Result<Vec<u8>> {
+        let archived = ArchivedProject {
+            format: ARCHIVE_FORMAT,
+            name: PROJECT_NAME.to_string(),
+            handlers: vec![handler],
+            variables: vec![],
+            trusted_keys,
+        };
+        pack(&archived, components)
+    }
+
+    /// Imports the archive into a repository accepting it
+    fn import(
+        archive: &[u8],
+        signature: Option<&str>,
+        quotas: QuotaService,
+    ) -> Result<GetProjectDTO, Error> {
+        let mut projects_mock = ProjectRepository::faux();
+        when!(projects_mock.belonging_to_by_name).then(|_| Ok(None));
+        when!(projects_mock.trashed_by_name).then(|_| Ok(None));
+        when!(projects_mock.import).then(|_| Ok(()));
+        let mut wasmstore_mock = WasmStore::faux();
+        when!(wasmstore_mock.create).then(|wasm| Ok(hash::hash(wasm)));
+
+        let archives = service(
+            projects_mock,
+            HandlerRepository::faux(),
+            HandlerVersionRepository::faux(),
+            StageVariableRepository::faux(),
+            TrustedKeyRepository::faux(),
+            wasmstore_mock,
+            quotas,
+        );
+        archives.import(&ACTOR, archive, None, signature)
+    }
+
+    #[test]
+    fn import_invalid_stage() -> anyhow::Result<()> {
+        let handler = ArchivedHandler {
+            stage: "prod/eu".to_string(),
+            ..archived_handler()
+        };
+        let archive = archive_of(handler, vec![], &[(STABLE_DIGEST.clone(), STABLE.clone())])?;
+
+        let result = import(&archive, None, quotas_mock());
+
+        assert!(matches!(result, Err(InvalidStage)));
+        Ok(())
+    }
+
+    #[test]
+    fn import_invalid_canary_weight() -> anyhow::Result<()> {
+        let handler = ArchivedHandler {
+            canary_weight: 101,
+            ..archived_handler()
+        };
+        let archive = archive_of(handler, vec![], &[(STABLE_DIGEST.clone(), STABLE.clone())])?;
+
+        let result = import(&archive, None, quotas_mock());
+
+        assert!(matches!(result, Err(InvalidCanaryWeight)));
+        Ok(())
+    }
+
+    #[test]
+    fn import_hash_mismatch() -> anyhow::Result<()> {
+        let mut handler = archived_handler();
+        handler.versions.push(ArchivedVersion {
+            number: 2,
+            hash: "OTHER_HASH".to_string(),
+            ..handler.versions[0].clone()
+        });
+        let archive = archive_of(handler, vec![], &[(STABLE_DIGEST.clone(), STABLE.clone())])?;
+
+        let result = import(&archive, None, quotas_mock());
+
+        assert!(matches!(result, Err(InvalidArchive(_))));
+        Ok(())
+    }
+
+    #[test]
+    fn import_untrusted_signature() -> anyhow::Result<()> {
+        let keys = vec![ArchivedKey {
+            name: "ci".to_string(),
+            public_key: PUBLIC_KEY.clone(),
+            created_at: chrono::Utc::now().naive_utc(),
+        }];
+        let archive = archive_of(
+            archived_handler(),
+            keys,
+            &[(STABLE_DIGEST.clone(), STABLE.clone())],
+        )?;
+        let untrusted = signature::sign(&signature::generate_key(), &archive);
+
+        assert!(matches!(
+            import(&archive, None, quotas_mock()),
+            Err(UntrustedSignature)
+        ));
+        assert!(matches!(
+            import(&archive, Some(&untrusted), quotas_mock()),
+            Err(UntrustedSignature)
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn import_storage_exceeded() -> anyhow::Result<()> {
+        let archive = archive_of(
+            archived_handler(),
+            vec![],
+            &[(STABLE_DIGEST.clone(), STABLE.clone())],
+        )?;
+        let mut quotas_mock = QuotaService::faux();
+        when!(quotas_mock.check_projects).then(|_| Ok(()));
+        when!(quotas_mock.check_handlers).then(|_| Ok(()));
+        when!(quotas_mock.check_storage).then(|_| {
+            Err(QuotaExceeded {
+                quota: Quota::Storage,
+                limit: 1,
+            })
+        });
+
+        let result = import(&archive, None, quotas_mock);
+
+        assert!(matches!(
+            result,
+            Err(QuotaExceeded {
+                quota: Quota::Storage,
+                ..
+            })
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn import_invalid_component() -> anyhow::Result<()> {
+        let module = std::fs::read(env!("CARGO_CDYLIB_FILE_RETURN_STATUS_CODE_200"))?;
+        let handler = ArchivedHandler {
+            versions: vec![ArchivedVersion {
+                blob: hash::hash(&module),
+                ..archived_handler().versions[0].clone()
+            }],
+            ..archived_handler()
+        };
+        let archive = archive_of(handler, vec![], &[(hash::hash(&module), module)])?;
+
+        let result = import(&archive, None, quotas_mock());
+
+        assert!(matches!(result, Err(InvalidArchive(_))));
+        Ok(())
+    }
+
+    #[test]
+    fn import_reserved_handler_name() -> anyhow::Result<()> {
+        let handler = ArchivedHandler {
+            name: "export".to_string(),
+            ..archived_handler()
+        };
+        let archive = archive_of(handler, vec![], &[(STABLE_DIGEST.clone(), STABLE.clone())])?;
+
+        let result = import(&archive, None, quotas_mock());
+
+        assert!(matches!(result, Err(ReservedName(name)) if name == "export"));
+        Ok(())
+    }
+
+    #[test]
+    fn import_purges_trashed_with_import() -> anyhow::Result<()> {
+        let archive = archive_of(
+            archived_handler(),
+            vec![],
+            &[(STABLE_DIGEST.clone(), STABLE.clone())],
+        )?;
+        let trashed = Project::new(PROJECT_NAME.to_string(), USER_ID.to_string());
+        let blobs = HashSet::from(["TRASHED_DIGEST".to_string()]);
+
+        // -------------------------------------------------------------------------------------
+
+        let import_with = |result: fn() -> anyhow::Result<()>, trash: TrashService| {
+            let mut projects_mock = ProjectRepository::faux();
+            when!(projects_mock.belonging_to_by_name).then(|_| Ok(None));
+            let trashed_by_name = trashed.clone();
+            when!(projects_mock.trashed_by_name).then(move |_| Ok(Some(trashed_by_name.clone())));
+            let trashed_id = trashed.id.clone();
+            when!(projects_mock.import).once().then(
+                move |(_, _, _, _, _, replaced, _): ImportArgs<'_>| {
+                    assert_eq!(Some(&trashed_id), replaced.map(|project| &project.id));
+                    result()
+                },
+            );
+            let mut versions_mock = HandlerVersionRepository::faux();
+            let trashed_blobs = blobs.clone();
+            when!(versions_mock.blobs_belonging_to).then(move |_| Ok(trashed_blobs.clone()));
+            let mut wasmstore_mock = WasmStore::faux();
+            when!(wasmstore_mock.create).then(|wasm| Ok(hash::hash(wasm)));
+
+            ArchiveService::new(
+                projects_mock,
+                HandlerRepository::faux(),
+                versions_mock,
+                StageVariableRepository::faux(),
+                TrustedKeyRepository::faux(),
+                wasmstore_mock,
+                trash,
+                quotas_mock(),
+                OrgService::new(OrgRepository::faux(), UserRepository::faux()),
+                audit_mock(),
+            )
+            .import(&ACTOR, &archive, None, None)
+        };
+
+        // A failed import leaves the trash alone
+        let result = import_with(|| anyhow::bail!("import failed"), TrashService::faux());
+        assert!(result.is_err());
+
+        let mut trash_mock = TrashService::faux();
+        when!(trash_mock.remove_components(blobs.clone()))
+            .once()
+            .then_return(());
+        import_with(|| Ok(()), trash_mock)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,187 @@
+use super::org::authorized_project;
+use crate::{
+    errors::Error,
+    repository::{
+        audit::{AuditEvent, AuditRepository, AuditScope},
+        project::{Project, ProjectRepository},
+        user::User,
+    },
+};
+use common::dtos::{AuditAction, AuditFilterDTO, GetAuditEventDTO, Role};
+use std::ops::Deref;
+
+/// The events returned without a limit
+const DEFAULT_LIMIT: i64 = 100;
+const MAX_LIMIT: i64 = 1000;
+
+/// The user behind a request and where it came from, as recorded in the audit log
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Actor {
+    pub user: User,
+    /// The address of the client
+    pub ip: Option<String>,
+    /// The name of the API token the request was sent with
+    pub token: Option<String>,
+}
+
+impl Actor {
+    pub fn new(user: User, ip: Option<String>, token: Option<String>) -> Self {
+        Self { user, ip, token }
+    }
+
+    /// An event of the action, to be completed with what it changed
+    pub fn event(&self, action: AuditAction) -> AuditEvent {
+        AuditEvent {
+            token: self.token.clone(),
+            ip: self.ip.clone(),
+            ..AuditEvent::new(self.user.id.clone(), self.user.login.clone(), action)
+        }
+    }
+
+    /// An event of the action in the project, which is named as in the request
+    pub fn project_event(
+        &self,
+        action: AuditAction,
+        project: &Project,
+        project_name: &str,
+    ) -> AuditEvent {
+        AuditEvent {
+            project_id: Some(project.id.clone()),
+            project: Some(project_name.to_string()),
+            ..self.event(action)
+        }
+    }
+}
+
+impl Deref for Actor {
+    type Target = User;
+
+    fn deref(&self) -> &User {
+        &self.user
+    }
+}
+
+impl From<User> for Actor {
+    fn from(user: User) -> Self {
+        Self {
+            user,
+            ..Default::default()
+        }
+    }
+}
+
+/// Records the management actions of the users in an append-only log. Members of a project
+/// see all events of the project, otherwise users see their own events.
+#[cfg_attr(test, faux::create)]
+#[derive(Debug, Clone)]
+pub struct AuditService {
+    events: AuditRepository,
+    projects: ProjectRepository,
+}
+
+#[cfg_attr(test, faux::methods)]
+impl AuditService {
+    pub fn new(events: AuditRepository, projects: ProjectRepository) -> Self {
+        Self { events, projects }
+    }
+
+    pub fn record(&self, event: AuditEvent) -> Result<(), Error> {
+        self.events.create(&event)?;
+        Ok(())
+    }
+
+    /// The newest events matching the filter. Viewers of the project of the filter see the
+    /// events of everyone in it.
+    pub fn search(
+        &self,
+        user: &User,
+        filter: &AuditFilterDTO,
+    ) -> Result<Vec<GetAuditEventDTO>, Error> {
+        let scope = match &filter.project {
+            Some(project_name) => AuditScope::Project(
+                authorized_project(&self.projects, user, project_name, Role::Viewer)?.id,
+            ),
+            None => AuditScope::User(user.id.clone()),
+        };
+        let limit = filter.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT);
+
+        let events = self
+            .events
+            .search(&scope, filter, limit)?
+            .into_iter()
+            .map(GetAuditEventDTO::from)
+            .collect();
+        Ok(events)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::errors::Error::ProjectNotFound;
+    use faux::when;
+
+    fn user(id: &str) -> User {
+        User {
+            id: id.to_string(),
+            login: id.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn event_of_actor() {
+        let actor = Actor::new(
+            user("alice"),
+            Some("127.0.0.1".to_string()),
+            Some("ci".to_string()),
+        );
+        let project = Project::new("shop".to_string(), "alice".to_string());
+
+        let event = actor.project_event(AuditAction::HandlerDeploy, &project, "acme/shop");
+
+        assert_eq!("alice", event.user_id);
+        assert_eq!(Some("127.0.0.1".to_string()), event.ip);
+        assert_eq!(Some("ci".to_string()), event.token);
+        assert_eq!(Some(project.id), event.project_id);
+        assert_eq!(Some("acme/shop".to_string()), event.project);
+    }
+
+    #[test]
+    fn search_scopes() -> anyhow::Result<()> {
+        let project = Project::new("shop".to_string(), "alice".to_string());
+        let mut projects_mock = ProjectRepository::faux();
+        let found = project.clone();
+        when!(projects_mock.belonging_to_by_name)
+            .times(2)
+            .then(move |(user, _)| match user.id.as_str() {
+                "alice" => Ok(Some(found.clone())),
+                _ => Ok(None),
+            });
+        let mut events_mock = AuditRepository::faux();
+        let project_id = project.id.clone();
+        when!(events_mock.search)
+            .times(2)
+            .then(move |(scope, _, limit)| {
+                assert!(
+                    *scope == AuditScope::Project(project_id.clone())
+                        || *scope == AuditScope::User("bob".to_string())
+                );
+                assert_eq!(DEFAULT_LIMIT, limit);
+                Ok(vec![])
+            });
+        let audit = AuditService::new(events_mock, projects_mock);
+        let project_filter = AuditFilterDTO {
+            project: Some("shop".to_string()),
+            ..Default::default()
+        };
+
+        audit.search(&user("alice"), &project_filter)?;
+        audit.search(&user("bob"), &AuditFilterDTO::default())?;
+        assert!(matches!(
+            audit.search(&user("bob"), &project_filter),
+            Err(ProjectNotFound)
+        ));
+        Ok(())
+    }
+}
@@ -1,70 +1,152 @@
-use crate::errors::Error::{self, UserNotRegistered};
-use crate::github::GithubClient;
-use crate::jwt::Jwt;
+use crate::errors::Error::{self, InvalidRefreshToken, TokenRevoked, UserNotRegistered};
+use crate::identity::IdentityClient;
+use crate::jwt::JwtConfig;
 use crate::repository::user::User;
-use crate::repository::{user::UserRepository, Repository};
-use common::dtos::GetJWTDTO;
-use jsonwebtoken::{DecodingKey, EncodingKey};
-use lazy_static::lazy_static;
-
-const JWT_SECRET: &str = "ieb9upai2pooYoo9guthohchio5xie6Poo1ooThaetubahCheemaixaeZei1rah0";
-const JWT_ISSUER: &str = "noops.io";
-const JWT_EXPIRATION_DELTA: u64 = 86400; // 24 hours
-
-lazy_static! {
-    pub static ref ENCODING_KEY: EncodingKey = EncodingKey::from_secret(JWT_SECRET.as_bytes());
-    pub static ref DECODING_KEY: DecodingKey = DecodingKey::from_secret(JWT_SECRET.as_bytes());
-}
+use crate::repository::{
+    audit::AuditEvent,
+    session::{Session, SessionRepository},
+    user::UserRepository,
+    Repository,
+};
+use crate::service::{audit::Actor, blocking};
+use chrono::{Duration, Utc};
+use common::{
+    dtos::{AuditAction, AuthProviderDTO, GetJWTDTO},
+    hash,
+};
+
+/// About 256 random bits
+const REFRESH_TOKEN_LENGTH: usize = 43;
 
+/// Logs users in with short-lived access tokens. A login starts a session, whose refresh token
+/// is exchanged for new tokens until the session expires or the user logs out.
 #[derive(Debug, Clone)]
 pub struct AuthService {
-    github_client: GithubClient,
+    identities: IdentityClient,
     users: UserRepository,
+    sessions: SessionRepository,
+    jwt: JwtConfig,
+    session_lifetime: Duration,
 }
 
 impl AuthService {
-    pub fn new(github_client: GithubClient, users: UserRepository) -> AuthService {
+    pub fn new(
+        identities: IdentityClient,
+        users: UserRepository,
+        sessions: SessionRepository,
+        jwt: JwtConfig,
+        session_lifetime: Duration,
+    ) -> AuthService {
         Self {
-            github_client,
+            identities,
             users,
+            sessions,
+            jwt,
+            session_lifetime,
         }
     }
 
-    pub async fn login(&self, github_access_token: String) -> Result<GetJWTDTO, Error> {
-        let gh_user = self
-            .github_client
-            .get_user(github_access_token.clone())
-            .await?;
-        let result = self.users.read_by_gh_id(gh_user.id)?;
-
-        let user = match result {
-            Some(user) => user,
-            None => {
-                let user = User::new(
-                    gh_user.email,
-                    gh_user.name,
-                    gh_user.location,
-                    gh_user.company,
-                    gh_user.id,
-                    gh_user.login,
-                    github_access_token,
-                );
-                self.users.create(&user)?;
-                user
-            }
-        };
+    /// The identity provider users log in with
+    pub fn provider(&self) -> AuthProviderDTO {
+        self.identities.device_flow()
+    }
+
+    /// Logs the user of the access token of the identity provider in, registering them on
+    /// their first login. The login is recorded with the address of the client.
+    pub async fn login(
+        &self,
+        access_token: String,
+        ip: Option<String>,
+    ) -> Result<GetJWTDTO, Error> {
+        let identity = self.identities.identify(access_token).await?;
+        let auth = self.clone();
+        blocking(move || {
+            let user = match auth
+                .users
+                .read_by_subject(&identity.provider, &identity.subject)?
+            {
+                Some(user) => user,
+                None => {
+                    let user = User::new(identity);
+                    auth.users.create(&user)?;
+                    user
+                }
+            };
 
-        let jwt = Jwt::create_token(
-            user.id,
-            JWT_ISSUER.to_string(),
-            JWT_EXPIRATION_DELTA,
-            &ENCODING_KEY,
-        )?;
-        Ok(GetJWTDTO { jwt })
+            let event = Actor::new(user.clone(), ip, None).event(AuditAction::Login);
+            auth.issue_token(&user, Some(&event))
+        })
+        .await
+    }
+
+    /// Starts a session of the user and issues its tokens. The event of a login is recorded
+    /// with the session.
+    pub fn issue_token(&self, user: &User, event: Option<&AuditEvent>) -> Result<GetJWTDTO, Error> {
+        self.sessions.delete_expired()?;
+
+        let refresh_token = create_refresh_token();
+        let session = Session::new(
+            user.id.clone(),
+            hash::hash(refresh_token.as_bytes()),
+            self.session_lifetime,
+        );
+        self.sessions.create(&session, event)?;
+
+        let jwt = self.jwt.issue(user.id.clone(), session.id)?;
+        Ok(GetJWTDTO { jwt, refresh_token })
+    }
+
+    /// Exchanges the refresh token for a new access token and a new refresh token. Every
+    /// refresh token can only be used once.
+    pub fn refresh(&self, refresh_token: &str) -> Result<GetJWTDTO, Error> {
+        let session = self
+            .sessions
+            .read_by_refresh_token(&hash::hash(refresh_token.as_bytes()))?
+            .ok_or(InvalidRefreshToken)?;
+        if session.is_expired() {
+            self.sessions.delete(&session.id, None)?;
+            return Err(InvalidRefreshToken);
+        }
+
+        let refresh_token = create_refresh_token();
+        let expires_at = Utc::now().naive_utc() + self.session_lifetime;
+        if !self
+            .sessions
+            .rotate(&session, &hash::hash(refresh_token.as_bytes()), expires_at)?
+        {
+            return Err(InvalidRefreshToken);
+        }
+
+        let jwt = self.jwt.issue(session.user_id, session.id)?;
+        Ok(GetJWTDTO { jwt, refresh_token })
+    }
+
+    /// Ends the session of the refresh token, which revokes its access tokens as well
+    pub fn logout(&self, refresh_token: &str, ip: Option<String>) -> Result<(), Error> {
+        if let Some(session) = self
+            .sessions
+            .read_by_refresh_token(&hash::hash(refresh_token.as_bytes()))?
+        {
+            let event = self
+                .users
+                .read(&session.user_id)?
+                .map(|user| Actor::new(user, ip, None).event(AuditAction::Logout));
+            self.sessions.delete(&session.id, event.as_ref())?;
+        }
+        Ok(())
     }
 
     pub fn authenticate(&self, jwt: &str) -> Result<User, Error> {
-        let (_, claims) = Jwt::decode(jwt, JWT_ISSUER, &DECODING_KEY)?;
+        let claims = self.jwt.verify(jwt)?;
+        if let Some(sid) = &claims.sid {
+            if !self
+                .sessions
+                .read(sid)?
+                .is_some_and(|session| !session.is_expired())
+            {
+                return Err(TokenRevoked);
+            }
+        }
         let user = self.users.read(&claims.sub)?;
         if user.is_none() {
             return Err(UserNotRegistered);
@@ -74,68 +156,139 @@ impl AuthService {
     }
 }
 
+fn create_refresh_token() -> String {
+    nanoid::nanoid!(REFRESH_TOKEN_LENGTH)
+}
+
 #[cfg(test)]
 mod tests {
     use faux::when;
 
     use super::*;
-    use crate::{github::GithubUser, repository::user::UserRepository};
+    use crate::{identity::Identity, jwt::JwtKeys, repository::user::UserRepository};
+    use lazy_static::lazy_static;
 
     const USER_EMAIL: &str = "test@example.com";
     const USER_NAME: &str = "user_name";
     const USER_LOCATION: &str = "Hamburg";
     const USER_COMPANY: &str = "Noops.io";
-    const USER_GH_ACCESS_TOKEN: &str = "Yiu0Hae4ietheereij4OhneuNe6tae0e";
-    const USER_GH_LOGIN: &str = "login_name";
-    const USER_GH_ID: i32 = 42;
+    const USER_ACCESS_TOKEN: &str = "Yiu0Hae4ietheereij4OhneuNe6tae0e";
+    const USER_LOGIN: &str = "login_name";
+    const USER_SUBJECT: &str = "42";
+    const REFRESH_TOKEN: &str = "REFRESH_TOKEN";
 
     lazy_static! {
-        static ref USER: User = User::new(
-            USER_EMAIL.to_string(),
-            Some(USER_NAME.to_string()),
-            Some(USER_LOCATION.to_string()),
-            Some(USER_COMPANY.to_string()),
-            USER_GH_ID,
-            USER_GH_LOGIN.to_string(),
-            USER_GH_ACCESS_TOKEN.to_string()
-        );
-        static ref JWT: String = Jwt::create_token(
+        static ref USER: User = User::new(identity());
+        static ref SESSION: Session = Session::new(
             USER.id.clone(),
-            JWT_ISSUER.to_string(),
-            JWT_EXPIRATION_DELTA,
-            &ENCODING_KEY,
-        )
-        .unwrap();
-        static ref GITHUB_USER: GithubUser = GithubUser {
-            id: USER_GH_ID,
+            hash::hash(REFRESH_TOKEN.as_bytes()),
+            Duration::days(30)
+        );
+        static ref JWT_CONFIG: JwtConfig = JwtConfig::new(
+            JwtKeys::secret("TEST_KID", b"ieb9upai2pooYoo9guthohchio5xie6P"),
+            "noops.io".to_string(),
+            900
+        );
+        static ref JWT: String = JWT_CONFIG
+            .issue(USER.id.clone(), SESSION.id.clone())
+            .unwrap();
+    }
+
+    fn identity() -> Identity {
+        Identity {
+            provider: "github".to_string(),
+            subject: USER_SUBJECT.to_string(),
+            login: USER_LOGIN.to_string(),
             email: USER_EMAIL.to_string(),
             name: Some(USER_NAME.to_string()),
             location: Some(USER_LOCATION.to_string()),
             company: Some(USER_COMPANY.to_string()),
-            login: USER_GH_LOGIN.to_string(),
-            access_token: USER_GH_ACCESS_TOKEN.to_string(),
-        };
+        }
+    }
+
+    fn auth_service(
+        identities: IdentityClient,
+        users: UserRepository,
+        sessions: SessionRepository,
+    ) -> AuthService {
+        AuthService::new(
+            identities,
+            users,
+            sessions,
+            JWT_CONFIG.clone(),
+            Duration::days(30),
+        )
     }
 
     #[tokio::test]
     async fn login_ok() -> anyhow::Result<()> {
         let mut users_mock = UserRepository::faux();
-        when!(users_mock.read_by_gh_id(USER_GH_ID))
+        when!(users_mock.read_by_subject)
             .once()
             .then_return(Ok(Some(USER.clone())));
 
-        let mut github_client_mock = GithubClient::faux();
-        when!(github_client_mock.get_user(USER_GH_ACCESS_TOKEN.to_string()))
+        let mut identities_mock = IdentityClient::faux();
+        when!(identities_mock.identify(USER_ACCESS_TOKEN.to_string()))
+            .once()
+            .then_return(Ok(identity()));
+
+        let mut sessions_mock = SessionRepository::faux();
+        when!(sessions_mock.delete_expired)
             .once()
-            .then_return(Ok(GITHUB_USER.clone()));
+            .then_return(Ok(0));
+        when!(sessions_mock.create).once().then(|(session, event)| {
+            let event = event.expect("login without audit event");
+            assert_eq!(AuditAction::Login, event.action);
+            assert_eq!(session.user_id, event.user_id);
+            assert_eq!(USER.id, event.user_id);
+            assert_eq!(Some("127.0.0.1".to_string()), event.ip);
+            Ok(())
+        });
 
         // -------------------------------------------------------------------------------------
 
-        let auth_service = AuthService::new(github_client_mock, users_mock);
-        let _ = auth_service
-            .login(USER_GH_ACCESS_TOKEN.to_string())
-            .await?
-            .jwt;
+        let auth_service = AuthService::new(
+            identities_mock,
+            users_mock,
+            sessions_mock,
+            JWT_CONFIG.clone(),
+            Duration::days(30),
+        );
+        let tokens = auth_service
+            .login(USER_ACCESS_TOKEN.to_string(), Some("127.0.0.1".to_string()))
+            .await?;
+        assert_eq!(REFRESH_TOKEN_LENGTH, tokens.refresh_token.len());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn login_registers_user() -> anyhow::Result<()> {
+        let mut users_mock = UserRepository::faux();
+        when!(users_mock.read_by_subject)
+            .once()
+            .then_return(Ok(None));
+        when!(users_mock.create).once().then(|user| {
+            assert_eq!(USER_SUBJECT, user.subject);
+            assert_eq!(USER_LOGIN, user.login);
+            Ok(())
+        });
+
+        let mut identities_mock = IdentityClient::faux();
+        when!(identities_mock.identify)
+            .once()
+            .then_return(Ok(identity()));
+
+        let mut sessions_mock = SessionRepository::faux();
+        when!(sessions_mock.delete_expired)
+            .once()
+            .then_return(Ok(0));
+        when!(sessions_mock.create).once().then_return(Ok(()));
+
+        let auth_service = auth_service(identities_mock, users_mock, sessions_mock);
+        auth_service
+            .login(USER_ACCESS_TOKEN.to_string(), None)
+            .await?;
 
         Ok(())
     }
@@ -147,11 +300,14 @@ mod tests {
             .once()
             .then_return(Ok(Some(USER.clone())));
 
-        let github_client_mock = GithubClient::faux();
+        let mut sessions_mock = SessionRepository::faux();
+        when!(sessions_mock.read(SESSION.id.as_ref()))
+            .once()
+            .then_return(Ok(Some(SESSION.clone())));
 
         // -------------------------------------------------------------------------------------
 
-        let auth_service = AuthService::new(github_client_mock, users_mock);
+        let auth_service = auth_service(IdentityClient::faux(), users_mock, sessions_mock);
         let user = auth_service.authenticate(&JWT)?;
         assert_eq!(*USER, user);
 
@@ -165,14 +321,115 @@ mod tests {
             .once()
             .then_return(Ok(None));
 
-        let github_client_mock = GithubClient::faux();
+        let mut sessions_mock = SessionRepository::faux();
+        when!(sessions_mock.read(SESSION.id.as_ref()))
+            .once()
+            .then_return(Ok(Some(SESSION.clone())));
 
         // -------------------------------------------------------------------------------------
 
-        let auth_service = AuthService::new(github_client_mock, users_mock);
+        let auth_service = auth_service(IdentityClient::faux(), users_mock, sessions_mock);
         let result = auth_service.authenticate(&JWT);
         assert!(result.is_err());
 
         Ok(())
     }
+
+    #[test]
+    fn authenticate_revoked() -> anyhow::Result<()> {
+        let mut sessions_mock = SessionRepository::faux();
+        when!(sessions_mock.read(SESSION.id.as_ref()))
+            .once()
+            .then_return(Ok(None));
+
+        // -------------------------------------------------------------------------------------
+
+        let auth_service = auth_service(
+            IdentityClient::faux(),
+            UserRepository::faux(),
+            sessions_mock,
+        );
+        let result = auth_service.authenticate(&JWT);
+        assert!(matches!(result, Err(TokenRevoked)));
+
+        Ok(())
+    }
+
+    #[test]
+    fn refresh_ok() -> anyhow::Result<()> {
+        let mut sessions_mock = SessionRepository::faux();
+        when!(sessions_mock.read_by_refresh_token(SESSION.refresh_token_hash.as_ref()))
+            .once()
+            .then_return(Ok(Some(SESSION.clone())));
+        when!(sessions_mock.rotate(SESSION.clone(), _, _))
+            .once()
+            .then_return(Ok(true));
+
+        // -------------------------------------------------------------------------------------
+
+        let auth_service = auth_service(
+            IdentityClient::faux(),
+            UserRepository::faux(),
+            sessions_mock,
+        );
+        let tokens = auth_service.refresh(REFRESH_TOKEN)?;
+        assert_ne!(REFRESH_TOKEN, tokens.refresh_token);
+
+        let claims = JWT_CONFIG.verify(&tokens.jwt)?;
+        assert_eq!(USER.id, claims.sub);
+        assert_eq!(Some(&SESSION.id), claims.sid.as_ref());
+
+        Ok(())
+    }
+
+    #[test]
+    fn refresh_used_twice() -> anyhow::Result<()> {
+        let mut sessions_mock = SessionRepository::faux();
+        when!(sessions_mock.read_by_refresh_token(SESSION.refresh_token_hash.as_ref()))
+            .once()
+            .then_return(Ok(Some(SESSION.clone())));
+        when!(sessions_mock.rotate(SESSION.clone(), _, _))
+            .once()
+            .then_return(Ok(false));
+
+        // -------------------------------------------------------------------------------------
+
+        let auth_service = auth_service(
+            IdentityClient::faux(),
+            UserRepository::faux(),
+            sessions_mock,
+        );
+        let result = auth_service.refresh(REFRESH_TOKEN);
+        assert!(matches!(result, Err(InvalidRefreshToken)));
+
+        Ok(())
+    }
+
+    #[test]
+    fn refresh_expired() -> anyhow::Result<()> {
+        let expired = Session {
+            expires_at: Utc::now().naive_utc(),
+            ..SESSION.clone()
+        };
+
+        let mut sessions_mock = SessionRepository::faux();
+        when!(sessions_mock.read_by_refresh_token(SESSION.refresh_token_hash.as_ref()))
+            .once()
+            .then_return(Ok(Some(expired)));
+        when!(sessions_mock.delete(SESSION.id.as_ref(), None))
+            .once()
+            .then_return(Ok(true));
+
+        // -------------------------------------------------------------------------------------
+
+        let auth_service = auth_service(
+            IdentityClient::faux(),
+            UserRepository::faux(),
+            sessions_mock,
+        );
+        let result = auth_service.refresh(REFRESH_TOKEN);
+        assert!(matches!(result, Err(InvalidRefreshToken)));
+
+        Ok(())
+    }
 }
@@ -0,0 +1,515 @@
+use super::gc::GRACE_PERIOD;
+use crate::{
+    errors::Error,
+    repository::{
+        handler::{Handler, HandlerRepository},
+        project::{Project, ProjectRepository},
+        user::UserRepository,
+        version::{HandlerVersion, HandlerVersionRepository},
+        Repository,
+    },
+    wasmstore::WasmStore,
+};
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::{self, Display},
+    time::SystemTime,
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Inconsistency {
+    /// A project whose user no longer exists
+    OrphanedProject(Project),
+    /// A handler whose project no longer exists
+    OrphanedHandler(Handler),
+    /// A version whose handler no longer exists
+    OrphanedVersion(HandlerVersion),
+    /// A handler whose active or canary version does not exist. `fallback` is the newest
+    /// intact version the handler can be rolled back to.
+    MissingVersion {
+        handler: Handler,
+        number: i32,
+        fallback: Option<HandlerVersion>,
+    },
+    /// A version whose component is missing or corrupt
+    MissingBlob {
+        handler: Handler,
+        version: HandlerVersion,
+        fallback: Option<HandlerVersion>,
+    },
+    /// A handler whose hash differs from the hash of its active version
+    HashMismatch {
+        handler: Handler,
+        version: HandlerVersion,
+    },
+    /// A stored component whose content does not match its digest
+    CorruptBlob(String),
+    /// A stored component no version references
+    OrphanedBlob(String),
+}
+
+impl Display for Inconsistency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Inconsistency::OrphanedProject(project) => write!(
+                f,
+                "Project {} ({}) belongs to the missing user {}",
+                project.name, project.id, project.user_id
+            ),
+            Inconsistency::OrphanedHandler(handler) => write!(
+                f,
+                "Handler {} ({}) belongs to the missing project {}",
+                handler.name, handler.id, handler.project_id
+            ),
+            Inconsistency::OrphanedVersion(version) => write!(
+                f,
+                "Version {} ({}) belongs to the missing handler {}",
+                version.number, version.id, version.handler_id
+            ),
+            Inconsistency::MissingVersion {
+                handler, number, ..
+            } => write!(
+                f,
+                "Handler {} ({}) references the missing version {}",
+                handler.name, handler.id, number
+            ),
+            Inconsistency::MissingBlob {
+                handler, version, ..
+            } => write!(
+                f,
+                "Version {} of handler {} ({}) references the missing component {}",
+                version.number, handler.name, handler.id, version.blob
+            ),
+            Inconsistency::HashMismatch { handler, version } => write!(
+                f,
+                "Handler {} ({}) has the hash {} but its active version {} has the hash {}",
+                handler.name, handler.id, handler.hash, version.number, version.hash
+            ),
+            Inconsistency::CorruptBlob(digest) => {
+                write!(f, "Component {} does not match its digest", digest)
+            }
+            Inconsistency::OrphanedBlob(digest) => {
+                write!(f, "Component {} is not referenced by any version", digest)
+            }
+        }
+    }
+}
+
+/// Cross-checks the database against the wasmstore
+#[derive(Debug, Clone)]
+pub struct ConsistencyChecker {
+    users: UserRepository,
+    projects: ProjectRepository,
+    handlers: HandlerRepository,
+    versions: HandlerVersionRepository,
+    wasmstore: WasmStore,
+}
+
+impl ConsistencyChecker {
+    pub fn new(
+        users: UserRepository,
+        projects: ProjectRepository,
+        handlers: HandlerRepository,
+        versions: HandlerVersionRepository,
+        wasmstore: WasmStore,
+    ) -> Self {
+        Self {
+            users,
+            projects,
+            handlers,
+            versions,
+            wasmstore,
+        }
+    }
+
+    /// Finds all inconsistencies. Records belonging to an orphaned parent are only reported
+    /// through their parent, since repairing the parent removes them as well.
+    pub fn check(&self) -> Result<Vec<Inconsistency>, Error> {
+        // Listing the blobs first ensures that a deploy running in between is either seen
+        // completely or within the grace period
+        let blobs = self.wasmstore.blobs()?;
+        let projects = self.projects.all()?;
+        let handlers = self.handlers.all()?;
+        let versions = self.versions.all()?;
+        let now = SystemTime::now();
+
+        let mut inconsistencies = Vec::new();
+
+        let mut project_ids = HashSet::new();
+        let mut orphaned_project_ids = HashSet::new();
+        for project in projects {
+            if self.users.read(&project.user_id)?.is_some() {
+                project_ids.insert(project.id);
+            } else {
+                orphaned_project_ids.insert(project.id.clone());
+                inconsistencies.push(Inconsistency::OrphanedProject(project));
+            }
+        }
+
+        let mut valid_handlers = Vec::new();
+        let mut skipped_handler_ids = HashSet::new();
+        for handler in handlers {
+            if project_ids.contains(&handler.project_id) {
+                valid_handlers.push(handler);
+                continue;
+            }
+            skipped_handler_ids.insert(handler.id.clone());
+            if !orphaned_project_ids.contains(&handler.project_id) {
+                inconsistencies.push(Inconsistency::OrphanedHandler(handler));
+            }
+        }
+
+        let mut intact_blobs = HashSet::new();
+        let mut corrupt_blobs = HashSet::new();
+        for blob in &blobs {
+            if self.wasmstore.verify(&blob.digest)? {
+                intact_blobs.insert(blob.digest.clone());
+            } else {
+                corrupt_blobs.insert(blob.digest.clone());
+            }
+        }
+
+        let referenced_blobs: HashSet<String> = versions.iter().map(|v| v.blob.clone()).collect();
+        let mut handler_versions: HashMap<String, Vec<HandlerVersion>> = HashMap::new();
+        for version in versions {
+            if skipped_handler_ids.contains(&version.handler_id) {
+                continue;
+            }
+            if !valid_handlers.iter().any(|h| h.id == version.handler_id) {
+                inconsistencies.push(Inconsistency::OrphanedVersion(version));
+                continue;
+            }
+            handler_versions
+                .entry(version.handler_id.clone())
+                .or_default()
+                .push(version);
+        }
+
+        for handler in valid_handlers {
+            let versions = handler_versions.remove(&handler.id).unwrap_or_default();
+            // Versions are ordered newest first
+            let fallback = |broken: i32| {
+                versions
+                    .iter()
+                    .filter(|v| v.number != broken && Some(v.number) != handler.canary_version)
+                    .find(|v| intact_blobs.contains(&v.blob))
+                    .cloned()
+            };
+
+            let numbers = std::iter::once(handler.version).chain(handler.canary_version);
+            for number in numbers {
+                if !versions.iter().any(|v| v.number == number) {
+                    inconsistencies.push(Inconsistency::MissingVersion {
+                        handler: handler.clone(),
+                        number,
+                        fallback: fallback(number),
+                    });
+                }
+            }
+
+            for version in &versions {
+                if !intact_blobs.contains(&version.blob) {
+                    inconsistencies.push(Inconsistency::MissingBlob {
+                        handler: handler.clone(),
+                        version: version.clone(),
+                        fallback: fallback(version.number),
+                    });
+                } else if version.number == handler.version && version.hash != handler.hash {
+                    inconsistencies.push(Inconsistency::HashMismatch {
+                        handler: handler.clone(),
+                        version: version.clone(),
+                    });
+                }
+            }
+        }
+
+        for blob in blobs {
+            if corrupt_blobs.contains(&blob.digest) {
+                inconsistencies.push(Inconsistency::CorruptBlob(blob.digest));
+                continue;
+            }
+            let expired = now
+                .duration_since(blob.modified)
+                .is_ok_and(|age| age > GRACE_PERIOD);
+            if expired && !referenced_blobs.contains(&blob.digest) {
+                inconsistencies.push(Inconsistency::OrphanedBlob(blob.digest));
+            }
+        }
+
+        Ok(inconsistencies)
+    }
+
+    /// Repairs the inconsistency and returns whether it could be repaired. A handler whose
+    /// active version is broken is rolled back to its newest intact version, without one it
+    /// has to be deployed again.
+    pub fn repair(&self, inconsistency: &Inconsistency) -> Result<bool, Error> {
+        match inconsistency {
+            Inconsistency::OrphanedProject(project) => {
+                self.projects.delete(&project.id)?;
+            }
+            Inconsistency::OrphanedHandler(handler) => {
+                self.handlers.delete(&handler.id)?;
+            }
+            Inconsistency::OrphanedVersion(version) => {
+                self.versions.delete(&version.id)?;
+            }
+            Inconsistency::MissingVersion {
+                handler,
+                number,
+                fallback,
+            } => {
+                if !self.replace_version(handler, *number, fallback)? {
+                    return Ok(false);
+                }
+            }
+            Inconsistency::MissingBlob {
+                handler,
+                version,
+                fallback,
+            } => {
+                if !self.replace_version(handler, version.number, fallback)? {
+                    return Ok(false);
+                }
+                self.versions.delete(&version.id)?;
+            }
+            Inconsistency::HashMismatch { handler, version } => {
+                self.handlers.activate(handler, version, None)?;
+            }
+            Inconsistency::CorruptBlob(digest) | Inconsistency::OrphanedBlob(digest) => {
+                self.wasmstore.delete(digest)?;
+            }
+        }
+
+        Ok(true)
+    }
+
+    /// Stops using the version `number` on the handler and returns false if the handler would
+    /// be left without an active version
+    fn replace_version(
+        &self,
+        handler: &Handler,
+        number: i32,
+        fallback: &Option<HandlerVersion>,
+    ) -> Result<bool, Error> {
+        if handler.canary_version == Some(number) {
+            self.handlers.stop_canary(handler, None)?;
+        } else if handler.version == number {
+            let Some(fallback) = fallback else {
+                return Ok(false);
+            };
+            self.handlers.activate(handler, fallback, None)?;
+        }
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{repository::user::User, wasmstore::Blob};
+    use common::dtos::Language;
+    use faux::when;
+
+    const INTACT: &str = "2c26b46b68ffc68ff99b453c1d30413413422d706483bfa0f98a5e886266e7ae";
+    const CORRUPT: &str = "fcde2b2edba56bf408601fb721fe9b5c338d10ee429ea04fae5511b68fbf8fb9";
+    const ORPHANED: &str = "baa5a0964d3320fbc0c6a922140453c8513ea24ab8fd0577034804a967248096";
+
+    struct Fixture {
+        project: Project,
+        handler: Handler,
+        versions: Vec<HandlerVersion>,
+    }
+
+    fn fixture() -> Fixture {
+        let project = Project::new("PROJECT_NAME".to_string(), "user_id".to_string());
+        let mut handler = Handler::new(
+            "handler_1".to_string(),
+            Language::Rust,
+            "hash_2".to_string(),
+            project.id.clone(),
+            "prod".to_string(),
+        );
+        handler.version = 2;
+        let versions = vec![
+            HandlerVersion::new(
+                handler.id.clone(),
+                2,
+                "hash_2".to_string(),
+                CORRUPT.to_string(),
+                0,
+                project.user_id.clone(),
+            ),
+            HandlerVersion::new(
+                handler.id.clone(),
+                1,
+                "hash_1".to_string(),
+                INTACT.to_string(),
+                0,
+                project.user_id.clone(),
+            ),
+        ];
+        Fixture {
+            project,
+            handler,
+            versions,
+        }
+    }
+
+    fn expired(digest: &str) -> Blob {
+        Blob {
+            digest: digest.to_string(),
+            modified: SystemTime::now() - GRACE_PERIOD * 2,
+        }
+    }
+
+    #[test]
+    fn check_ok() -> anyhow::Result<()> {
+        let Fixture {
+            project,
+            handler,
+            versions,
+        } = fixture();
+        let orphaned_project = Project::new("ORPHANED".to_string(), "deleted_user".to_string());
+        let orphaned_version = HandlerVersion::new(
+            "deleted_handler".to_string(),
+            1,
+            "hash".to_string(),
+            INTACT.to_string(),
+            0,
+            project.user_id.clone(),
+        );
+
+        // -------------------------------------------------------------------------------------
+
+        let mut users_mock = UserRepository::faux();
+        when!(users_mock.read("user_id"))
+            .once()
+            .then_return(Ok(Some(User::default())));
+        when!(users_mock.read("deleted_user"))
+            .once()
+            .then_return(Ok(None));
+
+        let mut projects_mock = ProjectRepository::faux();
+        when!(projects_mock.all())
+            .once()
+            .then_return(Ok(vec![project, orphaned_project.clone()]));
+
+        let mut handlers_mock = HandlerRepository::faux();
+        when!(handlers_mock.all())
+            .once()
+            .then_return(Ok(vec![handler.clone()]));
+
+        let mut versions_mock = HandlerVersionRepository::faux();
+        let mut all_versions = versions.clone();
+        all_versions.push(orphaned_version.clone());
+        when!(versions_mock.all())
+            .once()
+            .then_return(Ok(all_versions));
+
+        let mut wasmstore_mock = WasmStore::faux();
+        when!(wasmstore_mock.blobs()).once().then_return(Ok(vec![
+            expired(INTACT),
+            expired(CORRUPT),
+            expired(ORPHANED),
+        ]));
+        when!(wasmstore_mock.verify(CORRUPT))
+            .once()
+            .then_return(Ok(false));
+        when!(wasmstore_mock.verify(INTACT))
+            .once()
+            .then_return(Ok(true));
+        when!(wasmstore_mock.verify(ORPHANED))
+            .once()
+            .then_return(Ok(true));
+
+        // -------------------------------------------------------------------------------------
+
+        let checker = ConsistencyChecker::new(
+            users_mock,
+            projects_mock,
+            handlers_mock,
+            versions_mock,
+            wasmstore_mock,
+        );
+        let inconsistencies = checker.check()?;
+
+        assert_eq!(
+            vec![
+                Inconsistency::OrphanedProject(orphaned_project),
+                Inconsistency::OrphanedVersion(orphaned_version),
+                Inconsistency::MissingBlob {
+                    handler,
+                    version: versions[0].clone(),
+                    fallback: Some(versions[1].clone()),
+                },
+                Inconsistency::CorruptBlob(CORRUPT.to_string()),
+                Inconsistency::OrphanedBlob(ORPHANED.to_string()),
+            ],
+            inconsistencies
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn repair_missing_blob_rolls_back() -> anyhow::Result<()> {
+        let Fixture {
+            handler, versions, ..
+        } = fixture();
+        let inconsistency = Inconsistency::MissingBlob {
+            handler: handler.clone(),
+            version: versions[0].clone(),
+            fallback: Some(versions[1].clone()),
+        };
+
+        // -------------------------------------------------------------------------------------
+
+        let mut handlers_mock = HandlerRepository::faux();
+        when!(handlers_mock.activate(handler, versions[1].clone(), None))
+            .once()
+            .then_return(Ok(()));
+
+        let mut versions_mock = HandlerVersionRepository::faux();
+        let version_id = versions[0].id.clone();
+        when!(versions_mock.delete(_ = faux::from_fn!(move |id: &&str| *id == version_id)))
+            .once()
+            .then_return(Ok(()));
+
+        // -------------------------------------------------------------------------------------
+
+        let checker = ConsistencyChecker::new(
+            UserRepository::faux(),
+            ProjectRepository::faux(),
+            handlers_mock,
+            versions_mock,
+            WasmStore::faux(),
+        );
+
+        assert!(checker.repair(&inconsistency)?);
+        Ok(())
+    }
+
+    #[test]
+    fn repair_missing_blob_without_fallback() -> anyhow::Result<()> {
+        let Fixture {
+            handler, versions, ..
+        } = fixture();
+        let inconsistency = Inconsistency::MissingBlob {
+            handler,
+            version: versions[0].clone(),
+            fallback: None,
+        };
+
+        // -------------------------------------------------------------------------------------
+
+        let checker = ConsistencyChecker::new(
+            UserRepository::faux(),
+            ProjectRepository::faux(),
+            HandlerRepository::faux(),
+            HandlerVersionRepository::faux(),
+            WasmStore::faux(),
+        );
+
+        assert!(!checker.repair(&inconsistency)?);
+        Ok(())
+    }
+}
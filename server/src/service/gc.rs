@@ -0,0 +1,249 @@
+use crate::{
+    errors::Error,
+    repository::version::HandlerVersionRepository,
+    wasmstore::{Blob, Object, WasmStore},
+};
+use std::{
+    collections::HashSet,
+    time::{Duration, SystemTime},
+};
+
+/// Blobs younger than this are kept, because a deploy may store a blob before the version
+/// referencing it is written
+pub(super) const GRACE_PERIOD: Duration = Duration::from_secs(10 * 60);
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CollectedGarbage {
+    pub kept: usize,
+    pub removed: Vec<String>,
+    /// The temporary files of writes a crash interrupted
+    pub temp_files: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct GarbageCollector {
+    versions: HandlerVersionRepository,
+    wasmstore: WasmStore,
+}
+
+impl GarbageCollector {
+    pub fn new(versions: HandlerVersionRepository, wasmstore: WasmStore) -> Self {
+        Self {
+            versions,
+            wasmstore,
+        }
+    }
+
+    /// Removes all blobs no handler version references, and the temporary files of writes a
+    /// crash interrupted. With `dry_run` nothing is removed.
+    pub fn collect(&self, dry_run: bool) -> Result<CollectedGarbage, Error> {
+        let mut garbage = self.sweep(|_| true, dry_run)?;
+        garbage.temp_files = self.sweep_temp_files(dry_run)?;
+        Ok(garbage)
+    }
+
+    /// Removes the given blobs unless a handler version still references them, like the
+    /// components of a deleted project which other projects share
+    pub fn collect_blobs(&self, digests: &HashSet<String>) -> Result<CollectedGarbage, Error> {
+        self.sweep(|digest| digests.contains(digest), false)
+    }
+
+    fn sweep(
+        &self,
+        candidate: impl Fn(&str) -> bool,
+        dry_run: bool,
+    ) -> Result<CollectedGarbage, Error> {
+        // Listing the blobs before marking ensures that a blob written in between is either
+        // marked or within the grace period
+        let blobs = self.wasmstore.blobs()?;
+        let referenced = self.versions.blobs()?;
+        let now = SystemTime::now();
+
+        let mut garbage = CollectedGarbage::default();
+        for Blob { digest, modified } in blobs {
+            if !candidate(&digest) {
+                continue;
+            }
+            let expired = now
+                .duration_since(modified)
+                .is_ok_and(|age| age > GRACE_PERIOD);
+            if referenced.contains(&digest) || !expired {
+                garbage.kept += 1;
+                continue;
+            }
+
+            if !dry_run {
+                self.wasmstore.delete(&digest)?;
+            }
+            garbage.removed.push(digest);
+        }
+
+        Ok(garbage)
+    }
+
+    /// Removes the temporary files older than the grace period, younger ones may belong to a
+    /// write in progress
+    fn sweep_temp_files(&self, dry_run: bool) -> Result<Vec<String>, Error> {
+        let now = SystemTime::now();
+        let mut removed = Vec::new();
+        for Object { key, modified } in self.wasmstore.temp_files()? {
+            let expired = now
+                .duration_since(modified)
+                .is_ok_and(|age| age > GRACE_PERIOD);
+            if !expired {
+                continue;
+            }
+            if !dry_run {
+                self.wasmstore.delete_temp_file(&key)?;
+            }
+            removed.push(key);
+        }
+        Ok(removed)
+    }
+
+    /// Moves components stored as `{version_id}.wasm` to their content address and deletes
+    /// the ones no version references anymore
+    pub fn migrate_legacy_files(&self) -> Result<usize, Error> {
+        let legacy_files = self.wasmstore.legacy_files()?;
+        let referenced = self.versions.blobs()?;
+        for id in &legacy_files {
+            if !referenced.contains(id) {
+                self.wasmstore.delete_legacy_file(id)?;
+                continue;
+            }
+            let digest = self.wasmstore.migrate_legacy_file(id)?;
+            self.versions.replace_blob(id, &digest)?;
+        }
+
+        Ok(legacy_files.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use faux::when;
+
+    const REFERENCED: &str = "2c26b46b68ffc68ff99b453c1d30413413422d706483bfa0f98a5e886266e7ae";
+    const UNREFERENCED: &str = "fcde2b2edba56bf408601fb721fe9b5c338d10ee429ea04fae5511b68fbf8fb9";
+    const FRESH: &str = "baa5a0964d3320fbc0c6a922140453c8513ea24ab8fd0577034804a967248096";
+
+    const STALE_TEMP_FILE: &str = "sha256/stale.wasm.Oeb3aeGh.tmp";
+
+    fn temp_files() -> Vec<Object> {
+        vec![
+            Object {
+                key: STALE_TEMP_FILE.to_string(),
+                modified: SystemTime::now() - GRACE_PERIOD * 2,
+            },
+            Object {
+                key: "sha256/written.wasm.ieH6ohth.tmp".to_string(),
+                modified: SystemTime::now(),
+            },
+        ]
+    }
+
+    fn blobs() -> Vec<Blob> {
+        let expired = SystemTime::now() - GRACE_PERIOD * 2;
+        vec![
+            Blob {
+                digest: REFERENCED.to_string(),
+                modified: expired,
+            },
+            Blob {
+                digest: UNREFERENCED.to_string(),
+                modified: expired,
+            },
+            Blob {
+                digest: FRESH.to_string(),
+                modified: SystemTime::now(),
+            },
+        ]
+    }
+
+    #[test]
+    fn collect_ok() -> anyhow::Result<()> {
+        let mut versions_mock = HandlerVersionRepository::faux();
+        when!(versions_mock.blobs())
+            .once()
+            .then_return(Ok(HashSet::from([REFERENCED.to_string()])));
+
+        let mut wasmstore_mock = WasmStore::faux();
+        when!(wasmstore_mock.blobs())
+            .once()
+            .then_return(Ok(blobs()));
+        when!(wasmstore_mock.delete(UNREFERENCED))
+            .once()
+            .then_return(Ok(()));
+        when!(wasmstore_mock.temp_files())
+            .once()
+            .then_return(Ok(temp_files()));
+        when!(wasmstore_mock.delete_temp_file(STALE_TEMP_FILE))
+            .once()
+            .then_return(Ok(()));
+
+        // -------------------------------------------------------------------------------------
+
+        let gc = GarbageCollector::new(versions_mock, wasmstore_mock);
+        let garbage = gc.collect(false)?;
+
+        assert_eq!(2, garbage.kept);
+        assert_eq!(vec![UNREFERENCED.to_string()], garbage.removed);
+        assert_eq!(vec![STALE_TEMP_FILE.to_string()], garbage.temp_files);
+        Ok(())
+    }
+
+    #[test]
+    fn collect_blobs_ok() -> anyhow::Result<()> {
+        let mut versions_mock = HandlerVersionRepository::faux();
+        when!(versions_mock.blobs())
+            .once()
+            .then_return(Ok(HashSet::from([REFERENCED.to_string()])));
+
+        let mut wasmstore_mock = WasmStore::faux();
+        when!(wasmstore_mock.blobs())
+            .once()
+            .then_return(Ok(blobs()));
+        when!(wasmstore_mock.delete(UNREFERENCED))
+            .once()
+            .then_return(Ok(()));
+
+        // -------------------------------------------------------------------------------------
+
+        let gc = GarbageCollector::new(versions_mock, wasmstore_mock);
+        let garbage = gc.collect_blobs(&HashSet::from([
+            REFERENCED.to_string(),
+            UNREFERENCED.to_string(),
+            FRESH.to_string(),
+        ]))?;
+
+        assert_eq!(2, garbage.kept);
+        assert_eq!(vec![UNREFERENCED.to_string()], garbage.removed);
+        Ok(())
+    }
+
+    #[test]
+    fn collect_dry_run() -> anyhow::Result<()> {
+        let mut versions_mock = HandlerVersionRepository::faux();
+        when!(versions_mock.blobs())
+            .once()
+            .then_return(Ok(HashSet::from([REFERENCED.to_string()])));
+
+        let mut wasmstore_mock = WasmStore::faux();
+        when!(wasmstore_mock.blobs())
+            .once()
+            .then_return(Ok(blobs()));
+        when!(wasmstore_mock.temp_files())
+            .once()
+            .then_return(Ok(temp_files()));
+
+        // -------------------------------------------------------------------------------------
+
+        let gc = GarbageCollector::new(versions_mock, wasmstore_mock);
+        let garbage = gc.collect(true)?;
+
+        assert_eq!(vec![UNREFERENCED.to_string()], garbage.removed);
+        assert_eq!(vec![STALE_TEMP_FILE.to_string()], garbage.temp_files);
+        Ok(())
+    }
+}
@@ -1,64 +1,252 @@
 use crate::{
     bindgen,
-    errors::Error::{self, HandlerNotFound, ProjectNotFound},
+    errors::Error::{
+        self, CanaryNotFound, HandlerNotFound, InvalidCanaryWeight, InvalidStage, ReservedName,
+        SameStage, StageNotFound, UntrustedSignature, VersionNotFound,
+    },
     repository::{
+        audit::AuditEvent,
         handler::{Handler, HandlerRepository},
-        project::ProjectRepository,
+        project::{Project, ProjectRepository},
+        status_code::StatusCodeRepository,
+        trusted_key::{TrustedKey, TrustedKeyRepository},
         user::User,
+        variable::StageVariableRepository,
+        version::{HandlerVersion, HandlerVersionRepository},
         Repository,
     },
+    service::{audit::Actor, org::authorized_project, quota::QuotaService, trash::TrashService},
     wasmstore::WasmStore,
 };
 use common::{
-    dtos::{GetHandlerDTO, Language},
-    hash,
+    dtos::{
+        AuditAction, GetCanaryDTO, GetHandlerDTO, GetHandlerVersionDTO, Language, Role,
+        VersionStatusCodesDTO,
+    },
+    hash, signature,
 };
+use rand::Rng;
+use std::collections::{HashMap, HashSet};
+
+const MAX_CANARY_WEIGHT: i32 = 100;
+
+/// Routes of a project, a handler with one of these names couldn't be addressed
+const RESERVED_HANDLER_NAMES: &[&str] = &["export", "keys", "quotas", "restore", "stages"];
+
+/// Fails if the handler name is taken by a route of the project
+pub fn validate_handler_name(handler_name: &str) -> Result<(), Error> {
+    if RESERVED_HANDLER_NAMES.contains(&handler_name) {
+        return Err(ReservedName(handler_name.to_string()));
+    }
+    Ok(())
+}
+
+/// Fails unless the stage name can be used as a segment of a path
+pub fn validate_stage(stage: &str) -> Result<(), Error> {
+    if stage.is_empty() || stage.contains('/') {
+        return Err(InvalidStage);
+    }
+    Ok(())
+}
+
+/// Fails unless the weight is a share of the requests in percent
+pub fn validate_canary_weight(weight: i32) -> Result<(), Error> {
+    if !(0..=MAX_CANARY_WEIGHT).contains(&weight) {
+        return Err(InvalidCanaryWeight);
+    }
+    Ok(())
+}
+
+/// Fails if the project has trusted keys and the data isn't signed by one of them
+pub fn verify_signature(
+    trusted_keys: &[TrustedKey],
+    data: &[u8],
+    signature: Option<&str>,
+) -> Result<(), Error> {
+    if !trusted_keys.is_empty()
+        && !signature.is_some_and(|signature| {
+            trusted_keys
+                .iter()
+                .any(|key| signature::verify(&key.public_key, data, signature))
+        })
+    {
+        return Err(UntrustedSignature);
+    }
+    Ok(())
+}
+
+/// Decides which version of a handler with a running canary release serves a request
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CanaryRouting {
+    /// Routes the canary weight in percent of the requests to the canary version
+    Weighted,
+    Canary,
+    Stable,
+}
+
+impl CanaryRouting {
+    fn use_canary(self, weight: i32) -> bool {
+        match self {
+            CanaryRouting::Weighted => rand::thread_rng().gen_range(0..100) < weight,
+            CanaryRouting::Canary => true,
+            CanaryRouting::Stable => false,
+        }
+    }
+}
+
+/// The version of a handler selected to serve a request
+pub struct HandlerComponent {
+    pub version: HandlerVersion,
+    pub wasm: Vec<u8>,
+    /// The environment variables of the handler's stage
+    pub variables: Vec<(String, String)>,
+}
 
 #[derive(Debug, Clone)]
 pub struct HandlerService {
     projects: ProjectRepository,
     handlers: HandlerRepository,
+    versions: HandlerVersionRepository,
+    status_codes: StatusCodeRepository,
+    variables: StageVariableRepository,
+    trusted_keys: TrustedKeyRepository,
     wasmstore: WasmStore,
+    trash: TrashService,
+    quotas: QuotaService,
 }
 
 impl HandlerService {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         projects: ProjectRepository,
         handlers: HandlerRepository,
+        versions: HandlerVersionRepository,
+        status_codes: StatusCodeRepository,
+        variables: StageVariableRepository,
+        trusted_keys: TrustedKeyRepository,
         wasmstore: WasmStore,
+        trash: TrashService,
+        quotas: QuotaService,
     ) -> Self {
         Self {
             projects,
             handlers,
+            versions,
+            status_codes,
+            variables,
+            trusted_keys,
             wasmstore,
+            trash,
+            quotas,
         }
     }
 
+    /// Deploys a new version of a handler. With a canary weight the new version of an existing
+    /// handler is deployed as a canary release instead of being activated.
+    ///
+    /// The component is stored before the version referencing it, and the handler and its
+    /// version are written in one transaction. If the transaction fails the handler stays
+    /// unchanged, and the unreferenced component is removed by the garbage collection.
+    ///
+    /// Once the project has trusted keys, the module must be signed by one of them. A handler
+    /// with the same name in the trash is purged, it can't be restored anymore. The module and
+    /// a new handler must fit into the quotas of the project.
+    #[allow(clippy::too_many_arguments)]
     pub fn create(
         &self,
-        user: &User,
+        user: &Actor,
         project_name: &str,
+        stage: &str,
         handler_name: String,
         wasm: &[u8],
+        signature: Option<&str>,
+        canary: Option<u8>,
     ) -> Result<(), Error> {
-        let project = self
-            .projects
-            .belonging_to_by_name(user, project_name)?
-            .ok_or(ProjectNotFound)?;
+        validate_stage(stage)?;
+        validate_handler_name(&handler_name)?;
+        if let Some(weight) = canary {
+            validate_canary_weight(weight.into())?;
+        }
+        let project = authorized_project(&self.projects, user, project_name, Role::Maintainer)?;
+        self.quotas.check_artifact(&project, wasm.len())?;
+        verify_signature(&self.trusted_keys.belonging_to(&project)?, wasm, signature)?;
         let old_handler = self
             .handlers
-            .belonging_to_by_name(&project, &handler_name)?;
+            .belonging_to_by_name(&project, stage, &handler_name)?;
+        if old_handler.is_none() {
+            self.quotas.check_handlers(&project, 1)?;
+        }
 
         let hash = hash::hash(wasm);
         let wasm = bindgen::create_component(wasm)?;
-        // FIXME Pass correct Language
-        let handler = Handler::new(handler_name, Language::Rust, hash, project.id);
-        self.handlers.create(&handler)?;
-
-        if let Some(old_handler) = old_handler {
-            self.wasmstore.update(&old_handler.id, &wasm)?;
-        } else {
-            self.wasmstore.create(&handler.id, &wasm)?;
+        self.quotas.check_storage(
+            &project,
+            &HashMap::from([(hash::hash(&wasm), wasm.len() as i64)]),
+        )?;
+        let blob = self.wasmstore.create(&wasm)?;
+
+        let Some(old_handler) = old_handler else {
+            self.purge_trashed(&project, stage, &handler_name)?;
+            // FIXME Pass correct Language
+            let handler = Handler::new(
+                handler_name,
+                Language::Rust,
+                hash,
+                project.id,
+                stage.to_string(),
+            );
+            let version = HandlerVersion::new(
+                handler.id.clone(),
+                handler.version,
+                handler.hash.clone(),
+                blob,
+                wasm.len() as i64,
+                user.id.clone(),
+            );
+
+            let event = AuditEvent {
+                new_hash: Some(handler.hash.clone()),
+                details: Some(format!("version {}", version.number)),
+                ..handler_event(user, AuditAction::HandlerDeploy, &handler, project_name)
+            };
+            self.handlers.deploy(&handler, &version, &event)?;
+            return Ok(());
+        };
+
+        let latest = self.versions.latest(&old_handler)?;
+        let version = HandlerVersion::new(
+            old_handler.id.clone(),
+            latest.map_or(1, |version| version.number + 1),
+            hash,
+            blob,
+            wasm.len() as i64,
+            user.id.clone(),
+        );
+
+        let event = AuditEvent {
+            old_hash: Some(old_handler.hash.clone()),
+            new_hash: Some(version.hash.clone()),
+            details: Some(match canary {
+                Some(weight) => format!("canary version {} with weight {}", version.number, weight),
+                None => format!("version {}", version.number),
+            }),
+            ..handler_event(user, AuditAction::HandlerDeploy, &old_handler, project_name)
+        };
+        match canary {
+            Some(weight) => {
+                self.handlers
+                    .deploy_canary(&old_handler, &version, weight.into(), &event)?;
+            }
+            None => {
+                let handler = Handler {
+                    hash: version.hash.clone(),
+                    version: version.number,
+                    canary_version: None,
+                    canary_weight: 0,
+                    ..old_handler
+                };
+                self.handlers.deploy(&handler, &version, &event)?;
+            }
         }
 
         Ok(())
@@ -68,108 +256,780 @@ impl HandlerService {
         &self,
         user: &User,
         project_name: &str,
-        handler_name: String,
+        stage: &str,
+        handler_name: &str,
     ) -> Result<GetHandlerDTO, Error> {
-        let project = self
-            .projects
-            .belonging_to_by_name(user, project_name)?
-            .ok_or(ProjectNotFound)?;
+        let handler = self.get_handler(user, project_name, stage, handler_name, Role::Viewer)?;
+        Ok(handler.into())
+    }
+
+    /// Moves the handler to the trash. It's unreachable until it is restored or purged with
+    /// all its versions after the retention period.
+    pub fn delete(
+        &self,
+        user: &Actor,
+        project_name: &str,
+        stage: &str,
+        handler_name: &str,
+    ) -> Result<(), Error> {
+        let handler =
+            self.get_handler(user, project_name, stage, handler_name, Role::Maintainer)?;
+        let event = AuditEvent {
+            old_hash: Some(handler.hash.clone()),
+            ..handler_event(user, AuditAction::HandlerDelete, &handler, project_name)
+        };
+        self.handlers.trash(&handler, &event)?;
+        Ok(())
+    }
 
+    /// Takes the handler out of the trash, it serves requests with its active version again
+    pub fn restore(
+        &self,
+        user: &Actor,
+        project_name: &str,
+        stage: &str,
+        handler_name: &str,
+    ) -> Result<GetHandlerDTO, Error> {
+        let project = authorized_project(&self.projects, user, project_name, Role::Maintainer)?;
         let handler = self
             .handlers
-            .belonging_to_by_name(&project, &handler_name)?
-            .ok_or(Error::HandlerNotFound)?;
+            .trashed_by_name(&project, stage, handler_name)?
+            .ok_or(HandlerNotFound)?;
+        self.quotas.check_handlers(&project, 1)?;
+        let event = AuditEvent {
+            new_hash: Some(handler.hash.clone()),
+            ..handler_event(user, AuditAction::HandlerRestore, &handler, project_name)
+        };
+        self.handlers.restore(&handler, &event)?;
 
-        Ok(handler.into())
+        Ok(Handler {
+            deleted_at: None,
+            ..handler
+        }
+        .into())
+    }
+
+    pub fn versions(
+        &self,
+        user: &User,
+        project_name: &str,
+        stage: &str,
+        handler_name: &str,
+    ) -> Result<Vec<GetHandlerVersionDTO>, Error> {
+        let handler = self.get_handler(user, project_name, stage, handler_name, Role::Viewer)?;
+
+        let versions = self
+            .versions
+            .belonging_to_with_login(&handler)?
+            .into_iter()
+            .map(|(version, login)| GetHandlerVersionDTO {
+                number: version.number,
+                hash: version.hash,
+                deployed_by: login,
+                deployed_at: version.created_at,
+                active: version.number == handler.version,
+            })
+            .collect();
+
+        Ok(versions)
+    }
+
+    /// Activates an already deployed version of a handler. Without a version number the
+    /// version deployed before the active one is used.
+    pub fn rollback(
+        &self,
+        user: &Actor,
+        project_name: &str,
+        stage: &str,
+        handler_name: &str,
+        number: Option<i32>,
+    ) -> Result<GetHandlerDTO, Error> {
+        let handler =
+            self.get_handler(user, project_name, stage, handler_name, Role::Maintainer)?;
+
+        let version = match number {
+            Some(number) => self.versions.belonging_to_by_number(&handler, number)?,
+            None => self.versions.previous(&handler)?,
+        }
+        .ok_or(VersionNotFound)?;
+
+        let event = AuditEvent {
+            old_hash: Some(handler.hash.clone()),
+            new_hash: Some(version.hash.clone()),
+            details: Some(format!("version {}", version.number)),
+            ..handler_event(user, AuditAction::HandlerRollback, &handler, project_name)
+        };
+        self.handlers.activate(&handler, &version, Some(&event))?;
+
+        Ok(Handler {
+            hash: version.hash,
+            version: version.number,
+            ..handler
+        }
+        .into())
+    }
+
+    pub fn canary(
+        &self,
+        user: &User,
+        project_name: &str,
+        stage: &str,
+        handler_name: &str,
+    ) -> Result<GetCanaryDTO, Error> {
+        let handler = self.get_handler(user, project_name, stage, handler_name, Role::Viewer)?;
+        let canary = handler.canary_version.ok_or(CanaryNotFound)?;
+
+        Ok(GetCanaryDTO {
+            weight: handler.canary_weight as u8,
+            stable: self.version_status_codes(&handler, handler.version)?,
+            canary: self.version_status_codes(&handler, canary)?,
+        })
+    }
+
+    /// Makes the canary version the active version of the handler
+    pub fn promote_canary(
+        &self,
+        user: &Actor,
+        project_name: &str,
+        stage: &str,
+        handler_name: &str,
+    ) -> Result<GetHandlerDTO, Error> {
+        let handler =
+            self.get_handler(user, project_name, stage, handler_name, Role::Maintainer)?;
+        let canary = handler.canary_version.ok_or(CanaryNotFound)?;
+        let canary = self
+            .versions
+            .belonging_to_by_number(&handler, canary)?
+            .ok_or(VersionNotFound)?;
+
+        let event = AuditEvent {
+            old_hash: Some(handler.hash.clone()),
+            new_hash: Some(canary.hash.clone()),
+            details: Some(format!("version {}", canary.number)),
+            ..handler_event(user, AuditAction::CanaryPromote, &handler, project_name)
+        };
+        self.handlers.promote_canary(&handler, &canary, &event)?;
+
+        Ok(Handler {
+            hash: canary.hash,
+            version: canary.number,
+            canary_version: None,
+            canary_weight: 0,
+            ..handler
+        }
+        .into())
+    }
+
+    /// Routes all traffic back to the active version. The canary version is kept.
+    pub fn abort_canary(
+        &self,
+        user: &Actor,
+        project_name: &str,
+        stage: &str,
+        handler_name: &str,
+    ) -> Result<GetHandlerDTO, Error> {
+        let handler =
+            self.get_handler(user, project_name, stage, handler_name, Role::Maintainer)?;
+        let Some(canary) = handler.canary_version else {
+            return Err(CanaryNotFound);
+        };
+
+        let event = AuditEvent {
+            new_hash: Some(handler.hash.clone()),
+            details: Some(format!("canary version {}", canary)),
+            ..handler_event(user, AuditAction::CanaryAbort, &handler, project_name)
+        };
+        self.handlers.stop_canary(&handler, Some(&event))?;
+
+        Ok(Handler {
+            canary_version: None,
+            canary_weight: 0,
+            ..handler
+        }
+        .into())
+    }
+
+    /// Copies the active versions of all handlers of a stage to another stage. The new versions
+    /// reference the same blobs, so the target stage runs exactly the same artifacts.
+    pub fn promote(
+        &self,
+        user: &Actor,
+        project_name: &str,
+        source: &str,
+        target: &str,
+    ) -> Result<Vec<GetHandlerDTO>, Error> {
+        validate_stage(target)?;
+        if source == target {
+            return Err(SameStage);
+        }
+
+        let project = authorized_project(&self.projects, user, project_name, Role::Maintainer)?;
+        let handlers = self.handlers.belonging_to_stage(&project, source)?;
+        if handlers.is_empty() {
+            return Err(StageNotFound);
+        }
+        let existing: HashSet<String> = self
+            .handlers
+            .belonging_to_stage(&project, target)?
+            .into_iter()
+            .map(|handler| handler.name)
+            .collect();
+        let additional = handlers
+            .iter()
+            .filter(|handler| !existing.contains(&handler.name))
+            .count();
+        self.quotas.check_handlers(&project, additional as i64)?;
+
+        let mut promoted = Vec::new();
+        for handler in handlers {
+            let existing = self
+                .handlers
+                .belonging_to_by_name(&project, target, &handler.name)?;
+            let old_hash = existing.as_ref().map(|existing| existing.hash.clone());
+            let target_handler = match existing {
+                Some(target_handler) if target_handler.hash == handler.hash => {
+                    promoted.push(target_handler.into());
+                    continue;
+                }
+                Some(target_handler) => target_handler,
+                None => {
+                    self.purge_trashed(&project, target, &handler.name)?;
+                    Handler::new(
+                        handler.name.clone(),
+                        handler.language,
+                        handler.hash.clone(),
+                        project.id.clone(),
+                        target.to_string(),
+                    )
+                }
+            };
+
+            let source_version = self
+                .versions
+                .belonging_to_by_number(&handler, handler.version)?
+                .ok_or(VersionNotFound)?;
+
+            let latest = self.versions.latest(&target_handler)?;
+            let version = HandlerVersion::new(
+                target_handler.id.clone(),
+                latest.map_or(1, |version| version.number + 1),
+                handler.hash,
+                source_version.blob,
+                source_version.size,
+                user.id.clone(),
+            );
+            let target_handler = Handler {
+                hash: version.hash.clone(),
+                version: version.number,
+                canary_version: None,
+                canary_weight: 0,
+                ..target_handler
+            };
+
+            let event = AuditEvent {
+                old_hash,
+                new_hash: Some(target_handler.hash.clone()),
+                details: Some(format!("from stage {}", source)),
+                ..handler_event(
+                    user,
+                    AuditAction::StagePromote,
+                    &target_handler,
+                    project_name,
+                )
+            };
+            self.handlers.deploy(&target_handler, &version, &event)?;
+            promoted.push(target_handler.into());
+        }
+
+        Ok(promoted)
     }
 
-    pub fn delete(&self, user: &User, project_name: &str, handler_name: &str) -> Result<(), Error> {
+    /// Reads the component of the version serving the request. The invocation counts against
+    /// the quotas of the project.
+    pub fn read_component(
+        &self,
+        handler_id: &str,
+        routing: CanaryRouting,
+    ) -> Result<HandlerComponent, Error> {
+        let handler = self.handlers.read(handler_id)?.ok_or(HandlerNotFound)?;
         let project = self
             .projects
-            .belonging_to_by_name(user, project_name)?
-            .ok_or(ProjectNotFound)?;
+            .read(&handler.project_id)?
+            .ok_or(HandlerNotFound)?;
+        self.quotas.count_invocation(&project)?;
+        let number = match handler.canary_version {
+            Some(canary) if routing.use_canary(handler.canary_weight) => canary,
+            _ => handler.version,
+        };
+
+        let version = self
+            .versions
+            .belonging_to_by_number(&handler, number)?
+            .ok_or(HandlerNotFound)?;
+        let wasm = self.wasmstore.read(&version.blob)?;
+        let variables = self
+            .variables
+            .belonging_to_stage(&handler.project_id, &handler.stage)?
+            .into_iter()
+            .map(|variable| (variable.name, variable.value))
+            .collect();
+
+        Ok(HandlerComponent {
+            version,
+            wasm,
+            variables,
+        })
+    }
+
+    pub fn record_status_code(&self, version: &HandlerVersion, status: u16) -> Result<(), Error> {
+        self.status_codes.increment(version, status)?;
+        Ok(())
+    }
+
+    fn version_status_codes(
+        &self,
+        handler: &Handler,
+        number: i32,
+    ) -> Result<VersionStatusCodesDTO, Error> {
+        let version = self
+            .versions
+            .belonging_to_by_number(handler, number)?
+            .ok_or(VersionNotFound)?;
+
+        let status_codes = self
+            .status_codes
+            .belonging_to(&version)?
+            .into_iter()
+            .map(|count| (count.status_code as u16, count.count))
+            .collect();
+
+        Ok(VersionStatusCodesDTO {
+            number,
+            status_codes,
+        })
+    }
+
+    /// Purges a handler with the name from the trash, so a new one can take its name
+    fn purge_trashed(
+        &self,
+        project: &Project,
+        stage: &str,
+        handler_name: &str,
+    ) -> Result<(), Error> {
+        if let Some(trashed) = self
+            .handlers
+            .trashed_by_name(project, stage, handler_name)?
+        {
+            self.trash.purge_handler(&trashed)?;
+        }
+        Ok(())
+    }
+
+    /// The handler, if the role of the user in its project permits what requires `required`
+    fn get_handler(
+        &self,
+        user: &User,
+        project_name: &str,
+        stage: &str,
+        handler_name: &str,
+        required: Role,
+    ) -> Result<Handler, Error> {
+        let project = authorized_project(&self.projects, user, project_name, required)?;
 
         let handler = self
             .handlers
-            .belonging_to_by_name(&project, handler_name)?
+            .belonging_to_by_name(&project, stage, handler_name)?
             .ok_or(HandlerNotFound)?;
 
-        self.handlers.delete(&handler.id)?;
-        self.wasmstore.delete(&handler.id)?;
+        Ok(handler)
+    }
+}
 
-        Ok(())
+/// An event of the action on the handler, whose project is named as in the request
+fn handler_event(
+    user: &Actor,
+    action: AuditAction,
+    handler: &Handler,
+    project_name: &str,
+) -> AuditEvent {
+    AuditEvent {
+        project_id: Some(handler.project_id.clone()),
+        project: Some(project_name.to_string()),
+        stage: Some(handler.stage.clone()),
+        target: Some(handler.name.clone()),
+        ..user.event(action)
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::HandlerService;
+    use super::{
+        validate_handler_name, CanaryRouting, HandlerNotFound, HandlerService, InvalidStage,
+        ReservedName, UntrustedSignature,
+    };
     use crate::{
+        identity::Identity,
         repository::{
-            handler::HandlerRepository,
+            audit::AuditEvent,
+            handler::{Handler, HandlerRepository},
             project::{Project, ProjectRepository},
+            status_code::StatusCodeRepository,
+            trusted_key::{TrustedKey, TrustedKeyRepository},
             user::User,
+            variable::StageVariableRepository,
+            version::{HandlerVersion, HandlerVersionRepository},
         },
+        service::{audit::Actor, quota::QuotaService, trash::TrashService},
         wasmstore::WasmStore,
     };
+    use common::{
+        dtos::{AuditAction, Language},
+        signature,
+    };
     use faux::when;
     use lazy_static::lazy_static;
 
     const PROJECT_NAME: &str = "PROJECT_NAME";
+    const STAGE: &str = "prod";
 
     const USER_EMAIL: &str = "test@example.com";
     const USER_NAME: &str = "user_name";
     const USER_LOCATION: &str = "Hamburg";
     const USER_COMPANY: &str = "Noops.io";
-    const USER_GH_ACCESS_TOKEN: &str = "Yiu0Hae4ietheereij4OhneuNe6tae0e";
-    const USER_GH_LOGIN: &str = "login_name";
-    const USER_GH_ID: i32 = 42;
+    const USER_LOGIN: &str = "login_name";
+    const USER_SUBJECT: &str = "42";
 
     lazy_static! {
-        static ref USER: User = User::new(
-            USER_EMAIL.to_string(),
-            Some(USER_NAME.to_string()),
-            Some(USER_LOCATION.to_string()),
-            Some(USER_COMPANY.to_string()),
-            USER_GH_ID,
-            USER_GH_LOGIN.to_string(),
-            USER_GH_ACCESS_TOKEN.to_string()
-        );
+        static ref WASM: Vec<u8> =
+            std::fs::read(env!("CARGO_CDYLIB_FILE_RETURN_STATUS_CODE_200")).unwrap();
+        static ref USER: User = User::new(Identity {
+            provider: "github".to_string(),
+            subject: USER_SUBJECT.to_string(),
+            login: USER_LOGIN.to_string(),
+            email: USER_EMAIL.to_string(),
+            name: Some(USER_NAME.to_string()),
+            location: Some(USER_LOCATION.to_string()),
+            company: Some(USER_COMPANY.to_string()),
+        });
+        static ref ACTOR: Actor = Actor::from(USER.clone());
+    }
+
+    fn trusted_keys_mock(keys: Vec<TrustedKey>) -> TrustedKeyRepository {
+        let mut trusted_keys_mock = TrustedKeyRepository::faux();
+        when!(trusted_keys_mock.belonging_to(_)).then(move |_| Ok(keys.clone()));
+        trusted_keys_mock
+    }
+
+    /// Quotas which are never exceeded
+    fn quotas_mock() -> QuotaService {
+        let mut quotas_mock = QuotaService::faux();
+        when!(quotas_mock.check_projects).then(|_| Ok(()));
+        when!(quotas_mock.check_handlers).then(|_| Ok(()));
+        when!(quotas_mock.check_artifact).then(|_| Ok(()));
+        when!(quotas_mock.check_storage).then(|_| Ok(()));
+        when!(quotas_mock.count_invocation).then(|_| Ok(()));
+        quotas_mock
     }
 
     #[test]
-    #[ignore]
-    fn create_ok() {
-        // FIXME: Deactivated due to the lack of the faux crate to assert a handlers has been called
+    fn create_ok() -> anyhow::Result<()> {
+        let handler_name = "handler_1";
+        let blob = "Ohngai5e";
+        let project_expected = Project::new(PROJECT_NAME.to_string(), USER.id.clone());
+
+        // -------------------------------------------------------------------------------------
+
+        let mut projects_mock = ProjectRepository::faux();
+        when!(projects_mock.belonging_to_by_name(USER.clone(), PROJECT_NAME))
+            .once()
+            .then_return(Ok(Some(project_expected.clone())));
+
+        let mut wasmstore_mock = WasmStore::faux();
+        when!(wasmstore_mock.create(_))
+            .once()
+            .then_return(Ok(blob.to_string()));
+
+        let mut handlers_mock = HandlerRepository::faux();
+        when!(handlers_mock.trashed_by_name(project_expected.clone(), STAGE, handler_name))
+            .once()
+            .then_return(Ok(None));
+        when!(handlers_mock.belonging_to_by_name(project_expected, STAGE, handler_name))
+            .once()
+            .then_return(Ok(None));
+        when!(handlers_mock.deploy(
+            *_ = faux::from_fn!(move |handler: &Handler| handler.name == handler_name),
+            *_ = faux::from_fn!(
+                move |version: &HandlerVersion| version.blob == blob && version.number == 1
+            ),
+            *_ = faux::from_fn!(|event: &AuditEvent| event.action == AuditAction::HandlerDeploy)
+        ))
+        .once()
+        .then_return(Ok(()));
+
+        // -------------------------------------------------------------------------------------
+
+        let handler_service = HandlerService::new(
+            projects_mock,
+            handlers_mock,
+            HandlerVersionRepository::faux(),
+            StatusCodeRepository::faux(),
+            StageVariableRepository::faux(),
+            trusted_keys_mock(vec![]),
+            wasmstore_mock,
+            TrashService::faux(),
+            quotas_mock(),
+        );
+        handler_service.create(
+            &ACTOR,
+            PROJECT_NAME,
+            STAGE,
+            handler_name.to_string(),
+            &WASM,
+            None,
+            None,
+        )?;
+
+        Ok(())
     }
 
     #[test]
-    fn create_project_not_found() {
+    fn create_purges_trashed() -> anyhow::Result<()> {
+        let handler_name = "handler_1";
+        let project_expected = Project::new(PROJECT_NAME.to_string(), USER.id.clone());
+        let trashed = Handler::new(
+            handler_name.to_string(),
+            Language::Rust,
+            "Ahzo3ahc".to_string(),
+            project_expected.id.clone(),
+            STAGE.to_string(),
+        );
+
+        // -------------------------------------------------------------------------------------
+
         let mut projects_mock = ProjectRepository::faux();
         when!(projects_mock.belonging_to_by_name(USER.clone(), PROJECT_NAME))
+            .once()
+            .then_return(Ok(Some(project_expected.clone())));
+
+        let mut wasmstore_mock = WasmStore::faux();
+        when!(wasmstore_mock.create(_))
+            .once()
+            .then_return(Ok("Ohngai5e".to_string()));
+
+        let mut handlers_mock = HandlerRepository::faux();
+        when!(handlers_mock.belonging_to_by_name(project_expected.clone(), STAGE, handler_name))
             .once()
             .then_return(Ok(None));
+        when!(handlers_mock.trashed_by_name(project_expected, STAGE, handler_name))
+            .once()
+            .then_return(Ok(Some(trashed.clone())));
+        let trashed_id = trashed.id.clone();
+        when!(handlers_mock.deploy(
+            *_ = faux::from_fn!(move |handler: &Handler| handler.id != trashed_id),
+            _,
+            _
+        ))
+        .once()
+        .then_return(Ok(()));
 
-        let handlers_mock = HandlerRepository::faux();
-        let wasmstore_mock: WasmStore = WasmStore::faux();
+        let mut trash_mock = TrashService::faux();
+        when!(trash_mock.purge_handler(trashed))
+            .once()
+            .then_return(Ok(()));
+
+        // -------------------------------------------------------------------------------------
+
+        let handler_service = HandlerService::new(
+            projects_mock,
+            handlers_mock,
+            HandlerVersionRepository::faux(),
+            StatusCodeRepository::faux(),
+            StageVariableRepository::faux(),
+            trusted_keys_mock(vec![]),
+            wasmstore_mock,
+            trash_mock,
+            quotas_mock(),
+        );
+        handler_service.create(
+            &ACTOR,
+            PROJECT_NAME,
+            STAGE,
+            handler_name.to_string(),
+            &WASM,
+            None,
+            None,
+        )?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn create_transaction_failed() {
+        let handler_name = "handler_1";
+        let project_expected = Project::new(PROJECT_NAME.to_string(), USER.id.clone());
+        let handler_expected = Handler::new(
+            handler_name.to_string(),
+            Language::Rust,
+            "Ahzo3ahc".to_string(),
+            project_expected.id.clone(),
+            STAGE.to_string(),
+        );
+
+        // -------------------------------------------------------------------------------------
+
+        let mut projects_mock = ProjectRepository::faux();
+        when!(projects_mock.belonging_to_by_name(USER.clone(), PROJECT_NAME))
+            .once()
+            .then_return(Ok(Some(project_expected.clone())));
+
+        let mut wasmstore_mock = WasmStore::faux();
+        when!(wasmstore_mock.create(_))
+            .once()
+            .then_return(Ok("Ohngai5e".to_string()));
+
+        let mut handlers_mock = HandlerRepository::faux();
+        when!(handlers_mock.belonging_to_by_name(project_expected, STAGE, handler_name))
+            .once()
+            .then_return(Ok(Some(handler_expected)));
+        when!(handlers_mock.deploy(_, _, _))
+            .once()
+            .then_return(Err(anyhow::anyhow!("UNIQUE constraint failed")));
+
+        let mut versions_mock = HandlerVersionRepository::faux();
+        when!(versions_mock.latest(_)).once().then_return(Ok(None));
 
         // -------------------------------------------------------------------------------------
 
-        let handler_service = HandlerService::new(projects_mock, handlers_mock, wasmstore_mock);
-        let result =
-            handler_service.create(&USER, PROJECT_NAME, "handler_1".to_string(), &[0, 0, 0]);
+        let handler_service = HandlerService::new(
+            projects_mock,
+            handlers_mock,
+            versions_mock,
+            StatusCodeRepository::faux(),
+            StageVariableRepository::faux(),
+            trusted_keys_mock(vec![]),
+            wasmstore_mock,
+            TrashService::faux(),
+            quotas_mock(),
+        );
+        let result = handler_service.create(
+            &ACTOR,
+            PROJECT_NAME,
+            STAGE,
+            handler_name.to_string(),
+            &WASM,
+            None,
+            None,
+        );
 
         assert!(result.is_err())
     }
 
     #[test]
-    #[ignore]
-    fn delete_ok() {
-        // FIXME: Deactivated due to the lack of the faux crate to assert a handlers has been called
+    fn create_signed_ok() -> anyhow::Result<()> {
+        let handler_name = "handler_1";
+        let project_expected = Project::new(PROJECT_NAME.to_string(), USER.id.clone());
+        let key = signature::generate_key();
+        let trusted_key = TrustedKey::new(
+            project_expected.id.clone(),
+            "ci".to_string(),
+            signature::public_key(&key),
+        );
+
+        // -------------------------------------------------------------------------------------
+
+        let mut projects_mock = ProjectRepository::faux();
+        when!(projects_mock.belonging_to_by_name(USER.clone(), PROJECT_NAME))
+            .once()
+            .then_return(Ok(Some(project_expected.clone())));
+
+        let mut wasmstore_mock = WasmStore::faux();
+        when!(wasmstore_mock.create(_))
+            .once()
+            .then_return(Ok("Ohngai5e".to_string()));
+
+        let mut handlers_mock = HandlerRepository::faux();
+        when!(handlers_mock.trashed_by_name(project_expected.clone(), STAGE, handler_name))
+            .once()
+            .then_return(Ok(None));
+        when!(handlers_mock.belonging_to_by_name(project_expected, STAGE, handler_name))
+            .once()
+            .then_return(Ok(None));
+        when!(handlers_mock.deploy(_, _, _))
+            .once()
+            .then_return(Ok(()));
+
+        // -------------------------------------------------------------------------------------
+
+        let handler_service = HandlerService::new(
+            projects_mock,
+            handlers_mock,
+            HandlerVersionRepository::faux(),
+            StatusCodeRepository::faux(),
+            StageVariableRepository::faux(),
+            trusted_keys_mock(vec![trusted_key]),
+            wasmstore_mock,
+            TrashService::faux(),
+            quotas_mock(),
+        );
+        handler_service.create(
+            &ACTOR,
+            PROJECT_NAME,
+            STAGE,
+            handler_name.to_string(),
+            &WASM,
+            Some(&signature::sign(&key, &WASM)),
+            None,
+        )?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn create_untrusted_signature() {
+        let project_expected = Project::new(PROJECT_NAME.to_string(), USER.id.clone());
+        let trusted_key = TrustedKey::new(
+            project_expected.id.clone(),
+            "ci".to_string(),
+            signature::public_key(&signature::generate_key()),
+        );
+        let untrusted_signature = signature::sign(&signature::generate_key(), &WASM);
+
+        // -------------------------------------------------------------------------------------
+
+        let mut projects_mock = ProjectRepository::faux();
+        when!(projects_mock.belonging_to_by_name(USER.clone(), PROJECT_NAME))
+            .then(move |_| Ok(Some(project_expected.clone())));
+
+        // -------------------------------------------------------------------------------------
+
+        let handler_service = HandlerService::new(
+            projects_mock,
+            HandlerRepository::faux(),
+            HandlerVersionRepository::faux(),
+            StatusCodeRepository::faux(),
+            StageVariableRepository::faux(),
+            trusted_keys_mock(vec![trusted_key]),
+            WasmStore::faux(),
+            TrashService::faux(),
+            quotas_mock(),
+        );
+        for signature in [None, Some(untrusted_signature.as_str())] {
+            let result = handler_service.create(
+                &ACTOR,
+                PROJECT_NAME,
+                STAGE,
+                "handler_1".to_string(),
+                &WASM,
+                signature,
+                None,
+            );
+
+            assert!(matches!(result, Err(UntrustedSignature)));
+        }
     }
 
     #[test]
-    fn delete_project_not_found() {
+    fn create_project_not_found() {
         let mut projects_mock = ProjectRepository::faux();
         when!(projects_mock.belonging_to_by_name(USER.clone(), PROJECT_NAME))
             .once()
@@ -180,16 +1040,41 @@ mod tests {
 
         // -------------------------------------------------------------------------------------
 
-        let handler_service = HandlerService::new(projects_mock, handlers_mock, wasmstore_mock);
-        let result = handler_service.delete(&USER, PROJECT_NAME, "handler_1");
+        let handler_service = HandlerService::new(
+            projects_mock,
+            handlers_mock,
+            HandlerVersionRepository::faux(),
+            StatusCodeRepository::faux(),
+            StageVariableRepository::faux(),
+            TrustedKeyRepository::faux(),
+            wasmstore_mock,
+            TrashService::faux(),
+            quotas_mock(),
+        );
+        let result = handler_service.create(
+            &ACTOR,
+            PROJECT_NAME,
+            STAGE,
+            "handler_1".to_string(),
+            &[0, 0, 0],
+            None,
+            None,
+        );
 
         assert!(result.is_err())
     }
 
     #[test]
-    fn delete_handler_not_found() {
+    fn delete_ok() -> anyhow::Result<()> {
         let handler_name = "handler_1";
         let project_expected = Project::new(PROJECT_NAME.to_string(), USER.id.clone());
+        let handler_expected = Handler::new(
+            handler_name.to_string(),
+            Language::Rust,
+            "Ahzo3ahc".to_string(),
+            project_expected.id.clone(),
+            STAGE.to_string(),
+        );
 
         // -------------------------------------------------------------------------------------
 
@@ -199,16 +1084,720 @@ mod tests {
             .then_return(Ok(Some(project_expected.clone())));
 
         let mut handlers_mock = HandlerRepository::faux();
-        when!(handlers_mock.belonging_to_by_name(project_expected, handler_name))
+        when!(handlers_mock.belonging_to_by_name(project_expected, STAGE, handler_name))
             .once()
-            .then_return(Ok(None));
-        let wasmstore_mock: WasmStore = WasmStore::faux();
+            .then_return(Ok(Some(handler_expected.clone())));
+        when!(handlers_mock.trash(
+            handler_expected,
+            *_ = faux::from_fn!(|event: &AuditEvent| event.action == AuditAction::HandlerDelete)
+        ))
+        .once()
+        .then_return(Ok(()));
 
         // -------------------------------------------------------------------------------------
 
-        let handler_service = HandlerService::new(projects_mock, handlers_mock, wasmstore_mock);
-        let result = handler_service.delete(&USER, PROJECT_NAME, handler_name);
+        let handler_service = HandlerService::new(
+            projects_mock,
+            handlers_mock,
+            HandlerVersionRepository::faux(),
+            StatusCodeRepository::faux(),
+            StageVariableRepository::faux(),
+            TrustedKeyRepository::faux(),
+            WasmStore::faux(),
+            TrashService::faux(),
+            quotas_mock(),
+        );
+        handler_service.delete(&ACTOR, PROJECT_NAME, STAGE, handler_name)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn delete_project_not_found() {
+        let mut projects_mock = ProjectRepository::faux();
+        when!(projects_mock.belonging_to_by_name(USER.clone(), PROJECT_NAME))
+            .once()
+            .then_return(Ok(None));
+
+        let handlers_mock = HandlerRepository::faux();
+        let wasmstore_mock: WasmStore = WasmStore::faux();
+
+        // -------------------------------------------------------------------------------------
+
+        let handler_service = HandlerService::new(
+            projects_mock,
+            handlers_mock,
+            HandlerVersionRepository::faux(),
+            StatusCodeRepository::faux(),
+            StageVariableRepository::faux(),
+            TrustedKeyRepository::faux(),
+            wasmstore_mock,
+            TrashService::faux(),
+            quotas_mock(),
+        );
+        let result = handler_service.delete(&ACTOR, PROJECT_NAME, STAGE, "handler_1");
+
+        assert!(result.is_err())
+    }
+
+    #[test]
+    fn delete_handler_not_found() {
+        let handler_name = "handler_1";
+        let project_expected = Project::new(PROJECT_NAME.to_string(), USER.id.clone());
+
+        // -------------------------------------------------------------------------------------
+
+        let mut projects_mock = ProjectRepository::faux();
+        when!(projects_mock.belonging_to_by_name(USER.clone(), PROJECT_NAME))
+            .once()
+            .then_return(Ok(Some(project_expected.clone())));
+
+        let mut handlers_mock = HandlerRepository::faux();
+        when!(handlers_mock.belonging_to_by_name(project_expected, STAGE, handler_name))
+            .once()
+            .then_return(Ok(None));
+        let wasmstore_mock: WasmStore = WasmStore::faux();
+
+        // -------------------------------------------------------------------------------------
+
+        let handler_service = HandlerService::new(
+            projects_mock,
+            handlers_mock,
+            HandlerVersionRepository::faux(),
+            StatusCodeRepository::faux(),
+            StageVariableRepository::faux(),
+            TrustedKeyRepository::faux(),
+            wasmstore_mock,
+            TrashService::faux(),
+            quotas_mock(),
+        );
+        let result = handler_service.delete(&ACTOR, PROJECT_NAME, STAGE, handler_name);
+
+        assert!(result.is_err())
+    }
+
+    #[test]
+    fn rollback_previous_ok() -> anyhow::Result<()> {
+        let handler_name = "handler_1";
+        let project_expected = Project::new(PROJECT_NAME.to_string(), USER.id.clone());
+        let mut handler_expected = Handler::new(
+            handler_name.to_string(),
+            Language::Rust,
+            "Ahzo3ahc".to_string(),
+            project_expected.id.clone(),
+            STAGE.to_string(),
+        );
+        handler_expected.version = 2;
+        let version_expected = HandlerVersion::new(
+            handler_expected.id.clone(),
+            1,
+            "ooQu9eiW".to_string(),
+            "ooQu9eiW".to_string(),
+            0,
+            USER.id.clone(),
+        );
+
+        // -------------------------------------------------------------------------------------
+
+        let mut projects_mock = ProjectRepository::faux();
+        when!(projects_mock.belonging_to_by_name(USER.clone(), PROJECT_NAME))
+            .once()
+            .then_return(Ok(Some(project_expected.clone())));
+
+        let mut handlers_mock = HandlerRepository::faux();
+        when!(handlers_mock.belonging_to_by_name(project_expected, STAGE, handler_name))
+            .once()
+            .then_return(Ok(Some(handler_expected.clone())));
+        let expected = (handler_expected.clone(), version_expected.clone());
+        when!(handlers_mock.activate).once().then(
+            move |(handler, version, event): (&Handler, &HandlerVersion, Option<&AuditEvent>)| {
+                assert_eq!((&expected.0, &expected.1), (handler, version));
+                let event = event.expect("rollback without audit event");
+                assert_eq!(AuditAction::HandlerRollback, event.action);
+                assert_eq!(Some(PROJECT_NAME.to_string()), event.project);
+                assert_eq!(Some(handler_name.to_string()), event.target);
+                assert_eq!(Some(expected.0.hash.clone()), event.old_hash);
+                assert_eq!(Some(expected.1.hash.clone()), event.new_hash);
+                Ok(())
+            },
+        );
+
+        let mut versions_mock = HandlerVersionRepository::faux();
+        when!(versions_mock.previous(handler_expected.clone()))
+            .once()
+            .then_return(Ok(Some(version_expected.clone())));
+
+        // -------------------------------------------------------------------------------------
+
+        let handler_service = HandlerService::new(
+            projects_mock,
+            handlers_mock,
+            versions_mock,
+            StatusCodeRepository::faux(),
+            StageVariableRepository::faux(),
+            TrustedKeyRepository::faux(),
+            WasmStore::faux(),
+            TrashService::faux(),
+            quotas_mock(),
+        );
+        let handler = handler_service.rollback(&ACTOR, PROJECT_NAME, STAGE, handler_name, None)?;
+
+        assert_eq!(version_expected.number, handler.version);
+        assert_eq!(version_expected.hash, handler.hash);
+        Ok(())
+    }
+
+    #[test]
+    fn rollback_version_not_found() {
+        let handler_name = "handler_1";
+        let project_expected = Project::new(PROJECT_NAME.to_string(), USER.id.clone());
+        let handler_expected = Handler::new(
+            handler_name.to_string(),
+            Language::Rust,
+            "Ahzo3ahc".to_string(),
+            project_expected.id.clone(),
+            STAGE.to_string(),
+        );
+
+        // -------------------------------------------------------------------------------------
+
+        let mut projects_mock = ProjectRepository::faux();
+        when!(projects_mock.belonging_to_by_name(USER.clone(), PROJECT_NAME))
+            .once()
+            .then_return(Ok(Some(project_expected.clone())));
+
+        let mut handlers_mock = HandlerRepository::faux();
+        when!(handlers_mock.belonging_to_by_name(project_expected, STAGE, handler_name))
+            .once()
+            .then_return(Ok(Some(handler_expected.clone())));
+
+        let mut versions_mock = HandlerVersionRepository::faux();
+        when!(versions_mock.belonging_to_by_number(handler_expected, 7))
+            .once()
+            .then_return(Ok(None));
+
+        // -------------------------------------------------------------------------------------
+
+        let handler_service = HandlerService::new(
+            projects_mock,
+            handlers_mock,
+            versions_mock,
+            StatusCodeRepository::faux(),
+            StageVariableRepository::faux(),
+            TrustedKeyRepository::faux(),
+            WasmStore::faux(),
+            TrashService::faux(),
+            quotas_mock(),
+        );
+        let result = handler_service.rollback(&ACTOR, PROJECT_NAME, STAGE, handler_name, Some(7));
 
         assert!(result.is_err())
     }
+
+    #[test]
+    fn create_invalid_canary_weight() {
+        let handler_service = HandlerService::new(
+            ProjectRepository::faux(),
+            HandlerRepository::faux(),
+            HandlerVersionRepository::faux(),
+            StatusCodeRepository::faux(),
+            StageVariableRepository::faux(),
+            TrustedKeyRepository::faux(),
+            WasmStore::faux(),
+            TrashService::faux(),
+            quotas_mock(),
+        );
+        let result = handler_service.create(
+            &ACTOR,
+            PROJECT_NAME,
+            STAGE,
+            "handler_1".to_string(),
+            &[0, 0, 0],
+            None,
+            Some(101),
+        );
+
+        assert!(result.is_err())
+    }
+
+    #[test]
+    fn create_invalid_stage() {
+        let handler_service = HandlerService::new(
+            ProjectRepository::faux(),
+            HandlerRepository::faux(),
+            HandlerVersionRepository::faux(),
+            StatusCodeRepository::faux(),
+            StageVariableRepository::faux(),
+            TrustedKeyRepository::faux(),
+            WasmStore::faux(),
+            TrashService::faux(),
+            quotas_mock(),
+        );
+        let result = handler_service.create(
+            &ACTOR,
+            PROJECT_NAME,
+            "prod/eu",
+            "handler_1".to_string(),
+            &WASM,
+            None,
+            None,
+        );
+
+        assert!(matches!(result, Err(InvalidStage)))
+    }
+
+    #[test]
+    fn create_reserved_name() {
+        let handler_service = HandlerService::new(
+            ProjectRepository::faux(),
+            HandlerRepository::faux(),
+            HandlerVersionRepository::faux(),
+            StatusCodeRepository::faux(),
+            StageVariableRepository::faux(),
+            TrustedKeyRepository::faux(),
+            WasmStore::faux(),
+            TrashService::faux(),
+            quotas_mock(),
+        );
+        let result = handler_service.create(
+            &ACTOR,
+            PROJECT_NAME,
+            STAGE,
+            "keys".to_string(),
+            &WASM,
+            None,
+            None,
+        );
+
+        assert!(matches!(result, Err(ReservedName(name)) if name == "keys"))
+    }
+
+    #[test]
+    fn validate_handler_name_reserved() {
+        assert!(matches!(
+            validate_handler_name("restore"),
+            Err(ReservedName(_))
+        ));
+        assert!(matches!(
+            validate_handler_name("quotas"),
+            Err(ReservedName(_))
+        ));
+        assert!(validate_handler_name("restore_backup").is_ok());
+    }
+
+    #[test]
+    fn promote_canary_ok() -> anyhow::Result<()> {
+        let handler_name = "handler_1";
+        let project_expected = Project::new(PROJECT_NAME.to_string(), USER.id.clone());
+        let mut handler_expected = Handler::new(
+            handler_name.to_string(),
+            Language::Rust,
+            "Ahzo3ahc".to_string(),
+            project_expected.id.clone(),
+            STAGE.to_string(),
+        );
+        handler_expected.canary_version = Some(2);
+        handler_expected.canary_weight = 10;
+        let canary_expected = HandlerVersion::new(
+            handler_expected.id.clone(),
+            2,
+            "ooQu9eiW".to_string(),
+            "ooQu9eiW".to_string(),
+            0,
+            USER.id.clone(),
+        );
+
+        // -------------------------------------------------------------------------------------
+
+        let mut projects_mock = ProjectRepository::faux();
+        when!(projects_mock.belonging_to_by_name(USER.clone(), PROJECT_NAME))
+            .once()
+            .then_return(Ok(Some(project_expected.clone())));
+
+        let mut handlers_mock = HandlerRepository::faux();
+        when!(handlers_mock.belonging_to_by_name(project_expected, STAGE, handler_name))
+            .once()
+            .then_return(Ok(Some(handler_expected.clone())));
+        when!(handlers_mock.promote_canary(
+            handler_expected.clone(),
+            canary_expected.clone(),
+            *_ = faux::from_fn!(|event: &AuditEvent| event.action == AuditAction::CanaryPromote)
+        ))
+        .once()
+        .then_return(Ok(()));
+
+        let mut versions_mock = HandlerVersionRepository::faux();
+        when!(versions_mock.belonging_to_by_number(handler_expected, 2))
+            .once()
+            .then_return(Ok(Some(canary_expected.clone())));
+
+        // -------------------------------------------------------------------------------------
+
+        let handler_service = HandlerService::new(
+            projects_mock,
+            handlers_mock,
+            versions_mock,
+            StatusCodeRepository::faux(),
+            StageVariableRepository::faux(),
+            TrustedKeyRepository::faux(),
+            WasmStore::faux(),
+            TrashService::faux(),
+            quotas_mock(),
+        );
+        let handler = handler_service.promote_canary(&ACTOR, PROJECT_NAME, STAGE, handler_name)?;
+
+        assert_eq!(canary_expected.number, handler.version);
+        assert_eq!(None, handler.canary);
+        Ok(())
+    }
+
+    #[test]
+    fn abort_canary_not_found() {
+        let handler_name = "handler_1";
+        let project_expected = Project::new(PROJECT_NAME.to_string(), USER.id.clone());
+        let handler_expected = Handler::new(
+            handler_name.to_string(),
+            Language::Rust,
+            "Ahzo3ahc".to_string(),
+            project_expected.id.clone(),
+            STAGE.to_string(),
+        );
+
+        // -------------------------------------------------------------------------------------
+
+        let mut projects_mock = ProjectRepository::faux();
+        when!(projects_mock.belonging_to_by_name(USER.clone(), PROJECT_NAME))
+            .once()
+            .then_return(Ok(Some(project_expected.clone())));
+
+        let mut handlers_mock = HandlerRepository::faux();
+        when!(handlers_mock.belonging_to_by_name(project_expected, STAGE, handler_name))
+            .once()
+            .then_return(Ok(Some(handler_expected)));
+
+        // -------------------------------------------------------------------------------------
+
+        let handler_service = HandlerService::new(
+            projects_mock,
+            handlers_mock,
+            HandlerVersionRepository::faux(),
+            StatusCodeRepository::faux(),
+            StageVariableRepository::faux(),
+            TrustedKeyRepository::faux(),
+            WasmStore::faux(),
+            TrashService::faux(),
+            quotas_mock(),
+        );
+        let result = handler_service.abort_canary(&ACTOR, PROJECT_NAME, STAGE, handler_name);
+
+        assert!(result.is_err())
+    }
+
+    #[test]
+    fn restore_ok() -> anyhow::Result<()> {
+        let handler_name = "handler_1";
+        let project_expected = Project::new(PROJECT_NAME.to_string(), USER.id.clone());
+        let mut trashed = Handler::new(
+            handler_name.to_string(),
+            Language::Rust,
+            "Ahzo3ahc".to_string(),
+            project_expected.id.clone(),
+            STAGE.to_string(),
+        );
+        trashed.deleted_at = Some(chrono::Utc::now().naive_utc());
+
+        // -------------------------------------------------------------------------------------
+
+        let mut projects_mock = ProjectRepository::faux();
+        when!(projects_mock.belonging_to_by_name(USER.clone(), PROJECT_NAME))
+            .once()
+            .then_return(Ok(Some(project_expected.clone())));
+
+        let mut handlers_mock = HandlerRepository::faux();
+        when!(handlers_mock.trashed_by_name(project_expected, STAGE, handler_name))
+            .once()
+            .then_return(Ok(Some(trashed.clone())));
+        when!(handlers_mock.restore(
+            trashed,
+            *_ = faux::from_fn!(|event: &AuditEvent| event.action == AuditAction::HandlerRestore)
+        ))
+        .once()
+        .then_return(Ok(()));
+
+        // -------------------------------------------------------------------------------------
+
+        let handler_service = HandlerService::new(
+            projects_mock,
+            handlers_mock,
+            HandlerVersionRepository::faux(),
+            StatusCodeRepository::faux(),
+            StageVariableRepository::faux(),
+            TrustedKeyRepository::faux(),
+            WasmStore::faux(),
+            TrashService::faux(),
+            quotas_mock(),
+        );
+        let restored = handler_service.restore(&ACTOR, PROJECT_NAME, STAGE, handler_name)?;
+
+        assert_eq!(handler_name, restored.name);
+        Ok(())
+    }
+
+    #[test]
+    fn restore_not_in_trash() {
+        let project_expected = Project::new(PROJECT_NAME.to_string(), USER.id.clone());
+
+        // -------------------------------------------------------------------------------------
+
+        let mut projects_mock = ProjectRepository::faux();
+        when!(projects_mock.belonging_to_by_name(USER.clone(), PROJECT_NAME))
+            .once()
+            .then_return(Ok(Some(project_expected.clone())));
+
+        let mut handlers_mock = HandlerRepository::faux();
+        when!(handlers_mock.trashed_by_name(project_expected, STAGE, "handler_1"))
+            .once()
+            .then_return(Ok(None));
+
+        // -------------------------------------------------------------------------------------
+
+        let handler_service = HandlerService::new(
+            projects_mock,
+            handlers_mock,
+            HandlerVersionRepository::faux(),
+            StatusCodeRepository::faux(),
+            StageVariableRepository::faux(),
+            TrustedKeyRepository::faux(),
+            WasmStore::faux(),
+            TrashService::faux(),
+            quotas_mock(),
+        );
+        let result = handler_service.restore(&ACTOR, PROJECT_NAME, STAGE, "handler_1");
+
+        assert!(matches!(result, Err(HandlerNotFound)));
+    }
+
+    #[test]
+    fn promote_ok() -> anyhow::Result<()> {
+        let handler_name = "handler_1";
+        let blob = "yee3Ohgh";
+        let project_expected = Project::new(PROJECT_NAME.to_string(), USER.id.clone());
+        let mut handler_expected = Handler::new(
+            handler_name.to_string(),
+            Language::Rust,
+            "Ahzo3ahc".to_string(),
+            project_expected.id.clone(),
+            "staging".to_string(),
+        );
+        handler_expected.version = 3;
+        let version_expected = HandlerVersion::new(
+            handler_expected.id.clone(),
+            3,
+            handler_expected.hash.clone(),
+            blob.to_string(),
+            0,
+            USER.id.clone(),
+        );
+
+        // -------------------------------------------------------------------------------------
+
+        let mut projects_mock = ProjectRepository::faux();
+        when!(projects_mock.belonging_to_by_name(USER.clone(), PROJECT_NAME))
+            .once()
+            .then_return(Ok(Some(project_expected.clone())));
+
+        let mut handlers_mock = HandlerRepository::faux();
+        when!(handlers_mock.belonging_to_stage(project_expected.clone(), "staging"))
+            .once()
+            .then_return(Ok(vec![handler_expected.clone()]));
+        when!(handlers_mock.belonging_to_stage(project_expected.clone(), STAGE))
+            .once()
+            .then_return(Ok(vec![]));
+        when!(handlers_mock.trashed_by_name(project_expected.clone(), STAGE, handler_name))
+            .once()
+            .then_return(Ok(None));
+        when!(handlers_mock.belonging_to_by_name(project_expected, STAGE, handler_name))
+            .once()
+            .then_return(Ok(None));
+        when!(handlers_mock.deploy(
+            _,
+            *_ = faux::from_fn!(move |version: &HandlerVersion| version.blob == blob),
+            _
+        ))
+        .once()
+        .then_return(Ok(()));
+
+        let mut versions_mock = HandlerVersionRepository::faux();
+        when!(versions_mock.belonging_to_by_number(handler_expected, 3))
+            .once()
+            .then_return(Ok(Some(version_expected.clone())));
+        when!(versions_mock.latest(_)).once().then_return(Ok(None));
+
+        // -------------------------------------------------------------------------------------
+
+        let handler_service = HandlerService::new(
+            projects_mock,
+            handlers_mock,
+            versions_mock,
+            StatusCodeRepository::faux(),
+            StageVariableRepository::faux(),
+            TrustedKeyRepository::faux(),
+            WasmStore::faux(),
+            TrashService::faux(),
+            quotas_mock(),
+        );
+        let promoted = handler_service.promote(&ACTOR, PROJECT_NAME, "staging", STAGE)?;
+
+        assert_eq!(1, promoted.len());
+        assert_eq!(STAGE, promoted[0].stage);
+        assert_eq!("Ahzo3ahc", promoted[0].hash);
+        assert_eq!(1, promoted[0].version);
+        Ok(())
+    }
+
+    #[test]
+    fn promote_same_stage() {
+        let handler_service = HandlerService::new(
+            ProjectRepository::faux(),
+            HandlerRepository::faux(),
+            HandlerVersionRepository::faux(),
+            StatusCodeRepository::faux(),
+            StageVariableRepository::faux(),
+            TrustedKeyRepository::faux(),
+            WasmStore::faux(),
+            TrashService::faux(),
+            quotas_mock(),
+        );
+        let result = handler_service.promote(&ACTOR, PROJECT_NAME, STAGE, STAGE);
+
+        assert!(result.is_err())
+    }
+
+    #[test]
+    fn promote_invalid_stage() {
+        let handler_service = HandlerService::new(
+            ProjectRepository::faux(),
+            HandlerRepository::faux(),
+            HandlerVersionRepository::faux(),
+            StatusCodeRepository::faux(),
+            StageVariableRepository::faux(),
+            TrustedKeyRepository::faux(),
+            WasmStore::faux(),
+            TrashService::faux(),
+            quotas_mock(),
+        );
+        let result = handler_service.promote(&ACTOR, PROJECT_NAME, STAGE, "prod/eu");
+
+        assert!(matches!(result, Err(InvalidStage)))
+    }
+
+    #[test]
+    fn promote_stage_not_found() {
+        let project_expected = Project::new(PROJECT_NAME.to_string(), USER.id.clone());
+
+        // -------------------------------------------------------------------------------------
+
+        let mut projects_mock = ProjectRepository::faux();
+        when!(projects_mock.belonging_to_by_name(USER.clone(), PROJECT_NAME))
+            .once()
+            .then_return(Ok(Some(project_expected.clone())));
+
+        let mut handlers_mock = HandlerRepository::faux();
+        when!(handlers_mock.belonging_to_stage(project_expected, "staging"))
+            .once()
+            .then_return(Ok(vec![]));
+
+        // -------------------------------------------------------------------------------------
+
+        let handler_service = HandlerService::new(
+            projects_mock,
+            handlers_mock,
+            HandlerVersionRepository::faux(),
+            StatusCodeRepository::faux(),
+            StageVariableRepository::faux(),
+            TrustedKeyRepository::faux(),
+            WasmStore::faux(),
+            TrashService::faux(),
+            quotas_mock(),
+        );
+        let result = handler_service.promote(&ACTOR, PROJECT_NAME, "staging", STAGE);
+
+        assert!(result.is_err())
+    }
+
+    #[test]
+    fn read_component_of_org_project_counts_against_creator() -> anyhow::Result<()> {
+        let project = Project {
+            org_id: Some("Ahn4ooph".to_string()),
+            ..Project::new(PROJECT_NAME.to_string(), "creator".to_string())
+        };
+        let handler = Handler::new(
+            "handler_1".to_string(),
+            Language::Rust,
+            "hash".to_string(),
+            project.id.clone(),
+            STAGE.to_string(),
+        );
+        let version = HandlerVersion::new(
+            handler.id.clone(),
+            1,
+            "hash".to_string(),
+            "blob".to_string(),
+            0,
+            USER.id.clone(),
+        );
+
+        // -------------------------------------------------------------------------------------
+
+        let mut handlers_mock = HandlerRepository::faux();
+        let handler_read = handler.clone();
+        when!(handlers_mock.read)
+            .once()
+            .then(move |_| Ok(Some(handler_read.clone())));
+
+        let mut projects_mock = ProjectRepository::faux();
+        when!(projects_mock.read)
+            .once()
+            .then(move |_| Ok(Some(project.clone())));
+
+        let mut quotas_mock = QuotaService::faux();
+        when!(quotas_mock.count_invocation(
+            *_ = faux::from_fn!(|project: &Project| project.user_id == "creator")
+        ))
+        .once()
+        .then_return(Ok(()));
+
+        let mut versions_mock = HandlerVersionRepository::faux();
+        when!(versions_mock.belonging_to_by_number(handler, 1))
+            .once()
+            .then_return(Ok(Some(version)));
+
+        let mut wasmstore_mock = WasmStore::faux();
+        when!(wasmstore_mock.read("blob"))
+            .once()
+            .then_return(Ok(WASM.clone()));
+
+        let mut variables_mock = StageVariableRepository::faux();
+        when!(variables_mock.belonging_to_stage)
+            .once()
+            .then(|_| Ok(vec![]));
+
+        // -------------------------------------------------------------------------------------
+
+        let handler_service = HandlerService::new(
+            projects_mock,
+            handlers_mock,
+            versions_mock,
+            StatusCodeRepository::faux(),
+            variables_mock,
+            trusted_keys_mock(vec![]),
+            wasmstore_mock,
+            TrashService::faux(),
+            quotas_mock,
+        );
+        let component = handler_service.read_component("handler_1", CanaryRouting::Stable)?;
+
+        assert_eq!(1, component.version.number);
+        Ok(())
+    }
 }
@@ -1,15 +1,54 @@
-use common::dtos::GetHandlerDTO;
+use common::{
+    dtos::{CanaryDTO, GetHandlerDTO},
+    hash,
+};
 
-use crate::repository::handler::Handler;
+use crate::{errors::Error, repository::handler::Handler};
+use std::sync::OnceLock;
 
+pub mod archive;
+pub mod audit;
 pub mod auth;
+pub mod fsck;
+pub mod gc;
 pub mod handler;
+pub mod org;
 pub mod project;
+pub mod quota;
+pub mod token;
+pub mod trash;
 
 const URL: &str = "http://localhost:8080/";
 
+/// The URL the server is reachable at, set once at startup
+static PUBLIC_URL: OnceLock<String> = OnceLock::new();
+
+/// Sets the URL the links of the handlers start with, which has to end with a slash
+pub fn set_public_url(url: String) {
+    if PUBLIC_URL.set(url).is_err() {
+        tracing::warn!("The public URL is already set");
+    }
+}
+
 fn handler_url(handler_id: &str) -> String {
-    URL.to_string() + handler_id
+    PUBLIC_URL
+        .get()
+        .map(String::as_str)
+        .unwrap_or(URL)
+        .to_string()
+        + handler_id
+}
+
+/// Runs blocking work, like the database queries of the services, on the blocking thread pool
+/// of the runtime, so it doesn't stall the requests handled by the async workers
+pub async fn blocking<F, T>(work: F) -> Result<T, Error>
+where
+    F: FnOnce() -> Result<T, Error> + Send + 'static,
+    T: Send + 'static,
+{
+    tokio::task::spawn_blocking(work)
+        .await
+        .map_err(|err| Error::Unknown(err.into()))?
 }
 
 impl From<Handler> for GetHandlerDTO {
@@ -17,8 +56,15 @@ impl From<Handler> for GetHandlerDTO {
         GetHandlerDTO {
             name: value.name,
             language: value.language,
+            digest: hash::digest(&value.hash),
             hash: value.hash,
             link: handler_url(&value.id),
+            version: value.version,
+            canary: value.canary_version.map(|version| CanaryDTO {
+                version,
+                weight: value.canary_weight as u8,
+            }),
+            stage: value.stage,
         }
     }
 }
@@ -0,0 +1,373 @@
+use super::audit::Actor;
+use crate::{
+    errors::Error::{
+        self, AmbiguousLogin, InvalidOrgName, LastOwner, MemberNotFound, OrgExists, OrgNotFound,
+        ProjectNotFound, RoleForbidden, UserNotFound,
+    },
+    repository::{
+        audit::AuditEvent,
+        org::{Org, OrgMember, OrgRepository},
+        project::{self, Project, ProjectRepository},
+        user::{self, User, UserRepository},
+    },
+};
+use common::dtos::{AuditAction, GetMemberDTO, GetOrgDTO, Role};
+
+/// Fails unless the role of the user in the project permits what requires `required`. Users
+/// own their personal projects, in projects of organizations their role in it counts.
+pub fn authorize(
+    projects: &ProjectRepository,
+    user: &User,
+    project: &Project,
+    required: Role,
+) -> Result<(), Error> {
+    if project.org_id.is_none() && project.user_id == user.id {
+        return Ok(());
+    }
+    match projects.role(user, project)? {
+        Some(role) if role >= required => Ok(()),
+        Some(_) => Err(RoleForbidden(required)),
+        None => Err(ProjectNotFound),
+    }
+}
+
+/// The project of the user or of one of their organizations, if their role permits what
+/// requires `required`
+pub fn authorized_project(
+    projects: &ProjectRepository,
+    user: &User,
+    project_name: &str,
+    required: Role,
+) -> Result<Project, Error> {
+    let project = projects
+        .belonging_to_by_name(user, project_name)?
+        .ok_or(ProjectNotFound)?;
+    authorize(projects, user, &project, required)?;
+    Ok(project)
+}
+
+/// The user with the login, which is qualified with the identity provider like `github:alice`
+/// when users of several providers share it
+pub fn user_by_login(users: &UserRepository, login: &str) -> Result<Option<User>, Error> {
+    let (provider, name) = user::split_login(login);
+    let mut users = users.read_by_login(provider, name)?;
+    match users.len() {
+        0 => Ok(None),
+        1 => Ok(users.pop()),
+        _ => Err(AmbiguousLogin(name.to_string())),
+    }
+}
+
+/// Whether the login, qualified with the identity provider or not, is the one of the user
+fn is_login_of(user: &User, login: &str) -> bool {
+    match user::split_login(login) {
+        (Some(provider), name) => provider == user.provider && name == user.login,
+        (None, name) => name == user.login,
+    }
+}
+
+/// Manages organizations and their members. Every member may see the members, only owners
+/// may change them.
+#[derive(Debug, Clone)]
+pub struct OrgService {
+    orgs: OrgRepository,
+    users: UserRepository,
+}
+
+impl OrgService {
+    pub fn new(orgs: OrgRepository, users: UserRepository) -> Self {
+        Self { orgs, users }
+    }
+
+    /// Creates the organization with the user as its owner
+    pub fn create(&self, user: &Actor, name: &str) -> Result<(), Error> {
+        if name.is_empty() || name.contains('/') {
+            return Err(InvalidOrgName);
+        }
+        if self.orgs.read_by_name(name)?.is_some() {
+            return Err(OrgExists);
+        }
+        self.orgs.create_with_owner(
+            &Org::new(name.to_string()),
+            user,
+            &AuditEvent {
+                target: Some(name.to_string()),
+                ..user.event(AuditAction::OrgCreate)
+            },
+        )?;
+        Ok(())
+    }
+
+    pub fn list(&self, user: &User) -> Result<Vec<GetOrgDTO>, Error> {
+        let orgs = self
+            .orgs
+            .belonging_to(user)?
+            .into_iter()
+            .map(|(org, role)| GetOrgDTO {
+                name: org.name,
+                role,
+            })
+            .collect();
+        Ok(orgs)
+    }
+
+    pub fn members(&self, user: &User, org_name: &str) -> Result<Vec<GetMemberDTO>, Error> {
+        let org = self.org(user, org_name, Role::Viewer)?;
+        let members = self
+            .orgs
+            .members(&org)?
+            .into_iter()
+            .map(|(member, role)| GetMemberDTO {
+                login: member.login,
+                provider: member.provider,
+                role,
+            })
+            .collect();
+        Ok(members)
+    }
+
+    /// Adds the user with the login to the organization or changes their role. The user must
+    /// have logged in once. The login of the user themselves always names them.
+    pub fn set_member(
+        &self,
+        user: &Actor,
+        org_name: &str,
+        login: &str,
+        role: Role,
+    ) -> Result<(), Error> {
+        let org = self.org(user, org_name, Role::Owner)?;
+        let member = match is_login_of(user, login) {
+            true => user.user.clone(),
+            false => user_by_login(&self.users, login)?.ok_or(UserNotFound)?,
+        };
+        if role != Role::Owner && self.is_last_owner(&org, &member)? {
+            return Err(LastOwner);
+        }
+        let event = AuditEvent {
+            target: Some(login.to_string()),
+            details: Some(format!("{} in {}", role, org.name)),
+            ..user.event(AuditAction::MemberSet)
+        };
+        self.orgs
+            .save_member(&OrgMember::new(org.id, member.id, role), &event)?;
+        Ok(())
+    }
+
+    /// Removes the member from the organization. Owners may remove anyone, every member may
+    /// leave.
+    pub fn remove_member(&self, user: &Actor, org_name: &str, login: &str) -> Result<(), Error> {
+        let leaving = is_login_of(user, login);
+        let required = if leaving { Role::Viewer } else { Role::Owner };
+        let org = self.org(user, org_name, required)?;
+        let member = match leaving {
+            true => user.user.clone(),
+            false => user_by_login(&self.users, login)?.ok_or(MemberNotFound)?,
+        };
+        if self.is_last_owner(&org, &member)? {
+            return Err(LastOwner);
+        }
+        let event = AuditEvent {
+            target: Some(login.to_string()),
+            details: Some(format!("from {}", org.name)),
+            ..user.event(AuditAction::MemberRemove)
+        };
+        if !self.orgs.delete_member(&org.id, &member.id, &event)? {
+            return Err(MemberNotFound);
+        }
+        Ok(())
+    }
+
+    /// A new project of the user, or of the organization the name is qualified with, like
+    /// `acme/shop`. Only owners may create projects of an organization.
+    pub fn new_project(&self, user: &User, project_name: &str) -> Result<Project, Error> {
+        let project = match project::split_name(project_name) {
+            (Some(org_name), name) => {
+                let org = self.org(user, org_name, Role::Owner)?;
+                Project {
+                    org_id: Some(org.id),
+                    ..Project::new(name.to_string(), user.id.clone())
+                }
+            }
+            (None, name) => Project::new(name.to_string(), user.id.clone()),
+        };
+        Ok(project)
+    }
+
+    /// The organization, if the role of the user in it permits what requires `required`.
+    /// Organizations the user isn't a member of aren't found.
+    fn org(&self, user: &User, org_name: &str, required: Role) -> Result<Org, Error> {
+        let org = self.orgs.read_by_name(org_name)?.ok_or(OrgNotFound)?;
+        match self.orgs.role(&org.id, &user.id)? {
+            Some(role) if role >= required => Ok(org),
+            Some(_) => Err(RoleForbidden(required)),
+            None => Err(OrgNotFound),
+        }
+    }
+
+    fn is_last_owner(&self, org: &Org, member: &User) -> Result<bool, Error> {
+        Ok(self.orgs.role(&org.id, &member.id)? == Some(Role::Owner)
+            && self.orgs.count_owners(&org.id)? == 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use faux::when;
+
+    const ORG_ID: &str = "eeGh6aiy4Iecheid9ahJo";
+    const ORG_NAME: &str = "acme";
+
+    fn user(id: &str) -> User {
+        User {
+            id: id.to_string(),
+            login: id.to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn org() -> Org {
+        Org {
+            id: ORG_ID.to_string(),
+            name: ORG_NAME.to_string(),
+            created_at: Utc::now().naive_utc(),
+        }
+    }
+
+    fn org_project(user_id: &str) -> Project {
+        Project {
+            org_id: Some(ORG_ID.to_string()),
+            ..Project::new("shop".to_string(), user_id.to_string())
+        }
+    }
+
+    #[test]
+    fn authorize_personal_project() -> anyhow::Result<()> {
+        let project = Project::new("shop".to_string(), "alice".to_string());
+
+        authorize(
+            &ProjectRepository::faux(),
+            &user("alice"),
+            &project,
+            Role::Owner,
+        )?;
+        Ok(())
+    }
+
+    #[test]
+    fn authorize_org_project() -> anyhow::Result<()> {
+        let mut projects = ProjectRepository::faux();
+        when!(projects.role)
+            .times(3)
+            .then(|(user, _)| match user.id.as_str() {
+                "alice" => Ok(Some(Role::Maintainer)),
+                _ => Ok(None),
+            });
+        let project = org_project("alice");
+
+        authorize(&projects, &user("alice"), &project, Role::Maintainer)?;
+        assert!(matches!(
+            authorize(&projects, &user("alice"), &project, Role::Owner),
+            Err(RoleForbidden(Role::Owner))
+        ));
+        assert!(matches!(
+            authorize(&projects, &user("bob"), &project, Role::Viewer),
+            Err(ProjectNotFound)
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn create_invalid_name() -> anyhow::Result<()> {
+        let orgs = OrgService::new(OrgRepository::faux(), UserRepository::faux());
+
+        assert!(matches!(
+            orgs.create(&user("alice").into(), "acme/shop"),
+            Err(InvalidOrgName)
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn set_member_requires_owner() -> anyhow::Result<()> {
+        let mut orgs_mock = OrgRepository::faux();
+        when!(orgs_mock.read_by_name)
+            .once()
+            .then(|_| Ok(Some(org())));
+        when!(orgs_mock.role)
+            .once()
+            .then_return(Ok(Some(Role::Maintainer)));
+        let orgs = OrgService::new(orgs_mock, UserRepository::faux());
+
+        let result = orgs.set_member(&user("alice").into(), ORG_NAME, "bob", Role::Viewer);
+
+        assert!(matches!(result, Err(RoleForbidden(Role::Owner))));
+        Ok(())
+    }
+
+    #[test]
+    fn remove_last_owner_fails() -> anyhow::Result<()> {
+        let mut orgs_mock = OrgRepository::faux();
+        when!(orgs_mock.read_by_name)
+            .once()
+            .then(|_| Ok(Some(org())));
+        when!(orgs_mock.role)
+            .times(2)
+            .then(|_| Ok(Some(Role::Owner)));
+        when!(orgs_mock.count_owners).once().then_return(Ok(1));
+        let orgs = OrgService::new(orgs_mock, UserRepository::faux());
+
+        let result = orgs.remove_member(&user("alice").into(), ORG_NAME, "alice");
+
+        assert!(matches!(result, Err(LastOwner)));
+        Ok(())
+    }
+
+    #[test]
+    fn set_member_ambiguous_login() -> anyhow::Result<()> {
+        let mut orgs_mock = OrgRepository::faux();
+        when!(orgs_mock.read_by_name)
+            .times(2)
+            .then(|_| Ok(Some(org())));
+        when!(orgs_mock.role)
+            .times(3)
+            .then(|_| Ok(Some(Role::Owner)));
+        when!(orgs_mock.save_member).once().then(|(member, event)| {
+            assert_eq!("sso-bob", member.user_id);
+            assert_eq!(AuditAction::MemberSet, event.action);
+            Ok(())
+        });
+        let mut users_mock = UserRepository::faux();
+        when!(users_mock.read_by_login)
+            .times(2)
+            .then(|(provider, login)| {
+                assert_eq!("bob", login);
+                let github = User {
+                    provider: "github".to_string(),
+                    ..user("github-bob")
+                };
+                let sso = User {
+                    provider: "https://sso.example.com".to_string(),
+                    ..user("sso-bob")
+                };
+                Ok(match provider {
+                    None => vec![github, sso],
+                    Some("https://sso.example.com") => vec![sso],
+                    Some(_) => vec![],
+                })
+            });
+        let orgs = OrgService::new(orgs_mock, users_mock);
+
+        let result = orgs.set_member(&user("alice").into(), ORG_NAME, "bob", Role::Owner);
+        assert!(matches!(result, Err(AmbiguousLogin(login)) if login == "bob"));
+
+        orgs.set_member(
+            &user("alice").into(),
+            ORG_NAME,
+            "https://sso.example.com:bob",
+            Role::Owner,
+        )?;
+        Ok(())
+    }
+}
@@ -1,28 +1,87 @@
+use super::{
+    audit::Actor,
+    handler::validate_stage,
+    org::{authorize, authorized_project, OrgService},
+    quota::QuotaService,
+    trash::TrashService,
+};
 use crate::repository::handler::{Handler, HandlerRepository};
 use crate::{
-    errors::Error::{self, ProjectNotFound},
+    errors::Error::{
+        self, InvalidPublicKey, ProjectNotFound, ReservedName, TrustedKeyExists, TrustedKeyNotFound,
+    },
     repository::{
+        audit::AuditEvent,
         project::{Project, ProjectRepository},
+        trusted_key::{TrustedKey, TrustedKeyRepository},
         user::User,
-        Repository,
+        variable::{StageVariable, StageVariableRepository},
     },
 };
-use common::dtos::{GetHandlerDTO, GetProjectDTO};
+use common::{
+    dtos::{
+        AuditAction, CreateTrustedKeyDTO, GetHandlerDTO, GetProjectDTO, GetTrustedKeyDTO, Role,
+        StageVariablesDTO,
+    },
+    signature,
+};
+
+/// Routes of the API, a project with one of these names couldn't be addressed
+const RESERVED_PROJECT_NAMES: &[&str] = &["audit", "auth", "import", "orgs", "tokens"];
+
+/// Fails if the project name is taken by a route of the API
+pub fn validate_project_name(project_name: &str) -> Result<(), Error> {
+    if RESERVED_PROJECT_NAMES.contains(&project_name) {
+        return Err(ReservedName(project_name.to_string()));
+    }
+    Ok(())
+}
 
 #[derive(Debug, Clone)]
 pub struct ProjectService {
     projects: ProjectRepository,
     handlers: HandlerRepository,
+    variables: StageVariableRepository,
+    trusted_keys: TrustedKeyRepository,
+    trash: TrashService,
+    quotas: QuotaService,
+    orgs: OrgService,
 }
 
 impl ProjectService {
-    pub fn new(projects: ProjectRepository, handlers: HandlerRepository) -> Self {
-        Self { projects, handlers }
+    pub fn new(
+        projects: ProjectRepository,
+        handlers: HandlerRepository,
+        variables: StageVariableRepository,
+        trusted_keys: TrustedKeyRepository,
+        trash: TrashService,
+        quotas: QuotaService,
+        orgs: OrgService,
+    ) -> Self {
+        Self {
+            projects,
+            handlers,
+            variables,
+            trusted_keys,
+            trash,
+            quotas,
+            orgs,
+        }
     }
 
-    pub fn create(&self, user_id: String, project_name: String) -> Result<(), Error> {
-        let project = Project::new(project_name, user_id);
-        self.projects.create(&project)?;
+    /// Creates the project, a name like `acme/shop` creates it in the organization. A project
+    /// with the same name in the trash is purged, it can't be restored anymore.
+    pub fn create(&self, user: &Actor, project_name: String) -> Result<(), Error> {
+        validate_project_name(&project_name)?;
+        let project = self.orgs.new_project(user, &project_name)?;
+        self.quotas.check_projects(user)?;
+        if let Some(trashed) = self.projects.trashed_by_name(user, &project_name)? {
+            self.trash.purge_project(&trashed)?;
+        }
+        self.projects.create_with_audit(
+            &project,
+            &user.project_event(AuditAction::ProjectCreate, &project, &project_name),
+        )?;
         Ok(())
     }
 
@@ -35,13 +94,153 @@ impl ProjectService {
         })
     }
 
-    pub fn delete(&self, user: &User, project_name: &str) -> Result<(), Error> {
-        let (project, handlers) = self.get_project_and_handlers(user, project_name)?;
-        self.projects.delete(&project.id)?;
-        for handler in handlers {
-            self.handlers.delete(&handler.id)?;
+    /// Moves the project together with its handlers to the trash. It's unreachable until it is
+    /// restored or purged after the retention period.
+    pub fn delete(&self, user: &Actor, project_name: &str) -> Result<(), Error> {
+        let project = authorized_project(&self.projects, user, project_name, Role::Owner)?;
+        self.projects.trash(
+            &project,
+            &user.project_event(AuditAction::ProjectDelete, &project, project_name),
+        )?;
+
+        Ok(())
+    }
+
+    /// Takes the project out of the trash together with the handlers deleted with it
+    pub fn restore(&self, user: &Actor, project_name: &str) -> Result<GetProjectDTO, Error> {
+        let project = self
+            .projects
+            .trashed_by_name(user, project_name)?
+            .ok_or(ProjectNotFound)?;
+        authorize(&self.projects, user, &project, Role::Owner)?;
+        self.quotas.check_projects(user)?;
+        self.projects.restore(
+            &project,
+            &user.project_event(AuditAction::ProjectRestore, &project, project_name),
+        )?;
+
+        self.read(user, project_name)
+    }
+
+    pub fn variables(
+        &self,
+        user: &User,
+        project_name: &str,
+        stage: &str,
+    ) -> Result<StageVariablesDTO, Error> {
+        let project = authorized_project(&self.projects, user, project_name, Role::Maintainer)?;
+
+        let variables = self
+            .variables
+            .belonging_to_stage(&project.id, stage)?
+            .into_iter()
+            .map(|variable| (variable.name, variable.value))
+            .collect();
+
+        Ok(variables)
+    }
+
+    /// Replaces the environment variables passed to the handlers of a stage. The audit log gets
+    /// their names, the values may be secret.
+    pub fn update_variables(
+        &self,
+        user: &Actor,
+        project_name: &str,
+        stage: &str,
+        variables: StageVariablesDTO,
+    ) -> Result<(), Error> {
+        validate_stage(stage)?;
+        let project = authorized_project(&self.projects, user, project_name, Role::Maintainer)?;
+
+        let names: Vec<&str> = variables.keys().map(String::as_str).collect();
+        let event = AuditEvent {
+            stage: Some(stage.to_string()),
+            details: Some(names.join(", ")),
+            ..user.project_event(AuditAction::VariablesUpdate, &project, project_name)
+        };
+
+        let variables: Vec<StageVariable> = variables
+            .into_iter()
+            .map(|(name, value)| {
+                StageVariable::new(project.id.clone(), stage.to_string(), name, value)
+            })
+            .collect();
+        self.variables
+            .replace(&project, stage, &variables, &event)?;
+
+        Ok(())
+    }
+
+    pub fn trusted_keys(
+        &self,
+        user: &User,
+        project_name: &str,
+    ) -> Result<Vec<GetTrustedKeyDTO>, Error> {
+        let project = authorized_project(&self.projects, user, project_name, Role::Viewer)?;
+
+        let keys = self
+            .trusted_keys
+            .belonging_to(&project)?
+            .into_iter()
+            .map(|key| GetTrustedKeyDTO {
+                name: key.name,
+                public_key: key.public_key,
+                created_at: key.created_at,
+            })
+            .collect();
+
+        Ok(keys)
+    }
+
+    /// Trusts signatures of the key. Once a project has a trusted key, uploaded handlers must be
+    /// signed by one of its trusted keys.
+    pub fn add_trusted_key(
+        &self,
+        user: &Actor,
+        project_name: &str,
+        key: CreateTrustedKeyDTO,
+    ) -> Result<(), Error> {
+        if !signature::is_public_key(&key.public_key) {
+            return Err(InvalidPublicKey);
+        }
+        let project = authorized_project(&self.projects, user, project_name, Role::Owner)?;
+        if self
+            .trusted_keys
+            .belonging_to(&project)?
+            .iter()
+            .any(|trusted_key| trusted_key.name == key.name)
+        {
+            return Err(TrustedKeyExists);
         }
 
+        let trusted_key =
+            TrustedKey::new(project.id.clone(), key.name.clone(), key.public_key.clone());
+        self.trusted_keys.create(
+            &trusted_key,
+            &AuditEvent {
+                target: Some(key.name),
+                details: Some(key.public_key),
+                ..user.project_event(AuditAction::KeyAdd, &project, project_name)
+            },
+        )?;
+        Ok(())
+    }
+
+    pub fn remove_trusted_key(
+        &self,
+        user: &Actor,
+        project_name: &str,
+        name: &str,
+    ) -> Result<(), Error> {
+        let project = authorized_project(&self.projects, user, project_name, Role::Owner)?;
+
+        let event = AuditEvent {
+            target: Some(name.to_string()),
+            ..user.project_event(AuditAction::KeyRemove, &project, project_name)
+        };
+        if !self.trusted_keys.delete(&project, name, &event)? {
+            return Err(TrustedKeyNotFound);
+        }
         Ok(())
     }
 
@@ -50,10 +249,7 @@ impl ProjectService {
         user: &User,
         project_name: &str,
     ) -> Result<(Project, Vec<Handler>), Error> {
-        let project = self
-            .projects
-            .belonging_to_by_name(user, project_name)?
-            .ok_or(ProjectNotFound)?;
+        let project = authorized_project(&self.projects, user, project_name, Role::Viewer)?;
         let handlers = self.handlers.belonging_to(&project)?.into_iter().collect();
 
         Ok((project, handlers))
@@ -63,7 +259,11 @@ impl ProjectService {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::repository::user::User;
+    use crate::identity::Identity;
+    use crate::repository::{
+        org::OrgRepository,
+        user::{User, UserRepository},
+    };
     use common::dtos::Language;
     use faux::when;
     use lazy_static::lazy_static;
@@ -74,20 +274,36 @@ mod tests {
     const USER_NAME: &str = "user_name";
     const USER_LOCATION: &str = "Hamburg";
     const USER_COMPANY: &str = "Noops.io";
-    const USER_GH_ACCESS_TOKEN: &str = "Yiu0Hae4ietheereij4OhneuNe6tae0e";
-    const USER_GH_LOGIN: &str = "login_name";
-    const USER_GH_ID: i32 = 42;
+    const USER_LOGIN: &str = "login_name";
+    const USER_SUBJECT: &str = "42";
 
     lazy_static! {
-        static ref USER: User = User::new(
-            USER_EMAIL.to_string(),
-            Some(USER_NAME.to_string()),
-            Some(USER_LOCATION.to_string()),
-            Some(USER_COMPANY.to_string()),
-            USER_GH_ID,
-            USER_GH_LOGIN.to_string(),
-            USER_GH_ACCESS_TOKEN.to_string()
-        );
+        static ref USER: User = User::new(Identity {
+            provider: "github".to_string(),
+            subject: USER_SUBJECT.to_string(),
+            login: USER_LOGIN.to_string(),
+            email: USER_EMAIL.to_string(),
+            name: Some(USER_NAME.to_string()),
+            location: Some(USER_LOCATION.to_string()),
+            company: Some(USER_COMPANY.to_string()),
+        });
+        static ref ACTOR: Actor = Actor::from(USER.clone());
+    }
+
+    /// Organizations, which personal projects don't need
+    fn orgs() -> OrgService {
+        OrgService::new(OrgRepository::faux(), UserRepository::faux())
+    }
+
+    /// Quotas which are never exceeded
+    fn quotas_mock() -> QuotaService {
+        let mut quotas_mock = QuotaService::faux();
+        when!(quotas_mock.check_projects).then(|_| Ok(()));
+        when!(quotas_mock.check_handlers).then(|_| Ok(()));
+        when!(quotas_mock.check_artifact).then(|_| Ok(()));
+        when!(quotas_mock.check_storage).then(|_| Ok(()));
+        when!(quotas_mock.count_invocation).then(|_| Ok(()));
+        quotas_mock
     }
 
     #[test]
@@ -99,12 +315,14 @@ mod tests {
             Language::Rust,
             "lohSh8xi".to_string(),
             project_expected.id.clone(),
+            "prod".to_string(),
         );
         let handler_2 = Handler::new(
             "HANDLER_2".to_string(),
             Language::Rust,
             "yie7aeH1".to_string(),
             project_expected.id.clone(),
+            "prod".to_string(),
         );
         let handlers_expected = vec![handler_1.clone(), handler_2.clone()];
 
@@ -119,10 +337,19 @@ mod tests {
         when!(handlers_mock.belonging_to(project_expected))
             .once()
             .then_return(Ok(handlers_expected));
+        let variables_mock = StageVariableRepository::faux();
 
         // -------------------------------------------------------------------------------------
 
-        let project_service = ProjectService::new(projects_mock, handlers_mock);
+        let project_service = ProjectService::new(
+            projects_mock,
+            handlers_mock,
+            variables_mock,
+            TrustedKeyRepository::faux(),
+            TrashService::faux(),
+            quotas_mock(),
+            orgs(),
+        );
         let project = project_service.read(&USER, PROJECT_NAME)?;
 
         assert_eq!(PROJECT_NAME, project.name);
@@ -139,10 +366,19 @@ mod tests {
             .once()
             .then_return(Ok(None));
         let handlers_mock = HandlerRepository::faux();
+        let variables_mock = StageVariableRepository::faux();
 
         // -------------------------------------------------------------------------------------
 
-        let project_service = ProjectService::new(projects_mock, handlers_mock);
+        let project_service = ProjectService::new(
+            projects_mock,
+            handlers_mock,
+            variables_mock,
+            TrustedKeyRepository::faux(),
+            TrashService::faux(),
+            quotas_mock(),
+            orgs(),
+        );
         let result = project_service.read(&USER, PROJECT_NAME);
 
         assert!(result.is_err());
@@ -163,14 +399,251 @@ mod tests {
             .once()
             .then_return(Ok(None));
         let handlers_mock = HandlerRepository::faux();
+        let variables_mock = StageVariableRepository::faux();
 
         // -------------------------------------------------------------------------------------
 
-        let project_service = ProjectService::new(projects_mock, handlers_mock);
-        let result = project_service.delete(&USER, PROJECT_NAME);
+        let project_service = ProjectService::new(
+            projects_mock,
+            handlers_mock,
+            variables_mock,
+            TrustedKeyRepository::faux(),
+            TrashService::faux(),
+            quotas_mock(),
+            orgs(),
+        );
+        let result = project_service.delete(&ACTOR, PROJECT_NAME);
 
         assert!(result.is_err());
 
         Ok(())
     }
+
+    #[test]
+    fn create_purges_trashed() -> anyhow::Result<()> {
+        let trashed = Project::new(PROJECT_NAME.to_string(), USER.id.clone());
+
+        // -------------------------------------------------------------------------------------
+
+        let mut projects_mock = ProjectRepository::faux();
+        when!(projects_mock.trashed_by_name(USER.clone(), PROJECT_NAME))
+            .once()
+            .then_return(Ok(Some(trashed.clone())));
+        let trashed_id = trashed.id.clone();
+        when!(projects_mock.create_with_audit(
+            *_ = faux::from_fn!(move |project: &Project| project.id != trashed_id),
+            _
+        ))
+        .once()
+        .then_return(Ok(()));
+        let mut trash_mock = TrashService::faux();
+        when!(trash_mock.purge_project(trashed))
+            .once()
+            .then_return(Ok(()));
+
+        // -------------------------------------------------------------------------------------
+
+        let project_service = ProjectService::new(
+            projects_mock,
+            HandlerRepository::faux(),
+            StageVariableRepository::faux(),
+            TrustedKeyRepository::faux(),
+            trash_mock,
+            quotas_mock(),
+            orgs(),
+        );
+        project_service.create(&ACTOR, PROJECT_NAME.to_string())?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn create_reserved_name() {
+        let project_service = ProjectService::new(
+            ProjectRepository::faux(),
+            HandlerRepository::faux(),
+            StageVariableRepository::faux(),
+            TrustedKeyRepository::faux(),
+            TrashService::faux(),
+            quotas_mock(),
+            orgs(),
+        );
+        let result = project_service.create(&ACTOR, "import".to_string());
+
+        assert!(matches!(result, Err(ReservedName(name)) if name == "import"));
+    }
+
+    #[test]
+    fn validate_project_name_reserved() {
+        for name in ["tokens", "orgs", "audit"] {
+            assert!(matches!(validate_project_name(name), Err(ReservedName(_))));
+        }
+        assert!(validate_project_name("tokens_backup").is_ok());
+    }
+
+    #[test]
+    fn restore_ok() -> anyhow::Result<()> {
+        let mut trashed = Project::new(PROJECT_NAME.to_string(), USER.id.clone());
+        trashed.deleted_at = Some(chrono::Utc::now().naive_utc());
+        let restored = Project {
+            deleted_at: None,
+            ..trashed.clone()
+        };
+
+        // -------------------------------------------------------------------------------------
+
+        let mut projects_mock = ProjectRepository::faux();
+        when!(projects_mock.trashed_by_name(USER.clone(), PROJECT_NAME))
+            .once()
+            .then_return(Ok(Some(trashed.clone())));
+        when!(projects_mock.restore(
+            trashed,
+            *_ = faux::from_fn!(|event: &AuditEvent| event.action == AuditAction::ProjectRestore)
+        ))
+        .once()
+        .then_return(Ok(()));
+        when!(projects_mock.belonging_to_by_name(USER.clone(), PROJECT_NAME))
+            .once()
+            .then_return(Ok(Some(restored.clone())));
+        let mut handlers_mock = HandlerRepository::faux();
+        when!(handlers_mock.belonging_to(restored))
+            .once()
+            .then_return(Ok(vec![]));
+
+        // -------------------------------------------------------------------------------------
+
+        let project_service = ProjectService::new(
+            projects_mock,
+            handlers_mock,
+            StageVariableRepository::faux(),
+            TrustedKeyRepository::faux(),
+            TrashService::faux(),
+            quotas_mock(),
+            orgs(),
+        );
+        let project = project_service.restore(&ACTOR, PROJECT_NAME)?;
+
+        assert_eq!(PROJECT_NAME, project.name);
+        Ok(())
+    }
+
+    #[test]
+    fn variables_ok() -> anyhow::Result<()> {
+        let project = Project::new(PROJECT_NAME.to_string(), USER.id.clone());
+        let variable = StageVariable::new(
+            project.id.clone(),
+            "staging".to_string(),
+            "API_URL".to_string(),
+            "https://staging.example.com".to_string(),
+        );
+
+        // -------------------------------------------------------------------------------------
+
+        let mut projects_mock = ProjectRepository::faux();
+        when!(projects_mock.belonging_to_by_name(USER.clone(), PROJECT_NAME))
+            .once()
+            .then_return(Ok(Some(project.clone())));
+        let handlers_mock = HandlerRepository::faux();
+        let mut variables_mock = StageVariableRepository::faux();
+        when!(variables_mock.belonging_to_stage(_, "staging"))
+            .once()
+            .then_return(Ok(vec![variable]));
+
+        // -------------------------------------------------------------------------------------
+
+        let project_service = ProjectService::new(
+            projects_mock,
+            handlers_mock,
+            variables_mock,
+            TrustedKeyRepository::faux(),
+            TrashService::faux(),
+            quotas_mock(),
+            orgs(),
+        );
+        let variables = project_service.variables(&USER, PROJECT_NAME, "staging")?;
+
+        assert_eq!(
+            Some(&"https://staging.example.com".to_string()),
+            variables.get("API_URL")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn update_variables_invalid_stage() {
+        let project_service = ProjectService::new(
+            ProjectRepository::faux(),
+            HandlerRepository::faux(),
+            StageVariableRepository::faux(),
+            TrustedKeyRepository::faux(),
+            TrashService::faux(),
+            quotas_mock(),
+            orgs(),
+        );
+        let result = project_service.update_variables(
+            &ACTOR,
+            PROJECT_NAME,
+            "prod/eu",
+            StageVariablesDTO::new(),
+        );
+
+        assert!(matches!(result, Err(Error::InvalidStage)));
+    }
+
+    #[test]
+    fn add_trusted_key_invalid() {
+        let project_service = ProjectService::new(
+            ProjectRepository::faux(),
+            HandlerRepository::faux(),
+            StageVariableRepository::faux(),
+            TrustedKeyRepository::faux(),
+            TrashService::faux(),
+            quotas_mock(),
+            orgs(),
+        );
+        let key = CreateTrustedKeyDTO {
+            name: "ci".to_string(),
+            public_key: "Aiph4ahk".to_string(),
+        };
+        let result = project_service.add_trusted_key(&ACTOR, PROJECT_NAME, key);
+
+        assert!(matches!(result, Err(InvalidPublicKey)));
+    }
+
+    #[test]
+    fn add_trusted_key_exists() {
+        let project = Project::new(PROJECT_NAME.to_string(), USER.id.clone());
+        let public_key = signature::public_key(&signature::generate_key());
+        let trusted_key = TrustedKey::new(project.id.clone(), "ci".to_string(), public_key.clone());
+
+        // -------------------------------------------------------------------------------------
+
+        let mut projects_mock = ProjectRepository::faux();
+        when!(projects_mock.belonging_to_by_name(USER.clone(), PROJECT_NAME))
+            .once()
+            .then_return(Ok(Some(project.clone())));
+        let mut trusted_keys_mock = TrustedKeyRepository::faux();
+        when!(trusted_keys_mock.belonging_to(project))
+            .once()
+            .then_return(Ok(vec![trusted_key]));
+
+        // -------------------------------------------------------------------------------------
+
+        let project_service = ProjectService::new(
+            projects_mock,
+            HandlerRepository::faux(),
+            StageVariableRepository::faux(),
+            trusted_keys_mock,
+            TrashService::faux(),
+            quotas_mock(),
+            orgs(),
+        );
+        let key = CreateTrustedKeyDTO {
+            name: "ci".to_string(),
+            public_key,
+        };
+        let result = project_service.add_trusted_key(&ACTOR, PROJECT_NAME, key);
+
+        assert!(matches!(result, Err(TrustedKeyExists)));
+    }
 }
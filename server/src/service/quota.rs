@@ -0,0 +1,457 @@
+use super::org::authorized_project;
+use crate::{
+    errors::Error::{self, QuotaExceeded},
+    repository::{
+        handler::HandlerRepository,
+        project::{Project, ProjectRepository},
+        quota::{QuotaOverride, QuotaRepository},
+        user::User,
+        version::HandlerVersionRepository,
+    },
+    wasmstore::WasmStore,
+};
+use chrono::Utc;
+use common::dtos::{GetQuotasDTO, QuotaDTO, Role};
+use serde::Deserialize;
+use std::{
+    collections::HashMap,
+    fmt::{self, Display},
+};
+
+/// A limit of a user
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Quota {
+    Projects,
+    HandlersPerProject,
+    ArtifactSize,
+    Storage,
+    InvocationsPerDay,
+}
+
+impl Display for Quota {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Quota::Projects => "projects",
+            Quota::HandlersPerProject => "handlers per project",
+            Quota::ArtifactSize => "artifact size in bytes",
+            Quota::Storage => "storage in bytes",
+            Quota::InvocationsPerDay => "invocations per day",
+        })
+    }
+}
+
+/// The limits of a user, the defaults of the server apply unless the user has overrides
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Quotas {
+    pub projects: i64,
+    pub handlers_per_project: i64,
+    pub artifact_size: i64,
+    pub storage: i64,
+    pub invocations_per_day: i64,
+}
+
+impl Default for Quotas {
+    fn default() -> Self {
+        Self {
+            projects: 10,
+            handlers_per_project: 50,
+            artifact_size: 10_000_000,
+            storage: 1_000_000_000,
+            invocations_per_day: 100_000,
+        }
+    }
+}
+
+impl Quotas {
+    fn with_override(self, quota: &QuotaOverride) -> Self {
+        Self {
+            projects: quota.projects.unwrap_or(self.projects),
+            handlers_per_project: quota
+                .handlers_per_project
+                .unwrap_or(self.handlers_per_project),
+            artifact_size: quota.artifact_size.unwrap_or(self.artifact_size),
+            storage: quota.storage.unwrap_or(self.storage),
+            invocations_per_day: quota
+                .invocations_per_day
+                .unwrap_or(self.invocations_per_day),
+        }
+    }
+}
+
+/// Checks the quotas of the users before anything is created and counts the invocations of
+/// their handlers. Everything in a project counts against the quotas of the user who created
+/// it, also in projects of organizations, which have no quotas of their own. So deploys and
+/// invocations of a project are held to the same limits, whoever sends them.
+#[cfg_attr(test, faux::create)]
+#[derive(Debug, Clone)]
+pub struct QuotaService {
+    quotas: QuotaRepository,
+    projects: ProjectRepository,
+    handlers: HandlerRepository,
+    versions: HandlerVersionRepository,
+    wasmstore: WasmStore,
+    defaults: Quotas,
+}
+
+#[cfg_attr(test, faux::methods)]
+impl QuotaService {
+    pub fn new(
+        quotas: QuotaRepository,
+        projects: ProjectRepository,
+        handlers: HandlerRepository,
+        versions: HandlerVersionRepository,
+        wasmstore: WasmStore,
+        defaults: Quotas,
+    ) -> Self {
+        Self {
+            quotas,
+            projects,
+            handlers,
+            versions,
+            wasmstore,
+            defaults,
+        }
+    }
+
+    pub fn limits(&self, user_id: &str) -> Result<Quotas, Error> {
+        let limits = match self.quotas.read(user_id)? {
+            Some(quota) => self.defaults.with_override(&quota),
+            None => self.defaults,
+        };
+        Ok(limits)
+    }
+
+    /// Overrides the given quotas of the user, the others stay unchanged
+    pub fn set(&self, quota: &QuotaOverride) -> Result<(), Error> {
+        let current = self.quotas.read(&quota.user_id)?.unwrap_or_default();
+        self.quotas.save(&QuotaOverride {
+            user_id: quota.user_id.clone(),
+            projects: quota.projects.or(current.projects),
+            handlers_per_project: quota.handlers_per_project.or(current.handlers_per_project),
+            artifact_size: quota.artifact_size.or(current.artifact_size),
+            storage: quota.storage.or(current.storage),
+            invocations_per_day: quota.invocations_per_day.or(current.invocations_per_day),
+        })?;
+        Ok(())
+    }
+
+    /// Removes the overrides of the user
+    pub fn reset(&self, user_id: &str) -> Result<(), Error> {
+        self.quotas.delete(user_id)?;
+        Ok(())
+    }
+
+    /// Fails if the user can't have another project
+    pub fn check_projects(&self, user: &User) -> Result<(), Error> {
+        let limit = self.limits(&user.id)?.projects;
+        if self.projects.count_belonging_to(&user.id)? >= limit {
+            return Err(QuotaExceeded {
+                quota: Quota::Projects,
+                limit,
+            });
+        }
+        Ok(())
+    }
+
+    /// Fails if the project can't have `additional` more handlers
+    pub fn check_handlers(&self, project: &Project, additional: i64) -> Result<(), Error> {
+        let limit = self.limits(&project.user_id)?.handlers_per_project;
+        if self.handlers.count_belonging_to(project)? + additional > limit {
+            return Err(QuotaExceeded {
+                quota: Quota::HandlersPerProject,
+                limit,
+            });
+        }
+        Ok(())
+    }
+
+    /// Fails if the uploaded module is larger than the project may deploy
+    pub fn check_artifact(&self, project: &Project, size: usize) -> Result<(), Error> {
+        let limit = self.limits(&project.user_id)?.artifact_size;
+        if size as i64 > limit {
+            return Err(QuotaExceeded {
+                quota: Quota::ArtifactSize,
+                limit,
+            });
+        }
+        Ok(())
+    }
+
+    /// Fails if storing the components of the project, given as their sizes by digest, exceeds
+    /// the storage of its creator. Components the creator stores already don't count again.
+    pub fn check_storage(
+        &self,
+        project: &Project,
+        components: &HashMap<String, i64>,
+    ) -> Result<(), Error> {
+        let limit = self.limits(&project.user_id)?.storage;
+        let stored = self.versions.sizes_belonging_to(&project.user_id)?;
+        let additional: i64 = components
+            .iter()
+            .filter(|(digest, _)| !stored.contains_key(*digest))
+            .map(|(_, size)| size)
+            .sum();
+        if stored.values().sum::<i64>() + additional > limit {
+            return Err(QuotaExceeded {
+                quota: Quota::Storage,
+                limit,
+            });
+        }
+        Ok(())
+    }
+
+    /// Counts an invocation of a handler of the project, unless its creator has used up the
+    /// invocations of the day
+    pub fn count_invocation(&self, project: &Project) -> Result<(), Error> {
+        let user_id = &project.user_id;
+        let limit = self.limits(user_id)?.invocations_per_day;
+        let today = Utc::now().date_naive();
+        if !self.quotas.count_invocation(user_id, today, limit)? {
+            return Err(QuotaExceeded {
+                quota: Quota::InvocationsPerDay,
+                limit,
+            });
+        }
+        Ok(())
+    }
+
+    /// The usage of the quotas the project counts against, which are those of its creator
+    pub fn usage(&self, user: &User, project_name: &str) -> Result<GetQuotasDTO, Error> {
+        let project = authorized_project(&self.projects, user, project_name, Role::Viewer)?;
+        let owner = &project.user_id;
+        let limits = self.limits(owner)?;
+
+        Ok(GetQuotasDTO {
+            projects: QuotaDTO {
+                used: self.projects.count_belonging_to(owner)?,
+                limit: limits.projects,
+            },
+            handlers: QuotaDTO {
+                used: self.handlers.count_belonging_to(&project)?,
+                limit: limits.handlers_per_project,
+            },
+            artifact_size: limits.artifact_size,
+            storage: QuotaDTO {
+                used: self.versions.sizes_belonging_to(owner)?.values().sum(),
+                limit: limits.storage,
+            },
+            invocations: QuotaDTO {
+                used: self.quotas.invocations(owner, Utc::now().date_naive())?,
+                limit: limits.invocations_per_day,
+            },
+        })
+    }
+
+    /// Records the sizes of the components of versions deployed before sizes were recorded
+    /// and returns their number
+    pub fn measure_components(&self) -> anyhow::Result<usize> {
+        let blobs = self.versions.unmeasured_blobs()?;
+        for blob in &blobs {
+            let size = self.wasmstore.read(blob)?.len() as i64;
+            self.versions.set_size(blob, size)?;
+        }
+        Ok(blobs.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::identity::Identity;
+    use faux::when;
+
+    const USER_ID: &str = "aiCh0ohchoo5Ahquee3Ee";
+
+    fn user() -> User {
+        User {
+            id: USER_ID.to_string(),
+            ..User::new(Identity {
+                provider: "github".to_string(),
+                subject: "42".to_string(),
+                login: "login_name".to_string(),
+                email: "test@example.com".to_string(),
+                ..Default::default()
+            })
+        }
+    }
+
+    /// A project of an organization, created by the user
+    fn project() -> Project {
+        Project {
+            org_id: Some("ORG_ID".to_string()),
+            ..Project::new("shop".to_string(), USER_ID.to_string())
+        }
+    }
+
+    fn service(
+        quotas: QuotaRepository,
+        projects: ProjectRepository,
+        versions: HandlerVersionRepository,
+    ) -> QuotaService {
+        service_with_handlers(quotas, projects, HandlerRepository::faux(), versions)
+    }
+
+    fn service_with_handlers(
+        quotas: QuotaRepository,
+        projects: ProjectRepository,
+        handlers: HandlerRepository,
+        versions: HandlerVersionRepository,
+    ) -> QuotaService {
+        QuotaService::new(
+            quotas,
+            projects,
+            handlers,
+            versions,
+            WasmStore::faux(),
+            Quotas::default(),
+        )
+    }
+
+    #[test]
+    fn limits_override() -> anyhow::Result<()> {
+        let mut quotas_mock = QuotaRepository::faux();
+        when!(quotas_mock.read)
+            .once()
+            .then_return(Ok(Some(QuotaOverride {
+                user_id: USER_ID.to_string(),
+                projects: Some(20),
+                ..Default::default()
+            })));
+
+        let quotas = service(
+            quotas_mock,
+            ProjectRepository::faux(),
+            HandlerVersionRepository::faux(),
+        );
+
+        assert_eq!(
+            Quotas {
+                projects: 20,
+                ..Quotas::default()
+            },
+            quotas.limits(USER_ID)?
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn check_projects_exceeded() -> anyhow::Result<()> {
+        let mut quotas_mock = QuotaRepository::faux();
+        when!(quotas_mock.read).once().then_return(Ok(None));
+        let mut projects_mock = ProjectRepository::faux();
+        when!(projects_mock.count_belonging_to)
+            .once()
+            .then_return(Ok(Quotas::default().projects));
+
+        let quotas = service(quotas_mock, projects_mock, HandlerVersionRepository::faux());
+        let result = quotas.check_projects(&user());
+
+        assert!(matches!(
+            result,
+            Err(QuotaExceeded {
+                quota: Quota::Projects,
+                ..
+            })
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn check_handlers_against_creator() -> anyhow::Result<()> {
+        let mut quotas_mock = QuotaRepository::faux();
+        when!(quotas_mock.read(USER_ID))
+            .once()
+            .then_return(Ok(Some(QuotaOverride {
+                user_id: USER_ID.to_string(),
+                handlers_per_project: Some(2),
+                ..Default::default()
+            })));
+        let mut handlers_mock = HandlerRepository::faux();
+        when!(handlers_mock.count_belonging_to)
+            .once()
+            .then_return(Ok(2));
+
+        let quotas = service_with_handlers(
+            quotas_mock,
+            ProjectRepository::faux(),
+            handlers_mock,
+            HandlerVersionRepository::faux(),
+        );
+        let result = quotas.check_handlers(&project(), 1);
+
+        assert!(matches!(
+            result,
+            Err(QuotaExceeded {
+                quota: Quota::HandlersPerProject,
+                limit: 2
+            })
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn check_storage_counts_new_components() -> anyhow::Result<()> {
+        let mut quotas_mock = QuotaRepository::faux();
+        when!(quotas_mock.read).times(2).then(|_| {
+            Ok(Some(QuotaOverride {
+                user_id: USER_ID.to_string(),
+                storage: Some(100),
+                ..Default::default()
+            }))
+        });
+        let mut versions_mock = HandlerVersionRepository::faux();
+        when!(versions_mock.sizes_belonging_to)
+            .times(2)
+            .then(|_| Ok(HashMap::from([("STORED".to_string(), 80)])));
+
+        let quotas = service(quotas_mock, ProjectRepository::faux(), versions_mock);
+
+        quotas.check_storage(&project(), &HashMap::from([("STORED".to_string(), 80)]))?;
+        let result = quotas.check_storage(&project(), &HashMap::from([("NEW".to_string(), 30)]));
+        assert!(matches!(
+            result,
+            Err(QuotaExceeded {
+                quota: Quota::Storage,
+                limit: 100
+            })
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn count_invocation_exceeded() -> anyhow::Result<()> {
+        let mut quotas_mock = QuotaRepository::faux();
+        when!(quotas_mock.read).times(2).then(|_| {
+            Ok(Some(QuotaOverride {
+                user_id: USER_ID.to_string(),
+                invocations_per_day: Some(1),
+                ..Default::default()
+            }))
+        });
+        let mut count = 0;
+        when!(quotas_mock.count_invocation)
+            .times(2)
+            .then(move |(_, _, limit)| {
+                count += 1;
+                Ok(count <= limit)
+            });
+
+        let quotas = service(
+            quotas_mock,
+            ProjectRepository::faux(),
+            HandlerVersionRepository::faux(),
+        );
+
+        quotas.count_invocation(&project())?;
+        let result = quotas.count_invocation(&project());
+        assert!(matches!(
+            result,
+            Err(QuotaExceeded {
+                quota: Quota::InvocationsPerDay,
+                limit: 1
+            })
+        ));
+        Ok(())
+    }
+}
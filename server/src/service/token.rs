@@ -0,0 +1,415 @@
+use super::audit::Actor;
+use crate::{
+    errors::Error::{
+        self, InvalidApiToken, ProjectNotFound, TokenExists, TokenForbidden, TokenNotFound,
+    },
+    repository::{
+        api_token::{ApiToken, ApiTokenRepository},
+        audit::AuditEvent,
+        project::ProjectRepository,
+        user::{User, UserRepository},
+        Repository,
+    },
+};
+use axum::http::Method;
+use common::{
+    dtos::{AuditAction, CreateTokenDTO, CreatedTokenDTO, GetTokenDTO, TokenScope},
+    hash,
+};
+use percent_encoding::percent_decode_str;
+
+/// Distinguishes API tokens from access tokens
+pub const TOKEN_PREFIX: &str = "noops_";
+
+/// About 256 random bits
+const TOKEN_LENGTH: usize = 43;
+
+const PROJECT_ROUTE: &str = "/api/:project_name";
+const HANDLER_ROUTE: &str = "/api/:project_name/:function_name";
+const KEYS_ROUTE: &str = "/api/:project_name/keys";
+const VARIABLES_ROUTE: &str = "/api/:project_name/stages/:stage/variables";
+
+/// The requests a read token may send. The variables of the stages are left out, as they
+/// hold secrets.
+const READ_ROUTES: &[(Method, &str)] = &[
+    (Method::GET, PROJECT_ROUTE),
+    (Method::GET, KEYS_ROUTE),
+    (Method::GET, "/api/:project_name/quotas"),
+    (Method::GET, HANDLER_ROUTE),
+    (Method::GET, "/api/:project_name/:function_name/versions"),
+    (Method::GET, "/api/:project_name/:function_name/canary"),
+    (Method::GET, "/api/audit"),
+];
+
+/// The requests a deploy token may send besides those of a read token
+const DEPLOY_ROUTES: &[(Method, &str)] = &[
+    (Method::POST, PROJECT_ROUTE),
+    (Method::PUT, HANDLER_ROUTE),
+    (Method::POST, "/api/:project_name/:function_name/rollback"),
+    (
+        Method::POST,
+        "/api/:project_name/:function_name/canary/promote",
+    ),
+    (
+        Method::POST,
+        "/api/:project_name/:function_name/canary/abort",
+    ),
+    (Method::POST, "/api/:project_name/stages/:stage/promote"),
+];
+
+/// The requests a full token may send besides those of a deploy token. Tokens and
+/// organizations can't be managed with any token.
+const FULL_ROUTES: &[(Method, &str)] = &[
+    (Method::DELETE, PROJECT_ROUTE),
+    (Method::POST, "/api/:project_name/restore"),
+    (Method::POST, KEYS_ROUTE),
+    (Method::DELETE, "/api/:project_name/keys/:key_name"),
+    (Method::DELETE, HANDLER_ROUTE),
+    (Method::POST, "/api/:project_name/:function_name/restore"),
+    (Method::GET, VARIABLES_ROUTE),
+    (Method::PUT, VARIABLES_ROUTE),
+    (Method::GET, "/api/:project_name/export"),
+    (Method::POST, "/api/import"),
+];
+
+/// A request to check against the scope of a token
+#[derive(Debug, Clone, PartialEq)]
+pub struct Access {
+    pub method: Method,
+    /// The matched route, e.g. `/api/:project_name/keys`
+    pub route: String,
+    /// The project of the route, if it has one
+    pub project: Option<String>,
+}
+
+impl Access {
+    pub fn new(method: Method, route: &str, path: &str) -> Self {
+        let project = route
+            .starts_with(PROJECT_ROUTE)
+            .then(|| path.split('/').nth(2))
+            .flatten()
+            .map(|project| percent_decode_str(project).decode_utf8_lossy().into_owned());
+        Self {
+            method,
+            route: route.to_string(),
+            project,
+        }
+    }
+
+    /// Whether the scope of the token lists the request. Routes no scope lists, like new
+    /// ones, are refused to every token.
+    fn permitted_by(&self, token: &ApiToken) -> bool {
+        if token.project.is_some() && self.project != token.project {
+            return false;
+        }
+        let scopes: &[&[(Method, &str)]] = match token.scope {
+            TokenScope::Read => &[READ_ROUTES],
+            TokenScope::Deploy => &[READ_ROUTES, DEPLOY_ROUTES],
+            TokenScope::Full => &[READ_ROUTES, DEPLOY_ROUTES, FULL_ROUTES],
+        };
+        scopes
+            .iter()
+            .flat_map(|routes| routes.iter())
+            .any(|(method, route)| self.method == method && self.route == *route)
+    }
+}
+
+pub fn is_api_token(token: &str) -> bool {
+    token.starts_with(TOKEN_PREFIX)
+}
+
+/// Manages the API tokens of the users and authenticates requests sent with them
+#[cfg_attr(test, faux::create)]
+#[derive(Debug, Clone)]
+pub struct TokenService {
+    tokens: ApiTokenRepository,
+    users: UserRepository,
+    projects: ProjectRepository,
+}
+
+#[cfg_attr(test, faux::methods)]
+impl TokenService {
+    pub fn new(
+        tokens: ApiTokenRepository,
+        users: UserRepository,
+        projects: ProjectRepository,
+    ) -> Self {
+        Self {
+            tokens,
+            users,
+            projects,
+        }
+    }
+
+    /// Creates a token of the user, the returned secret can't be read again
+    pub fn create(&self, user: &Actor, token: &CreateTokenDTO) -> Result<CreatedTokenDTO, Error> {
+        if self.tokens.read(&user.id, &token.name)?.is_some() {
+            return Err(TokenExists);
+        }
+        if let Some(project) = &token.project {
+            self.projects
+                .belonging_to_by_name(user, project)?
+                .ok_or(ProjectNotFound)?;
+        }
+
+        let secret = format!("{}{}", TOKEN_PREFIX, nanoid::nanoid!(TOKEN_LENGTH));
+        self.tokens.create(
+            &ApiToken::new(
+                user.id.clone(),
+                token.name.clone(),
+                hash::hash(secret.as_bytes()),
+                token.scope,
+                token.project.clone(),
+            ),
+            &AuditEvent {
+                project: token.project.clone(),
+                target: Some(token.name.clone()),
+                details: Some(format!("scope {}", token.scope)),
+                ..user.event(AuditAction::TokenCreate)
+            },
+        )?;
+
+        Ok(CreatedTokenDTO {
+            name: token.name.clone(),
+            token: secret,
+        })
+    }
+
+    pub fn list(&self, user: &User) -> Result<Vec<GetTokenDTO>, Error> {
+        let tokens = self.tokens.belonging_to(&user.id)?;
+        Ok(tokens.into_iter().map(GetTokenDTO::from).collect())
+    }
+
+    pub fn revoke(&self, user: &Actor, name: &str) -> Result<(), Error> {
+        let event = AuditEvent {
+            target: Some(name.to_string()),
+            ..user.event(AuditAction::TokenRevoke)
+        };
+        if !self.tokens.delete(&user.id, name, &event)? {
+            return Err(TokenNotFound);
+        }
+        Ok(())
+    }
+
+    /// The user and the name of the token, if its scope permits the request
+    pub fn authenticate(&self, token: &str, access: &Access) -> Result<(User, String), Error> {
+        let token = self
+            .tokens
+            .read_by_hash(&hash::hash(token.as_bytes()))?
+            .ok_or(InvalidApiToken)?;
+        if !access.permitted_by(&token) {
+            return Err(TokenForbidden);
+        }
+        let user = self.users.read(&token.user_id)?.ok_or(InvalidApiToken)?;
+        self.tokens.touch(&token)?;
+        Ok((user, token.name))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::identity::Identity;
+    use faux::when;
+
+    const USER_ID: &str = "aiCh0ohchoo5Ahquee3Ee";
+    const PROJECT_NAME: &str = "PROJECT_NAME";
+    const TOKEN_NAME: &str = "ci";
+
+    fn user() -> User {
+        User {
+            id: USER_ID.to_string(),
+            ..User::new(Identity {
+                provider: "github".to_string(),
+                subject: "42".to_string(),
+                login: "login_name".to_string(),
+                email: "test@example.com".to_string(),
+                ..Default::default()
+            })
+        }
+    }
+
+    fn token(scope: TokenScope, project: Option<&str>) -> ApiToken {
+        ApiToken::new(
+            USER_ID.to_string(),
+            TOKEN_NAME.to_string(),
+            "HASH".to_string(),
+            scope,
+            project.map(str::to_string),
+        )
+    }
+
+    fn access(method: Method, route: &str, path: &str) -> Access {
+        Access::new(method, route, path)
+    }
+
+    #[test]
+    fn access_project() {
+        let access = access(
+            Method::PUT,
+            "/api/:project_name/:function_name",
+            "/api/PROJECT_NAME/handler",
+        );
+        assert_eq!(Some(PROJECT_NAME.to_string()), access.project);
+        assert_eq!(
+            None,
+            Access::new(Method::POST, "/api/import", "/api/import").project
+        );
+        assert_eq!(
+            Some("acme/shop".to_string()),
+            Access::new(Method::GET, PROJECT_ROUTE, "/api/acme%2Fshop").project
+        );
+    }
+
+    #[test]
+    fn read_scope() {
+        let token = token(TokenScope::Read, None);
+        assert!(access(Method::GET, PROJECT_ROUTE, "/api/PROJECT_NAME").permitted_by(&token));
+        assert!(!access(Method::POST, PROJECT_ROUTE, "/api/PROJECT_NAME").permitted_by(&token));
+        assert!(!access(
+            Method::GET,
+            "/api/:project_name/export",
+            "/api/PROJECT_NAME/export"
+        )
+        .permitted_by(&token));
+    }
+
+    #[test]
+    fn deploy_scope() {
+        let token = token(TokenScope::Deploy, None);
+        assert!(access(
+            Method::PUT,
+            "/api/:project_name/:function_name",
+            "/api/PROJECT_NAME/handler"
+        )
+        .permitted_by(&token));
+        assert!(access(Method::POST, PROJECT_ROUTE, "/api/PROJECT_NAME").permitted_by(&token));
+        assert!(!access(Method::DELETE, PROJECT_ROUTE, "/api/PROJECT_NAME").permitted_by(&token));
+        assert!(!access(
+            Method::POST,
+            "/api/:project_name/keys",
+            "/api/PROJECT_NAME/keys"
+        )
+        .permitted_by(&token));
+    }
+
+    #[test]
+    fn variables_and_trash_need_full_scope() {
+        let requests = [
+            (
+                Method::GET,
+                VARIABLES_ROUTE,
+                "/api/PROJECT_NAME/stages/prod/variables",
+            ),
+            (
+                Method::PUT,
+                VARIABLES_ROUTE,
+                "/api/PROJECT_NAME/stages/prod/variables",
+            ),
+            (
+                Method::POST,
+                "/api/:project_name/restore",
+                "/api/PROJECT_NAME/restore",
+            ),
+            (
+                Method::POST,
+                "/api/:project_name/:function_name/restore",
+                "/api/PROJECT_NAME/handler/restore",
+            ),
+        ];
+        for (method, route, path) in requests {
+            let access = access(method, route, path);
+            assert!(!access.permitted_by(&token(TokenScope::Read, None)));
+            assert!(!access.permitted_by(&token(TokenScope::Deploy, None)));
+            assert!(access.permitted_by(&token(TokenScope::Full, None)));
+        }
+    }
+
+    #[test]
+    fn project_and_tokens_forbidden() {
+        let project_token = token(TokenScope::Full, Some(PROJECT_NAME));
+        assert!(
+            access(Method::DELETE, PROJECT_ROUTE, "/api/PROJECT_NAME").permitted_by(&project_token)
+        );
+        assert!(
+            !access(Method::GET, PROJECT_ROUTE, "/api/OTHER_PROJECT").permitted_by(&project_token)
+        );
+        assert!(!access(Method::POST, "/api/import", "/api/import").permitted_by(&project_token));
+
+        let full_token = token(TokenScope::Full, None);
+        assert!(!access(Method::GET, "/api/tokens", "/api/tokens").permitted_by(&full_token));
+        assert!(!access(Method::GET, "/api/orgs", "/api/orgs").permitted_by(&full_token));
+        assert!(!access(
+            Method::GET,
+            "/api/:project_name/new",
+            "/api/PROJECT_NAME/new"
+        )
+        .permitted_by(&full_token));
+    }
+
+    #[test]
+    fn authenticate_ok() -> anyhow::Result<()> {
+        let mut tokens_mock = ApiTokenRepository::faux();
+        when!(tokens_mock.read_by_hash)
+            .once()
+            .then_return(Ok(Some(token(TokenScope::Deploy, Some(PROJECT_NAME)))));
+        when!(tokens_mock.touch).once().then_return(Ok(()));
+        let mut users_mock = UserRepository::faux();
+        when!(users_mock.read).once().then_return(Ok(Some(user())));
+
+        let tokens = TokenService::new(tokens_mock, users_mock, ProjectRepository::faux());
+        let (user, name) = tokens.authenticate(
+            "noops_TOKEN",
+            &access(Method::GET, PROJECT_ROUTE, "/api/PROJECT_NAME"),
+        )?;
+
+        assert_eq!(USER_ID, user.id);
+        assert_eq!(TOKEN_NAME, name);
+        Ok(())
+    }
+
+    #[test]
+    fn authenticate_forbidden() -> anyhow::Result<()> {
+        let mut tokens_mock = ApiTokenRepository::faux();
+        when!(tokens_mock.read_by_hash)
+            .once()
+            .then_return(Ok(Some(token(TokenScope::Read, None))));
+
+        let tokens = TokenService::new(
+            tokens_mock,
+            UserRepository::faux(),
+            ProjectRepository::faux(),
+        );
+        let result = tokens.authenticate(
+            "noops_TOKEN",
+            &access(Method::DELETE, PROJECT_ROUTE, "/api/PROJECT_NAME"),
+        );
+
+        assert!(matches!(result, Err(TokenForbidden)));
+        Ok(())
+    }
+
+    #[test]
+    fn create_existing_name() -> anyhow::Result<()> {
+        let mut tokens_mock = ApiTokenRepository::faux();
+        when!(tokens_mock.read)
+            .once()
+            .then_return(Ok(Some(token(TokenScope::Read, None))));
+
+        let tokens = TokenService::new(
+            tokens_mock,
+            UserRepository::faux(),
+            ProjectRepository::faux(),
+        );
+        let result = tokens.create(
+            &user().into(),
+            &CreateTokenDTO {
+                name: "ci".to_string(),
+                ..Default::default()
+            },
+        );
+
+        assert!(matches!(result, Err(TokenExists)));
+        Ok(())
+    }
+}
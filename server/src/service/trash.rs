@@ -0,0 +1,262 @@
+use super::gc::GarbageCollector;
+use crate::{
+    errors::Error,
+    repository::{
+        handler::{Handler, HandlerRepository},
+        project::{Project, ProjectRepository},
+        version::HandlerVersionRepository,
+        Repository,
+    },
+};
+use chrono::Utc;
+use std::{collections::HashSet, time::Duration};
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Purged {
+    pub projects: usize,
+    pub handlers: usize,
+}
+
+/// Deleted projects and handlers stay in the trash for the retention period, during which they
+/// can be restored. Afterwards they are purged for good.
+#[cfg_attr(test, faux::create)]
+#[derive(Debug, Clone)]
+pub struct TrashService {
+    projects: ProjectRepository,
+    handlers: HandlerRepository,
+    versions: HandlerVersionRepository,
+    gc: GarbageCollector,
+    retention: Duration,
+}
+
+#[cfg_attr(test, faux::methods)]
+impl TrashService {
+    pub fn new(
+        projects: ProjectRepository,
+        handlers: HandlerRepository,
+        versions: HandlerVersionRepository,
+        gc: GarbageCollector,
+        retention: Duration,
+    ) -> Self {
+        Self {
+            projects,
+            handlers,
+            versions,
+            gc,
+            retention,
+        }
+    }
+
+    /// Purges the projects and handlers whose retention period has ended
+    pub fn purge(&self) -> Result<Purged, Error> {
+        let retention =
+            chrono::Duration::from_std(self.retention).map_err(|err| Error::Unknown(err.into()))?;
+        let before = Utc::now().naive_utc() - retention;
+
+        let mut purged = Purged::default();
+        // Purging a project purges the handlers trashed with it, so the handlers are listed
+        // afterwards
+        for project in self.projects.trashed_before(before)? {
+            self.purge_project(&project)?;
+            purged.projects += 1;
+        }
+        for handler in self.handlers.trashed_before(before)? {
+            self.purge_handler(&handler)?;
+            purged.handlers += 1;
+        }
+
+        Ok(purged)
+    }
+
+    /// Deletes the project for good, the database cascades to its handlers, versions, status
+    /// codes, variables and trusted keys
+    pub fn purge_project(&self, project: &Project) -> Result<(), Error> {
+        let blobs = self.versions.blobs_belonging_to(project)?;
+        self.projects.delete(&project.id)?;
+        self.remove_components(&blobs);
+        Ok(())
+    }
+
+    /// Deletes the handler for good, the database cascades to its versions and status codes
+    pub fn purge_handler(&self, handler: &Handler) -> Result<(), Error> {
+        let blobs = self
+            .versions
+            .belonging_to_with_login(handler)?
+            .into_iter()
+            .map(|(version, _)| version.blob)
+            .collect();
+        self.handlers.delete(&handler.id)?;
+        self.remove_components(&blobs);
+        Ok(())
+    }
+
+    /// Removes the components of a purged item no other handler version references. The item
+    /// is gone already, so a component left behind is removed by the next garbage collection.
+    pub fn remove_components(&self, blobs: &HashSet<String>) {
+        if let Err(err) = self.gc.collect_blobs(blobs) {
+            tracing::warn!("Removing components failed: {}", err);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::identity::Identity;
+    use crate::{
+        repository::{user::User, version::HandlerVersion},
+        service::gc::GRACE_PERIOD,
+        wasmstore::{Blob, WasmStore},
+    };
+    use common::dtos::Language;
+    use faux::when;
+    use std::time::SystemTime;
+
+    const USER_ID: &str = "Eeghaeng8ohxeiqu7Aek";
+    const OWN: &str = "2c26b46b68ffc68ff99b453c1d30413413422d706483bfa0f98a5e886266e7ae";
+    const SHARED: &str = "fcde2b2edba56bf408601fb721fe9b5c338d10ee429ea04fae5511b68fbf8fb9";
+    const FRESH: &str = "baa5a0964d3320fbc0c6a922140453c8513ea24ab8fd0577034804a967248096";
+    const FOREIGN: &str = "486ea46224d1bb4fb680f34f7c9ad96a8f24ec88be73ea8e5a6c65260e9cb8a7";
+
+    /// A garbage collector which only removes the component `OWN`. Fresh and still referenced
+    /// components are kept, deleting any other component fails the test.
+    fn gc() -> GarbageCollector {
+        let expired = SystemTime::now() - GRACE_PERIOD * 2;
+        let blob = |digest: &str, modified| Blob {
+            digest: digest.to_string(),
+            modified,
+        };
+
+        let mut versions_mock = HandlerVersionRepository::faux();
+        when!(versions_mock.blobs())
+            .once()
+            .then_return(Ok(HashSet::from([SHARED.to_string(), FOREIGN.to_string()])));
+
+        let mut wasmstore_mock = WasmStore::faux();
+        when!(wasmstore_mock.blobs()).once().then_return(Ok(vec![
+            blob(OWN, expired),
+            blob(SHARED, expired),
+            blob(FRESH, SystemTime::now()),
+            blob(FOREIGN, expired),
+        ]));
+        when!(wasmstore_mock.delete(OWN)).once().then_return(Ok(()));
+
+        GarbageCollector::new(versions_mock, wasmstore_mock)
+    }
+
+    #[test]
+    fn purge_project_ok() -> anyhow::Result<()> {
+        let project = Project::new("PROJECT_NAME".to_string(), USER_ID.to_string());
+
+        let mut projects_mock = ProjectRepository::faux();
+        let project_id = project.id.clone();
+        when!(projects_mock.delete(_ = faux::from_fn!(move |id: &&str| *id == project_id)))
+            .once()
+            .then_return(Ok(()));
+
+        let mut versions_mock = HandlerVersionRepository::faux();
+        when!(versions_mock.blobs_belonging_to(project.clone()))
+            .once()
+            .then_return(Ok(HashSet::from([
+                OWN.to_string(),
+                SHARED.to_string(),
+                FRESH.to_string(),
+            ])));
+
+        // -------------------------------------------------------------------------------------
+
+        let trash = TrashService::new(
+            projects_mock,
+            HandlerRepository::faux(),
+            versions_mock,
+            gc(),
+            Duration::ZERO,
+        );
+        trash.purge_project(&project)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn purge_ok() -> anyhow::Result<()> {
+        let project = Project::new("PROJECT_NAME".to_string(), USER_ID.to_string());
+        let handler = Handler::new(
+            "HANDLER_NAME".to_string(),
+            Language::Rust,
+            "ooKae9ah".to_string(),
+            "xiekaiphoe7Luk3zeuNie".to_string(),
+            "prod".to_string(),
+        );
+        let version = HandlerVersion::new(
+            handler.id.clone(),
+            1,
+            handler.hash.clone(),
+            OWN.to_string(),
+            0,
+            USER_ID.to_string(),
+        );
+
+        let mut projects_mock = ProjectRepository::faux();
+        when!(projects_mock.trashed_before)
+            .once()
+            .then_return(Ok(vec![project.clone()]));
+        when!(projects_mock.delete).once().then_return(Ok(()));
+
+        let mut handlers_mock = HandlerRepository::faux();
+        when!(handlers_mock.trashed_before)
+            .once()
+            .then_return(Ok(vec![handler.clone()]));
+        let handler_id = handler.id.clone();
+        when!(handlers_mock.delete(_ = faux::from_fn!(move |id: &&str| *id == handler_id)))
+            .once()
+            .then_return(Ok(()));
+
+        let mut versions_mock = HandlerVersionRepository::faux();
+        when!(versions_mock.blobs_belonging_to(project))
+            .once()
+            .then_return(Ok(HashSet::new()));
+        let user = User::new(Identity {
+            provider: "github".to_string(),
+            subject: "42".to_string(),
+            login: "login_name".to_string(),
+            email: "test@example.com".to_string(),
+            ..Default::default()
+        });
+        when!(versions_mock.belonging_to_with_login(handler))
+            .once()
+            .then_return(Ok(vec![(version, Some(user.login))]));
+
+        let mut gc_versions_mock = HandlerVersionRepository::faux();
+        when!(gc_versions_mock.blobs())
+            .times(2)
+            .then(|_| Ok(HashSet::new()));
+        let mut wasmstore_mock = WasmStore::faux();
+        when!(wasmstore_mock.blobs()).times(2).then(|_| {
+            Ok(vec![Blob {
+                digest: OWN.to_string(),
+                modified: SystemTime::now() - GRACE_PERIOD * 2,
+            }])
+        });
+        when!(wasmstore_mock.delete(OWN)).once().then_return(Ok(()));
+
+        // -------------------------------------------------------------------------------------
+
+        let trash = TrashService::new(
+            projects_mock,
+            handlers_mock,
+            versions_mock,
+            GarbageCollector::new(gc_versions_mock, wasmstore_mock),
+            Duration::from_secs(7 * 24 * 60 * 60),
+        );
+        let purged = trash.purge()?;
+
+        assert_eq!(
+            Purged {
+                projects: 1,
+                handlers: 1
+            },
+            purged
+        );
+        Ok(())
+    }
+}
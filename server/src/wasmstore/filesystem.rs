@@ -0,0 +1,114 @@
+use super::{Object, Storage, TEMP_EXTENSION};
+use crate::repository::create_id;
+use std::{
+    fs::{self, File},
+    io::{ErrorKind, Write},
+    path::{Path, PathBuf},
+};
+
+/// Stores objects as files below a local directory
+#[derive(Debug, Clone)]
+pub struct FileSystem {
+    prefix: PathBuf,
+}
+
+impl FileSystem {
+    pub fn new(path: &Path) -> anyhow::Result<Self> {
+        fs::create_dir_all(path)?;
+        Ok(Self {
+            prefix: path.to_path_buf(),
+        })
+    }
+}
+
+impl Storage for FileSystem {
+    /// Writes to a temporary file first, so readers never see a partially written object. The
+    /// file is synced before it is moved into place and the directory after, so a crash can't
+    /// leave an object behind which is empty or truncated.
+    fn put(&self, key: &str, data: &[u8]) -> anyhow::Result<()> {
+        let path = self.prefix.join(key);
+        let parent = path.parent().unwrap_or(&self.prefix);
+        fs::create_dir_all(parent)?;
+        let temp_path = path.with_extension(format!("{}.{}", create_id(), TEMP_EXTENSION));
+        let mut file = File::create(&temp_path)?;
+        file.write_all(data)?;
+        file.sync_all()?;
+        fs::rename(temp_path, &path)?;
+        File::open(parent)?.sync_all()?;
+        Ok(())
+    }
+
+    fn get(&self, key: &str) -> anyhow::Result<Option<Vec<u8>>> {
+        match fs::read(self.prefix.join(key)) {
+            Ok(data) => Ok(Some(data)),
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    fn delete(&self, key: &str) -> anyhow::Result<()> {
+        match fs::remove_file(self.prefix.join(key)) {
+            Err(err) if err.kind() != ErrorKind::NotFound => Err(err.into()),
+            _ => Ok(()),
+        }
+    }
+
+    fn list(&self, directory: &str) -> anyhow::Result<Vec<Object>> {
+        let entries = match fs::read_dir(self.prefix.join(directory)) {
+            Ok(entries) => entries,
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(err) => return Err(err.into()),
+        };
+
+        let mut objects = Vec::new();
+        for entry in entries {
+            let entry = entry?;
+            let metadata = entry.metadata()?;
+            if !metadata.is_file() {
+                continue;
+            }
+            let name = entry.file_name().to_string_lossy().to_string();
+            let key = if directory.is_empty() {
+                name
+            } else {
+                format!("{}/{}", directory, name)
+            };
+            objects.push(Object {
+                key,
+                modified: metadata.modified()?,
+            });
+        }
+        Ok(objects)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn list_ok() -> anyhow::Result<()> {
+        let temp_dir = tempdir()?;
+        let storage = FileSystem::new(temp_dir.path())?;
+        storage.put("root.wasm", b"root")?;
+        storage.put("sha256/nested.wasm", b"nested")?;
+
+        let root: Vec<String> = storage.list("")?.into_iter().map(|o| o.key).collect();
+        let nested: Vec<String> = storage.list("sha256")?.into_iter().map(|o| o.key).collect();
+
+        assert_eq!(vec!["root.wasm".to_string()], root);
+        assert_eq!(vec!["sha256/nested.wasm".to_string()], nested);
+        Ok(())
+    }
+
+    #[test]
+    fn get_not_found() -> anyhow::Result<()> {
+        let temp_dir = tempdir()?;
+        let storage = FileSystem::new(temp_dir.path())?;
+
+        assert_eq!(None, storage.get("missing.wasm")?);
+        storage.delete("missing.wasm")?;
+        Ok(())
+    }
+}
@@ -0,0 +1,264 @@
+pub mod filesystem;
+pub mod s3;
+
+use crate::errors::Error::{self, HandlerNotFound};
+use common::hash;
+use std::{fmt::Debug, path::Path, sync::Arc, time::SystemTime};
+
+const BLOB_DIRECTORY: &str = "sha256";
+const WASM_EXTENSION: &str = "wasm";
+/// The extension of the files a backend writes before moving them into place
+const TEMP_EXTENSION: &str = "tmp";
+
+/// An object kept by a storage backend
+#[derive(Debug, Clone, PartialEq)]
+pub struct Object {
+    /// The key relative to the root of the storage, directories are separated by `/`
+    pub key: String,
+    pub modified: SystemTime,
+}
+
+/// A backend the wasmstore keeps its components in
+pub trait Storage: Debug + Send + Sync {
+    /// Stores the object, replacing an existing one and renewing its modification time
+    fn put(&self, key: &str, data: &[u8]) -> anyhow::Result<()>;
+
+    fn get(&self, key: &str) -> anyhow::Result<Option<Vec<u8>>>;
+
+    /// Deletes the object, deleting a missing object is not an error
+    fn delete(&self, key: &str) -> anyhow::Result<()>;
+
+    /// Lists the objects directly in `directory`, the empty string is the root
+    fn list(&self, directory: &str) -> anyhow::Result<Vec<Object>>;
+}
+
+/// A component stored in the wasmstore
+#[derive(Debug, Clone, PartialEq)]
+pub struct Blob {
+    pub digest: String,
+    pub modified: SystemTime,
+}
+
+/// Stores components by the SHA-256 digest of their content, so identical components are
+/// stored once no matter how many handler versions reference them.
+#[cfg_attr(test, faux::create)]
+#[derive(Debug, Clone)]
+pub struct WasmStore {
+    storage: Arc<dyn Storage>,
+}
+
+#[cfg_attr(test, faux::methods)]
+impl WasmStore {
+    pub fn new(storage: Arc<dyn Storage>) -> Self {
+        Self { storage }
+    }
+
+    /// Stores the component and returns its digest. An already stored component is written
+    /// again to renew its modification time, which protects it from a concurrent garbage
+    /// collection.
+    pub fn create(&self, wasm: &[u8]) -> Result<String, Error> {
+        let digest = hash::hash(wasm);
+        self.storage.put(&blob_key(&digest), wasm)?;
+        Ok(digest)
+    }
+
+    pub fn delete(&self, digest: &str) -> Result<(), Error> {
+        self.storage.delete(&blob_key(digest))?;
+        Ok(())
+    }
+
+    pub fn read(&self, digest: &str) -> Result<Vec<u8>, Error> {
+        self.storage.get(&blob_key(digest))?.ok_or(HandlerNotFound)
+    }
+
+    /// Whether the stored content of the component still matches its digest
+    pub fn verify(&self, digest: &str) -> Result<bool, Error> {
+        let wasm = self.read(digest)?;
+        Ok(hash::hash(&wasm) == digest)
+    }
+
+    pub fn blobs(&self) -> anyhow::Result<Vec<Blob>> {
+        let blobs = self
+            .storage
+            .list(BLOB_DIRECTORY)?
+            .into_iter()
+            .filter_map(|object| {
+                Some(Blob {
+                    digest: wasm_file_stem(&object.key)?,
+                    modified: object.modified,
+                })
+            })
+            .collect();
+        Ok(blobs)
+    }
+
+    /// Temporary files left behind by writes a crash interrupted, and those still being written
+    pub fn temp_files(&self) -> anyhow::Result<Vec<Object>> {
+        let files = self
+            .storage
+            .list(BLOB_DIRECTORY)?
+            .into_iter()
+            .filter(|object| is_temp_file(&object.key))
+            .collect();
+        Ok(files)
+    }
+
+    pub fn delete_temp_file(&self, key: &str) -> anyhow::Result<()> {
+        if !is_temp_file(key) {
+            anyhow::bail!("{} is not a temporary file", key);
+        }
+        self.storage.delete(key)
+    }
+
+    /// Ids of the components still stored as `{version_id}.wasm` by earlier versions
+    pub fn legacy_files(&self) -> anyhow::Result<Vec<String>> {
+        let ids = self
+            .storage
+            .list("")?
+            .into_iter()
+            .filter_map(|object| wasm_file_stem(&object.key))
+            .collect();
+        Ok(ids)
+    }
+
+    /// Moves a legacy file to its content address and returns the digest
+    pub fn migrate_legacy_file(&self, id: &str) -> anyhow::Result<String> {
+        let key = legacy_key(id);
+        let wasm = self
+            .storage
+            .get(&key)?
+            .ok_or(anyhow::anyhow!("Component {} not found", key))?;
+        let digest = self.create(&wasm)?;
+        self.storage.delete(&key)?;
+        Ok(digest)
+    }
+
+    pub fn delete_legacy_file(&self, id: &str) -> anyhow::Result<()> {
+        self.storage.delete(&legacy_key(id))
+    }
+}
+
+fn blob_key(digest: &str) -> String {
+    format!("{}/{}.{}", BLOB_DIRECTORY, digest, WASM_EXTENSION)
+}
+
+fn legacy_key(id: &str) -> String {
+    format!("{}.{}", id, WASM_EXTENSION)
+}
+
+fn is_temp_file(key: &str) -> bool {
+    Path::new(key)
+        .extension()
+        .is_some_and(|extension| extension == TEMP_EXTENSION)
+}
+
+fn wasm_file_stem(key: &str) -> Option<String> {
+    let path = Path::new(key);
+    if path.extension()? != WASM_EXTENSION {
+        return None;
+    }
+    Some(path.file_stem()?.to_string_lossy().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{filesystem::FileSystem, *};
+    use std::fs;
+    use tempfile::{tempdir, TempDir};
+
+    const WASM: &[u8] = b"\0asm";
+    const WASM_DIGEST: &str = "cd5d4935a48c0672cb06407bb443bc0087aff947c6b864bac886982c73b3027f";
+    const LEGACY_ID: &str = "Phai5ohquoh7thohmeeT2";
+
+    fn setup() -> anyhow::Result<(TempDir, WasmStore)> {
+        let temp_dir = tempdir()?;
+        let wasmstore = WasmStore::new(Arc::new(FileSystem::new(temp_dir.path())?));
+        Ok((temp_dir, wasmstore))
+    }
+
+    #[test]
+    fn create_ok() -> anyhow::Result<()> {
+        let (_temp_dir, wasmstore) = setup()?;
+        let digest = wasmstore.create(WASM)?;
+
+        assert_eq!(WASM_DIGEST, digest);
+        assert_eq!(WASM, wasmstore.read(&digest)?);
+        Ok(())
+    }
+
+    #[test]
+    fn create_deduplicates() -> anyhow::Result<()> {
+        let (_temp_dir, wasmstore) = setup()?;
+        wasmstore.create(WASM)?;
+        wasmstore.create(WASM)?;
+
+        assert_eq!(1, wasmstore.blobs()?.len());
+        Ok(())
+    }
+
+    #[test]
+    fn read_not_found() -> anyhow::Result<()> {
+        let (_temp_dir, wasmstore) = setup()?;
+        let result = wasmstore.read(WASM_DIGEST);
+
+        assert!(result.is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn verify_corrupt() -> anyhow::Result<()> {
+        let (temp_dir, wasmstore) = setup()?;
+        let digest = wasmstore.create(WASM)?;
+        assert!(wasmstore.verify(&digest)?);
+
+        fs::write(temp_dir.path().join(blob_key(&digest)), b"corrupt")?;
+        assert!(!wasmstore.verify(&digest)?);
+        Ok(())
+    }
+
+    #[test]
+    fn temp_files_ok() -> anyhow::Result<()> {
+        let (temp_dir, wasmstore) = setup()?;
+        let digest = wasmstore.create(WASM)?;
+        let temp_key = format!("{}/{}.{}.tmp", BLOB_DIRECTORY, digest, LEGACY_ID);
+        fs::write(temp_dir.path().join(&temp_key), b"\0a")?;
+
+        let temp_files: Vec<String> = wasmstore
+            .temp_files()?
+            .into_iter()
+            .map(|object| object.key)
+            .collect();
+        assert_eq!(vec![temp_key.clone()], temp_files);
+        assert_eq!(1, wasmstore.blobs()?.len());
+        assert!(wasmstore.delete_temp_file(&blob_key(&digest)).is_err());
+
+        wasmstore.delete_temp_file(&temp_key)?;
+        assert!(wasmstore.temp_files()?.is_empty());
+        assert_eq!(WASM, wasmstore.read(&digest)?);
+        Ok(())
+    }
+
+    #[test]
+    fn delete_ok() -> anyhow::Result<()> {
+        let (_temp_dir, wasmstore) = setup()?;
+        let digest = wasmstore.create(WASM)?;
+        wasmstore.delete(&digest)?;
+
+        assert!(wasmstore.blobs()?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn migrate_legacy_file_ok() -> anyhow::Result<()> {
+        let (temp_dir, wasmstore) = setup()?;
+        fs::write(temp_dir.path().join(format!("{}.wasm", LEGACY_ID)), WASM)?;
+        assert_eq!(vec![LEGACY_ID.to_string()], wasmstore.legacy_files()?);
+
+        let digest = wasmstore.migrate_legacy_file(LEGACY_ID)?;
+
+        assert_eq!(WASM_DIGEST, digest);
+        assert!(wasmstore.legacy_files()?.is_empty());
+        assert_eq!(WASM, wasmstore.read(&digest)?);
+        Ok(())
+    }
+}